@@ -1,4 +1,7 @@
-use clap::Subcommand;
+use std::path::PathBuf;
+
+use clap::{Subcommand, ValueEnum};
+use clap_complete::Shell;
 
 #[derive(Debug, Subcommand)]
 pub enum Commands {
@@ -16,6 +19,137 @@ pub enum Commands {
         #[command(subcommand)]
         subcommand: PasswordCommands,
     },
+    /// Run a command with secrets from the vault in its environment.
+    Run {
+        /// A variable to set, e.g. `DB_PASS=vault://prod-db/password`. The entry is an ID,
+        /// UUID or service name, the field one of password, username, url, notes, totp or
+        /// a custom field.
+        #[arg(
+            short,
+            long = "env",
+            value_name = "NAME=vault://ENTRY/FIELD",
+            required = true
+        )]
+        env: Vec<String>,
+        /// Pass the output of the command through unchanged instead of masking the secrets
+        /// in it, e.g. for interactive programs.
+        #[arg(long)]
+        no_mask: bool,
+        /// The command to run and its arguments, after `--`.
+        #[arg(last = true, required = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+    /// Render a template with `{{ vault "entry/field" }}` placeholders into a file.
+    Inject {
+        /// The template to render.
+        #[arg(short, long)]
+        input: PathBuf,
+        /// The file to write. Prints to stdout if omitted.
+        #[arg(short = 'o', long)]
+        path: Option<PathBuf>,
+    },
+    /// Answer git as a credential helper, with logins from the vault. Set it up with
+    /// `git config --global credential.helper '!rusty-toolkit git-credential'`.
+    GitCredential {
+        /// The operation git asks for: get, store or erase. Other operations are ignored.
+        operation: String,
+    },
+    /// Serve the SSH keys stored in the vault to `ssh` as an SSH agent on a Unix socket.
+    SshAgent {
+        /// The path of the socket. Defaults to `ssh-agent.sock` in the runtime directory.
+        #[arg(short = 'a', long)]
+        socket: Option<PathBuf>,
+        /// Ask before every signature, with the `SSH_ASKPASS` program if it is set.
+        #[arg(short, long)]
+        confirm: bool,
+        /// The number of seconds after which the keys are removed and the agent stops.
+        #[arg(short = 't', long)]
+        lifetime: Option<u64>,
+    },
+    /// Serve search, get, match, add and update of entries as JSON-RPC over HTTP on a Unix socket.
+    /// Clients authenticate with a token from `api-token create`.
+    Serve {
+        /// The path of the socket. Defaults to `api.sock` in the runtime directory.
+        #[arg(short = 'a', long)]
+        socket: Option<PathBuf>,
+    },
+    /// Manage the tokens clients of `serve` authenticate with.
+    ApiToken {
+        #[command(subcommand)]
+        subcommand: ApiTokenCommands,
+    },
+    /// Fill and save logins from a browser extension through `serve`, as a native-messaging
+    /// host.
+    NativeHost {
+        #[command(subcommand)]
+        subcommand: NativeHostCommands,
+    },
+    /// Print a completion script for a shell.
+    Completions {
+        /// The shell to complete for.
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Write man pages for all commands.
+    Manpage {
+        /// The directory to write the pages to.
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ApiTokenCommands {
+    /// Create a token for a client and print it. It is shown only once.
+    Create {
+        /// The name of the client, shown in the audit log.
+        name: String,
+        /// Only allow the client to search, get and match entries.
+        #[arg(long)]
+        read_only: bool,
+    },
+    /// List the clients with a token.
+    List,
+    /// Revoke the token of a client.
+    Revoke {
+        /// The name of the client.
+        name: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum NativeHostCommands {
+    /// Install the host for a browser: a token, a launcher script and the host manifest.
+    /// Installing again replaces the token.
+    Install {
+        /// The browser to install the host for.
+        #[arg(value_enum)]
+        browser: Browser,
+        /// The ID of the extension that may start the host.
+        #[arg(short, long)]
+        extension_id: String,
+        /// The socket `serve` listens on. Defaults to `api.sock` in the runtime directory.
+        #[arg(short = 'a', long)]
+        socket: Option<PathBuf>,
+        /// Only print the manifest, e.g. for another browser. Nothing is installed and no
+        /// token is created.
+        #[arg(long)]
+        print: bool,
+    },
+    /// Answer the extension on stdin and stdout. The browser starts this with the launcher
+    /// script written by `install`.
+    Run {
+        /// The socket `serve` listens on. Defaults to `api.sock` in the runtime directory.
+        #[arg(short = 'a', long)]
+        socket: Option<PathBuf>,
+        /// The file with the API token of the host.
+        #[arg(long)]
+        token_file: PathBuf,
+        /// The arguments the browser passes, e.g. the origin of the extension. They are
+        /// ignored, the manifest decides which extensions may start the host.
+        #[arg(hide = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        browser_args: Vec<String>,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -31,6 +165,41 @@ pub enum PasswordCommands {
         #[command(subcommand)]
         subcommand: PasswordManagerCommands,
     },
+    /// Unlock the vault once and run list, show, search, add and copy commands in a shell.
+    Shell {
+        /// The number of seconds without input after which the vault is locked again, 0 to
+        /// keep it unlocked.
+        #[arg(long, default_value_t = 300)]
+        lock_after: u64,
+    },
+    /// Sync the password vault with a copy on a WebDAV server.
+    Sync {
+        /// The remote folder, e.g. `webdav://dav.example.com/team/vault`. Use
+        /// `webdav+http://` for servers without TLS. Defaults to the only stored remote.
+        #[arg(short, long)]
+        remote: Option<String>,
+        /// The username for the remote. Asks for a new login.
+        #[arg(short, long)]
+        username: Option<String>,
+        /// Ask for a new login instead of using the stored one.
+        #[arg(short, long)]
+        login: bool,
+        /// Upgrade the remote vault if it was written by an older version. Clients that
+        /// still run that version cannot sync with it afterwards.
+        #[arg(long)]
+        migrate_remote: bool,
+    },
+    /// Clear the clipboard after a copy, unless something else was copied meanwhile. Reads
+    /// the fingerprint of the copied value from stdin.
+    #[command(hide = true)]
+    ClearClipboard {
+        /// The clipboard backend the value was copied with.
+        #[arg(long)]
+        backend: String,
+        /// The number of seconds to wait before clearing.
+        #[arg(long)]
+        after: u64,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -53,6 +222,15 @@ pub enum PasswordManagerCommands {
         #[arg(short, long)]
         notes: Option<String>,
     },
+    /// Store an SSH private key in the vault, so the SSH agent can serve it.
+    AddSshKey {
+        /// The unencrypted private key in the OpenSSH format, `-` to read it from stdin.
+        #[arg(short, long)]
+        key: String,
+        /// The name of the entry. Defaults to the comment of the key.
+        #[arg(short, long)]
+        service: Option<String>,
+    },
     /// Remove a password from the password manager.
     Remove {
         /// The name of the password to remove.
@@ -87,7 +265,24 @@ pub enum PasswordManagerCommands {
         /// The ID of the password to show.
         #[arg(short, long)]
         id: Option<i32>,
+        /// Show the password, the TOTP secret and hidden fields instead of masking them.
+        #[arg(short, long)]
+        reveal: bool,
+    },
+    /// Copy a field of an entry to the clipboard and clear it after a while.
+    Copy {
+        /// The ID, UUID or service name of the entry.
+        selector: String,
+        /// The field to copy.
+        #[arg(short, long, value_enum, default_value_t = CopyField::Password)]
+        field: CopyField,
+        /// The number of seconds until the clipboard is cleared, 0 to keep the value.
+        /// Defaults to `clear_after` in the configuration.
+        #[arg(short, long)]
+        clear_after: Option<u64>,
     },
+    /// Browse, copy and edit entries in a full-screen terminal UI.
+    Tui,
     /// Search for a password in the password manager.
     Search {
         /// The query to search for.
@@ -99,12 +294,71 @@ pub enum PasswordManagerCommands {
         /// The path to export the password manager to.
         #[arg(short, long)]
         path: Option<String>,
+        /// The format of the export.
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Encrypted)]
+        format: ExportFormat,
     },
-    /// Import passwords from a file.
+    /// Write a printable HTML sheet of selected entries for a disaster recovery binder.
+    EmergencySheet {
+        /// The path to write the sheet to.
+        #[arg(short = 'o', long)]
+        path: Option<String>,
+        /// Include the entries of a folder and its subfolders. Can be repeated.
+        #[arg(long)]
+        folder: Vec<String>,
+        /// Include the entries with a tag. Can be repeated.
+        #[arg(short, long)]
+        tag: Vec<String>,
+        /// Include an entry by ID, UUID or service name. Can be repeated.
+        #[arg(short, long)]
+        entry: Vec<String>,
+    },
+    /// Import passwords from an encrypted export, a CSV file or another password manager.
     Import {
         /// The path to import passwords from.
         #[arg(short, long)]
         path: Option<String>,
+        /// The format of the file to import.
+        #[arg(short, long, value_enum, default_value_t = ImportFormat::Auto)]
+        format: ImportFormat,
+        /// The key file of a KeePass database.
+        #[arg(short, long)]
+        keyfile: Option<String>,
+        /// Map CSV columns onto fields, e.g. `service=name,url=origin`.
+        #[arg(short, long)]
+        map: Option<String>,
+        /// How to handle entries with the same service, username and URL as a stored entry.
+        #[arg(long, value_enum, default_value_t = ConflictStrategy::Skip)]
+        on_conflict: ConflictStrategy,
+        /// Print what would be added, changed or skipped without changing the vault.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Verify the integrity of the password vault.
+    Verify,
+    /// Create a backup of the password vault.
+    Backup {
+        /// List the existing backups instead of creating one.
+        #[arg(short, long)]
+        list: bool,
+    },
+    /// Restore the password vault from a backup.
+    Restore {
+        /// The name or path of the backup to restore.
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+    /// Merge another copy of the vault, e.g. from a shared folder, and update both copies.
+    Merge {
+        /// The path of the other vault database.
+        path: String,
+        /// The master key file of the other vault. Defaults to `master.key` next to it.
+        #[arg(short, long)]
+        key: Option<String>,
+        /// Upgrade the other vault if it was written by an older version. Clients that
+        /// still run that version cannot open it afterwards.
+        #[arg(long)]
+        migrate: bool,
     },
     /// Generate a import template.
     GenerateImportTemplate {
@@ -113,3 +367,67 @@ pub enum PasswordManagerCommands {
         path: Option<String>,
     },
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Browser {
+    /// Google Chrome.
+    Chrome,
+    /// Chromium.
+    Chromium,
+    /// Mozilla Firefox.
+    Firefox,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CopyField {
+    /// The password of the entry.
+    Password,
+    /// The username of the entry.
+    Username,
+    /// The current one-time code of the entry's TOTP secret.
+    Totp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// A password protected, encrypted file.
+    Encrypted,
+    /// A plain text JSON file with every field of the vault.
+    Json,
+    /// A plain text CSV file. Every password is readable by anyone with access to the file.
+    Csv,
+    /// A plain text Bitwarden JSON export.
+    BitwardenJson,
+    /// A plain text KeePass 2 XML file, which KeePass and KeePassXC can import.
+    KeepassXml,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+    /// Detect an encrypted export or a KeePass database, otherwise read a CSV file.
+    Auto,
+    /// An encrypted export of this password manager.
+    Encrypted,
+    /// A plain JSON export of this password manager.
+    Json,
+    /// A CSV file from this tool, a browser or another password manager.
+    Csv,
+    /// An unencrypted Bitwarden JSON export.
+    BitwardenJson,
+    /// A KeePass KDBX 4 database.
+    Kdbx,
+    /// An unencrypted KeePass 2 XML export.
+    KeepassXml,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConflictStrategy {
+    /// Keep the stored entry.
+    Skip,
+    /// Replace the stored entry with the imported one.
+    Overwrite,
+    /// Import the entry as a new entry next to the stored one.
+    KeepBoth,
+    /// Keep whichever entry was updated last.
+    Newest,
+}
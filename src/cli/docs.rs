@@ -0,0 +1,91 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use clap::{Command, CommandFactory};
+use clap_complete::Shell;
+use clap_mangen::Man;
+
+use crate::cli::Cli;
+
+/// Print a completion script for a shell to stdout.
+///
+/// # Arguments
+///
+/// * `shell` - The shell to complete for.
+///
+/// # Returns
+///
+/// A `Result` containing `()` or an error.
+///
+/// # Errors
+///
+/// An error will be returned if stdout cannot be written to.
+pub fn print_completions(shell: Shell) -> Result<(), Box<dyn std::error::Error>> {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, name, &mut script);
+
+    std::io::stdout().write_all(&script)?;
+    Ok(())
+}
+
+/// Write a man page for every command and subcommand.
+///
+/// The pages are named after the command path, e.g. `rusty-toolkit-password-manage.1`.
+/// Hidden commands get no page.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to write the pages to. It is created if it does not exist.
+///
+/// # Returns
+///
+/// A `Result` containing the paths of the written pages or an error.
+///
+/// # Errors
+///
+/// An error will be returned if a page cannot be written.
+pub fn write_manpages(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    fn write(command: Command, dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), std::io::Error> {
+        paths.push(Man::new(command.clone()).generate_to(dir)?);
+        for subcommand in command.get_subcommands().filter(|s| !s.is_hide_set()) {
+            write(subcommand.clone(), dir, paths)?;
+        }
+        Ok(())
+    }
+
+    std::fs::create_dir_all(dir)?;
+    let mut command = Cli::command().disable_help_subcommand(true);
+    command.build();
+
+    let mut paths = Vec::new();
+    write(command, dir, &mut paths)?;
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_write_manpages() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = write_manpages(dir.path()).unwrap();
+
+        assert_eq!(paths[0], dir.path().join("rusty-toolkit.1"));
+        let manage = dir.path().join("rusty-toolkit-password-manage-copy.1");
+        assert!(paths.contains(&manage));
+        let page = std::fs::read_to_string(manage).unwrap();
+        assert!(page.contains("clipboard"));
+        assert!(!dir
+            .path()
+            .join("rusty-toolkit-password-clear-clipboard.1")
+            .exists());
+    }
+}
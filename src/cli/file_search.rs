@@ -1,9 +1,24 @@
+use crate::cli::input::Ask;
+use crate::cli::output::{self, Tabular};
 use crate::utils::errors::FileSearchError;
 use indicatif::{ProgressBar, ProgressStyle};
 use inquire::Text;
 use log::info;
+use serde::Serialize;
 use std::path::PathBuf;
 
+/// A file found by the search.
+#[derive(Serialize, Debug)]
+struct FoundFile {
+    path: String,
+}
+
+impl Tabular for FoundFile {
+    fn columns(&self) -> Vec<(String, String)> {
+        vec![("Path".to_string(), self.path.clone())]
+    }
+}
+
 #[derive(Debug)]
 pub struct FileSearch {
     pub path: Option<String>,
@@ -45,7 +60,7 @@ impl FileSearch {
             path
         } else {
             info!("Request path from user");
-            Text::new("Enter the path to search for files in:").prompt()?
+            Text::new("Enter the path to search for files in:").ask()?
         };
 
         let name = if let Some(name) = self.name.clone() {
@@ -53,7 +68,7 @@ impl FileSearch {
             name
         } else {
             info!("Request name from user");
-            Text::new("Enter the name of the file to search for:").prompt()?
+            Text::new("Enter the name of the file to search for:").ask()?
         };
 
         let progress = ProgressBar::new_spinner();
@@ -64,10 +79,14 @@ impl FileSearch {
 
         self.search(path, &name, progress.clone())?;
 
-        progress.finish_with_message(format!("Found {} files:", self.result.len()));
-        for file in &self.result {
-            println!("  • {}", file);
-        }
+        progress.finish_and_clear();
+        let files = self
+            .result
+            .iter()
+            .map(|path| FoundFile { path: path.clone() })
+            .collect::<Vec<_>>();
+        output::print_list(&files, "No files found.")
+            .map_err(|e| FileSearchError::SearchError(e.to_string()))?;
 
         info!("Found {} files", self.result.len());
 
@@ -0,0 +1,212 @@
+use std::env;
+use std::fmt::Display;
+use std::io::BufRead;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use clap::Args;
+use inquire::list_option::ListOption;
+use inquire::{Confirm, MultiSelect, Password, Select, Text};
+
+use crate::prelude::{CliError, SecretString};
+
+/// Whether prompts fail instead of asking, set once from `--no-input`.
+static NO_INPUT: AtomicBool = AtomicBool::new(false);
+
+/// Global options controlling where input comes from.
+#[derive(Debug, Clone, Default, Args)]
+pub struct InputArgs {
+    /// Fail instead of prompting when input is missing. Prompts with a default answer,
+    /// such as confirmations, take the default.
+    #[arg(long, global = true)]
+    pub no_input: bool,
+    /// Read the master password from the first line of stdin.
+    #[arg(long, global = true, conflicts_with_all = ["password_fd", "password_env"])]
+    pub password_stdin: bool,
+    /// Read the master password from the first line of an open file descriptor.
+    #[arg(
+        long,
+        global = true,
+        value_name = "FD",
+        conflicts_with = "password_env"
+    )]
+    pub password_fd: Option<u32>,
+    /// Read the master password from the named environment variable.
+    #[arg(long, global = true, value_name = "NAME")]
+    pub password_env: Option<String>,
+}
+
+impl InputArgs {
+    /// Apply `--no-input` to every prompt of this process.
+    pub fn apply(&self) {
+        NO_INPUT.store(self.no_input, Ordering::Relaxed);
+    }
+
+    /// Read the master password from the source given on the command line.
+    ///
+    /// The environment variable is removed after reading, so commands started later do
+    /// not inherit the password.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the master password, `None` if no source was given, or an
+    /// error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the source cannot be read.
+    pub fn master_password(&self) -> Result<Option<SecretString>, CliError> {
+        let password = if self.password_stdin {
+            read_first_line(std::io::stdin().lock(), "stdin")?
+        } else if let Some(fd) = self.password_fd {
+            let file = std::fs::File::open(format!("/dev/fd/{}", fd)).map_err(|e| {
+                CliError::InvalidInput(format!("Cannot read file descriptor {}: {}", fd, e))
+            })?;
+            read_first_line(std::io::BufReader::new(file), "the file descriptor")?
+        } else if let Some(name) = &self.password_env {
+            let password = env::var(name).map_err(|_| {
+                CliError::InvalidInput(format!("The environment variable {} is not set", name))
+            })?;
+            env::remove_var(name);
+            SecretString::new(password)
+        } else {
+            return Ok(None);
+        };
+
+        if password.is_empty() {
+            return Err(CliError::InvalidInput(
+                "The master password is empty".to_string(),
+            ));
+        }
+        Ok(Some(password))
+    }
+}
+
+/// Read the first line of a reader without its line ending.
+fn read_first_line(mut reader: impl BufRead, source: &str) -> Result<SecretString, CliError> {
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|e| CliError::InvalidInput(format!("Cannot read {}: {}", source, e)))?;
+    let length = line.trim_end_matches(['\r', '\n']).len();
+    line.truncate(length);
+    Ok(SecretString::new(line))
+}
+
+/// Check if prompts are allowed.
+///
+/// # Returns
+///
+/// A `bool` indicating if `--no-input` was not given.
+pub fn interactive() -> bool {
+    !NO_INPUT.load(Ordering::Relaxed)
+}
+
+/// The error of a prompt that was skipped because of `--no-input`.
+fn input_required(message: &str) -> CliError {
+    CliError::InvalidInput(format!(
+        "\"{}\" needs an answer, but prompts are disabled by --no-input",
+        message.trim()
+    ))
+}
+
+/// Prompts that respect `--no-input`.
+pub trait Ask {
+    type Output;
+
+    /// Show the prompt, or take its default answer or fail if prompts are disabled.
+    fn ask(self) -> Result<Self::Output, CliError>;
+}
+
+impl Ask for Text<'_> {
+    type Output = String;
+
+    fn ask(self) -> Result<String, CliError> {
+        if interactive() {
+            Ok(self.prompt()?)
+        } else {
+            self.default
+                .map(str::to_string)
+                .ok_or_else(|| input_required(self.message))
+        }
+    }
+}
+
+impl Ask for Password<'_> {
+    type Output = String;
+
+    fn ask(self) -> Result<String, CliError> {
+        if interactive() {
+            Ok(self.prompt()?)
+        } else {
+            Err(input_required(self.message))
+        }
+    }
+}
+
+impl Ask for Confirm<'_> {
+    type Output = bool;
+
+    fn ask(self) -> Result<bool, CliError> {
+        if interactive() {
+            Ok(self.prompt()?)
+        } else {
+            self.default.ok_or_else(|| input_required(self.message))
+        }
+    }
+}
+
+impl<T: Display> Ask for Select<'_, T> {
+    type Output = ListOption<T>;
+
+    fn ask(self) -> Result<ListOption<T>, CliError> {
+        if interactive() {
+            Ok(self.raw_prompt()?)
+        } else {
+            Err(input_required(self.message))
+        }
+    }
+}
+
+impl<T: Display> Ask for MultiSelect<'_, T> {
+    type Output = Vec<ListOption<T>>;
+
+    fn ask(self) -> Result<Vec<ListOption<T>>, CliError> {
+        if interactive() {
+            Ok(self.raw_prompt()?)
+        } else {
+            Err(input_required(self.message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_first_line() {
+        let password = read_first_line("secret\r\nnext line\n".as_bytes(), "stdin").unwrap();
+        assert_eq!(password.expose_secret(), "secret");
+
+        let password = read_first_line("  spaced  ".as_bytes(), "stdin").unwrap();
+        assert_eq!(password.expose_secret(), "  spaced  ");
+    }
+
+    #[test]
+    fn test_password_env() {
+        env::set_var("RUSTY_TOOLKIT_TEST_PASSWORD", "from-env");
+        let args = InputArgs {
+            password_env: Some("RUSTY_TOOLKIT_TEST_PASSWORD".to_string()),
+            ..InputArgs::default()
+        };
+
+        let password = args.master_password().unwrap().unwrap();
+        assert_eq!(password.expose_secret(), "from-env");
+        assert!(env::var("RUSTY_TOOLKIT_TEST_PASSWORD").is_err());
+        assert!(matches!(
+            args.master_password(),
+            Err(CliError::InvalidInput(_))
+        ));
+        assert!(InputArgs::default().master_password().unwrap().is_none());
+    }
+}
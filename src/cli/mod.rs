@@ -1,11 +1,26 @@
 use clap::Parser;
-pub use commands::{Commands, PasswordCommands, PasswordManagerCommands};
+pub use commands::{
+    ApiTokenCommands, Browser, Commands, ConflictStrategy, CopyField, ExportFormat, ImportFormat,
+    NativeHostCommands, PasswordCommands, PasswordManagerCommands,
+};
+pub use docs::{print_completions, write_manpages};
 pub use file_search::FileSearch;
+pub use input::InputArgs;
+pub use native_host::{install_native_host, run_native_host};
+pub use output::{print_message, OutputFormat};
 pub use password::PasswordManager;
+pub use serve::{create_api_token, list_api_tokens, revoke_api_token};
 
 mod commands;
+mod docs;
 mod file_search;
+mod input;
+mod native_host;
+mod output;
 mod password;
+mod serve;
+mod shell;
+mod tui;
 
 #[derive(Parser, Debug)]
 #[clap(
@@ -16,4 +31,9 @@ mod password;
 pub struct Cli {
     #[clap(subcommand)]
     pub commands: Commands,
+    #[command(flatten)]
+    pub input: InputArgs,
+    /// The format of the results of a command.
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
 }
@@ -0,0 +1,478 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::cli::commands::Browser;
+use crate::prelude::{CliError, SecretString};
+
+/// The name of the host, as extensions pass it to `connectNative`.
+pub const HOST_NAME: &str = "com.karnes_development.rusty_toolkit";
+
+/// A message of the extension, see the README for the protocol.
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+enum HostRequest {
+    /// The logins for a page, best match first.
+    GetLogins { origin: String },
+    /// Store a login submitted on a page, or update the password of a stored one.
+    SaveLogin {
+        origin: String,
+        #[serde(default)]
+        username: String,
+        password: SecretString,
+    },
+}
+
+fn browser_name(browser: Browser) -> &'static str {
+    match browser {
+        Browser::Chrome => "chrome",
+        Browser::Chromium => "chromium",
+        Browser::Firefox => "firefox",
+    }
+}
+
+/// The directory a browser looks for the manifests of native-messaging hosts of the user in.
+fn manifest_dir(browser: Browser) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let config_dir = dirs::config_dir().ok_or("Cannot find the configuration directory")?;
+    let home_dir = dirs::home_dir().ok_or("Cannot find the home directory")?;
+    let dir = match (browser, cfg!(target_os = "macos")) {
+        (Browser::Chrome, true) => config_dir.join("Google/Chrome"),
+        (Browser::Chrome, false) => config_dir.join("google-chrome"),
+        (Browser::Chromium, true) => config_dir.join("Chromium"),
+        (Browser::Chromium, false) => config_dir.join("chromium"),
+        (Browser::Firefox, true) => return Ok(config_dir.join("Mozilla/NativeMessagingHosts")),
+        (Browser::Firefox, false) => return Ok(home_dir.join(".mozilla/native-messaging-hosts")),
+    };
+    Ok(dir.join("NativeMessagingHosts"))
+}
+
+/// Build the manifest that tells a browser how to start the host and which extension may.
+///
+/// # Arguments
+///
+/// * `browser` - The browser to build the manifest for.
+/// * `launcher` - The program the browser starts.
+/// * `extension_id` - The ID of the extension.
+///
+/// # Returns
+///
+/// A `Result` containing the manifest or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the extension ID is not valid for the browser.
+fn manifest(
+    browser: Browser,
+    launcher: &Path,
+    extension_id: &str,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let mut manifest = json!({
+        "name": HOST_NAME,
+        "description": "Logins from the rusty-toolkit vault",
+        "path": launcher,
+        "type": "stdio",
+    });
+
+    if browser == Browser::Firefox {
+        if extension_id.is_empty() || extension_id.contains(char::is_whitespace) {
+            return Err(CliError::InvalidInput(format!(
+                "Invalid Firefox extension ID {:?}",
+                extension_id
+            ))
+            .into());
+        }
+        manifest["allowed_extensions"] = json!([extension_id]);
+    } else {
+        // Chrome extension IDs are 32 letters from a to p.
+        if extension_id.len() != 32 || !extension_id.bytes().all(|b| (b'a'..=b'p').contains(&b)) {
+            return Err(CliError::InvalidInput(format!(
+                "Invalid Chrome extension ID {:?}",
+                extension_id
+            ))
+            .into());
+        }
+        manifest["allowed_origins"] = json!([format!("chrome-extension://{}/", extension_id)]);
+    }
+
+    Ok(manifest)
+}
+
+/// Install the native-messaging host for a browser.
+///
+/// A token named `browser-<browser>` is created for the host, replacing an earlier one,
+/// and stored next to a launcher script in the configuration directory. The manifest
+/// points the browser at the script.
+///
+/// # Arguments
+///
+/// * `browser` - The browser to install the host for.
+/// * `extension_id` - The ID of the extension that may start the host.
+/// * `socket` - The socket `serve` listens on. Defaults to `api.sock` in the runtime
+///   directory, or in the configuration directory if there is none.
+/// * `print` - Whether to only print the manifest. No token, launcher or manifest is
+///   written then.
+///
+/// # Returns
+///
+/// A `Result` containing `()` or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the extension ID is invalid or a file cannot be written.
+#[cfg(unix)]
+pub fn install_native_host(
+    browser: Browser,
+    extension_id: String,
+    socket: Option<PathBuf>,
+    print: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    use log::info;
+
+    use crate::cli::output::message;
+    use crate::cli::serve;
+    use crate::prelude::Config;
+    use crate::utils::api::ApiTokens;
+    use crate::utils::socket as unix_socket;
+
+    let name = browser_name(browser);
+    let config_dir = Config::get_config_dir()?;
+    let token_file = config_dir.join(format!("native-host-{}.token", name));
+    let launcher = config_dir.join(format!("native-host-{}.sh", name));
+    let manifest = serde_json::to_string_pretty(&manifest(browser, &launcher, &extension_id)?)?;
+    if print {
+        println!("{}", manifest);
+        return Ok(());
+    }
+
+    let socket = match socket {
+        Some(path) => path,
+        None => unix_socket::default_path("api.sock")?,
+    };
+
+    let client = format!("browser-{}", name);
+    let mut tokens = ApiTokens::load(&serve::tokens_path()?)?;
+    if tokens.list().iter().any(|token| token.name == client) {
+        tokens.revoke(&client)?;
+    }
+    let token = tokens.create(&client, false)?;
+    info!(
+        "Created the API token {} for the native-messaging host",
+        client
+    );
+
+    // The mode only applies to new files, replaced ones get it set before they are written.
+    let write = |path: &Path, mode: u32, contents: &str| {
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(mode)
+            .open(path)?;
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(mode))?;
+        file.write_all(contents.as_bytes())
+    };
+    write(&token_file, 0o600, token.expose_secret())?;
+
+    let quote = |path: &Path| {
+        shlex::try_quote(&path.to_string_lossy())
+            .map(|quoted| quoted.into_owned())
+            .map_err(|_| format!("Cannot quote {}", path.display()))
+    };
+    let script = format!(
+        "#!/bin/sh\n# Started by {} for the extension {}.\nexec {} native-host run --socket {} --token-file {} \"$@\"\n",
+        name,
+        extension_id,
+        quote(&std::env::current_exe()?)?,
+        quote(&socket)?,
+        quote(&token_file)?
+    );
+    write(&launcher, 0o700, &script)?;
+
+    let dir = manifest_dir(browser)?;
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.json", HOST_NAME));
+    std::fs::write(&path, manifest)?;
+    message!(
+        "Installed the native-messaging host for {} in {}.",
+        name,
+        path.display()
+    );
+
+    Ok(())
+}
+
+/// Install the native-messaging host for a browser. Only supported on Unix.
+#[cfg(not(unix))]
+pub fn install_native_host(
+    _browser: Browser,
+    _extension_id: String,
+    _socket: Option<PathBuf>,
+    _print: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Err("The native-messaging host is only supported on Unix".into())
+}
+
+/// Answer the messages of the browser extension until the browser closes stdin.
+///
+/// The host holds no secrets itself: it calls the `match`, `add` and `update` methods of
+/// a running `serve` with its own token, so the vault only has to be unlocked once.
+///
+/// # Arguments
+///
+/// * `socket` - The socket `serve` listens on. Defaults to `api.sock` in the runtime
+///   directory, or in the configuration directory if there is none.
+/// * `token_file` - The file with the token of the host.
+///
+/// # Returns
+///
+/// A `Result` containing `()` or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the token cannot be read or the browser sends a message
+/// that is not framed JSON.
+#[cfg(unix)]
+pub fn run_native_host(
+    socket: Option<PathBuf>,
+    token_file: PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::utils::api::ApiClient;
+    use crate::utils::native_messaging::{read_message, write_message};
+    use crate::utils::socket as unix_socket;
+
+    let token = std::fs::read_to_string(&token_file)
+        .map_err(|e| format!("Cannot read the token {}: {}", token_file.display(), e))?;
+    let socket = match socket {
+        Some(path) => path,
+        None => unix_socket::default_path("api.sock")?,
+    };
+    let client = ApiClient::new(socket, SecretString::new(token.trim().to_string()));
+
+    let mut stdin = std::io::stdin().lock();
+    let mut stdout = std::io::stdout().lock();
+    while let Some(message) = read_message(&mut stdin)? {
+        let response = handle(|method, params| client.call(method, params), message);
+        write_message(&mut stdout, &response)?;
+    }
+
+    Ok(())
+}
+
+/// Answer the messages of the browser extension. Only supported on Unix.
+#[cfg(not(unix))]
+pub fn run_native_host(
+    _socket: Option<PathBuf>,
+    _token_file: PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Err("The native-messaging host is only supported on Unix".into())
+}
+
+/// Answer one message of the extension. The `id` of the message, if any, is sent back so
+/// the extension can match the response.
+///
+/// # Arguments
+///
+/// * `call` - Calls a method of the API.
+/// * `message` - The message of the extension.
+///
+/// # Returns
+///
+/// The response, with `ok` set to whether the message succeeded.
+fn handle(
+    call: impl Fn(&str, Value) -> Result<Value, Box<dyn std::error::Error>>,
+    message: Value,
+) -> Value {
+    let id = message.get("id").cloned();
+    let result = serde_json::from_value::<HostRequest>(message)
+        .map_err(|e| CliError::InvalidInput(format!("Invalid message: {}", e)).into())
+        .and_then(|request| match request {
+            HostRequest::GetLogins { origin } => get_logins(&call, &origin),
+            HostRequest::SaveLogin {
+                origin,
+                username,
+                password,
+            } => save_login(&call, &origin, username, password),
+        });
+
+    let mut response = match result {
+        Ok(mut response) => {
+            response["ok"] = json!(true);
+            response
+        }
+        Err(e) => json!({ "ok": false, "error": e.to_string() }),
+    };
+    if let Some(id) = id {
+        response["id"] = id;
+    }
+    response
+}
+
+type HostResult = Result<Value, Box<dyn std::error::Error>>;
+
+/// The logins for a page, with only the fields needed to fill them in.
+fn get_logins(call: &impl Fn(&str, Value) -> HostResult, origin: &str) -> HostResult {
+    let logins = call("match", json!({ "url": origin }))?
+        .as_array()
+        .ok_or("Unexpected result of match")?
+        .iter()
+        .map(|login| {
+            json!({
+                "uuid": login["uuid"],
+                "service": login["service"],
+                "username": login["username"],
+                "password": login["password"],
+                "url": login["url"],
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(json!({ "logins": logins }))
+}
+
+/// Save a login submitted on a page.
+///
+/// A matching login with the same username gets the new password, otherwise a new entry
+/// named after the host of the page is added.
+fn save_login(
+    call: &impl Fn(&str, Value) -> HostResult,
+    origin: &str,
+    username: String,
+    password: SecretString,
+) -> HostResult {
+    use crate::utils::domain;
+
+    let host = domain::page_host(origin).ok_or_else(|| {
+        CliError::InvalidInput(format!(
+            "Logins are only saved for http and https pages, not {}",
+            origin
+        ))
+    })?;
+    if password.is_empty() {
+        return Err(CliError::InvalidInput("The password must not be empty".to_string()).into());
+    }
+
+    let logins = call("match", json!({ "url": origin }))?;
+    let existing = logins
+        .as_array()
+        .ok_or("Unexpected result of match")?
+        .iter()
+        .find(|login| login["username"] == username.as_str());
+
+    let (saved, entry) = match existing {
+        Some(login) if login["password"] == password.expose_secret() => {
+            ("unchanged", login["uuid"].clone())
+        }
+        Some(login) => {
+            let params = json!({ "entry": login["uuid"], "password": password });
+            ("updated", call("update", params)?["uuid"].take())
+        }
+        None => {
+            let params = json!({
+                "service": host,
+                "username": username,
+                "password": password,
+                "url": origin,
+            });
+            ("added", call("add", params)?["uuid"].take())
+        }
+    };
+    Ok(json!({ "saved": saved, "uuid": entry }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_manifest() {
+        let launcher = Path::new("/home/alice/native-host-chrome.sh");
+        let id = "abcdefghijklmnopabcdefghijklmnop";
+        let chrome = manifest(Browser::Chrome, launcher, id).unwrap();
+        assert_eq!(chrome["name"], HOST_NAME);
+        assert_eq!(chrome["type"], "stdio");
+        assert_eq!(chrome["path"], "/home/alice/native-host-chrome.sh");
+        assert_eq!(
+            chrome["allowed_origins"],
+            json!(["chrome-extension://abcdefghijklmnopabcdefghijklmnop/"])
+        );
+        assert!(manifest(Browser::Chromium, launcher, "logins@example.com").is_err());
+
+        let firefox = manifest(Browser::Firefox, launcher, "logins@example.com").unwrap();
+        assert_eq!(firefox["allowed_extensions"], json!(["logins@example.com"]));
+        assert!(firefox.get("allowed_origins").is_none());
+        assert!(manifest(Browser::Firefox, launcher, "").is_err());
+    }
+
+    #[test]
+    fn test_handle() {
+        let calls = RefCell::new(Vec::new());
+        let call = |method: &str, params: Value| -> HostResult {
+            calls
+                .borrow_mut()
+                .push((method.to_string(), params.clone()));
+            match method {
+                "match" => Ok(json!([{
+                    "uuid": "u1",
+                    "service": "Example",
+                    "username": "alice",
+                    "password": "old",
+                    "url": "https://example.com",
+                    "notes": "not for the browser",
+                }])),
+                _ => Ok(json!({ "uuid": "u2" })),
+            }
+        };
+
+        let response = handle(
+            call,
+            json!({ "id": 7, "action": "get-logins", "origin": "https://www.example.com" }),
+        );
+        assert_eq!(response["ok"], true);
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["logins"][0]["password"], "old");
+        assert!(response["logins"][0].get("notes").is_none());
+
+        let save = |username: &str, password: &str| {
+            handle(
+                call,
+                json!({
+                    "action": "save-login",
+                    "origin": "https://www.example.com",
+                    "username": username,
+                    "password": password,
+                }),
+            )
+        };
+        assert_eq!(save("alice", "old")["saved"], "unchanged");
+        assert_eq!(save("alice", "new")["saved"], "updated");
+        assert_eq!(
+            calls.borrow().last().unwrap(),
+            &(
+                "update".to_string(),
+                json!({ "entry": "u1", "password": "new" })
+            )
+        );
+        let response = save("bob", "secret");
+        assert_eq!(response["saved"], "added");
+        assert_eq!(response["uuid"], "u2");
+        assert_eq!(
+            calls.borrow().last().unwrap().1["service"],
+            "www.example.com"
+        );
+
+        let response = handle(call, json!({ "id": "a", "action": "delete" }));
+        assert_eq!(response["ok"], false);
+        assert_eq!(response["id"], "a");
+        let response = save("alice", "");
+        assert_eq!(response["ok"], false);
+        let response = handle(
+            call,
+            json!({ "action": "save-login", "origin": "file:///tmp", "password": "x" }),
+        );
+        assert!(response["error"].as_str().unwrap().contains("http"));
+    }
+}
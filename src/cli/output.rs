@@ -0,0 +1,400 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use clap::ValueEnum;
+use serde::Serialize;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// The widest a table column gets before its values are truncated.
+const MAX_COLUMN_WIDTH: usize = 40;
+/// What a secret is shown as unless it is revealed.
+pub const MASK: &str = "********";
+
+/// The output format of this process, set once from `--output`.
+static FORMAT: AtomicU8 = AtomicU8::new(OutputFormat::Table as u8);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[repr(u8)]
+pub enum OutputFormat {
+    /// Aligned columns for reading, with long values truncated.
+    #[default]
+    Table,
+    /// JSON with a stable schema per command.
+    Json,
+    /// YAML with the same schema as JSON.
+    Yaml,
+    /// Tab separated values with a header row.
+    Tsv,
+}
+
+impl OutputFormat {
+    /// Use this format for all output of this process.
+    pub fn apply(self) {
+        FORMAT.store(self as u8, Ordering::Relaxed);
+    }
+
+    /// Get the output format of this process.
+    ///
+    /// # Returns
+    ///
+    /// The format set with `apply`, `Table` by default.
+    pub fn current() -> Self {
+        match FORMAT.load(Ordering::Relaxed) {
+            x if x == Self::Json as u8 => Self::Json,
+            x if x == Self::Yaml as u8 => Self::Yaml,
+            x if x == Self::Tsv as u8 => Self::Tsv,
+            _ => Self::Table,
+        }
+    }
+}
+
+/// A record that can be shown as a row of a table.
+pub trait Tabular {
+    /// The names and values of the columns. Secrets are masked unless they were revealed.
+    fn columns(&self) -> Vec<(String, String)>;
+}
+
+/// Print a human readable message.
+///
+/// Messages go to stdout for tables and to stderr for the other formats, so stdout only
+/// carries data that tools can parse.
+///
+/// # Arguments
+///
+/// * `message` - The message to print.
+pub fn print_message(message: &str) {
+    if OutputFormat::current() == OutputFormat::Table {
+        println!("{}", message);
+    } else {
+        eprintln!("{}", message);
+    }
+}
+
+/// Print a human readable message with `format!` arguments, see `print_message`.
+macro_rules! message {
+    ($($arg:tt)*) => {
+        $crate::cli::output::print_message(&format!($($arg)*))
+    };
+}
+pub(crate) use message;
+
+/// Print a list of records in the current output format.
+///
+/// # Arguments
+///
+/// * `records` - The records to print.
+/// * `empty` - The message printed instead of an empty table.
+///
+/// # Returns
+///
+/// A `Result` containing `()` or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the records cannot be serialized.
+pub fn print_list<T: Serialize + Tabular>(
+    records: &[T],
+    empty: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    print!("{}", render_list(OutputFormat::current(), records, empty)?);
+    Ok(())
+}
+
+/// Print a single record in the current output format.
+///
+/// # Arguments
+///
+/// * `record` - The record to print.
+///
+/// # Returns
+///
+/// A `Result` containing `()` or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the record cannot be serialized.
+pub fn print_record<T: Serialize + Tabular>(record: &T) -> Result<(), Box<dyn std::error::Error>> {
+    print!("{}", render_record(OutputFormat::current(), record)?);
+    Ok(())
+}
+
+/// Print the result of a command that changed something: a message for tables, the record
+/// in the other formats.
+///
+/// # Arguments
+///
+/// * `record` - The result for tools.
+/// * `message` - The result for people.
+///
+/// # Returns
+///
+/// A `Result` containing `()` or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the record cannot be serialized.
+pub fn print_result<T: Serialize + Tabular>(
+    record: &T,
+    message: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    print!(
+        "{}",
+        render_result(OutputFormat::current(), record, message)?
+    );
+    Ok(())
+}
+
+/// Render the result of a command, see `print_result`.
+fn render_result<T: Serialize + Tabular>(
+    format: OutputFormat,
+    record: &T,
+    message: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    match format {
+        OutputFormat::Table => Ok(format!("{}\n", message)),
+        format => render_record(format, record),
+    }
+}
+
+/// Render a list of records. JSON and YAML lists are arrays of objects.
+fn render_list<T: Serialize + Tabular>(
+    format: OutputFormat,
+    records: &[T],
+    empty: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let rows = records.iter().map(Tabular::columns).collect::<Vec<_>>();
+    match format {
+        OutputFormat::Table if rows.is_empty() => Ok(format!("{}\n", empty)),
+        OutputFormat::Table => Ok(table(&rows)),
+        OutputFormat::Tsv => Ok(tsv(&rows)),
+        OutputFormat::Json => Ok(format!("{}\n", serde_json::to_string_pretty(records)?)),
+        OutputFormat::Yaml => Ok(serde_norway::to_string(records)?),
+    }
+}
+
+/// Render a single record. Tables show one line per column.
+fn render_record<T: Serialize + Tabular>(
+    format: OutputFormat,
+    record: &T,
+) -> Result<String, Box<dyn std::error::Error>> {
+    match format {
+        OutputFormat::Table => {
+            let columns = record.columns();
+            let width = columns
+                .iter()
+                .map(|(name, _)| name.width())
+                .max()
+                .unwrap_or(0);
+            let mut output = String::new();
+            for (name, value) in columns {
+                let value = value
+                    .replace('\t', " ")
+                    .replace('\n', &format!("\n{}", " ".repeat(width + 2)));
+                let line = format!("{}{}  {}", name, pad(&name, width), value);
+                output.push_str(line.trim_end());
+                output.push('\n');
+            }
+            Ok(output)
+        }
+        OutputFormat::Tsv => Ok(tsv(&[record.columns()])),
+        OutputFormat::Json => Ok(format!("{}\n", serde_json::to_string_pretty(record)?)),
+        OutputFormat::Yaml => Ok(serde_norway::to_string(record)?),
+    }
+}
+
+/// Render rows as aligned columns under a header.
+fn table(rows: &[Vec<(String, String)>]) -> String {
+    let header = rows[0].iter().map(|(name, _)| name.clone()).collect();
+    let mut lines: Vec<Vec<String>> = vec![header];
+    for row in rows {
+        lines.push(row.iter().map(|(_, value)| truncate(value)).collect());
+    }
+
+    let widths = (0..lines[0].len())
+        .map(|column| {
+            lines
+                .iter()
+                .map(|line| line[column].width())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+
+    let mut output = String::new();
+    for line in lines {
+        let cells = line
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{}{}", cell, pad(cell, *width)))
+            .collect::<Vec<_>>();
+        output.push_str(cells.join("  ").trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+/// Render rows as tab separated values under a header.
+///
+/// Backslashes, tabs and line breaks in values are escaped as `\\`, `\t`, `\n` and `\r`.
+fn tsv(rows: &[Vec<(String, String)>]) -> String {
+    let mut output = String::new();
+    if let Some(first) = rows.first() {
+        let header = first
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        output.push_str(&header.join("\t"));
+        output.push('\n');
+    }
+    for row in rows {
+        let values = row
+            .iter()
+            .map(|(_, value)| {
+                value
+                    .replace('\\', "\\\\")
+                    .replace('\t', "\\t")
+                    .replace('\n', "\\n")
+                    .replace('\r', "\\r")
+            })
+            .collect::<Vec<_>>();
+        output.push_str(&values.join("\t"));
+        output.push('\n');
+    }
+    output
+}
+
+/// Shorten a value to the maximum column width and put it on one line.
+fn truncate(value: &str) -> String {
+    let value = value.replace(['\n', '\r', '\t'], " ");
+    if value.width() <= MAX_COLUMN_WIDTH {
+        return value;
+    }
+
+    let mut truncated = String::new();
+    let mut width = 0;
+    for c in value.chars() {
+        width += c.width().unwrap_or(0);
+        if width > MAX_COLUMN_WIDTH - 1 {
+            break;
+        }
+        truncated.push(c);
+    }
+    truncated.push('…');
+    truncated
+}
+
+/// The spaces that pad a value to the given display width.
+fn pad(value: &str, width: usize) -> String {
+    " ".repeat(width.saturating_sub(value.width()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Login {
+        service: String,
+        password: Option<String>,
+    }
+
+    impl Tabular for Login {
+        fn columns(&self) -> Vec<(String, String)> {
+            vec![
+                ("Service".to_string(), self.service.clone()),
+                (
+                    "Password".to_string(),
+                    self.password.clone().unwrap_or_else(|| MASK.to_string()),
+                ),
+            ]
+        }
+    }
+
+    fn create_logins() -> Vec<Login> {
+        vec![
+            Login {
+                service: "Mail".to_string(),
+                password: None,
+            },
+            Login {
+                service: "Bank\tof\nTomorrow".to_string(),
+                password: Some("secret".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_table() {
+        let output = render_list(OutputFormat::Table, &create_logins(), "None").unwrap();
+
+        assert_eq!(
+            output,
+            "Service           Password\nMail              ********\nBank of Tomorrow  secret\n"
+        );
+        assert_eq!(
+            render_list::<Login>(OutputFormat::Table, &[], "None").unwrap(),
+            "None\n"
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        let long = "x".repeat(60);
+        let truncated = truncate(&long);
+
+        assert_eq!(truncated.width(), MAX_COLUMN_WIDTH);
+        assert!(truncated.ends_with('…'));
+        assert_eq!(truncate("日本語"), "日本語");
+    }
+
+    #[test]
+    fn test_tsv() {
+        let output = render_list(OutputFormat::Tsv, &create_logins(), "None").unwrap();
+
+        assert_eq!(
+            output,
+            "Service\tPassword\nMail\t********\nBank\\tof\\nTomorrow\tsecret\n"
+        );
+    }
+
+    #[test]
+    fn test_json_and_yaml() {
+        let logins = create_logins();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render_list(OutputFormat::Json, &logins, "").unwrap()).unwrap();
+        assert_eq!(json[0]["service"], "Mail");
+        assert!(json[0]["password"].is_null());
+        assert_eq!(json[1]["password"], "secret");
+
+        let yaml = render_record(OutputFormat::Yaml, &logins[0]).unwrap();
+        assert_eq!(yaml, "service: Mail\npassword: null\n");
+    }
+
+    #[test]
+    fn test_record_table() {
+        let output = render_record(OutputFormat::Table, &create_logins()[1]).unwrap();
+
+        assert_eq!(
+            output,
+            "Service   Bank of\n          Tomorrow\nPassword  secret\n"
+        );
+    }
+
+    #[test]
+    fn test_result() {
+        let login = &create_logins()[0];
+
+        assert_eq!(
+            render_result(OutputFormat::Table, login, "Added Mail.").unwrap(),
+            "Added Mail.\n"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&render_result(OutputFormat::Json, login, "Added Mail.").unwrap())
+                .unwrap();
+        assert_eq!(json["service"], "Mail");
+        assert_eq!(
+            render_result(OutputFormat::Tsv, login, "Added Mail.").unwrap(),
+            "Service\tPassword\nMail\t********\n"
+        );
+    }
+}
@@ -1,100 +1,499 @@
-use std::collections::HashMap;
-
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{Local, Utc};
 use csv::Writer;
-use inquire::{validator::Validation, Confirm, Password, Text};
+use inquire::{validator::Validation, Confirm, MultiSelect, Password, Select, Text};
 use log::info;
 use ring::rand::{SecureRandom, SystemRandom};
 use serde::{Deserialize, Serialize};
 
-use crate::prelude::{Config, Database, Encryption, PasswordEntry};
+use crate::cli::input::{self, Ask};
+use crate::cli::output::{self, message, Tabular, MASK};
+use crate::cli::{serve, shell, tui};
+use crate::cli::{ConflictStrategy, CopyField, ExportFormat, ImportFormat, InputArgs};
+use crate::prelude::{
+    BackupManager, CliError, Config, CustomField, Database, DatabaseError, Encryption,
+    ExportPayload, PasswordEntry, RemoteCredentials, SecretString, WebDavRemote,
+};
+use crate::utils::clipboard::{self, Clipboard};
+use crate::utils::errors::EXIT_FAILURE;
+use crate::utils::export;
+use crate::utils::exporters::{self, emergency_sheet, keepass_xml};
+use crate::utils::git_credential::GitCredential;
+use crate::utils::importers::plan::ImportPlan;
+use crate::utils::importers::{bitwarden, csv_mapping, kdbx, ImportResult};
+use crate::utils::merge::{self, Conflict, MergeChanges, VaultState};
+use crate::utils::reference::{self, Masker, Reference};
+#[cfg(unix)]
+use crate::utils::socket;
+#[cfg(unix)]
+use crate::utils::ssh_agent::{self, SshAgent};
+use crate::utils::ssh_key::{self, SshKey};
+use crate::utils::totp;
 
 #[derive(Debug)]
 pub struct PasswordManager {
     pub length: usize,
     pub database: Database,
     pub encryption: Encryption,
+    pub backups: BackupManager,
+    master_password: SecretString,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct PasswordExportImport {
     service: String,
     username: String,
-    password: String,
+    password: SecretString,
+    url: String,
+    notes: String,
+    created_at: String,
+    updated_at: String,
+}
+
+/// An entry as listed by `list` and `search`.
+#[derive(Serialize, Debug)]
+pub(crate) struct EntrySummary {
+    id: Option<i32>,
+    uuid: String,
+    service: String,
+    username: String,
+    url: String,
+    folder: String,
+    tags: Vec<String>,
+    notes: String,
+    updated_at: String,
+}
+
+/// An entry as shown by `show`. Secrets are `None` unless they were revealed.
+#[derive(Serialize, Debug)]
+pub(crate) struct EntryDetails {
+    id: Option<i32>,
+    uuid: String,
+    service: String,
+    username: String,
+    password: Option<SecretString>,
     url: String,
+    folder: String,
+    tags: Vec<String>,
     notes: String,
+    totp: Option<SecretString>,
+    custom_fields: Vec<FieldDetails>,
     created_at: String,
     updated_at: String,
+    source: String,
+}
+
+/// A custom field as shown by `show`. Hidden values are `None` unless they were revealed.
+#[derive(Serialize, Debug)]
+struct FieldDetails {
+    name: String,
+    value: Option<SecretString>,
+    hidden: bool,
+}
+
+/// A freshly generated password.
+#[derive(Serialize, Debug)]
+struct GeneratedPassword {
+    password: SecretString,
+}
+
+/// A backup as listed or created by `backup`.
+#[derive(Serialize, Debug)]
+struct BackupSummary {
+    name: String,
+    path: PathBuf,
+}
+
+/// The result of `verify`.
+#[derive(Serialize, Debug)]
+struct VerifySummary {
+    checked: usize,
+    corrupt_entries: Vec<String>,
+    integrity_errors: Vec<String>,
+    intact: bool,
+}
+
+/// An entry changed by `add`, `update` or `remove`.
+#[derive(Serialize, Debug)]
+struct EntryChange {
+    /// `added`, `updated` or `removed`.
+    action: &'static str,
+    id: Option<i32>,
+    uuid: String,
+    service: String,
+}
+
+/// A value put on the clipboard by `copy`.
+#[derive(Serialize, Debug)]
+struct CopiedValue {
+    id: Option<i32>,
+    uuid: String,
+    service: String,
+    field: &'static str,
+    /// Seconds until the clipboard is cleared, 0 if it is kept.
+    clear_after: u64,
+    /// Whether the clipboard is cleared even if something else was copied meanwhile.
+    clears_unconditionally: bool,
+}
+
+/// The result of `export` and `emergency-sheet`.
+#[derive(Serialize, Debug)]
+struct ExportSummary {
+    path: String,
+    format: String,
+    entries: usize,
+    /// The vault fingerprint printed on an emergency sheet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fingerprint: Option<String>,
+}
+
+/// The result of `import`.
+#[derive(Serialize, Debug)]
+struct ImportSummary {
+    path: String,
+    dry_run: bool,
+    added: usize,
+    updated: usize,
+    skipped: usize,
+    errors: usize,
+}
+
+/// The result of `merge` and `sync`.
+#[derive(Serialize, Debug)]
+struct MergeSummary {
+    /// The path of the other vault or the URL of the remote.
+    vault: String,
+    /// Whether the remote vault was created by this sync.
+    created: bool,
+    /// Whether the other vault was written.
+    uploaded: bool,
+    /// The changes made to this vault.
+    pulled: MergeChanges,
+    /// The changes made to the other vault.
+    pushed: MergeChanges,
+    conflicts: Vec<Conflict>,
+}
+
+impl From<&PasswordEntry> for EntrySummary {
+    fn from(entry: &PasswordEntry) -> Self {
+        Self {
+            id: entry.id,
+            uuid: entry.uuid.clone(),
+            service: entry.service.clone(),
+            username: entry.username.clone(),
+            url: entry.url.clone(),
+            folder: entry.folder.clone(),
+            tags: entry.tags.clone(),
+            notes: entry.notes.clone(),
+            updated_at: entry.updated_at.clone(),
+        }
+    }
+}
+
+impl From<PathBuf> for BackupSummary {
+    fn from(path: PathBuf) -> Self {
+        Self {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path,
+        }
+    }
+}
+
+impl EntryDetails {
+    /// Collect the details of an entry, keeping secrets only if they are revealed.
+    pub(crate) fn new(entry: PasswordEntry, reveal: bool) -> Self {
+        let secret = |value: SecretString| (reveal || value.is_empty()).then_some(value);
+        Self {
+            id: entry.id,
+            uuid: entry.uuid,
+            service: entry.service,
+            username: entry.username,
+            password: secret(entry.password),
+            url: entry.url,
+            folder: entry.folder,
+            tags: entry.tags,
+            notes: entry.notes,
+            totp: secret(entry.totp),
+            custom_fields: entry
+                .custom_fields
+                .into_iter()
+                .map(|field| FieldDetails {
+                    name: field.name,
+                    value: (reveal || !field.hidden).then_some(field.value),
+                    hidden: field.hidden,
+                })
+                .collect(),
+            created_at: entry.created_at,
+            updated_at: entry.updated_at,
+            source: entry.source,
+        }
+    }
+}
+
+/// Show a secret, or the mask if it was not revealed.
+fn masked(secret: &Option<SecretString>) -> String {
+    secret.as_ref().map_or(MASK.to_string(), |secret| {
+        secret.expose_secret().to_string()
+    })
+}
+
+/// Show an optional ID, or nothing.
+fn format_id(id: Option<i32>) -> String {
+    id.map(|id| id.to_string()).unwrap_or_default()
+}
+
+impl Tabular for EntrySummary {
+    fn columns(&self) -> Vec<(String, String)> {
+        vec![
+            ("ID".to_string(), format_id(self.id)),
+            ("Service".to_string(), self.service.clone()),
+            ("Username".to_string(), self.username.clone()),
+            ("URL".to_string(), self.url.clone()),
+            ("Folder".to_string(), self.folder.clone()),
+            ("Tags".to_string(), self.tags.join(", ")),
+            ("Notes".to_string(), self.notes.clone()),
+            ("Updated".to_string(), self.updated_at.clone()),
+        ]
+    }
+}
+
+impl Tabular for EntryDetails {
+    fn columns(&self) -> Vec<(String, String)> {
+        let mut columns = vec![
+            ("ID".to_string(), format_id(self.id)),
+            ("UUID".to_string(), self.uuid.clone()),
+            ("Service".to_string(), self.service.clone()),
+            ("Username".to_string(), self.username.clone()),
+            ("Password".to_string(), masked(&self.password)),
+            ("URL".to_string(), self.url.clone()),
+            ("Folder".to_string(), self.folder.clone()),
+            ("Tags".to_string(), self.tags.join(", ")),
+            ("Notes".to_string(), self.notes.clone()),
+            ("TOTP".to_string(), masked(&self.totp)),
+        ];
+        for field in &self.custom_fields {
+            columns.push((field.name.clone(), masked(&field.value)));
+        }
+        columns.extend([
+            ("Created".to_string(), self.created_at.clone()),
+            ("Updated".to_string(), self.updated_at.clone()),
+            ("Source".to_string(), self.source.clone()),
+        ]);
+        columns
+    }
+}
+
+impl Tabular for GeneratedPassword {
+    fn columns(&self) -> Vec<(String, String)> {
+        vec![(
+            "Password".to_string(),
+            self.password.expose_secret().to_string(),
+        )]
+    }
+}
+
+impl Tabular for BackupSummary {
+    fn columns(&self) -> Vec<(String, String)> {
+        vec![
+            ("Name".to_string(), self.name.clone()),
+            ("Path".to_string(), self.path.display().to_string()),
+        ]
+    }
+}
+
+impl Tabular for VerifySummary {
+    fn columns(&self) -> Vec<(String, String)> {
+        vec![
+            ("Checked".to_string(), self.checked.to_string()),
+            ("Corrupt".to_string(), self.corrupt_entries.join("\n")),
+            ("Integrity".to_string(), self.integrity_errors.join("\n")),
+            (
+                "Intact".to_string(),
+                if self.intact { "yes" } else { "no" }.to_string(),
+            ),
+        ]
+    }
+}
+
+impl Tabular for EntryChange {
+    fn columns(&self) -> Vec<(String, String)> {
+        vec![
+            ("Action".to_string(), self.action.to_string()),
+            ("ID".to_string(), format_id(self.id)),
+            ("UUID".to_string(), self.uuid.clone()),
+            ("Service".to_string(), self.service.clone()),
+        ]
+    }
+}
+
+impl Tabular for CopiedValue {
+    fn columns(&self) -> Vec<(String, String)> {
+        vec![
+            ("ID".to_string(), format_id(self.id)),
+            ("UUID".to_string(), self.uuid.clone()),
+            ("Service".to_string(), self.service.clone()),
+            ("Field".to_string(), self.field.to_string()),
+            ("Clear After".to_string(), self.clear_after.to_string()),
+            (
+                "Unconditionally".to_string(),
+                if self.clears_unconditionally {
+                    "yes"
+                } else {
+                    "no"
+                }
+                .to_string(),
+            ),
+        ]
+    }
+}
+
+impl Tabular for ExportSummary {
+    fn columns(&self) -> Vec<(String, String)> {
+        vec![
+            ("Path".to_string(), self.path.clone()),
+            ("Format".to_string(), self.format.clone()),
+            ("Entries".to_string(), self.entries.to_string()),
+            (
+                "Fingerprint".to_string(),
+                self.fingerprint.clone().unwrap_or_default(),
+            ),
+        ]
+    }
+}
+
+impl Tabular for ImportSummary {
+    fn columns(&self) -> Vec<(String, String)> {
+        vec![
+            ("Path".to_string(), self.path.clone()),
+            (
+                "Dry Run".to_string(),
+                if self.dry_run { "yes" } else { "no" }.to_string(),
+            ),
+            ("Added".to_string(), self.added.to_string()),
+            ("Updated".to_string(), self.updated.to_string()),
+            ("Skipped".to_string(), self.skipped.to_string()),
+            ("Errors".to_string(), self.errors.to_string()),
+        ]
+    }
+}
+
+impl Tabular for MergeSummary {
+    fn columns(&self) -> Vec<(String, String)> {
+        vec![
+            ("Vault".to_string(), self.vault.clone()),
+            (
+                "Created".to_string(),
+                if self.created { "yes" } else { "no" }.to_string(),
+            ),
+            (
+                "Uploaded".to_string(),
+                if self.uploaded { "yes" } else { "no" }.to_string(),
+            ),
+            ("Added Here".to_string(), self.pulled.added.to_string()),
+            ("Updated Here".to_string(), self.pulled.updated.to_string()),
+            ("Deleted Here".to_string(), self.pulled.deleted.to_string()),
+            ("Added There".to_string(), self.pushed.added.to_string()),
+            ("Updated There".to_string(), self.pushed.updated.to_string()),
+            ("Deleted There".to_string(), self.pushed.deleted.to_string()),
+            ("Conflicts".to_string(), self.conflicts.len().to_string()),
+        ]
+    }
 }
 
 impl PasswordManager {
     /// Create a new `PasswordManager` instance.
     ///
+    /// The master password is read from the source given on the command line, otherwise
+    /// it is asked for.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The global input options.
+    ///
     /// # Returns
     ///
     /// A `Result` containing the `PasswordManager` instance or an error.
     ///
     /// # Errors
     ///
-    /// An error will be returned if the master password is invalid.
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+    /// A `CliError::Locked` will be returned if the master password is invalid or cannot be
+    /// asked for because of `--no-input`.
+    pub fn new(input: &InputArgs) -> Result<Self, Box<dyn std::error::Error>> {
         let config = Config::load()?;
         let config_dir = Config::get_config_dir()?;
         let master_file = config_dir.join("master.key");
+        let is_new_vault = !master_file.exists();
+        let given_password = input.master_password()?;
+        if given_password.is_none() && !input::interactive() {
+            return Err(CliError::Locked(
+                "Pass the master password with --password-stdin, --password-fd or --password-env"
+                    .to_string(),
+            )
+            .into());
+        }
 
-        let (salt, master_password) = if !master_file.exists() {
-            let rng = SystemRandom::new();
-            let mut salt = [0u8; 16];
-            rng.fill(&mut salt).unwrap();
-
-            let password = if Confirm::new("Do you want to generate a password? ")
-                .with_default(true)
-                .prompt()?
-            {
-                Self::generate_password(Some(16))?
+        let (salt, master_password) = if is_new_vault {
+            let password = if let Some(password) = given_password {
+                password
             } else {
-                Password::new("Please enter your master password:").prompt()?
-            };
+                let password = if Confirm::new("Do you want to generate a password? ")
+                    .with_default(true)
+                    .ask()?
+                {
+                    Self::generate_password(Some(16))?
+                } else {
+                    SecretString::new(Password::new("Please enter your master password:").ask()?)
+                };
 
-            println!(
-                "The master password is: {}. Please take it secure!",
+                message!(
+                    "The master password is: {}. Please take it secure!",
+                    password.expose_secret()
+                );
                 password
-            );
-
-            let encryption = Encryption::new(&password, &salt);
-            let verification_data = encryption.encrypt(&password).unwrap();
+            };
 
-            let mut file_content = Vec::new();
-            file_content.extend_from_slice(&salt);
-            file_content.extend_from_slice(&verification_data);
-            std::fs::write(&master_file, file_content)?;
+            let salt = Encryption::create_master_file(&master_file, &password)?;
 
             (salt, password)
         } else {
-            let file_content = std::fs::read(&master_file)?;
-            let salt: [u8; 16] = file_content[..16].try_into()?;
-            let verification_data = &file_content[16..];
-
-            let password = Password::new("Please enter your master password:")
-                .without_confirmation()
-                .prompt()?;
-
-            let encryption = Encryption::new(&password, &salt);
+            let password = match given_password {
+                Some(password) => password,
+                None => SecretString::new(
+                    Password::new("Please enter your master password:")
+                        .without_confirmation()
+                        .ask()?,
+                ),
+            };
 
-            if let Ok(decrypted) = encryption.decrypt(verification_data) {
-                if decrypted != password {
-                    return Err("Invalid master password".into());
-                }
-            } else {
-                return Err("Invalid master password".into());
-            }
+            let salt = Encryption::unlock_master_file(&master_file, &password)
+                .map_err(|e| CliError::Locked(e.to_string()))?;
 
             (salt, password)
         };
 
+        let backups = BackupManager::new(&config)?;
+        let database = Database::open(config.get_db_path()?, &master_password, &salt)?;
+        if !is_new_vault && database.has_pending_migrations()? {
+            info!("Backing up the vault before migrating it");
+            backups.create(&database, "migration")?;
+        }
+        database.migrate()?;
+
         Ok(Self {
             length: 16,
-            database: Database::new(config.get_db_path()?, &master_password, &salt)?,
+            database,
             encryption: Encryption::new(&master_password, &salt),
+            backups,
+            master_password,
         })
     }
 
@@ -106,18 +505,20 @@ impl PasswordManager {
     ///
     /// # Returns
     ///
-    /// A `Result` containing the generated password as a `String`.
+    /// A `Result` containing the generated password as a `SecretString`.
     ///
     /// # Errors
     ///
     /// An error will be returned if the password cannot be generated.
-    pub fn generate_password(length: Option<usize>) -> Result<String, Box<dyn std::error::Error>> {
+    pub fn generate_password(
+        length: Option<usize>,
+    ) -> Result<SecretString, Box<dyn std::error::Error>> {
         let charset: &[u8] =
             b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789!@#$%^&*()-_=+";
 
         let password_length = if let Some(length) = length {
             if !Self::is_valid_password_length(&length.to_string()) {
-                return Err("Invalid password length".into());
+                return Err(CliError::InvalidInput("Invalid password length".to_string()).into());
             }
             length
         } else {
@@ -137,11 +538,11 @@ impl PasswordManager {
             };
             let length = Text::new("Please enter your password length:")
                 .with_validator(validator)
-                .prompt()?;
+                .ask()?;
             if let Ok(length) = length.parse::<usize>() {
                 length
             } else {
-                return Err("Invalid password length".into());
+                return Err(CliError::InvalidInput("Invalid password length".to_string()).into());
             }
         };
 
@@ -154,8 +555,9 @@ impl PasswordManager {
             let index = byte[0] as usize % charset.len();
             password.push(charset[index] as char);
         }
+        let password = SecretString::new(password);
 
-        if Self::is_valid_password(password.as_str()) {
+        if Self::is_valid_password(password.expose_secret()) {
             Ok(password)
         } else {
             Self::generate_password(length)
@@ -209,6 +611,28 @@ impl PasswordManager {
         }
     }
 
+    /// Generate a new password and print it.
+    ///
+    /// # Arguments
+    ///
+    /// * `length` - The length of the password to generate.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the password cannot be generated.
+    pub fn print_generated_password(
+        length: Option<usize>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let password = Self::generate_password(length)?;
+        output::print_record(&GeneratedPassword { password })?;
+
+        Ok(())
+    }
+
     /// Add a new password to the password manager.
     ///
     /// # Arguments
@@ -230,21 +654,20 @@ impl PasswordManager {
         &self,
         service: Option<String>,
         username: Option<String>,
-        password: Option<String>,
+        password: Option<SecretString>,
         url: Option<String>,
         notes: Option<String>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let input_data = Self::get_user_data(service, username, password, url, notes)?;
-
-        let entry = PasswordEntry::new(
-            input_data["service"].clone(),
-            input_data["username"].clone(),
-            input_data["password"].clone(),
-            input_data["url"].clone(),
-            input_data["notes"].clone(),
-        )?;
+        let entry = Self::get_user_data(service, username, password, url, notes)?;
 
         self.database.create(&entry)?;
+        let change = EntryChange {
+            action: "added",
+            id: self.database.find_by_uuid(&entry.uuid)?,
+            uuid: entry.uuid,
+            service: entry.service,
+        };
+        output::print_result(&change, &format!("Added {}.", change.service))?;
 
         Ok(())
     }
@@ -261,7 +684,7 @@ impl PasswordManager {
     ///
     /// # Returns
     ///
-    /// A `Result` containing a `HashMap` of the user input or an error.
+    /// A `Result` containing a `PasswordEntry` built from the user input or an error.
     ///
     /// # Errors
     ///
@@ -269,60 +692,51 @@ impl PasswordManager {
     fn get_user_data(
         service: Option<String>,
         username: Option<String>,
-        password: Option<String>,
+        password: Option<SecretString>,
         url: Option<String>,
         notes: Option<String>,
-    ) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
-        let mut input = HashMap::new();
+    ) -> Result<PasswordEntry, Box<dyn std::error::Error>> {
         let service = if let Some(service) = service {
             service
         } else {
-            Text::new("Please enter the service name:").prompt()?
+            Text::new("Please enter the service name:").ask()?
         };
 
         let username = if let Some(username) = username {
             username
         } else {
-            Text::new("Please enter the username:").prompt()?
+            Text::new("Please enter the username:").ask()?
         };
 
         let password = if let Some(password) = password {
             password
         } else if Confirm::new("Do you want to generate a password? (y/n)")
             .with_default(true)
-            .prompt()?
+            .ask()?
         {
             Self::generate_password(Some(16))?
         } else {
-            Password::new("Please enter the password:").prompt()?
+            SecretString::new(Password::new("Please enter the password:").ask()?)
         };
 
         let url = if let Some(url) = url {
             url
         } else {
-            Text::new("Please enter the URL:").prompt()?
+            Text::new("Please enter the URL:").ask()?
         };
 
-        let notes = if Confirm::new("Do you want to add notes? (y/n)")
+        let notes = if let Some(notes) = notes {
+            notes
+        } else if Confirm::new("Do you want to add notes? (y/n)")
             .with_default(false)
-            .prompt()?
+            .ask()?
         {
-            if let Some(notes) = notes {
-                notes
-            } else {
-                Text::new("Please enter the notes:").prompt()?
-            }
+            Text::new("Please enter the notes:").ask()?
         } else {
             "".to_string()
         };
 
-        input.insert("service".to_string(), service);
-        input.insert("username".to_string(), username);
-        input.insert("password".to_string(), password);
-        input.insert("url".to_string(), url);
-        input.insert("notes".to_string(), notes);
-
-        Ok(input)
+        PasswordEntry::new(service, username, password, url, notes)
     }
 
     /// List all passwords in the password manager.
@@ -335,20 +749,15 @@ impl PasswordManager {
     ///
     /// An error will be returned if the passwords cannot be listed.
     pub fn list_passwords(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let passwords = self.database.read()?;
-
-        if passwords.is_empty() {
-            println!("No passwords found.");
-            return Ok(());
+        let mut summaries = Vec::new();
+        for entry in self.database.read_entries()? {
+            match entry {
+                Ok(entry) => summaries.push(EntrySummary::from(&entry)),
+                Err(e) => eprintln!("Warning: {}", e),
+            }
         }
 
-        println!("ID\tService\tUsername\tURL\tNotes");
-        for password in passwords {
-            println!(
-                "{:?}\t{}\t{}\t{}\t{}",
-                password.id, password.service, password.username, password.url, password.notes
-            );
-        }
+        output::print_list(&summaries, "No passwords found.")?;
 
         Ok(())
     }
@@ -365,20 +774,34 @@ impl PasswordManager {
     ///
     /// # Errors
     ///
-    /// An error will be returned if the password cannot be removed.
+    /// An error will be returned if the password does not exist or cannot be removed.
     pub fn remove_password(&self, id: Option<i32>) -> Result<(), Box<dyn std::error::Error>> {
         let id = if let Some(id) = id {
             id
         } else {
-            let id = Text::new("Please enter the ID of the password to remove:").prompt()?;
+            let id = Text::new("Please enter the ID of the password to remove:").ask()?;
             if let Ok(id) = id.parse::<i32>() {
                 id
             } else {
-                return Err("Invalid ID".into());
+                return Err(CliError::InvalidInput(format!("{} is not a valid ID", id)).into());
             }
         };
 
+        // A corrupt entry can still be removed, it is only reported without its service.
+        let (uuid, service) = match self.database.read_by_id(id) {
+            Ok(entry) => (entry.uuid, entry.service),
+            Err(DatabaseError::CorruptEntry { .. }) => (String::new(), String::new()),
+            Err(e) => return Err(e.into()),
+        };
         self.database.delete(id)?;
+        let change = EntryChange {
+            action: "removed",
+            id: Some(id),
+            uuid,
+            service,
+        };
+        output::print_result(&change, &format!("Removed entry {}.", id))?;
+
         Ok(())
     }
 
@@ -405,32 +828,37 @@ impl PasswordManager {
         id: Option<i32>,
         service: Option<String>,
         username: Option<String>,
-        password: Option<String>,
+        password: Option<SecretString>,
         url: Option<String>,
         notes: Option<String>,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let id = if let Some(id) = id {
             id
         } else {
-            let id = Text::new("Please enter the ID of the password to update:").prompt()?;
+            let id = Text::new("Please enter the ID of the password to update:").ask()?;
             if let Ok(id) = id.parse::<i32>() {
                 id
             } else {
-                return Err("Invalid ID".into());
+                return Err(CliError::InvalidInput(format!("{} is not a valid ID", id)).into());
             }
         };
 
-        let input_data = Self::get_user_data(service, username, password, url, notes)?;
-
-        let entry = PasswordEntry::new(
-            input_data["service"].clone(),
-            input_data["username"].clone(),
-            input_data["password"].clone(),
-            input_data["url"].clone(),
-            input_data["notes"].clone(),
-        )?;
-
+        let existing = self.database.read_by_id(id)?;
+        let mut entry = Self::get_user_data(service, username, password, url, notes)?;
+        entry.folder = existing.folder;
+        entry.totp = existing.totp;
+        entry.custom_fields = existing.custom_fields;
+        entry.source = existing.source;
+        entry.tags = existing.tags;
+
+        let change = EntryChange {
+            action: "updated",
+            id: Some(id),
+            uuid: existing.uuid,
+            service: entry.service.clone(),
+        };
         self.database.update(id, entry)?;
+        output::print_result(&change, &format!("Updated {}.", change.service))?;
 
         Ok(())
     }
@@ -440,6 +868,7 @@ impl PasswordManager {
     /// # Arguments
     ///
     /// * `id` - The ID of the password to show.
+    /// * `reveal` - Whether to show secrets instead of masking them.
     ///
     /// # Returns
     ///
@@ -448,38 +877,40 @@ impl PasswordManager {
     /// # Errors
     ///
     /// An error will be returned if the password cannot be shown.
-    pub fn show_password(&self, id: Option<i32>) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn show_password(
+        &self,
+        id: Option<i32>,
+        reveal: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         let id = if let Some(id) = id {
             id
         } else {
-            let id = Text::new("Please enter the ID of the password to show:").prompt()?;
+            let id = Text::new("Please enter the ID of the password to show:").ask()?;
             if let Ok(id) = id.parse::<i32>() {
                 id
             } else {
-                return Err("Invalid ID".into());
+                return Err(CliError::InvalidInput(format!("{} is not a valid ID", id)).into());
             }
         };
 
-        let password = self.database.read_by_id(id)?;
-
-        println!(
-            "ID: {:#?}\nService: {}\nUsername: {}\nPassword: {}\nURL: {}\nNotes: {}",
-            password.id,
-            password.service,
-            password.username,
-            password.password,
-            password.url,
-            password.notes
-        );
+        let entry = self.database.read_by_id(id)?;
+        output::print_record(&EntryDetails::new(entry, reveal))?;
 
         Ok(())
     }
 
-    /// Search for a password in the password manager.
+    /// Copy a field of an entry to the clipboard.
+    ///
+    /// A background process clears the clipboard after the timeout, unless something else
+    /// was copied in the meantime. Backends that cannot read the clipboard, like OSC 52,
+    /// clear it regardless, which the message points out.
     ///
     /// # Arguments
     ///
-    /// * `query` - The query to search for.
+    /// * `selector` - The ID, UUID or service name of the entry.
+    /// * `field` - The field to copy.
+    /// * `clear_after` - The number of seconds until the clipboard is cleared, 0 to keep the
+    ///   value. Defaults to the configured timeout.
     ///
     /// # Returns
     ///
@@ -487,37 +918,62 @@ impl PasswordManager {
     ///
     /// # Errors
     ///
-    /// An error will be returned if the password cannot be found.
-    pub fn search_password(&self, query: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
-        let query = if let Some(query) = query {
-            query
-        } else {
-            Text::new("Please enter the query to search for:").prompt()?
+    /// An error will be returned if no entry matches, the field is empty or the clipboard
+    /// cannot be used.
+    pub fn copy_password(
+        &self,
+        selector: String,
+        field: CopyField,
+        clear_after: Option<u64>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let config = Config::load()?;
+        let clipboard = clipboard::backend(&config.clipboard.backend)?;
+        let entry = self.select_entry(&selector)?;
+        let (name, value) = Self::copy_field(clipboard.as_ref(), &entry, field)?;
+
+        let clear_after = clear_after.unwrap_or(config.clipboard.clear_after);
+        let copied = CopiedValue {
+            id: entry.id,
+            uuid: entry.uuid.clone(),
+            service: entry.service.clone(),
+            field: name,
+            clear_after,
+            clears_unconditionally: clear_after > 0 && !clipboard.can_paste(),
         };
-
-        let passwords = self.database.search(&query)?;
-
-        if passwords.is_empty() {
-            println!("No passwords found.");
+        if clear_after == 0 {
+            output::print_result(
+                &copied,
+                &format!("Copied the {} of {} to the clipboard.", name, entry.service),
+            )?;
             return Ok(());
         }
 
-        println!("ID\tService\tUsername\tURL\tNotes");
-        for password in passwords {
-            println!(
-                "{:#?}\t{}\t{}\t{}\t{}",
-                password.id, password.service, password.username, password.url, password.notes
-            );
-        }
+        Self::schedule_clear(clipboard.name(), &value, clear_after)?;
+        let unconditionally = if copied.clears_unconditionally {
+            ", even if something else is copied meanwhile"
+        } else {
+            ""
+        };
+        output::print_result(
+            &copied,
+            &format!(
+                "Copied the {} of {} to the clipboard. It will be cleared in {} seconds{}.",
+                name, entry.service, clear_after, unconditionally
+            ),
+        )?;
 
         Ok(())
     }
 
-    /// Export all passwords to a CSV file.
+    /// Clear the clipboard after a copy, unless something else was copied meanwhile.
+    ///
+    /// This runs in the background process started by `copy_password`, which passes the
+    /// fingerprint of the copied value on stdin.
     ///
     /// # Arguments
     ///
-    /// * `path` - The path to export the passwords to.
+    /// * `backend` - The clipboard backend the value was copied with.
+    /// * `after` - The number of seconds to wait before clearing.
     ///
     /// # Returns
     ///
@@ -525,53 +981,136 @@ impl PasswordManager {
     ///
     /// # Errors
     ///
-    /// An error will be returned if the passwords cannot be exported.
-    pub fn export_passwords(&self, path: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
-        let path = if let Some(path) = path {
-            path
-        } else {
-            Text::new("Please enter the path to export the passwords to:").prompt()?
-        };
-
-        let passwords = self.database.read()?;
-        let mut writer = Writer::from_path(path.clone())?;
+    /// An error will be returned if the fingerprint cannot be read or the clipboard cannot
+    /// be cleared.
+    pub fn clear_clipboard(backend: String, after: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let mut fingerprint = String::new();
+        std::io::stdin().read_line(&mut fingerprint)?;
+        let clipboard = clipboard::backend(&backend)?;
 
-        writer.write_record(&[
-            "Service",
-            "Username",
-            "Password",
-            "URL",
-            "Notes",
-            "Created At",
-            "Updated At",
-        ])?;
+        thread::sleep(Duration::from_secs(after));
+        if clipboard::clear_if_unchanged(clipboard.as_ref(), fingerprint.trim())? {
+            info!("Cleared the clipboard");
+        } else {
+            info!("The clipboard changed since the copy, leaving it as it is");
+        }
 
-        for password in passwords {
-            let export = PasswordExportImport {
-                service: password.service,
-                username: password.username,
-                password: password.password,
-                url: password.url,
-                notes: password.notes,
-                created_at: password.created_at.to_string(),
-                updated_at: password.updated_at.to_string(),
-            };
+        Ok(())
+    }
 
-            writer.serialize(export)?;
+    /// Run a command with secrets from the vault in its environment.
+    ///
+    /// The vault is locked before the command starts. Unless masking is turned off, the
+    /// output of the command is copied line by line with the passwords, TOTP codes and
+    /// custom fields replaced by `********`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - The variables to set as `NAME=vault://entry/field`.
+    /// * `command` - The command and its arguments.
+    /// * `mask` - Whether to mask the secrets in the output of the command.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the exit code of the command or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if a variable or reference is invalid or the command
+    /// cannot be started.
+    pub fn run_with_secrets(
+        self,
+        env: Vec<String>,
+        command: Vec<String>,
+        mask: bool,
+    ) -> Result<u8, Box<dyn std::error::Error>> {
+        let entries = self.database.read()?;
+        let mut variables = Vec::new();
+        for variable in &env {
+            let (name, value) = variable
+                .split_once('=')
+                .filter(|(name, _)| !name.is_empty())
+                .ok_or_else(|| {
+                    CliError::InvalidInput(format!(
+                        "Expected NAME={}entry/field, got {}",
+                        reference::SCHEME,
+                        variable
+                    ))
+                })?;
+            if !value.starts_with(reference::SCHEME) {
+                return Err(CliError::InvalidInput(format!(
+                    "The value of {} is not a {} reference",
+                    name,
+                    reference::SCHEME
+                ))
+                .into());
+            }
+            let reference = Reference::parse(value)?;
+            variables.push((name, reference.resolve(&entries)?, reference.is_secret()));
+        }
+        drop(entries);
+        drop(self);
+
+        let (program, arguments) = command
+            .split_first()
+            .ok_or_else(|| CliError::InvalidInput("No command to run".to_string()))?;
+        let mut child = Command::new(program);
+        child.args(arguments).envs(
+            variables
+                .iter()
+                .map(|(name, value, _)| (name, value.expose_secret())),
+        );
+        if mask {
+            child.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+        let mut child = child
+            .spawn()
+            .map_err(|e| format!("Cannot run {}: {}", program, e))?;
+        info!("Started {} with {} secrets", program, variables.len());
+
+        let masker = Arc::new(Masker::new(
+            &variables
+                .into_iter()
+                .filter(|(_, _, secret)| *secret)
+                .map(|(_, value, _)| value)
+                .collect::<Vec<_>>(),
+        ));
+        let mut copies = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            let masker = Arc::clone(&masker);
+            copies.push(thread::spawn(move || {
+                masker.copy(stdout, std::io::stdout())
+            }));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            let masker = Arc::clone(&masker);
+            copies.push(thread::spawn(move || {
+                masker.copy(stderr, std::io::stderr())
+            }));
         }
 
-        writer.flush()?;
-
-        println!("Passwords successfully exported to: {}", path);
+        let status = child.wait()?;
+        for copy in copies {
+            copy.join().map_err(|_| "Copying the output failed")??;
+        }
 
-        Ok(())
+        #[cfg(unix)]
+        if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+            return Ok(128u8.saturating_add(u8::try_from(signal).unwrap_or(0)));
+        }
+        Ok(status
+            .code()
+            .and_then(|code| u8::try_from(code).ok())
+            .unwrap_or(EXIT_FAILURE))
     }
 
-    /// Import passwords from a CSV file.
+    /// Render a template with `{{ vault "entry/field" }}` placeholders.
     ///
     /// # Arguments
     ///
-    /// * `path` - The path to import the passwords from.
+    /// * `input` - The template to render.
+    /// * `path` - The file to write, which is made readable only by the owner. Prints to
+    ///   stdout if `None`.
     ///
     /// # Returns
     ///
@@ -579,51 +1118,43 @@ impl PasswordManager {
     ///
     /// # Errors
     ///
-    /// An error will be returned if the passwords cannot be imported.
-    pub fn import_passwords(&self, path: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
-        let path = if let Some(path) = path {
-            path
-        } else {
-            Text::new("Please enter the path to import the passwords from:").prompt()?
-        };
-
-        let mut reader = csv::Reader::from_path(&path)?;
-        let entries: Vec<PasswordEntry> = reader
-            .deserialize()
-            .filter_map(|result| match result {
-                Ok(record) => {
-                    let record: PasswordExportImport = record;
-                    PasswordEntry::new(
-                        record.service,
-                        record.username,
-                        record.password,
-                        record.url,
-                        record.notes,
-                    )
-                    .ok()
-                }
-                Err(e) => {
-                    eprintln!("Warning: Skipping invalid entry: {}", e);
-                    None
-                }
-            })
-            .collect();
+    /// An error will be returned if the template cannot be read or rendered, or the output
+    /// cannot be written.
+    pub fn inject(
+        &self,
+        input: PathBuf,
+        path: Option<PathBuf>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let template = std::fs::read_to_string(&input)
+            .map_err(|e| format!("Cannot read {}: {}", input.display(), e))?;
+        let rendered = reference::render(&template, &self.database.read()?)?;
 
-        for entry in entries {
-            if let Err(e) = self.database.create(&entry) {
-                eprintln!("Warning: Failed to import entry: {}", e);
+        match path {
+            Some(path) => {
+                Self::create_private(&path)?.write_all(rendered.as_bytes())?;
+
+                message!("Rendered {} to {}.", input.display(), path.display());
+            }
+            None => {
+                let mut stdout = std::io::stdout().lock();
+                stdout.write_all(rendered.as_bytes())?;
+                stdout.flush()?;
             }
         }
 
-        println!("Passwords successfully imported from: {}", path);
         Ok(())
     }
 
-    /// Generate an import template for passwords.
+    /// Answer a request of the git credential helper protocol read from stdin.
+    ///
+    /// `get` prints the best matching login. `store` and `erase` change logins in the
+    /// configured folder if storing is enabled in the configuration. Other operations are
+    /// ignored, as the protocol asks, and the vault is only unlocked when it is needed.
     ///
     /// # Arguments
     ///
-    /// * `path` - The path to generate the import template.
+    /// * `operation` - The operation git asks for.
+    /// * `input` - Where the master password comes from. stdin carries the request.
     ///
     /// # Returns
     ///
@@ -631,33 +1162,1372 @@ impl PasswordManager {
     ///
     /// # Errors
     ///
-    /// An error will be returned if the import template cannot be generated.
-    pub fn generate_import_template(
-        &self,
-        path: Option<String>,
+    /// An error will be returned if the request is invalid or the vault cannot be unlocked.
+    pub fn git_credential(
+        operation: String,
+        input: &InputArgs,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let path = if let Some(path) = path {
-            path
-        } else {
-            Text::new("Please enter the path to generate the import template:").prompt()?
-        };
+        if input.password_stdin {
+            return Err(CliError::InvalidInput(
+                "git sends its request on stdin, pass the master password with --password-fd or --password-env"
+                    .to_string(),
+            )
+            .into());
+        }
 
-        let mut writer = Writer::from_path(&path)?;
+        let config = Config::load()?;
+        let credential = GitCredential::read(std::io::stdin().lock())?;
+        // Answering git must not create a vault, which would print its new master password.
+        let vault_exists = Config::get_config_dir()?.join("master.key").exists();
+        let store = vault_exists && config.git_credential.store;
+        let folder = &config.git_credential.folder;
+
+        match operation.as_str() {
+            "get" if vault_exists => {
+                let pw = Self::new(input)?;
+                if let Some(entry) = credential.find(&pw.database.read()?) {
+                    info!("Answering git with the login of entry {:?}", entry.id);
+                    GitCredential::write_login(entry, std::io::stdout().lock())?;
+                }
+            }
+            "store" if store => Self::new(input)?.store_git_credential(&credential, folder)?,
+            "erase" if store => Self::new(input)?.erase_git_credential(&credential, folder)?,
+            _ => info!("Ignoring the git credential operation {}", operation),
+        }
 
-        writer.write_record(&[
-            "Service",
-            "Username",
-            "Password",
-            "URL",
+        Ok(())
+    }
+
+    /// Save a login git used successfully, updating the entry for its URL and username.
+    fn store_git_credential(
+        &self,
+        credential: &GitCredential,
+        folder: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if credential.username.is_empty() || credential.password.is_empty() {
+            return Ok(());
+        }
+
+        let url = credential.url();
+        let existing = self.database.read()?.into_iter().find(|entry| {
+            entry.folder == folder && entry.url == url && entry.username == credential.username
+        });
+        match existing {
+            Some(entry) if entry.password == credential.password => {}
+            Some(mut entry) => {
+                let id = entry.id.ok_or("The entry has no ID")?;
+                entry.password = credential.password.clone();
+                entry.updated_at = Utc::now().to_rfc3339();
+                self.database.update(id, entry)?;
+                info!("Updated the git login for {}", url);
+            }
+            None => {
+                let service = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+                let mut entry = PasswordEntry::new(
+                    service.to_string(),
+                    credential.username.clone(),
+                    credential.password.clone(),
+                    url.clone(),
+                    String::new(),
+                )?;
+                entry.folder = folder.to_string();
+                entry.source = "git-credential".to_string();
+                self.database.create(&entry)?;
+                info!("Stored a git login for {}", url);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove a login git rejected. Only entries with the same URL, username and password
+    /// in the git folder are removed.
+    fn erase_git_credential(
+        &self,
+        credential: &GitCredential,
+        folder: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let url = credential.url();
+        for entry in self.database.read()? {
+            if entry.folder == folder
+                && entry.url == url
+                && (credential.username.is_empty() || entry.username == credential.username)
+                && (credential.password.is_empty() || entry.password == credential.password)
+            {
+                self.database
+                    .delete(entry.id.ok_or("The entry has no ID")?)?;
+                info!("Erased the git login for {}", url);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Store an SSH private key in the vault.
+    ///
+    /// The key is kept in the hidden custom field `ssh_private_key` and its public key in
+    /// `ssh_public_key`, with the comment of the key as the username.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The path of the private key, `-` for stdin.
+    /// * `service` - The name of the entry. Defaults to the comment of the key.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the key cannot be read or parsed, or the entry cannot be
+    /// added.
+    pub fn add_ssh_key(
+        &self,
+        key: String,
+        service: Option<String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let text = if key == "-" {
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut text)?;
+            SecretString::new(text)
+        } else {
+            SecretString::new(
+                std::fs::read_to_string(&key).map_err(|e| format!("Cannot read {}: {}", key, e))?,
+            )
+        };
+        let parsed = SshKey::parse(text.expose_secret())?;
+
+        let service = service
+            .filter(|service| !service.is_empty())
+            .unwrap_or_else(|| match parsed.comment.as_str() {
+                "" => format!("SSH key {}", parsed.fingerprint()),
+                comment => comment.to_string(),
+            });
+        let mut entry = PasswordEntry::new(
+            service.clone(),
+            parsed.comment.clone(),
+            SecretString::default(),
+            String::new(),
+            String::new(),
+        )?;
+        entry.custom_fields = vec![
+            CustomField {
+                name: ssh_key::PRIVATE_KEY_FIELD.to_string(),
+                value: SecretString::new(text.expose_secret().trim().to_string() + "\n"),
+                hidden: true,
+            },
+            CustomField {
+                name: ssh_key::PUBLIC_KEY_FIELD.to_string(),
+                value: SecretString::new(parsed.authorized_key()),
+                hidden: false,
+            },
+        ];
+        self.database.create(&entry)?;
+
+        message!("Added the SSH key {} as {}.", parsed.fingerprint(), service);
+        if key != "-" {
+            message!(
+                "The vault keeps the key encrypted, you can delete {} now.",
+                key
+            );
+        }
+        Ok(())
+    }
+
+    /// Serve the SSH keys of the vault as an SSH agent until the lifetime runs out.
+    ///
+    /// The keys are loaded from the entries with an `ssh_private_key` field, then the vault
+    /// is closed. Keys that cannot be parsed are skipped with a warning.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The path of the socket. Defaults to `ssh-agent.sock` in the runtime
+    ///   directory, or in the configuration directory if there is none.
+    /// * `confirm` - Whether to ask before every signature.
+    /// * `lifetime` - The number of seconds to serve the keys, `None` to serve until the
+    ///   process ends.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the vault has no SSH keys or the socket cannot be
+    /// created.
+    #[cfg(unix)]
+    pub fn ssh_agent(
+        self,
+        socket: Option<PathBuf>,
+        confirm: bool,
+        lifetime: Option<u64>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut keys = Vec::new();
+        for entry in self.database.read()? {
+            let Some(field) = entry
+                .custom_fields
+                .iter()
+                .find(|field| field.name == ssh_key::PRIVATE_KEY_FIELD)
+            else {
+                continue;
+            };
+            match SshKey::parse(field.value.expose_secret()) {
+                Ok(key) => keys.push(key),
+                Err(e) => eprintln!("WARNING: Skipping the SSH key of {}: {}", entry.service, e),
+            }
+        }
+        drop(self);
+        if keys.is_empty() {
+            return Err(CliError::NotFound(
+                "No SSH keys in the vault, add one with `password manage add-ssh-key`".to_string(),
+            )
+            .into());
+        }
+
+        let path = match socket {
+            Some(path) => path,
+            None => socket::default_path("ssh-agent.sock")?,
+        };
+        let listener = socket::bind(&path)?;
+
+        // The same line as `ssh-agent` prints, so the output can be evaluated by a shell.
+        println!("SSH_AUTH_SOCK={}; export SSH_AUTH_SOCK;", path.display());
+        std::io::stdout().flush()?;
+        eprintln!(
+            "Serving {} SSH keys{}. Press Ctrl-C to stop.",
+            keys.len(),
+            lifetime.map_or(String::new(), |secs| format!(" for {} seconds", secs))
+        );
+        info!("Serving {} SSH keys on {}", keys.len(), path.display());
+
+        let confirm: Option<ssh_agent::Confirm> =
+            confirm.then(|| Box::new(Self::confirm_ssh_key) as ssh_agent::Confirm);
+        let agent = Arc::new(SshAgent::new(keys, confirm));
+        let served = agent.serve(listener, lifetime.map(Duration::from_secs));
+        std::fs::remove_file(&path)?;
+        served?;
+
+        eprintln!("The lifetime of the SSH keys ran out.");
+        Ok(())
+    }
+
+    /// Serve the SSH keys of the vault as an SSH agent. Unix sockets are needed.
+    #[cfg(not(unix))]
+    pub fn ssh_agent(
+        self,
+        _socket: Option<PathBuf>,
+        _confirm: bool,
+        _lifetime: Option<u64>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Err("The SSH agent needs Unix sockets, which this platform does not have".into())
+    }
+
+    /// Serve search, get, add and update of entries as JSON-RPC over HTTP on a Unix socket,
+    /// until the process ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The path of the socket. Defaults to `api.sock` in the runtime
+    ///   directory, or in the configuration directory if there is none.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the socket cannot be created.
+    #[cfg(unix)]
+    pub fn serve(&self, socket: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+        let path = match socket {
+            Some(path) => path,
+            None => socket::default_path("api.sock")?,
+        };
+        let server = tiny_http::Server::from_listener(socket::bind(&path)?, None)
+            .map_err(|e| format!("Cannot serve on {}: {}", path.display(), e))?;
+
+        eprintln!(
+            "Serving the vault on {}. Press Ctrl-C to stop.",
+            path.display()
+        );
+        info!("Serving the vault API on {}", path.display());
+        serve::run(self, server)
+    }
+
+    /// Serve the vault as JSON-RPC. Unix sockets are needed.
+    #[cfg(not(unix))]
+    pub fn serve(&self, _socket: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+        Err("Serving the vault needs Unix sockets, which this platform does not have".into())
+    }
+
+    /// Ask whether an SSH key may sign, with the `SSH_ASKPASS` program if it is set, as
+    /// `ssh-agent -c` does, otherwise on the terminal.
+    #[cfg(unix)]
+    fn confirm_ssh_key(key: &SshKey) -> bool {
+        let prompt = format!(
+            "Allow use of the SSH key {} {}?",
+            key.comment,
+            key.fingerprint()
+        );
+        match std::env::var("SSH_ASKPASS") {
+            Ok(askpass) if !askpass.is_empty() => Command::new(askpass)
+                .arg(&prompt)
+                .env("SSH_ASKPASS_PROMPT", "confirm")
+                .stdin(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success()),
+            _ => Confirm::new(&prompt)
+                .with_default(false)
+                .ask()
+                .unwrap_or(false),
+        }
+    }
+
+    /// Find the entry a selector refers to.
+    ///
+    /// The selector is tried as an ID, a UUID and a service name, then as a search query.
+    /// If several entries match, the user picks one.
+    fn select_entry(&self, selector: &str) -> Result<PasswordEntry, Box<dyn std::error::Error>> {
+        if let Ok(id) = selector.parse::<i32>() {
+            return Ok(self.database.read_by_id(id)?);
+        }
+        if let Some(id) = self.database.find_by_uuid(selector)? {
+            return Ok(self.database.read_by_id(id)?);
+        }
+
+        let mut candidates: Vec<PasswordEntry> = self
+            .database
+            .read()?
+            .into_iter()
+            .filter(|entry| entry.service.eq_ignore_ascii_case(selector))
+            .collect();
+        if candidates.is_empty() {
+            candidates = self.database.search(selector)?;
+        }
+
+        match candidates.len() {
+            0 => Err(CliError::NotFound(format!("No entry matches {}", selector)).into()),
+            1 => Ok(candidates.remove(0)),
+            _ => {
+                let options = candidates
+                    .iter()
+                    .map(|entry| {
+                        format!(
+                            "{} - {} ({})",
+                            entry.id.unwrap_or_default(),
+                            entry.service,
+                            entry.username
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let choice =
+                    Select::new("Several entries match, please select one:", options).ask()?;
+                Ok(candidates.remove(choice.index))
+            }
+        }
+    }
+
+    /// Copy a field of an entry to a clipboard.
+    ///
+    /// Returns the name of the field and the copied value.
+    pub(crate) fn copy_field(
+        clipboard: &dyn Clipboard,
+        entry: &PasswordEntry,
+        field: CopyField,
+    ) -> Result<(&'static str, SecretString), Box<dyn std::error::Error>> {
+        let (name, value) = match field {
+            CopyField::Password => ("password", entry.password.clone()),
+            CopyField::Username => ("username", SecretString::new(entry.username.clone())),
+            CopyField::Totp if entry.totp.is_empty() => ("TOTP code", SecretString::default()),
+            CopyField::Totp => (
+                "TOTP code",
+                totp::generate(&entry.totp, Utc::now().timestamp().try_into()?)?,
+            ),
+        };
+        if value.is_empty() {
+            return Err(format!("{} has no {}", entry.service, name).into());
+        }
+
+        clipboard.copy(&value)?;
+        Ok((name, value))
+    }
+
+    /// Start a background process that clears the clipboard after a timeout.
+    pub(crate) fn schedule_clear(
+        backend: &str,
+        value: &SecretString,
+        after: u64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut command = Command::new(std::env::current_exe()?);
+        command
+            .args(["password", "clear-clipboard", "--backend", backend])
+            .args(["--after", &after.to_string()])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        // Keep the process alive when the terminal sends a signal to the foreground group.
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+        let mut child = command.spawn()?;
+        writeln!(
+            child
+                .stdin
+                .take()
+                .ok_or("Failed to start the clipboard clearer")?,
+            "{}",
+            clipboard::fingerprint(value)
+        )?;
+
+        Ok(())
+    }
+
+    /// Run an interactive shell that keeps the vault unlocked between commands.
+    ///
+    /// # Arguments
+    ///
+    /// * `lock_after` - The number of idle seconds after which the vault is locked, 0 to
+    ///   keep it unlocked.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if prompts are disabled or the terminal cannot be read.
+    pub fn run_shell(self, lock_after: u64) -> Result<(), Box<dyn std::error::Error>> {
+        shell::run(self, lock_after)
+    }
+
+    /// Browse the vault in a full-screen terminal UI.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if prompts are disabled, the clipboard backend is unknown
+    /// or the terminal fails.
+    pub fn browse(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if !input::interactive() {
+            return Err(CliError::InvalidInput(
+                "The vault browser cannot run with --no-input".to_string(),
+            )
+            .into());
+        }
+
+        let config = Config::load()?;
+        let clipboard = clipboard::backend(&config.clipboard.backend)?;
+        tui::run(
+            &self.database,
+            clipboard.as_ref(),
+            config.clipboard.clear_after,
+        )
+    }
+
+    /// Search for a password in the password manager.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The query to search for.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the password cannot be found.
+    pub fn search_password(&self, query: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+        let query = if let Some(query) = query {
+            query
+        } else {
+            Text::new("Please enter the query to search for:").ask()?
+        };
+
+        let summaries = self
+            .database
+            .search(&query)?
+            .iter()
+            .map(EntrySummary::from)
+            .collect::<Vec<_>>();
+        output::print_list(&summaries, "No passwords found.")?;
+
+        Ok(())
+    }
+
+    /// Verify the integrity of the password vault.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the vault cannot be checked or if any problem was found.
+    pub fn verify_vault(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let report = self.database.verify()?;
+        let summary = VerifySummary {
+            checked: report.checked,
+            corrupt_entries: report
+                .corrupt_entries
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            integrity_errors: report.integrity_errors.clone(),
+            intact: report.is_ok(),
+        };
+        output::print_record(&summary)?;
+
+        if summary.intact {
+            Ok(())
+        } else {
+            Err("Vault verification failed".into())
+        }
+    }
+
+    /// Create a backup of the password vault or list the existing backups.
+    ///
+    /// # Arguments
+    ///
+    /// * `list` - Whether to list the existing backups instead of creating one.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the backup cannot be created or listed.
+    pub fn backup_vault(&self, list: bool) -> Result<(), Box<dyn std::error::Error>> {
+        if list {
+            let backups = self
+                .backups
+                .list()?
+                .into_iter()
+                .map(BackupSummary::from)
+                .collect::<Vec<_>>();
+            output::print_list(&backups, "No backups found.")?;
+            return Ok(());
+        }
+
+        let backup = self.backups.create(&self.database, "manual")?;
+        message!("Backup created.");
+        output::print_record(&BackupSummary::from(backup))?;
+
+        Ok(())
+    }
+
+    /// Merge another copy of the vault into this one and bring both copies up to date.
+    ///
+    /// The other vault is unlocked with its own master key file and master password. Both
+    /// vaults are backed up first, the backup of the other vault goes to a subdirectory of
+    /// the backup directory named after its vault ID, or `vault-unversioned` for a vault
+    /// too old to have one. A vault written by an older version is only upgraded, after
+    /// its backup, if `migrate` is set. Then the other vault and after it this one receive
+    /// the merged entries and deletions, so a vault that cannot be written leaves this one
+    /// unchanged. Conflicts are resolved automatically and reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the other vault database.
+    /// * `key` - The master key file of the other vault, `master.key` next to it by default.
+    /// * `migrate` - Whether to upgrade the other vault if it was written by an older version.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the other vault cannot be unlocked, needs an upgrade
+    /// without `migrate`, or either vault cannot be read or written.
+    pub fn merge_vault(
+        &self,
+        path: String,
+        key: Option<String>,
+        migrate: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = PathBuf::from(path);
+        if !path.is_file() {
+            return Err(format!("Vault not found: {}", path.display()).into());
+        }
+        if path.canonicalize()? == self.database.path.canonicalize()? {
+            return Err("Cannot merge a vault with itself".into());
+        }
+        let master_file = match key {
+            Some(key) => PathBuf::from(key),
+            None => path.with_file_name("master.key"),
+        };
+
+        let password = SecretString::new(
+            Password::new("Please enter the master password of the other vault:")
+                .without_confirmation()
+                .ask()?,
+        );
+        let salt = Encryption::unlock_master_file(&master_file, &password)?;
+        let other = Database::open(path.clone(), &password, &salt)?;
+        let migrated = other.has_pending_migrations()?;
+        if migrated && !migrate {
+            return Err(
+                "The other vault was written by an older version. Merge with --migrate to upgrade it once every client is updated"
+                    .into(),
+            );
+        }
+
+        self.backups.create(&self.database, "merge")?;
+        let id = match other.vault_id() {
+            Ok(id) => id,
+            Err(_) if migrated => "unversioned".to_string(),
+            Err(e) => return Err(e.into()),
+        };
+        let other_backups = BackupManager {
+            directory: self.backups.directory.join(format!("vault-{}", id)),
+            retention: self.backups.retention,
+            db_path: path.clone(),
+            master_file,
+        };
+        let other_backup = other_backups.create(&other, "merge")?;
+        other.migrate().map_err(|e| {
+            format!(
+                "Failed to upgrade the other vault, it can be restored from {}: {}",
+                other_backup.display(),
+                e
+            )
+        })?;
+
+        let local_id = self.database.vault_id()?;
+        let other_id = other.vault_id()?;
+
+        let base = self.database.merge_base(&other_id)?;
+        let result = merge::merge(
+            &VaultState::read(&self.database)?,
+            &VaultState::read(&other)?,
+            base.as_deref(),
+        );
+
+        let other_changes = merge::apply(&other, &result).map_err(|e| {
+            format!(
+                "Failed to update the other vault, it can be restored from {}: {}",
+                other_backup.display(),
+                e
+            )
+        })?;
+        let local_changes = merge::apply(&self.database, &result)?;
+
+        let merged_at = Utc::now().to_rfc3339();
+        other.set_merge_base(&local_id, &merged_at)?;
+        self.database.set_merge_base(&other_id, &merged_at)?;
+
+        for conflict in &result.conflicts {
+            message!("Conflict: {}", conflict.describe());
+        }
+        let text = format!(
+            "Merged {}: {} added, {} updated, {} deleted here; {} added, {} updated, {} deleted there; {} conflicts",
+            path.display(),
+            local_changes.added,
+            local_changes.updated,
+            local_changes.deleted,
+            other_changes.added,
+            other_changes.updated,
+            other_changes.deleted,
+            result.conflicts.len()
+        );
+        let summary = MergeSummary {
+            vault: path.display().to_string(),
+            created: false,
+            uploaded: true,
+            pulled: local_changes,
+            pushed: other_changes,
+            conflicts: result.conflicts,
+        };
+        output::print_result(&summary, &text)?;
+
+        Ok(())
+    }
+
+    /// Sync the password vault with a copy on a WebDAV server.
+    ///
+    /// The login for the remote is asked for on the first sync and stored in the vault. The
+    /// vault is backed up before it is synced.
+    ///
+    /// # Arguments
+    ///
+    /// * `remote` - The WebDAV URL of the remote folder. Defaults to the only stored remote.
+    /// * `username` - The username for the remote, which asks for a new login.
+    /// * `login` - Whether to ask for a new login instead of using the stored one.
+    /// * `migrate_remote` - Whether to upgrade a remote vault written by an older version.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if no remote is given or stored, or the sync fails.
+    pub fn sync_vault(
+        &self,
+        remote: Option<String>,
+        username: Option<String>,
+        login: bool,
+        migrate_remote: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let url = match remote {
+            Some(remote) => remote,
+            None => {
+                let mut remotes = self.database.list_remotes()?;
+                if remotes.len() != 1 {
+                    return Err("Please choose a remote with --remote".into());
+                }
+                remotes.remove(0)
+            }
+        };
+
+        let stored = self.database.read_remote(&url)?;
+        let credentials = match stored {
+            Some(credentials) if !login && username.is_none() => credentials,
+            _ => {
+                let username = match username {
+                    Some(username) => username,
+                    None => Text::new("Please enter the username for the remote:")
+                        .with_help_message("Leave empty if the server needs no login")
+                        .ask()?,
+                };
+                let password = if username.is_empty() {
+                    SecretString::default()
+                } else {
+                    SecretString::new(
+                        Password::new("Please enter the password for the remote:")
+                            .without_confirmation()
+                            .ask()?,
+                    )
+                };
+                RemoteCredentials {
+                    url,
+                    username,
+                    password,
+                }
+            }
+        };
+
+        let remote = WebDavRemote::new(&credentials)?;
+        self.backups.create(&self.database, "sync")?;
+        let report = remote.sync(
+            &self.database,
+            &self.backups.master_file,
+            &self.master_password,
+            migrate_remote,
+        )?;
+        self.database.save_remote(&credentials)?;
+
+        for conflict in &report.conflicts {
+            message!("Conflict: {}", conflict.describe());
+        }
+        let text = if report.created {
+            format!("Uploaded the vault to {}.", credentials.url)
+        } else {
+            format!(
+                "Synced with {}: {} added, {} updated, {} deleted here; {} added, {} updated, {} deleted there; {} conflicts",
+                credentials.url,
+                report.pulled.added,
+                report.pulled.updated,
+                report.pulled.deleted,
+                report.pushed.added,
+                report.pushed.updated,
+                report.pushed.deleted,
+                report.conflicts.len()
+            )
+        };
+        let summary = MergeSummary {
+            vault: credentials.url.clone(),
+            created: report.created,
+            uploaded: report.uploaded,
+            pulled: report.pulled,
+            pushed: report.pushed,
+            conflicts: report.conflicts,
+        };
+        output::print_result(&summary, &text)?;
+
+        Ok(())
+    }
+
+    /// Restore the password vault from a backup.
+    ///
+    /// The current vault does not need to be unlocked, so a damaged vault can be restored.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name or path of the backup to restore.
+    /// * `input` - The global input options, which may hold the master password of the
+    ///   backup.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the backup cannot be unlocked, verified or restored.
+    pub fn restore_backup(
+        name: Option<String>,
+        input: &InputArgs,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let config = Config::load()?;
+        let backups = BackupManager::new(&config)?;
+
+        let backup = if let Some(name) = name {
+            backups.resolve(&name)?
+        } else {
+            let options = backups
+                .list()?
+                .into_iter()
+                .rev()
+                .filter_map(|path| path.file_name().map(|n| n.to_string_lossy().to_string()))
+                .collect::<Vec<_>>();
+            if options.is_empty() {
+                return Err(CliError::NotFound("No backups found".to_string()).into());
+            }
+            let name = Select::new("Please select the backup to restore:", options)
+                .ask()?
+                .value;
+            backups.resolve(&name)?
+        };
+
+        let master_password = match input.master_password()? {
+            Some(password) => password,
+            None => SecretString::new(
+                Password::new("Please enter the master password of the backup:")
+                    .without_confirmation()
+                    .ask()?,
+            ),
+        };
+
+        backups.restore(&backup, &master_password)?;
+
+        Ok(())
+    }
+
+    /// Export all passwords to a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to export the passwords to.
+    /// * `format` - The format to export the passwords in.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the passwords cannot be exported.
+    pub fn export_passwords(
+        &self,
+        path: Option<String>,
+        format: ExportFormat,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = if let Some(path) = path {
+            path
+        } else {
+            Text::new("Please enter the path to export the passwords to:").ask()?
+        };
+
+        let passwords = self.database.read()?;
+        let summary = ExportSummary {
+            path: path.clone(),
+            format: clap::ValueEnum::to_possible_value(&format)
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default(),
+            entries: passwords.len(),
+            fingerprint: None,
+        };
+
+        match format {
+            ExportFormat::Encrypted => {
+                let password = SecretString::new(
+                    Password::new("Please enter a password to protect the export:").ask()?,
+                );
+                let data = export::seal(&ExportPayload::new(passwords), &password)?;
+                Self::create_private(&path)?.write_all(&data)?;
+            }
+            format => {
+                if !Self::confirm_plain_text("export")? {
+                    message!("Export cancelled.");
+                    return Ok(());
+                }
+
+                match format {
+                    ExportFormat::Json => {
+                        let json = export::to_json(&ExportPayload::new(passwords))?;
+                        Self::create_private(&path)?.write_all(json.as_bytes())?;
+                    }
+                    ExportFormat::BitwardenJson => {
+                        let json = exporters::bitwarden::write(&passwords)?;
+                        Self::create_private(&path)?.write_all(json.as_bytes())?;
+                    }
+                    ExportFormat::KeepassXml => {
+                        let xml = keepass_xml::write(&passwords)?;
+                        Self::create_private(&path)?.write_all(xml.as_bytes())?;
+                    }
+                    ExportFormat::Csv => Self::write_csv(&path, passwords)?,
+                    ExportFormat::Encrypted => {
+                        unreachable!("encrypted exports are written before the plain text check")
+                    }
+                }
+            }
+        }
+
+        output::print_result(
+            &summary,
+            &format!("Passwords successfully exported to: {}", path),
+        )?;
+
+        Ok(())
+    }
+
+    /// Write a printable emergency sheet of selected entries.
+    ///
+    /// Entries are selected by folder, tag or selector. Without any of them, the entries are
+    /// picked from a list.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - The path to write the sheet to, which is made readable only by the user.
+    /// * `folders` - Folders whose entries, including those of subfolders, are printed.
+    /// * `tags` - Tags whose entries are printed, ignoring case.
+    /// * `selectors` - IDs, UUIDs or service names of entries to print.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if no entries are selected or the sheet cannot be written.
+    pub fn write_emergency_sheet(
+        &self,
+        output: Option<String>,
+        folders: Vec<String>,
+        tags: Vec<String>,
+        selectors: Vec<String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let output = if let Some(output) = output {
+            output
+        } else {
+            Text::new("Please enter the path to write the sheet to:")
+                .with_default("emergency-sheet.html")
+                .ask()?
+        };
+
+        let entries = if folders.is_empty() && tags.is_empty() && selectors.is_empty() {
+            let entries = self.database.read()?;
+            let options = entries
+                .iter()
+                .map(|entry| format!("{} ({})", entry.service, entry.username))
+                .collect::<Vec<_>>();
+            let chosen = MultiSelect::new("Please select the entries to print:", options)
+                .ask()?
+                .into_iter()
+                .map(|option| option.index)
+                .collect::<Vec<_>>();
+            entries
+                .into_iter()
+                .enumerate()
+                .filter(|(index, _)| chosen.contains(index))
+                .map(|(_, entry)| entry)
+                .collect()
+        } else {
+            self.select_sheet_entries(&folders, &tags, &selectors)?
+        };
+        if entries.is_empty() {
+            return Err("No entries selected".into());
+        }
+
+        if !Self::confirm_plain_text("emergency sheet")? {
+            message!("Emergency sheet cancelled.");
+            return Ok(());
+        }
+
+        let fingerprint = emergency_sheet::fingerprint(
+            &self.database.vault_id()?,
+            &std::fs::read(&self.backups.master_file)?,
+        );
+        let html = emergency_sheet::write(&entries, &fingerprint, Local::now())?;
+        Self::create_private(&output)?.write_all(html.as_bytes())?;
+
+        let text = format!(
+            "Emergency sheet with {} entries written to: {}\nVault fingerprint: {}",
+            entries.len(),
+            output,
+            fingerprint
+        );
+        let summary = ExportSummary {
+            path: output,
+            format: "html".to_string(),
+            entries: entries.len(),
+            fingerprint: Some(fingerprint),
+        };
+        output::print_result(&summary, &text)?;
+
+        Ok(())
+    }
+
+    /// Collect the entries in the given folders, those with one of the tags and those
+    /// matching the selectors.
+    fn select_sheet_entries(
+        &self,
+        folders: &[String],
+        tags: &[String],
+        selectors: &[String],
+    ) -> Result<Vec<PasswordEntry>, Box<dyn std::error::Error>> {
+        let in_folder = |entry: &PasswordEntry| {
+            folders.iter().any(|folder| {
+                let folder = folder.trim_matches('/');
+                entry.folder == folder || entry.folder.starts_with(&format!("{}/", folder))
+            })
+        };
+        let has_tag = |entry: &PasswordEntry| {
+            tags.iter().any(|tag| {
+                entry
+                    .tags
+                    .iter()
+                    .any(|own| own.to_lowercase() == tag.to_lowercase())
+            })
+        };
+        let mut entries: Vec<PasswordEntry> = self
+            .database
+            .read()?
+            .into_iter()
+            .filter(|entry| in_folder(entry) || has_tag(entry))
+            .collect();
+
+        for selector in selectors {
+            let entry = self.select_entry(selector)?;
+            if !entries.iter().any(|selected| selected.id == entry.id) {
+                entries.push(entry);
+            }
+        }
+
+        entries.sort_by(|a, b| {
+            (&a.folder, a.service.to_lowercase()).cmp(&(&b.folder, b.service.to_lowercase()))
+        });
+        Ok(entries)
+    }
+
+    /// Warn that a file will contain passwords in plain text and ask to continue.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - What is written, e.g. `export`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `bool` indicating if the user wants to continue or an error.
+    fn confirm_plain_text(kind: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        eprintln!("WARNING: This {} stores passwords in PLAIN TEXT.", kind);
+        eprintln!("WARNING: Anyone who can read it can read these passwords.");
+
+        Ok(Confirm::new(&format!(
+            "Do you really want to write an unencrypted {}?",
+            kind
+        ))
+        .with_default(false)
+        .ask()?)
+    }
+
+    /// Create or truncate a file that only the user can read.
+    ///
+    /// The mode of `OpenOptions` only applies to new files, so the permissions of an
+    /// existing file are set as well before anything is written.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the opened file or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the file cannot be opened or its permissions cannot be
+    /// set.
+    fn create_private(path: impl AsRef<std::path::Path>) -> std::io::Result<std::fs::File> {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options.open(path)?;
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+        Ok(file)
+    }
+
+    /// Write passwords to a plain text CSV file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to write the CSV file to.
+    /// * `passwords` - The passwords to write.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the file cannot be written.
+    fn write_csv(
+        path: &str,
+        passwords: Vec<PasswordEntry>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut writer = Writer::from_writer(Self::create_private(path)?);
+
+        writer.write_record([
+            "Service",
+            "Username",
+            "Password",
+            "URL",
+            "Notes",
+            "Created At",
+            "Updated At",
+        ])?;
+
+        for password in passwords {
+            let export = PasswordExportImport {
+                service: password.service,
+                username: password.username,
+                password: password.password,
+                url: password.url,
+                notes: password.notes,
+                created_at: password.created_at.to_string(),
+                updated_at: password.updated_at.to_string(),
+            };
+
+            writer.serialize(export)?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Import passwords from an encrypted export, a CSV file or another password manager.
+    ///
+    /// Entries that already exist are detected by their service, username and URL and
+    /// resolved with the conflict strategy. A summary is printed at the end.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to import the passwords from.
+    /// * `format` - The format of the file to import.
+    /// * `keyfile` - The key file of a KeePass database.
+    /// * `map` - Column mapping overrides for CSV files, e.g. `service=name,url=origin`.
+    /// * `on_conflict` - How to resolve entries that already exist.
+    /// * `dry_run` - Print the planned changes without changing the vault.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the file cannot be read or parsed. Entries that fail to
+    /// import are counted as errors in the summary.
+    pub fn import_passwords(
+        &self,
+        path: Option<String>,
+        format: ImportFormat,
+        keyfile: Option<String>,
+        map: Option<String>,
+        on_conflict: ConflictStrategy,
+        dry_run: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = if let Some(path) = path {
+            path
+        } else {
+            Text::new("Please enter the path to import the passwords from:").ask()?
+        };
+
+        let data = std::fs::read(&path)?;
+        let format = match format {
+            ImportFormat::Auto if export::is_encrypted_export(&data) => ImportFormat::Encrypted,
+            ImportFormat::Auto if kdbx::is_kdbx(&data) => ImportFormat::Kdbx,
+            ImportFormat::Auto => ImportFormat::Csv,
+            format => format,
+        };
+
+        let (mut result, source) = match format {
+            ImportFormat::Encrypted => {
+                let password = SecretString::new(
+                    Password::new("Please enter the password of the export:")
+                        .without_confirmation()
+                        .ask()?,
+                );
+                let result = ImportResult {
+                    entries: export::open(&data, &password)?.entries,
+                    unmapped: Vec::new(),
+                };
+                (result, "encrypted")
+            }
+            ImportFormat::Json => {
+                let result = ImportResult {
+                    entries: export::from_json(&data)?.entries,
+                    unmapped: Vec::new(),
+                };
+                (result, "json")
+            }
+            ImportFormat::BitwardenJson => {
+                (bitwarden::parse(std::str::from_utf8(&data)?)?, "bitwarden")
+            }
+            ImportFormat::KeepassXml => {
+                (kdbx::parse_xml(std::str::from_utf8(&data)?)?, "keepass-xml")
+            }
+            ImportFormat::Kdbx => {
+                let keyfile = keyfile.map(std::fs::read).transpose()?;
+                let password = SecretString::new(
+                    Password::new("Please enter the password of the KeePass database:")
+                        .without_confirmation()
+                        .ask()?,
+                );
+                (kdbx::parse(&data, &password, keyfile.as_deref())?, "kdbx")
+            }
+            ImportFormat::Csv | ImportFormat::Auto => {
+                let overrides = map
+                    .as_deref()
+                    .map(csv_mapping::parse_overrides)
+                    .transpose()?
+                    .unwrap_or_default();
+                (csv_mapping::parse(&data, &overrides)?, "csv")
+            }
+        };
+
+        let source = format!("import:{}:{}", source, Utc::now().format("%Y-%m-%d"));
+        for entry in &mut result.entries {
+            // Entries from our own exports keep their original source.
+            if entry.source.is_empty() {
+                entry.source = source.clone();
+            }
+        }
+
+        for item in &result.unmapped {
+            eprintln!("Warning: Could not import: {}", item);
+        }
+        let mut errors = result.unmapped.len();
+
+        let plan = ImportPlan::new(self.database.read()?, result.entries, on_conflict);
+
+        if dry_run {
+            for line in plan.diff() {
+                message!("{}", line);
+            }
+            let summary = ImportSummary {
+                path,
+                dry_run,
+                added: plan.added.len(),
+                updated: plan.updated.len(),
+                skipped: plan.skipped.len(),
+                errors,
+            };
+            output::print_result(
+                &summary,
+                &format!(
+                    "Dry run: {} to add, {} to update, {} to skip, {} errors",
+                    summary.added, summary.updated, summary.skipped, summary.errors
+                ),
+            )?;
+            return Ok(());
+        }
+
+        if !plan.is_empty() {
+            self.backups.create(&self.database, "import")?;
+        }
+
+        let mut added = 0;
+        for entry in &plan.added {
+            match self.database.create(entry) {
+                Ok(()) => added += 1,
+                Err(e) => {
+                    eprintln!("Warning: Failed to import {}: {}", entry.service, e);
+                    errors += 1;
+                }
+            }
+        }
+
+        let mut updated = 0;
+        for (entry, _) in plan.updated {
+            let id = entry.id.ok_or("Updated entry has no id")?;
+            let service = entry.service.clone();
+            match self.database.update(id, entry) {
+                Ok(()) => updated += 1,
+                Err(e) => {
+                    eprintln!("Warning: Failed to update {}: {}", service, e);
+                    errors += 1;
+                }
+            }
+        }
+
+        let text = format!(
+            "Imported from {}: {} added, {} updated, {} skipped, {} errors",
+            path,
+            added,
+            updated,
+            plan.skipped.len(),
+            errors
+        );
+        let summary = ImportSummary {
+            path,
+            dry_run,
+            added,
+            updated,
+            skipped: plan.skipped.len(),
+            errors,
+        };
+        output::print_result(&summary, &text)?;
+        Ok(())
+    }
+
+    /// Generate an import template for passwords.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to generate the import template.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the import template cannot be generated.
+    pub fn generate_import_template(
+        &self,
+        path: Option<String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = if let Some(path) = path {
+            path
+        } else {
+            Text::new("Please enter the path to generate the import template:").ask()?
+        };
+
+        let mut writer = Writer::from_writer(Self::create_private(&path)?);
+
+        writer.write_record([
+            "Service",
+            "Username",
+            "Password",
+            "URL",
             "Notes",
             "Created At",
             "Updated At",
         ])?;
 
-        let entries = vec![PasswordExportImport {
+        let entries = [PasswordExportImport {
             service: "Google".to_string(),
             username: "username@gmail.com".to_string(),
-            password: "secure_password".to_string(),
+            password: "secure_password".into(),
             url: "https://google.com/gemail".to_string(),
             notes: "Notes".to_string(),
             created_at: "".to_string(),
@@ -670,8 +2540,8 @@ impl PasswordManager {
 
         writer.flush()?;
 
-        println!("Import template successfully generated at: {}", path);
-        println!("Please Note: The 'Created At' and 'Updated At' fields are optional. You can leave them empty.");
+        message!("Import template successfully generated at: {}", path);
+        message!("Please Note: The 'Created At' and 'Updated At' fields are optional. You can leave them empty.");
 
         Ok(())
     }
@@ -680,11 +2550,12 @@ impl PasswordManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::clipboard::MockClipboard;
 
     #[test]
     fn test_generate_password() {
         let password = PasswordManager::generate_password(Some(16)).unwrap();
-        assert_eq!(password.len(), 16);
+        assert_eq!(password.expose_secret().len(), 16);
     }
 
     #[test]
@@ -697,4 +2568,176 @@ mod tests {
     fn test_is_valid_password_length() {
         assert!(PasswordManager::is_valid_password_length("16"));
     }
+
+    fn create_test_manager(
+        master_password: &SecretString,
+        dir: &tempfile::TempDir,
+    ) -> PasswordManager {
+        let salt = [7u8; 16];
+        let database = Database::new(":memory:".into(), master_password, &salt).unwrap();
+        PasswordManager {
+            length: 16,
+            database,
+            encryption: Encryption::new(master_password, &salt),
+            backups: BackupManager {
+                directory: dir.path().join("backups"),
+                retention: 10,
+                db_path: dir.path().join("pass.db"),
+                master_file: dir.path().join("master.key"),
+            },
+            master_password: master_password.clone(),
+        }
+    }
+
+    #[test]
+    fn test_debug_hides_secrets() {
+        let master_password = SecretString::from("master-secret");
+        let dir = tempfile::tempdir().unwrap();
+        let pw = create_test_manager(&master_password, &dir);
+        let key = pw.database.encryption.get_key(&master_password).unwrap();
+
+        let entry = PasswordEntry::new(
+            "service".to_string(),
+            "user".to_string(),
+            "entry-secret".into(),
+            "https://example.com".to_string(),
+            "".to_string(),
+        )
+        .unwrap();
+        pw.database.create(&entry).unwrap();
+        let entries = pw.database.read().unwrap();
+
+        for output in [
+            format!("{:?}", pw),
+            format!("{:?}", pw.database),
+            format!("{:?}", entries),
+        ] {
+            assert!(!output.contains("master-secret"));
+            assert!(!output.contains("entry-secret"));
+            assert!(!output.contains(key.expose_secret()));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_csv_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        std::fs::write(&path, "").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let entry = PasswordEntry::new(
+            "Mail".to_string(),
+            "alice".to_string(),
+            "mail-secret".into(),
+            String::new(),
+            String::new(),
+        )
+        .unwrap();
+        PasswordManager::write_csv(path.to_str().unwrap(), vec![entry]).unwrap();
+
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("mail-secret"));
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_select_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let pw = create_test_manager(&"master".into(), &dir);
+        for service in ["Mail", "Mailbox", "Bank"] {
+            let entry = PasswordEntry::new(
+                service.to_string(),
+                "user".to_string(),
+                "secret".into(),
+                String::new(),
+                String::new(),
+            )
+            .unwrap();
+            pw.database.create(&entry).unwrap();
+        }
+        let bank = pw.database.search("Bank").unwrap().remove(0);
+
+        assert_eq!(pw.select_entry("mail").unwrap().service, "Mail");
+        assert_eq!(pw.select_entry("box").unwrap().service, "Mailbox");
+        assert_eq!(pw.select_entry(&bank.uuid).unwrap().service, "Bank");
+        let id = bank.id.unwrap().to_string();
+        assert_eq!(pw.select_entry(&id).unwrap().service, "Bank");
+        assert!(pw.select_entry("shop").is_err());
+    }
+
+    #[test]
+    fn test_copy_field() {
+        let clipboard = MockClipboard::default();
+        let mut entry = PasswordEntry::new(
+            "Mail".to_string(),
+            "user".to_string(),
+            "entry-secret".into(),
+            String::new(),
+            String::new(),
+        )
+        .unwrap();
+
+        PasswordManager::copy_field(&clipboard, &entry, CopyField::Password).unwrap();
+        assert_eq!(clipboard.content.borrow().expose_secret(), "entry-secret");
+        PasswordManager::copy_field(&clipboard, &entry, CopyField::Username).unwrap();
+        assert_eq!(clipboard.content.borrow().expose_secret(), "user");
+        assert!(PasswordManager::copy_field(&clipboard, &entry, CopyField::Totp).is_err());
+
+        entry.totp = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".into();
+        let (_, code) = PasswordManager::copy_field(&clipboard, &entry, CopyField::Totp).unwrap();
+        assert_eq!(code.expose_secret().len(), 6);
+        assert_eq!(*clipboard.content.borrow(), code);
+    }
+
+    #[test]
+    fn test_select_sheet_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let pw = create_test_manager(&"master".into(), &dir);
+        for (service, folder) in [
+            ("Bank", "Finance"),
+            ("Broker", "Finance/Stocks"),
+            ("Mail", "Personal"),
+            ("Finance Portal", "Financial"),
+        ] {
+            let mut entry = PasswordEntry::new(
+                service.to_string(),
+                "user".to_string(),
+                "secret".into(),
+                String::new(),
+                String::new(),
+            )
+            .unwrap();
+            entry.folder = folder.to_string();
+            if service == "Finance Portal" {
+                entry.tags = vec!["Recovery".to_string()];
+            }
+            pw.database.create(&entry).unwrap();
+        }
+
+        let services = |entries: Vec<PasswordEntry>| {
+            entries
+                .into_iter()
+                .map(|entry| entry.service)
+                .collect::<Vec<_>>()
+        };
+        let entries = pw
+            .select_sheet_entries(&["Finance/".to_string()], &[], &["mail".to_string()])
+            .unwrap();
+        assert_eq!(services(entries), ["Bank", "Broker", "Mail"]);
+
+        let entries = pw
+            .select_sheet_entries(&[], &[], &["bank".to_string(), "Bank".to_string()])
+            .unwrap();
+        assert_eq!(services(entries), ["Bank"]);
+
+        let entries = pw
+            .select_sheet_entries(&[], &["recovery".to_string()], &["Bank".to_string()])
+            .unwrap();
+        assert_eq!(services(entries), ["Bank", "Finance Portal"]);
+    }
 }
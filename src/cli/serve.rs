@@ -0,0 +1,532 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use log::{error, info, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::cli::output::{self, message, Tabular};
+use crate::cli::password::{EntryDetails, EntrySummary};
+use crate::prelude::{CliError, Config, PasswordEntry, PasswordManager, SecretString};
+use crate::utils::api::{
+    self, ApiToken, ApiTokens, AuditLog, AuditRecord, RpcError, RpcRequest, FORBIDDEN,
+    INTERNAL_ERROR, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR, UNAUTHORIZED,
+};
+use crate::utils::{domain, reference};
+
+/// The file in the configuration directory that holds the hashes of the client tokens.
+const TOKENS_FILE: &str = "api-tokens.json";
+/// The file in the configuration directory every access is recorded in.
+const AUDIT_FILE: &str = "api-audit.log";
+/// The largest request body that is read.
+const MAX_BODY: u64 = 1024 * 1024;
+/// The method recorded in the audit log for requests that cannot be parsed.
+const INVALID_METHOD: &str = "invalid";
+/// The methods of the API and whether they change the vault.
+const METHODS: [(&str, bool); 5] = [
+    ("search", false),
+    ("get", false),
+    ("match", false),
+    ("add", true),
+    ("update", true),
+];
+
+/// A client with a freshly created token.
+#[derive(Serialize, Debug)]
+struct CreatedToken {
+    name: String,
+    token: SecretString,
+    read_only: bool,
+}
+
+/// A client as listed by `api-token list`.
+#[derive(Serialize, Debug)]
+struct TokenSummary {
+    name: String,
+    read_only: bool,
+    created_at: String,
+}
+
+impl Tabular for CreatedToken {
+    fn columns(&self) -> Vec<(String, String)> {
+        vec![
+            ("Name".to_string(), self.name.clone()),
+            ("Token".to_string(), self.token.expose_secret().to_string()),
+            ("Read only".to_string(), yes_no(self.read_only)),
+        ]
+    }
+}
+
+impl Tabular for TokenSummary {
+    fn columns(&self) -> Vec<(String, String)> {
+        vec![
+            ("Name".to_string(), self.name.clone()),
+            ("Read only".to_string(), yes_no(self.read_only)),
+            ("Created".to_string(), self.created_at.clone()),
+        ]
+    }
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "yes" } else { "no" }.to_string()
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SearchParams {
+    query: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GetParams {
+    /// The ID, UUID or service name of the entry.
+    entry: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MatchParams {
+    /// The origin or URL of the page to fill a login into.
+    url: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AddParams {
+    service: String,
+    #[serde(default)]
+    username: String,
+    password: SecretString,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    notes: String,
+    #[serde(default)]
+    folder: String,
+}
+
+/// The fields to change. Fields that are left out keep their value.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UpdateParams {
+    entry: String,
+    service: Option<String>,
+    username: Option<String>,
+    password: Option<SecretString>,
+    url: Option<String>,
+    notes: Option<String>,
+    folder: Option<String>,
+}
+
+pub(crate) fn tokens_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(Config::get_config_dir()?.join(TOKENS_FILE))
+}
+
+/// Create a token for a client of `serve` and print it. It cannot be shown again.
+///
+/// # Arguments
+///
+/// * `name` - The name of the client, shown in the audit log.
+/// * `read_only` - Whether the client may only search, get and match entries.
+///
+/// # Returns
+///
+/// A `Result` containing `()` or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the name is taken or the token cannot be saved.
+pub fn create_api_token(name: String, read_only: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let token = ApiTokens::load(&tokens_path()?)?.create(&name, read_only)?;
+    info!("Created an API token for {}", name);
+    output::print_record(&CreatedToken {
+        name,
+        token,
+        read_only,
+    })?;
+
+    Ok(())
+}
+
+/// List the clients of `serve` with a token.
+///
+/// # Returns
+///
+/// A `Result` containing `()` or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the tokens cannot be read.
+pub fn list_api_tokens() -> Result<(), Box<dyn std::error::Error>> {
+    let summaries = ApiTokens::load(&tokens_path()?)?
+        .list()
+        .iter()
+        .map(|token| TokenSummary {
+            name: token.name.clone(),
+            read_only: token.read_only,
+            created_at: token.created_at.clone(),
+        })
+        .collect::<Vec<_>>();
+    output::print_list(&summaries, "No API tokens found.")
+}
+
+/// Revoke the token of a client of `serve`. A running server rejects it right away.
+///
+/// # Arguments
+///
+/// * `name` - The name of the client.
+///
+/// # Returns
+///
+/// A `Result` containing `()` or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the client has no token or the change cannot be saved.
+pub fn revoke_api_token(name: String) -> Result<(), Box<dyn std::error::Error>> {
+    ApiTokens::load(&tokens_path()?)?.revoke(&name)?;
+    info!("Revoked the API token of {}", name);
+    message!("Revoked the token of {}.", name);
+
+    Ok(())
+}
+
+/// Answer JSON-RPC requests over HTTP until the process ends.
+///
+/// Requests are `POST`ed with an `Authorization: Bearer <token>` header and answered one
+/// after another. The tokens are read for every request, so created and revoked tokens
+/// take effect without a restart.
+///
+/// # Arguments
+///
+/// * `pw` - The unlocked vault.
+/// * `server` - The server listening on the socket.
+///
+/// # Returns
+///
+/// A `Result` containing `()` or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the configuration directory cannot be found.
+pub fn run(pw: &PasswordManager, server: Server) -> Result<(), Box<dyn std::error::Error>> {
+    let config_dir = Config::get_config_dir()?;
+    let tokens = config_dir.join(TOKENS_FILE);
+    let audit = AuditLog::new(config_dir.join(AUDIT_FILE));
+    let content_type =
+        Header::from_bytes("Content-Type", "application/json").map_err(|_| "Invalid header")?;
+
+    for mut request in server.incoming_requests() {
+        let (status, body) = handle(pw, &tokens, &audit, &mut request);
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(content_type.clone());
+        if let Err(e) = request.respond(response) {
+            warn!("Cannot answer an API client: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Authorize and answer one request, recording it in the audit log.
+///
+/// # Returns
+///
+/// The HTTP status and the JSON-RPC response.
+fn handle(
+    pw: &PasswordManager,
+    tokens: &Path,
+    audit: &AuditLog,
+    request: &mut Request,
+) -> (u16, Value) {
+    let failure = |status, code, message: &str| {
+        (
+            status,
+            api::response(Value::Null, Err(RpcError::new(code, message))),
+        )
+    };
+    if *request.method() != Method::Post {
+        return failure(405, INVALID_REQUEST, "Requests must be POSTed");
+    }
+
+    let token = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+        .map(|token| SecretString::new(token.trim().to_string()));
+    let tokens = match ApiTokens::load(tokens) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            error!("Cannot read the API tokens: {}", e);
+            return failure(500, INTERNAL_ERROR, "Cannot read the API tokens");
+        }
+    };
+    let client = token.and_then(|token| tokens.verify(token.expose_secret()).cloned());
+
+    let client_name = client.as_ref().map(|client| client.name.as_str());
+    let rpc = match read_request(request) {
+        Ok(rpc) => rpc,
+        Err(error) => {
+            let (status, error) = match client {
+                Some(_) => (400, error),
+                None => (401, unauthorized()),
+            };
+            let result = Err(error);
+            if let Err(e) = record(audit, client_name, INVALID_METHOD, &result) {
+                error!("Cannot write the audit log: {}", e);
+            }
+            return (
+                status,
+                api::response(Value::Null, result.map(|(value, _)| value)),
+            );
+        }
+    };
+
+    let Some(client) = client.as_ref() else {
+        let result = Err(unauthorized());
+        if let Err(e) = record(audit, None, &rpc.method, &result) {
+            error!("Cannot write the audit log: {}", e);
+        }
+        return (401, api::response(rpc.id, result.map(|(value, _)| value)));
+    };
+    let result = if rpc.jsonrpc != "2.0" {
+        Err(RpcError::new(
+            INVALID_REQUEST,
+            "Only JSON-RPC 2.0 is supported",
+        ))
+    } else {
+        call(pw, client, &rpc.method, rpc.params)
+    };
+
+    let result = match record(audit, Some(&client.name), &rpc.method, &result) {
+        Ok(()) => result.map(|(value, _)| value),
+        Err(e) => {
+            error!("Cannot write the audit log: {}", e);
+            match result {
+                // A change is already stored, so it is reported along with the failure.
+                Ok((mut value, _)) if writes(&rpc.method) => {
+                    if let Value::Object(object) = &mut value {
+                        object.insert(
+                            "audit_error".to_string(),
+                            Value::from("Cannot write the audit log"),
+                        );
+                    }
+                    Ok(value)
+                }
+                // Secrets are not handed out without a record of it.
+                _ => Err(RpcError::new(INTERNAL_ERROR, "Cannot write the audit log")),
+            }
+        }
+    };
+    (200, api::response(rpc.id, result))
+}
+
+/// Read the JSON-RPC request from the body.
+fn read_request(request: &mut Request) -> Result<RpcRequest, RpcError> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_BODY)
+        .read_to_string(&mut body)
+        .map_err(|e| RpcError::new(PARSE_ERROR, format!("Cannot read the request: {}", e)))?;
+    serde_json::from_str(&body)
+        .map_err(|e| RpcError::new(PARSE_ERROR, format!("Invalid JSON-RPC request: {}", e)))
+}
+
+/// Whether a method changes the vault.
+fn writes(method: &str) -> bool {
+    METHODS
+        .iter()
+        .any(|(name, writes)| *name == method && *writes)
+}
+
+fn unauthorized() -> RpcError {
+    RpcError::new(UNAUTHORIZED, "A valid token is needed")
+}
+
+/// Write an access to the audit log.
+fn record(
+    audit: &AuditLog,
+    client: Option<&str>,
+    method: &str,
+    result: &Result<(Value, Option<String>), RpcError>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!("API client {:?} called {}", client, method);
+    audit.record(&AuditRecord {
+        time: Utc::now().to_rfc3339(),
+        client,
+        method,
+        entry: result.as_ref().ok().and_then(|(_, entry)| entry.clone()),
+        ok: result.is_ok(),
+        error: result.as_ref().err().map(|error| error.message.clone()),
+    })
+}
+
+/// Call a method of the API.
+///
+/// # Returns
+///
+/// The result of the method and the UUID of the entry it read or changed, or an error.
+fn call(
+    pw: &PasswordManager,
+    client: &ApiToken,
+    method: &str,
+    params: Value,
+) -> Result<(Value, Option<String>), RpcError> {
+    let Some((_, writes)) = METHODS.iter().find(|(name, _)| *name == method) else {
+        return Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method {}", method),
+        ));
+    };
+    if *writes && client.read_only {
+        return Err(RpcError::new(
+            FORBIDDEN,
+            format!("The token of {} is read-only", client.name),
+        ));
+    }
+
+    let result = match method {
+        "search" => search(pw, parse(params)?),
+        "get" => get(pw, parse(params)?),
+        "match" => find_logins(pw, parse(params)?),
+        "add" => add(pw, parse(params)?),
+        _ => update(pw, parse(params)?),
+    };
+    result.map_err(|e| RpcError::from_error(e.as_ref()))
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| {
+        RpcError::from_error(&CliError::InvalidInput(format!("Invalid params: {}", e)))
+    })
+}
+
+type MethodResult = Result<(Value, Option<String>), Box<dyn std::error::Error>>;
+
+fn search(pw: &PasswordManager, params: SearchParams) -> MethodResult {
+    let summaries = pw
+        .database
+        .search(&params.query)?
+        .iter()
+        .map(EntrySummary::from)
+        .collect::<Vec<_>>();
+    Ok((serde_json::to_value(summaries)?, None))
+}
+
+fn get(pw: &PasswordManager, params: GetParams) -> MethodResult {
+    let entries = pw.database.read()?;
+    let entry = reference::find_entry(&entries, &params.entry)?.clone();
+    let uuid = entry.uuid.clone();
+    Ok((
+        serde_json::to_value(EntryDetails::new(entry, true))?,
+        Some(uuid),
+    ))
+}
+
+/// The logins for a web page, best match first, see `domain::match_origin`.
+fn find_logins(pw: &PasswordManager, params: MatchParams) -> MethodResult {
+    if domain::page_host(&params.url).is_none() {
+        return Err(CliError::InvalidInput(format!(
+            "Logins are only filled into http and https pages, not {}",
+            params.url
+        ))
+        .into());
+    }
+
+    let mut logins = pw
+        .database
+        .read()?
+        .into_iter()
+        .filter(|entry| !entry.password.is_empty())
+        .filter_map(|entry| domain::match_origin(&entry.url, &params.url).map(|rank| (rank, entry)))
+        .collect::<Vec<_>>();
+    logins.sort_by(|(a, a_entry), (b, b_entry)| {
+        b.cmp(a).then(b_entry.updated_at.cmp(&a_entry.updated_at))
+    });
+
+    let uuids = logins
+        .iter()
+        .map(|(_, entry)| entry.uuid.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+    let details = logins
+        .into_iter()
+        .map(|(_, entry)| EntryDetails::new(entry, true))
+        .collect::<Vec<_>>();
+    Ok((
+        serde_json::to_value(details)?,
+        (!uuids.is_empty()).then_some(uuids),
+    ))
+}
+
+fn add(pw: &PasswordManager, params: AddParams) -> MethodResult {
+    if params.service.trim().is_empty() {
+        return Err(CliError::InvalidInput("The service must not be empty".to_string()).into());
+    }
+
+    let mut entry = PasswordEntry::new(
+        params.service,
+        params.username,
+        params.password,
+        params.url,
+        params.notes,
+    )?;
+    entry.folder = params.folder;
+    entry.source = "api".to_string();
+    pw.database.create(&entry)?;
+
+    stored(pw, &entry.uuid)
+}
+
+fn update(pw: &PasswordManager, params: UpdateParams) -> MethodResult {
+    let entries = pw.database.read()?;
+    let mut entry = reference::find_entry(&entries, &params.entry)?.clone();
+    let id = entry.id.ok_or("The entry has no ID")?;
+
+    if let Some(service) = params.service {
+        if service.trim().is_empty() {
+            return Err(CliError::InvalidInput("The service must not be empty".to_string()).into());
+        }
+        entry.service = service;
+    }
+    if let Some(username) = params.username {
+        entry.username = username;
+    }
+    if let Some(password) = params.password {
+        entry.password = password;
+    }
+    if let Some(url) = params.url {
+        entry.url = url;
+    }
+    if let Some(notes) = params.notes {
+        entry.notes = notes;
+    }
+    if let Some(folder) = params.folder {
+        entry.folder = folder;
+    }
+    entry.updated_at = Utc::now().to_rfc3339();
+    let uuid = entry.uuid.clone();
+    pw.database.update(id, entry)?;
+
+    stored(pw, &uuid)
+}
+
+/// The summary of an entry after it was stored.
+fn stored(pw: &PasswordManager, uuid: &str) -> MethodResult {
+    let id = pw
+        .database
+        .find_by_uuid(uuid)?
+        .ok_or("The stored entry cannot be found")?;
+    let entry = pw.database.read_by_id(id)?;
+    Ok((
+        serde_json::to_value(EntrySummary::from(&entry))?,
+        Some(uuid.to_string()),
+    ))
+}
@@ -0,0 +1,459 @@
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::{Command, FromArgMatches, Subcommand};
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Context, Editor, ExternalPrinter, Helper};
+
+use crate::cli::input;
+use crate::cli::output::message;
+use crate::prelude::{CliError, InputArgs, PasswordManager, PasswordManagerCommands, SecretString};
+
+/// The commands of `PasswordManagerCommands` the shell accepts.
+const SHELL_COMMANDS: [&str; 5] = ["list", "show", "search", "add", "copy"];
+/// The commands of the shell itself.
+const BUILTINS: [&str; 4] = ["lock", "clear", "exit", "quit"];
+/// The help of the builtins, shown below the help of the commands.
+const BUILTINS_HELP: &str = "Shell commands:
+  lock   Lock the vault
+  clear  Clear the screen
+  exit   Leave the shell";
+/// The escape sequence that clears the screen and the scrollback.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[3J\x1b[H";
+
+/// A line read by the shell.
+#[derive(Debug)]
+enum Line {
+    Empty,
+    Lock,
+    Clear,
+    Exit,
+    Command(PasswordManagerCommands),
+    /// Help or a usage error printed by clap.
+    Help(clap::Error),
+}
+
+/// The state shared by the shell and its idle timer.
+struct Session {
+    /// The unlocked vault, `None` while locked.
+    manager: Option<PasswordManager>,
+    /// The services of the entries, for tab completion.
+    names: Vec<String>,
+    last_used: Instant,
+}
+
+/// Completes command names and entry names.
+struct ShellHelper {
+    session: Arc<Mutex<Session>>,
+}
+
+impl Session {
+    /// Lock the vault and forget the entry names.
+    fn lock(&mut self) {
+        self.manager = None;
+        self.names.clear();
+    }
+
+    /// Read the entry names again after unlocking or adding an entry.
+    fn refresh_names(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(manager) = &self.manager {
+            self.names = manager
+                .database
+                .read()?
+                .into_iter()
+                .map(|entry| entry.service)
+                .collect();
+            self.names.sort();
+            self.names.dedup();
+        }
+        Ok(())
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let session = lock(&self.session);
+        Ok(complete(&line[..pos], &session.names))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+/// Lock the shared session, also if the idle timer panicked while holding it.
+fn lock(session: &Mutex<Session>) -> MutexGuard<'_, Session> {
+    session.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The clap definition of the shell commands, built from `PasswordManagerCommands`.
+///
+/// Commands the shell does not accept are hidden from the help and rejected by
+/// `execute`.
+fn command() -> Command {
+    let command = Command::new("shell")
+        .no_binary_name(true)
+        .subcommand_required(true)
+        .override_usage("<COMMAND> [OPTIONS]")
+        .after_help(BUILTINS_HELP);
+    let command = PasswordManagerCommands::augment_subcommands(command);
+    let hidden = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .filter(|name| !SHELL_COMMANDS.contains(&name.as_str()))
+        .collect::<Vec<_>>();
+    hidden.iter().fold(command, |command, name| {
+        command.mut_subcommand(name, |subcommand| subcommand.hide(true))
+    })
+}
+
+/// Parse a line with the syntax of the one-shot command line.
+///
+/// # Arguments
+///
+/// * `line` - The line the user entered.
+///
+/// # Returns
+///
+/// A `Result` containing the parsed line or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the line has unbalanced quotes.
+fn parse(line: &str) -> Result<Line, CliError> {
+    let words = shlex::split(line)
+        .ok_or_else(|| CliError::InvalidInput("The line has unbalanced quotes".to_string()))?;
+
+    match words.first().map(String::as_str) {
+        None => Ok(Line::Empty),
+        Some("lock") if words.len() == 1 => Ok(Line::Lock),
+        Some("clear") if words.len() == 1 => Ok(Line::Clear),
+        Some("exit") | Some("quit") if words.len() == 1 => Ok(Line::Exit),
+        Some(_) => match command().try_get_matches_from(&words) {
+            Ok(matches) => PasswordManagerCommands::from_arg_matches(&matches)
+                .map(Line::Command)
+                .or_else(|e| Ok(Line::Help(e))),
+            Err(e) => Ok(Line::Help(e)),
+        },
+    }
+}
+
+/// Complete the word before the cursor: a command name for the first word, an entry name
+/// for the others.
+///
+/// # Arguments
+///
+/// * `line` - The line up to the cursor.
+/// * `names` - The entry names to complete.
+///
+/// # Returns
+///
+/// The start of the completed word and the candidates, quoted as needed.
+fn complete(line: &str, names: &[String]) -> (usize, Vec<Pair>) {
+    // Find the start of the last word, treating quoted spaces as part of the word.
+    let mut start = 0;
+    let mut words = 0;
+    let mut quote = None;
+    let mut in_word = false;
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                if !in_word {
+                    start = index;
+                    in_word = true;
+                }
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words += 1;
+                }
+                in_word = false;
+            }
+            (None, _) if !in_word => {
+                start = index;
+                in_word = true;
+            }
+            (None, _) => {}
+        }
+    }
+    if !in_word {
+        start = line.len();
+    }
+
+    let word = line[start..].trim_start_matches(['"', '\'']).to_lowercase();
+    let candidates: Vec<Pair> = if words == 0 {
+        SHELL_COMMANDS
+            .iter()
+            .chain(BUILTINS.iter())
+            .filter(|command| command.starts_with(&word))
+            .map(|command| Pair {
+                display: command.to_string(),
+                replacement: format!("{} ", command),
+            })
+            .collect()
+    } else {
+        names
+            .iter()
+            .filter(|name| name.to_lowercase().starts_with(&word))
+            .map(|name| Pair {
+                display: name.clone(),
+                replacement: shlex::try_quote(name)
+                    .map(|quoted| quoted.into_owned())
+                    .unwrap_or_else(|_| name.clone()),
+            })
+            .collect()
+    };
+
+    (start, candidates)
+}
+
+/// Run a shell command on the unlocked vault.
+fn execute(
+    session: &mut Session,
+    command: PasswordManagerCommands,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if session.manager.is_none() {
+        session.manager = Some(PasswordManager::new(&InputArgs::default())?);
+        session.refresh_names()?;
+    }
+    let Some(pw) = &session.manager else {
+        unreachable!("the vault was unlocked above")
+    };
+
+    match command {
+        PasswordManagerCommands::List => pw.list_passwords()?,
+        PasswordManagerCommands::Show { id, reveal } => pw.show_password(id, reveal)?,
+        PasswordManagerCommands::Search { query } => pw.search_password(query)?,
+        PasswordManagerCommands::Copy {
+            selector,
+            field,
+            clear_after,
+        } => pw.copy_password(selector, field, clear_after)?,
+        PasswordManagerCommands::Add {
+            service,
+            username,
+            password,
+            url,
+            notes,
+        } => {
+            pw.add_password(
+                service,
+                username,
+                password.map(SecretString::new),
+                url,
+                notes,
+            )?;
+            session.refresh_names()?;
+        }
+        _ => {
+            return Err(CliError::InvalidInput(format!(
+                "The shell only runs {}, use the command line for the others",
+                SHELL_COMMANDS.join(", ")
+            ))
+            .into())
+        }
+    }
+
+    Ok(())
+}
+
+/// Lock the session once it was idle for the given time, until the shell ends.
+fn watch_idle(
+    session: Weak<Mutex<Session>>,
+    lock_after: Duration,
+    mut printer: Option<impl ExternalPrinter>,
+) {
+    loop {
+        thread::sleep(Duration::from_secs(1));
+        let Some(session) = session.upgrade() else {
+            return;
+        };
+        let mut session = lock(&session);
+        if session.manager.is_some() && session.last_used.elapsed() >= lock_after {
+            session.lock();
+            let notice = format!(
+                "{}Vault locked after {} seconds without input. The next command unlocks it.\n",
+                CLEAR_SCREEN,
+                lock_after.as_secs()
+            );
+            match printer.as_mut() {
+                Some(printer) => {
+                    let _ = printer.print(notice);
+                }
+                None => eprint!("{}", notice),
+            }
+        }
+    }
+}
+
+/// Run the shell on an unlocked vault until the user leaves it.
+///
+/// # Arguments
+///
+/// * `manager` - The unlocked password manager.
+/// * `lock_after` - The number of idle seconds after which the vault is locked, 0 to keep
+///   it unlocked.
+///
+/// # Returns
+///
+/// A `Result` containing `()` or an error.
+///
+/// # Errors
+///
+/// An error will be returned if prompts are disabled or the terminal cannot be read.
+pub fn run(manager: PasswordManager, lock_after: u64) -> Result<(), Box<dyn std::error::Error>> {
+    if !input::interactive() {
+        return Err(
+            CliError::InvalidInput("The shell cannot run with --no-input".to_string()).into(),
+        );
+    }
+
+    let session = Arc::new(Mutex::new(Session {
+        manager: Some(manager),
+        names: Vec::new(),
+        last_used: Instant::now(),
+    }));
+    lock(&session).refresh_names()?;
+
+    let config = rustyline::Config::builder()
+        .history_ignore_space(true)
+        .completion_type(CompletionType::List)
+        .build();
+    let mut editor = Editor::<ShellHelper, DefaultHistory>::with_config(config)?;
+    editor.set_helper(Some(ShellHelper {
+        session: Arc::clone(&session),
+    }));
+
+    if lock_after > 0 {
+        let printer = editor.create_external_printer().ok();
+        let session = Arc::downgrade(&session);
+        thread::spawn(move || watch_idle(session, Duration::from_secs(lock_after), printer));
+    }
+
+    message!("Type help for the commands, exit or Ctrl-D to leave.");
+    loop {
+        let line = match editor.readline("vault> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        let parsed = match parse(&line) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                continue;
+            }
+        };
+        // Lines with a password on them stay out of the history.
+        if !matches!(
+            parsed,
+            Line::Command(PasswordManagerCommands::Add {
+                password: Some(_),
+                ..
+            })
+        ) {
+            editor.add_history_entry(line.as_str())?;
+        }
+
+        let mut session = lock(&session);
+        match parsed {
+            Line::Empty => {}
+            Line::Lock => {
+                session.lock();
+                message!("Vault locked. The next command unlocks it.");
+            }
+            Line::Clear => editor.clear_screen()?,
+            Line::Exit => break,
+            Line::Help(e) => e.print()?,
+            Line::Command(command) => {
+                if let Err(e) = execute(&mut session, command) {
+                    eprintln!("Error: {}", e);
+                }
+            }
+        }
+        session.last_used = Instant::now();
+    }
+
+    lock(&session).lock();
+    editor.clear_screen()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replacements(line: &str, names: &[String]) -> (usize, Vec<String>) {
+        let (start, candidates) = complete(line, names);
+        (
+            start,
+            candidates
+                .into_iter()
+                .map(|candidate| candidate.replacement)
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(matches!(parse("  ").unwrap(), Line::Empty));
+        assert!(matches!(parse("exit").unwrap(), Line::Exit));
+        assert!(matches!(parse("lock").unwrap(), Line::Lock));
+        assert!(matches!(
+            parse("show -i 3 --reveal").unwrap(),
+            Line::Command(PasswordManagerCommands::Show {
+                id: Some(3),
+                reveal: true
+            })
+        ));
+        match parse("copy 'My Bank' -f username").unwrap() {
+            Line::Command(PasswordManagerCommands::Copy { selector, .. }) => {
+                assert_eq!(selector, "My Bank")
+            }
+            line => panic!("unexpected line {:?}", line),
+        }
+        assert!(matches!(parse("show -i x").unwrap(), Line::Help(_)));
+        assert!(matches!(parse("help").unwrap(), Line::Help(_)));
+        assert!(parse("copy 'My Bank").is_err());
+    }
+
+    #[test]
+    fn test_complete() {
+        let names = vec!["Mail".to_string(), "My Bank".to_string()];
+
+        assert_eq!(replacements("se", &names), (0, vec!["search ".to_string()]));
+        assert_eq!(
+            replacements("copy m", &names),
+            (5, vec!["Mail".to_string(), "'My Bank'".to_string()])
+        );
+        assert_eq!(
+            replacements("copy 'My B", &names),
+            (5, vec!["'My Bank'".to_string()])
+        );
+        assert_eq!(replacements("search ", &names).1.len(), 2);
+    }
+}
@@ -0,0 +1,953 @@
+use chrono::Utc;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Clear, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use zeroize::Zeroizing;
+
+use crate::cli::output::MASK;
+use crate::cli::{CopyField, PasswordManager};
+use crate::prelude::{Database, PasswordEntry, SecretString};
+use crate::utils::clipboard::Clipboard;
+use crate::utils::totp;
+
+/// The labels of the fields of the entry form, in order.
+const FORM_FIELDS: [&str; 6] = ["Service", "Username", "Password", "URL", "Folder", "Notes"];
+/// The position of the password in `FORM_FIELDS`.
+const PASSWORD_FIELD: usize = 2;
+
+/// The pane that receives the arrow keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Folders,
+    Tags,
+    Entries,
+}
+
+/// An entry being added or edited.
+#[derive(Debug)]
+struct Form {
+    /// The ID of the edited entry, `None` for a new entry.
+    id: Option<i32>,
+    values: Vec<Zeroizing<String>>,
+    focus: usize,
+}
+
+/// What the keyboard currently controls.
+#[derive(Debug)]
+enum Mode {
+    Browse,
+    Filter,
+    Edit(Form),
+    ConfirmSave(Form),
+    ConfirmDelete { id: i32, service: String },
+}
+
+/// The state of the vault browser.
+pub struct App<'a> {
+    database: &'a Database,
+    clipboard: &'a dyn Clipboard,
+    clear_after: u64,
+    entries: Vec<PasswordEntry>,
+    /// The folder paths, including parents of nested folders. The empty path shows all
+    /// entries.
+    folders: Vec<String>,
+    folder: usize,
+    /// The tags of all entries. The empty tag shows all entries.
+    tags: Vec<String>,
+    tag: usize,
+    filter: String,
+    /// The positions in `entries` of the entries in the selected folder with the selected
+    /// tag that match the filter.
+    visible: Vec<usize>,
+    list: ListState,
+    focus: Focus,
+    mode: Mode,
+    reveal: bool,
+    status: String,
+    quit: bool,
+}
+
+impl Form {
+    /// Create a form for a new entry, or one filled with an existing entry.
+    fn new(entry: Option<&PasswordEntry>) -> Self {
+        let values = match entry {
+            Some(entry) => vec![
+                entry.service.clone(),
+                entry.username.clone(),
+                entry.password.expose_secret().to_string(),
+                entry.url.clone(),
+                entry.folder.clone(),
+                entry.notes.clone(),
+            ],
+            None => vec![String::new(); FORM_FIELDS.len()],
+        };
+
+        Self {
+            id: entry.and_then(|entry| entry.id),
+            values: values.into_iter().map(Zeroizing::new).collect(),
+            focus: 0,
+        }
+    }
+}
+
+impl<'a> App<'a> {
+    /// Create a new `App` instance showing every entry of the vault.
+    ///
+    /// # Arguments
+    ///
+    /// * `database` - The unlocked vault.
+    /// * `clipboard` - The clipboard the copy shortcuts use.
+    /// * `clear_after` - The number of seconds until copied values are cleared, 0 to keep
+    ///   them.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `App` instance or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the entries cannot be read.
+    pub fn new(
+        database: &'a Database,
+        clipboard: &'a dyn Clipboard,
+        clear_after: u64,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut app = Self {
+            database,
+            clipboard,
+            clear_after,
+            entries: Vec::new(),
+            folders: Vec::new(),
+            folder: 0,
+            tags: Vec::new(),
+            tag: 0,
+            filter: String::new(),
+            visible: Vec::new(),
+            list: ListState::default(),
+            focus: Focus::Entries,
+            mode: Mode::Browse,
+            reveal: false,
+            status: String::new(),
+            quit: false,
+        };
+        app.reload()?;
+        Ok(app)
+    }
+
+    /// Run the browser until the user quits.
+    ///
+    /// # Arguments
+    ///
+    /// * `terminal` - The terminal to draw on.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the terminal cannot be drawn on or read from.
+    pub fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read the entries again, keeping the selected folder and tag if they still exist.
+    fn reload(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let folder = self.folders.get(self.folder).cloned().unwrap_or_default();
+        let tag = self.tags.get(self.tag).cloned().unwrap_or_default();
+        let selected = self.selected().map(|entry| entry.uuid.clone());
+
+        self.visible.clear();
+        self.entries = self.database.read()?;
+        self.entries
+            .sort_by_key(|entry| (entry.service.to_lowercase(), entry.username.to_lowercase()));
+
+        let mut folders = vec![String::new()];
+        for entry in &self.entries {
+            let mut path = String::new();
+            for part in entry.folder.split('/').filter(|part| !part.is_empty()) {
+                if !path.is_empty() {
+                    path.push('/');
+                }
+                path.push_str(part);
+                folders.push(path.clone());
+            }
+        }
+        folders.sort();
+        folders.dedup();
+        self.folder = folders.iter().position(|f| *f == folder).unwrap_or(0);
+        self.folders = folders;
+
+        let mut tags = vec![String::new()];
+        tags.extend(self.entries.iter().flat_map(|entry| entry.tags.clone()));
+        tags.sort();
+        tags.dedup();
+        self.tag = tags.iter().position(|t| *t == tag).unwrap_or(0);
+        self.tags = tags;
+
+        self.apply_filter();
+        if let Some(uuid) = selected {
+            self.select_uuid(&uuid);
+        }
+        Ok(())
+    }
+
+    /// Update the visible entries after the folder, the tag or the filter changed.
+    fn apply_filter(&mut self) {
+        let selected = self.selected().map(|entry| entry.uuid.clone());
+        let folder = &self.folders[self.folder];
+        let tag = &self.tags[self.tag];
+        let filter = self.filter.to_lowercase();
+
+        self.visible = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| {
+                folder.is_empty()
+                    || entry.folder == *folder
+                    || entry.folder.starts_with(&format!("{}/", folder))
+            })
+            .filter(|(_, entry)| tag.is_empty() || entry.tags.contains(tag))
+            .filter(|(_, entry)| {
+                [&entry.service, &entry.username, &entry.url, &entry.folder]
+                    .into_iter()
+                    .chain(&entry.tags)
+                    .any(|value| value.to_lowercase().contains(&filter))
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        match selected {
+            Some(uuid) => self.select_uuid(&uuid),
+            None => self.list.select((!self.visible.is_empty()).then_some(0)),
+        }
+    }
+
+    /// Select the visible entry with the given UUID, or the first entry without it.
+    fn select_uuid(&mut self, uuid: &str) {
+        let position = self
+            .visible
+            .iter()
+            .position(|index| self.entries[*index].uuid == uuid);
+        self.list.select(match position {
+            Some(position) => Some(position),
+            None => (!self.visible.is_empty()).then_some(0),
+        });
+    }
+
+    /// The highlighted entry.
+    fn selected(&self) -> Option<&PasswordEntry> {
+        self.list
+            .selected()
+            .and_then(|position| self.visible.get(position))
+            .map(|index| &self.entries[*index])
+    }
+
+    /// React to a key press. Failures are shown in the status line.
+    fn handle_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+
+        let result = match std::mem::replace(&mut self.mode, Mode::Browse) {
+            Mode::Browse => self.browse_key(key),
+            Mode::Filter => {
+                self.filter_key(key);
+                Ok(())
+            }
+            Mode::Edit(form) => self.edit_key(form, key),
+            Mode::ConfirmSave(form) => self.confirm_save_key(form, key),
+            Mode::ConfirmDelete { id, service } => self.confirm_delete_key(id, service, key),
+        };
+        if let Err(e) = result {
+            self.status = format!("Error: {}", e);
+        }
+    }
+
+    fn browse_key(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
+        self.status.clear();
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc if !self.filter.is_empty() => {
+                self.filter.clear();
+                self.apply_filter();
+            }
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Left | KeyCode::Char('h') => self.focus = Focus::Folders,
+            KeyCode::Right | KeyCode::Char('l') => self.focus = Focus::Entries,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Folders => Focus::Tags,
+                    Focus::Tags => Focus::Entries,
+                    Focus::Entries => Focus::Folders,
+                }
+            }
+            KeyCode::Enter if self.focus != Focus::Entries => self.focus = Focus::Entries,
+            KeyCode::Char('/') => self.mode = Mode::Filter,
+            KeyCode::Char('r') => self.reveal = !self.reveal,
+            KeyCode::Char('c') => self.copy(CopyField::Password)?,
+            KeyCode::Char('u') => self.copy(CopyField::Username)?,
+            KeyCode::Char('t') => self.copy(CopyField::Totp)?,
+            KeyCode::Char('a') => self.mode = Mode::Edit(Form::new(None)),
+            KeyCode::Char('e') | KeyCode::Enter => {
+                if let Some(entry) = self.selected() {
+                    self.mode = Mode::Edit(Form::new(Some(entry)));
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(entry) = self.selected() {
+                    self.mode = Mode::ConfirmDelete {
+                        id: entry.id.ok_or("The entry has no ID")?,
+                        service: entry.service.clone(),
+                    };
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn filter_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => return,
+            KeyCode::Esc => self.filter.clear(),
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            KeyCode::Char(c) => self.filter.push(c),
+            _ => {}
+        }
+        if key.code != KeyCode::Esc {
+            self.mode = Mode::Filter;
+        }
+        self.apply_filter();
+    }
+
+    fn edit_key(
+        &mut self,
+        mut form: Form,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => {
+                self.status = "Changes discarded.".to_string();
+                return Ok(());
+            }
+            KeyCode::Enter if form.values[0].trim().is_empty() => {
+                self.status = "The service must not be empty.".to_string();
+            }
+            KeyCode::Enter => {
+                self.mode = Mode::ConfirmSave(form);
+                return Ok(());
+            }
+            KeyCode::Tab | KeyCode::Down => form.focus = (form.focus + 1) % FORM_FIELDS.len(),
+            KeyCode::BackTab | KeyCode::Up => {
+                form.focus = (form.focus + FORM_FIELDS.len() - 1) % FORM_FIELDS.len()
+            }
+            KeyCode::Char('g') if control => {
+                let password = PasswordManager::generate_password(Some(16))?;
+                form.values[PASSWORD_FIELD] = Zeroizing::new(password.expose_secret().to_string());
+                form.focus = PASSWORD_FIELD;
+            }
+            KeyCode::Char('r') if control => self.reveal = !self.reveal,
+            KeyCode::Backspace => {
+                form.values[form.focus].pop();
+            }
+            KeyCode::Char(c) if !control => form.values[form.focus].push(c),
+            _ => {}
+        }
+        self.mode = Mode::Edit(form);
+        Ok(())
+    }
+
+    fn confirm_save_key(
+        &mut self,
+        form: Form,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => self.save(form),
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.mode = Mode::Edit(form);
+                Ok(())
+            }
+            _ => {
+                self.mode = Mode::ConfirmSave(form);
+                Ok(())
+            }
+        }
+    }
+
+    fn confirm_delete_key(
+        &mut self,
+        id: i32,
+        service: String,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                self.database.delete(id)?;
+                self.reload()?;
+                self.status = format!("Deleted {}.", service);
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {}
+            _ => self.mode = Mode::ConfirmDelete { id, service },
+        }
+        Ok(())
+    }
+
+    /// Move the highlight of the focused pane.
+    fn move_selection(&mut self, step: isize) {
+        match self.focus {
+            Focus::Folders => {
+                self.folder = self
+                    .folder
+                    .saturating_add_signed(step)
+                    .min(self.folders.len() - 1);
+                self.apply_filter();
+            }
+            Focus::Tags => {
+                self.tag = self
+                    .tag
+                    .saturating_add_signed(step)
+                    .min(self.tags.len() - 1);
+                self.apply_filter();
+            }
+            Focus::Entries => {
+                if let Some(position) = self.list.selected() {
+                    let position = position
+                        .saturating_add_signed(step)
+                        .min(self.visible.len().saturating_sub(1));
+                    self.list.select(Some(position));
+                }
+            }
+        }
+    }
+
+    /// Copy a field of the highlighted entry and schedule clearing the clipboard.
+    fn copy(&mut self, field: CopyField) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(entry) = self.selected() else {
+            return Ok(());
+        };
+        let service = entry.service.clone();
+        let (name, value) = PasswordManager::copy_field(self.clipboard, entry, field)?;
+
+        if self.clear_after == 0 {
+            self.status = format!("Copied the {} of {}.", name, service);
+        } else {
+            PasswordManager::schedule_clear(self.clipboard.name(), &value, self.clear_after)?;
+            let unconditionally = if self.clipboard.can_paste() {
+                ""
+            } else {
+                ", even if something else is copied meanwhile"
+            };
+            self.status = format!(
+                "Copied the {} of {}, clearing it in {} seconds{}.",
+                name, service, self.clear_after, unconditionally
+            );
+        }
+        Ok(())
+    }
+
+    /// Add or update the entry of a confirmed form.
+    fn save(&mut self, form: Form) -> Result<(), Box<dyn std::error::Error>> {
+        let [service, username, password, url, folder, notes] = [0, 1, 2, 3, 4, 5].map(|field| {
+            form.values[field]
+                .trim_end_matches(['\r', '\n'])
+                .to_string()
+        });
+        let password = SecretString::new(password);
+
+        let uuid = match form.id {
+            Some(id) => {
+                let mut entry = self.database.read_by_id(id)?;
+                entry.service = service;
+                entry.username = username;
+                entry.password = password;
+                entry.url = url;
+                entry.folder = folder;
+                entry.notes = notes;
+                entry.updated_at = Utc::now().to_rfc3339();
+                let uuid = entry.uuid.clone();
+                self.database.update(id, entry)?;
+                self.status = "Entry updated.".to_string();
+                uuid
+            }
+            None => {
+                let mut entry = PasswordEntry::new(service, username, password, url, notes)?;
+                entry.folder = folder;
+                self.database.create(&entry)?;
+                self.status = "Entry added.".to_string();
+                entry.uuid
+            }
+        };
+
+        self.reload()?;
+        self.select_uuid(&uuid);
+        Ok(())
+    }
+
+    /// Draw the browser.
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        let [sidebar, entries, details] = Layout::horizontal([
+            Constraint::Percentage(22),
+            Constraint::Percentage(33),
+            Constraint::Percentage(45),
+        ])
+        .areas(main);
+        let [folders, tags] =
+            Layout::vertical([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(sidebar);
+
+        self.draw_folders(frame, folders);
+        self.draw_tags(frame, tags);
+        self.draw_entries(frame, entries);
+        self.draw_details(frame, details);
+        frame.render_widget(Paragraph::new(self.status_line()), status);
+
+        match &self.mode {
+            Mode::Edit(form) => self.draw_form(frame, form),
+            Mode::ConfirmSave(form) => {
+                let question = match form.id {
+                    Some(_) => format!("Save the changes to {}? (y/n)", form.values[0].as_str()),
+                    None => format!("Add {}? (y/n)", form.values[0].as_str()),
+                };
+                draw_question(frame, &question);
+            }
+            Mode::ConfirmDelete { service, .. } => {
+                draw_question(frame, &format!("Delete {}? (y/n)", service));
+            }
+            Mode::Browse | Mode::Filter => {}
+        }
+    }
+
+    fn draw_folders(&self, frame: &mut Frame, area: Rect) {
+        let items = self.folders.iter().map(|folder| {
+            if folder.is_empty() {
+                ListItem::new("All entries")
+            } else {
+                let depth = folder.matches('/').count();
+                let name = folder.rsplit('/').next().unwrap_or(folder);
+                ListItem::new(format!("{}{}", "  ".repeat(depth), name))
+            }
+        });
+        let mut state = ListState::default().with_selected(Some(self.folder));
+        let list = List::new(items)
+            .block(self.pane("Folders", self.focus == Focus::Folders))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn draw_tags(&self, frame: &mut Frame, area: Rect) {
+        let items = self.tags.iter().map(|tag| {
+            if tag.is_empty() {
+                ListItem::new("All tags")
+            } else {
+                ListItem::new(tag.clone())
+            }
+        });
+        let mut state = ListState::default().with_selected(Some(self.tag));
+        let list = List::new(items)
+            .block(self.pane("Tags", self.focus == Focus::Tags))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn draw_entries(&mut self, frame: &mut Frame, area: Rect) {
+        let items = self
+            .visible
+            .iter()
+            .map(|index| {
+                let entry = &self.entries[*index];
+                if entry.username.is_empty() {
+                    ListItem::new(entry.service.clone())
+                } else {
+                    ListItem::new(format!("{} ({})", entry.service, entry.username))
+                }
+            })
+            .collect::<Vec<_>>();
+        let title = if self.filter.is_empty() {
+            format!("Entries ({})", self.visible.len())
+        } else {
+            format!(
+                "Entries ({}) matching \"{}\"",
+                self.visible.len(),
+                self.filter
+            )
+        };
+        let list = List::new(items)
+            .block(self.pane(&title, self.focus == Focus::Entries))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.list);
+    }
+
+    fn draw_details(&self, frame: &mut Frame, area: Rect) {
+        let Some(entry) = self.selected() else {
+            frame.render_widget(
+                Paragraph::new("No entry selected.").block(self.pane("Details", false)),
+                area,
+            );
+            return;
+        };
+
+        let secret = |value: &SecretString| {
+            if self.reveal || value.is_empty() {
+                value.expose_secret().to_string()
+            } else {
+                MASK.to_string()
+            }
+        };
+        let mut lines = vec![
+            Line::from(format!("Service:  {}", entry.service)),
+            Line::from(format!("Username: {}", entry.username)),
+            Line::from(format!("Password: {}", secret(&entry.password))),
+            Line::from(format!("URL:      {}", entry.url)),
+            Line::from(format!("Folder:   {}", entry.folder)),
+            Line::from(format!("Tags:     {}", entry.tags.join(", "))),
+        ];
+        if !entry.totp.is_empty() {
+            let code = match totp::generate(&entry.totp, Utc::now().timestamp().max(0) as u64) {
+                Ok(code) => secret(&code),
+                Err(e) => e,
+            };
+            lines.push(Line::from(format!("TOTP:     {}", code)));
+        }
+        for field in &entry.custom_fields {
+            let value = if field.hidden {
+                secret(&field.value)
+            } else {
+                field.value.expose_secret().to_string()
+            };
+            lines.push(Line::from(format!("{}: {}", field.name, value)));
+        }
+        lines.push(Line::from(format!("Updated:  {}", entry.updated_at)));
+        if !entry.notes.is_empty() {
+            lines.push(Line::from(""));
+            lines.extend(entry.notes.lines().map(|line| Line::from(line.to_string())));
+        }
+
+        frame.render_widget(
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .block(self.pane("Details", false)),
+            area,
+        );
+    }
+
+    fn draw_form(&self, frame: &mut Frame, form: &Form) {
+        let area = centered(frame.area(), 60, FORM_FIELDS.len() as u16 + 4);
+        let lines = FORM_FIELDS
+            .iter()
+            .enumerate()
+            .map(|(field, label)| {
+                let value = if field == PASSWORD_FIELD && !self.reveal {
+                    "*".repeat(form.values[field].chars().count())
+                } else {
+                    form.values[field].to_string()
+                };
+                let cursor = if field == form.focus { "_" } else { "" };
+                let line = Line::from(format!("{:<9} {}{}", format!("{}:", label), value, cursor));
+                if field == form.focus {
+                    line.style(Style::new().add_modifier(Modifier::BOLD))
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>();
+        let title = if form.id.is_some() {
+            "Edit entry"
+        } else {
+            "Add entry"
+        };
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(
+                Block::bordered().title(title).title_bottom(
+                    "Tab next  Ctrl-G generate  Ctrl-R reveal  Enter save  Esc cancel",
+                ),
+            ),
+            area,
+        );
+    }
+
+    /// The bordered block of a pane, highlighted if it has the focus.
+    fn pane(&self, title: &str, focused: bool) -> Block<'static> {
+        let block = Block::bordered().title(title.to_string());
+        if focused {
+            block.border_style(Style::new().add_modifier(Modifier::BOLD))
+        } else {
+            block
+        }
+    }
+
+    /// The text of the status line: the last message, the filter or the key help.
+    fn status_line(&self) -> String {
+        match &self.mode {
+            Mode::Filter => format!("Filter: {}_  (Enter keep, Esc clear)", self.filter),
+            _ if !self.status.is_empty() => self.status.clone(),
+            _ => "/ filter  Tab pane  r reveal  c copy password  u username  t TOTP  a add  e edit  d delete  q quit"
+                .to_string(),
+        }
+    }
+}
+
+/// Draw a yes/no question in the middle of the screen.
+fn draw_question(frame: &mut Frame, question: &str) {
+    let area = centered(frame.area(), question.len() as u16 + 4, 3);
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(question).block(Block::bordered().title("Confirm")),
+        area,
+    );
+}
+
+/// A rectangle of the given size in the middle of an area.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}
+
+/// Browse the vault in a full-screen terminal UI until the user quits.
+///
+/// # Arguments
+///
+/// * `database` - The unlocked vault.
+/// * `clipboard` - The clipboard the copy shortcuts use.
+/// * `clear_after` - The number of seconds until copied values are cleared, 0 to keep them.
+///
+/// # Returns
+///
+/// A `Result` containing `()` or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the entries cannot be read or the terminal fails.
+pub fn run(
+    database: &Database,
+    clipboard: &dyn Clipboard,
+    clear_after: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut app = App::new(database, clipboard, clear_after)?;
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::clipboard::MockClipboard;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn create_test_database() -> Database {
+        let database = Database::new(":memory:".into(), &"master".into(), &[7u8; 16]).unwrap();
+        for (service, username, password, folder, tags) in [
+            ("Mail", "alice", "mail-secret", "Personal", &["family"][..]),
+            (
+                "Bank",
+                "alice",
+                "bank-secret",
+                "Finance",
+                &["family", "money"][..],
+            ),
+            (
+                "Broker",
+                "bob",
+                "broker-secret",
+                "Finance/Stocks",
+                &["money"][..],
+            ),
+        ] {
+            let mut entry = PasswordEntry::new(
+                service.to_string(),
+                username.to_string(),
+                password.into(),
+                String::new(),
+                String::new(),
+            )
+            .unwrap();
+            entry.folder = folder.to_string();
+            entry.tags = tags.iter().map(|tag| tag.to_string()).collect();
+            database.create(&entry).unwrap();
+        }
+        database
+    }
+
+    fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+    }
+
+    fn press_code(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::from(code));
+    }
+
+    fn visible_services(app: &App) -> Vec<String> {
+        app.visible
+            .iter()
+            .map(|index| app.entries[*index].service.clone())
+            .collect()
+    }
+
+    fn render(app: &mut App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(120, 20)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_and_folders() {
+        let database = create_test_database();
+        let clipboard = MockClipboard::default();
+        let mut app = App::new(&database, &clipboard, 0).unwrap();
+        assert_eq!(visible_services(&app), ["Bank", "Broker", "Mail"]);
+        assert_eq!(app.folders, ["", "Finance", "Finance/Stocks", "Personal"]);
+
+        press(&mut app, "/b");
+        assert_eq!(visible_services(&app), ["Bank", "Broker"]);
+        press(&mut app, "ob");
+        assert_eq!(visible_services(&app), ["Broker"]);
+        press_code(&mut app, KeyCode::Esc);
+        assert_eq!(visible_services(&app).len(), 3);
+
+        press_code(&mut app, KeyCode::Left);
+        press_code(&mut app, KeyCode::Down);
+        assert_eq!(visible_services(&app), ["Bank", "Broker"]);
+        press_code(&mut app, KeyCode::Down);
+        assert_eq!(visible_services(&app), ["Broker"]);
+    }
+
+    #[test]
+    fn test_tags() {
+        let database = create_test_database();
+        let clipboard = MockClipboard::default();
+        let mut app = App::new(&database, &clipboard, 0).unwrap();
+        assert_eq!(app.tags, ["", "family", "money"]);
+
+        press_code(&mut app, KeyCode::Tab);
+        press_code(&mut app, KeyCode::Tab);
+        assert_eq!(app.focus, Focus::Tags);
+        press_code(&mut app, KeyCode::Down);
+        assert_eq!(visible_services(&app), ["Bank", "Mail"]);
+        press_code(&mut app, KeyCode::Down);
+        assert_eq!(visible_services(&app), ["Bank", "Broker"]);
+
+        press_code(&mut app, KeyCode::Left);
+        press_code(&mut app, KeyCode::Down);
+        press_code(&mut app, KeyCode::Down);
+        assert_eq!(visible_services(&app), ["Broker"]);
+
+        press_code(&mut app, KeyCode::Up);
+        press_code(&mut app, KeyCode::Up);
+        press_code(&mut app, KeyCode::Right);
+        press(&mut app, "/family");
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(visible_services(&app), ["Bank"]);
+        assert!(render(&mut app).contains("Tags:     family, money"));
+    }
+
+    #[test]
+    fn test_reveal_and_copy() {
+        let database = create_test_database();
+        let clipboard = MockClipboard::default();
+        let mut app = App::new(&database, &clipboard, 0).unwrap();
+
+        assert!(!render(&mut app).contains("bank-secret"));
+        press(&mut app, "r");
+        assert!(render(&mut app).contains("bank-secret"));
+
+        press(&mut app, "jc");
+        assert_eq!(clipboard.content.borrow().expose_secret(), "broker-secret");
+        press(&mut app, "u");
+        assert_eq!(clipboard.content.borrow().expose_secret(), "bob");
+        press(&mut app, "t");
+        assert!(app.status.starts_with("Error"));
+    }
+
+    #[test]
+    fn test_add_edit_and_delete() {
+        let database = create_test_database();
+        let clipboard = MockClipboard::default();
+        let mut app = App::new(&database, &clipboard, 0).unwrap();
+
+        press(&mut app, "aShop");
+        press_code(&mut app, KeyCode::Tab);
+        press(&mut app, "carol");
+        press_code(&mut app, KeyCode::Tab);
+        press(&mut app, "shop-secret");
+        press_code(&mut app, KeyCode::Enter);
+        press(&mut app, "y");
+        let shop = database.search("Shop").unwrap().remove(0);
+        assert_eq!(shop.username, "carol");
+        assert_eq!(shop.password.expose_secret(), "shop-secret");
+        assert_eq!(app.selected().unwrap().service, "Shop");
+
+        press(&mut app, "e");
+        press_code(&mut app, KeyCode::Tab);
+        press_code(&mut app, KeyCode::Backspace);
+        press(&mut app, "e");
+        press_code(&mut app, KeyCode::Enter);
+        press(&mut app, "n");
+        press_code(&mut app, KeyCode::Esc);
+        assert_eq!(
+            database.read_by_id(shop.id.unwrap()).unwrap().username,
+            "carol"
+        );
+
+        press(&mut app, "e");
+        press_code(&mut app, KeyCode::Tab);
+        press_code(&mut app, KeyCode::Backspace);
+        press(&mut app, "e");
+        press_code(&mut app, KeyCode::Enter);
+        press(&mut app, "y");
+        assert_eq!(
+            database.read_by_id(shop.id.unwrap()).unwrap().username,
+            "caroe"
+        );
+
+        press(&mut app, "dn");
+        assert_eq!(database.read().unwrap().len(), 4);
+        press(&mut app, "dy");
+        assert_eq!(database.read().unwrap().len(), 3);
+        assert_eq!(app.status, "Deleted Shop.");
+    }
+}
@@ -3,10 +3,20 @@ mod utils;
 
 pub mod prelude {
     pub use crate::cli::{
-        Cli, Commands, FileSearch, PasswordCommands, PasswordManager, PasswordManagerCommands,
+        create_api_token, install_native_host, list_api_tokens, print_completions, print_message,
+        revoke_api_token, run_native_host, write_manpages, ApiTokenCommands, Browser, Cli,
+        Commands, ConflictStrategy, CopyField, ExportFormat, FileSearch, ImportFormat, InputArgs,
+        NativeHostCommands, OutputFormat, PasswordCommands, PasswordManager,
+        PasswordManagerCommands,
     };
+    pub use crate::utils::backup::BackupManager;
     pub use crate::utils::config::Config;
-    pub use crate::utils::database::{Database, PasswordEntry};
+    pub use crate::utils::database::{
+        CustomField, Database, PasswordEntry, RemoteCredentials, VerifyReport,
+    };
     pub use crate::utils::encryption::Encryption;
-    pub use crate::utils::errors::FileSearchError;
+    pub use crate::utils::errors::{exit_code, CliError, DatabaseError, FileSearchError};
+    pub use crate::utils::export::ExportPayload;
+    pub use crate::utils::secret::SecretString;
+    pub use crate::utils::sync::{SyncReport, WebDavRemote};
 }
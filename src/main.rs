@@ -1,13 +1,29 @@
+use std::process::ExitCode;
+
 use clap::Parser;
-use log::info;
+use log::{error, info};
 use rusty_toolkit::prelude::*;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli) {
+        Ok(code) => code,
+        Err(e) => {
+            error!("{}", e);
+            eprintln!("Error: {}", e);
+            ExitCode::from(exit_code(e.as_ref()))
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<ExitCode, Box<dyn std::error::Error>> {
     let config = Config::load()?;
     config.setup_logger()?;
 
     info!("Starting rusty-toolkit...");
-    let cli = Cli::parse();
+    cli.input.apply();
+    cli.output.apply();
 
     match cli.commands {
         Commands::FileSearch { path, name } => {
@@ -18,15 +34,117 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             let mut file_search = FileSearch::new(path, name)?;
             file_search.run()?;
         }
+        Commands::Run {
+            env,
+            no_mask,
+            command,
+        } => {
+            info!("Running a command with {} secrets", env.len());
+            let pw = PasswordManager::new(&cli.input)?;
+            let code = pw.run_with_secrets(env, command, !no_mask)?;
+            return Ok(ExitCode::from(code));
+        }
+        Commands::Inject { input, path } => {
+            info!("Rendering the template {:?}", input);
+            let pw = PasswordManager::new(&cli.input)?;
+            pw.inject(input, path)?;
+        }
+        Commands::GitCredential { operation } => {
+            info!("Answering git credential {}", operation);
+            PasswordManager::git_credential(operation, &cli.input)?;
+        }
+        Commands::SshAgent {
+            socket,
+            confirm,
+            lifetime,
+        } => {
+            info!("Starting the SSH agent");
+            let pw = PasswordManager::new(&cli.input)?;
+            pw.ssh_agent(socket, confirm, lifetime)?;
+        }
+        Commands::Serve { socket } => {
+            info!("Serving the vault");
+            let pw = PasswordManager::new(&cli.input)?;
+            pw.serve(socket)?;
+        }
+        Commands::ApiToken { subcommand } => match subcommand {
+            ApiTokenCommands::Create { name, read_only } => {
+                info!("Creating an API token");
+                create_api_token(name, read_only)?;
+            }
+            ApiTokenCommands::List => {
+                info!("Listing the API tokens");
+                list_api_tokens()?;
+            }
+            ApiTokenCommands::Revoke { name } => {
+                info!("Revoking an API token");
+                revoke_api_token(name)?;
+            }
+        },
+        Commands::NativeHost { subcommand } => match subcommand {
+            NativeHostCommands::Install {
+                browser,
+                extension_id,
+                socket,
+                print,
+            } => {
+                info!("Installing the native-messaging host for {:?}", browser);
+                install_native_host(browser, extension_id, socket, print)?;
+            }
+            NativeHostCommands::Run {
+                socket,
+                token_file,
+                browser_args,
+            } => {
+                info!("Starting the native-messaging host for {:?}", browser_args);
+                run_native_host(socket, token_file)?;
+            }
+        },
+        Commands::Completions { shell } => {
+            info!("Printing the completions for {}", shell);
+            print_completions(shell)?;
+        }
+        Commands::Manpage { dir } => {
+            info!("Writing man pages to {:?}", dir);
+            for path in write_manpages(&dir)? {
+                print_message(&format!("Wrote {}", path.display()));
+            }
+        }
         Commands::Password { subcommand } => match subcommand {
             PasswordCommands::Generate { length } => {
                 info!("Generating password with length: {:?}", length);
-                let password = PasswordManager::generate_password(length)?;
-                println!("Generated password: {}", password);
+                PasswordManager::print_generated_password(length)?;
                 info!("Generating Password successfully");
             }
+            PasswordCommands::Shell { lock_after } => {
+                info!("Starting the password shell");
+                let pw = PasswordManager::new(&cli.input)?;
+                pw.run_shell(lock_after)?;
+            }
+            PasswordCommands::Sync {
+                remote,
+                username,
+                login,
+                migrate_remote,
+            } => {
+                info!("Syncing the password vault");
+                let pw = PasswordManager::new(&cli.input)?;
+                pw.sync_vault(remote, username, login, migrate_remote)?;
+            }
+            PasswordCommands::ClearClipboard { backend, after } => {
+                info!("Clearing the clipboard in {} seconds", after);
+                PasswordManager::clear_clipboard(backend, after)?;
+            }
+            PasswordCommands::Manage {
+                subcommand: PasswordManagerCommands::Restore { name },
+            } => {
+                info!("Restoring a backup");
+                PasswordManager::restore_backup(name, &cli.input)?;
+
+                print_message("Backup restored.");
+            }
             PasswordCommands::Manage { subcommand } => {
-                let pw = PasswordManager::new()?;
+                let pw = PasswordManager::new(&cli.input)?;
 
                 match subcommand {
                     PasswordManagerCommands::Add {
@@ -37,15 +155,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         notes,
                     } => {
                         info!("Adding a new password");
-                        pw.add_password(service, username, password, url, notes)?;
-
-                        println!("New Password added.");
+                        pw.add_password(
+                            service,
+                            username,
+                            password.map(SecretString::new),
+                            url,
+                            notes,
+                        )?;
+                    }
+                    PasswordManagerCommands::AddSshKey { key, service } => {
+                        info!("Adding an SSH key");
+                        pw.add_ssh_key(key, service)?;
                     }
                     PasswordManagerCommands::Remove { id } => {
                         info!("Removing a Password");
                         pw.remove_password(id)?;
-
-                        println!("Password removed.");
                     }
                     PasswordManagerCommands::List => {
                         info!("Listing all Passwords");
@@ -60,25 +184,73 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         notes,
                     } => {
                         info!("Updating a Password");
-                        pw.update_password(id, service, username, password, url, notes)?;
-
-                        println!("Password updated.");
+                        pw.update_password(
+                            id,
+                            service,
+                            username,
+                            password.map(SecretString::new),
+                            url,
+                            notes,
+                        )?;
                     }
-                    PasswordManagerCommands::Show { id } => {
+                    PasswordManagerCommands::Show { id, reveal } => {
                         info!("Showing a Password");
-                        pw.show_password(id)?;
+                        pw.show_password(id, reveal)?;
+                    }
+                    PasswordManagerCommands::Copy {
+                        selector,
+                        field,
+                        clear_after,
+                    } => {
+                        info!("Copying the {:?} of an entry", field);
+                        pw.copy_password(selector, field, clear_after)?;
+                    }
+                    PasswordManagerCommands::Tui => {
+                        info!("Browsing the vault");
+                        pw.browse()?;
                     }
                     PasswordManagerCommands::Search { query } => {
                         info!("Searching for a Password");
                         pw.search_password(query)?;
                     }
-                    PasswordManagerCommands::Export { path } => {
-                        info!("Exporting Passwords");
-                        pw.export_passwords(path)?;
+                    PasswordManagerCommands::Export { path, format } => {
+                        info!("Exporting Passwords as {:?}", format);
+                        pw.export_passwords(path, format)?;
+                    }
+                    PasswordManagerCommands::EmergencySheet {
+                        path,
+                        folder,
+                        tag,
+                        entry,
+                    } => {
+                        info!("Writing an emergency sheet");
+                        pw.write_emergency_sheet(path, folder, tag, entry)?;
+                    }
+                    PasswordManagerCommands::Import {
+                        path,
+                        format,
+                        keyfile,
+                        map,
+                        on_conflict,
+                        dry_run,
+                    } => {
+                        info!("Importing Passwords as {:?}", format);
+                        pw.import_passwords(path, format, keyfile, map, on_conflict, dry_run)?;
+                    }
+                    PasswordManagerCommands::Verify => {
+                        info!("Verifying the password vault");
+                        pw.verify_vault()?;
+                    }
+                    PasswordManagerCommands::Backup { list } => {
+                        info!("Backing up the password vault");
+                        pw.backup_vault(list)?;
+                    }
+                    PasswordManagerCommands::Merge { path, key, migrate } => {
+                        info!("Merging another vault");
+                        pw.merge_vault(path, key, migrate)?;
                     }
-                    PasswordManagerCommands::Import { path } => {
-                        info!("Importing Passwords");
-                        pw.import_passwords(path)?;
+                    PasswordManagerCommands::Restore { .. } => {
+                        unreachable!("restore is handled before unlocking the vault")
                     }
                     PasswordManagerCommands::GenerateImportTemplate { path } => {
                         info!("Generating Import Template");
@@ -89,5 +261,5 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         },
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,469 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chrono::Utc;
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::prelude::{CliError, DatabaseError, SecretString};
+
+/// The prefix of tokens, so they are easy to spot in configuration and scanners.
+const TOKEN_PREFIX: &str = "rtk_";
+
+/// JSON-RPC error codes, see <https://www.jsonrpc.org/specification#error_object>.
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+/// The request has no valid token.
+pub const UNAUTHORIZED: i64 = -32001;
+/// The token may not call the method, e.g. a read-only token calling `add`.
+pub const FORBIDDEN: i64 = -32003;
+/// The entry the request refers to does not exist.
+pub const NOT_FOUND: i64 = -32004;
+
+/// A client allowed to call the API. Only a hash of its token is stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub name: String,
+    hash: String,
+    /// Whether the client may only search, get and match entries.
+    pub read_only: bool,
+    pub created_at: String,
+}
+
+/// The tokens of the API clients, stored in a file only the user can read.
+#[derive(Debug)]
+pub struct ApiTokens {
+    path: PathBuf,
+    tokens: Vec<ApiToken>,
+}
+
+impl ApiTokens {
+    /// Load the tokens from a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file. A missing file has no tokens.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the tokens or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let tokens = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(path)?)
+                .map_err(|e| format!("Cannot parse {}: {}", path.display(), e))?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            tokens,
+        })
+    }
+
+    /// The clients with a token.
+    pub fn list(&self) -> &[ApiToken] {
+        &self.tokens
+    }
+
+    /// Create a token for a new client and save it.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the client, shown in the audit log.
+    /// * `read_only` - Whether the client may only search, get and match entries.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the token, which cannot be shown again, or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the name is empty or taken, or the file cannot be written.
+    pub fn create(
+        &mut self,
+        name: &str,
+        read_only: bool,
+    ) -> Result<SecretString, Box<dyn std::error::Error>> {
+        if name.trim().is_empty() {
+            return Err(CliError::InvalidInput("The client needs a name".to_string()).into());
+        }
+        if self.tokens.iter().any(|token| token.name == name) {
+            return Err(CliError::InvalidInput(format!(
+                "A client named {} already has a token, revoke it first",
+                name
+            ))
+            .into());
+        }
+
+        let mut bytes = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| "Failed to generate a token")?;
+        let token = SecretString::new(format!("{}{}", TOKEN_PREFIX, URL_SAFE_NO_PAD.encode(bytes)));
+
+        self.tokens.push(ApiToken {
+            name: name.to_string(),
+            hash: hash(token.expose_secret()),
+            read_only,
+            created_at: Utc::now().to_rfc3339(),
+        });
+        self.save()?;
+        Ok(token)
+    }
+
+    /// Revoke the token of a client and save the change.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the client.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the client has no token or the file cannot be written.
+    pub fn revoke(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let count = self.tokens.len();
+        self.tokens.retain(|token| token.name != name);
+        if self.tokens.len() == count {
+            return Err(CliError::NotFound(format!("No client named {}", name)).into());
+        }
+        self.save()
+    }
+
+    /// Find the client a token belongs to.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The token the client sent.
+    ///
+    /// # Returns
+    ///
+    /// The client, or `None` if the token is unknown.
+    pub fn verify(&self, token: &str) -> Option<&ApiToken> {
+        let hash = hash(token);
+        self.tokens.iter().find(|token| token.hash == hash)
+    }
+
+    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&self.path)?;
+        // The mode only applies to new files, an existing store is restricted here.
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+        file.write_all(serde_json::to_string_pretty(&self.tokens)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Hash a token. Tokens are random, so a plain SHA-256 is enough.
+fn hash(token: &str) -> String {
+    STANDARD.encode(digest(&SHA256, token.as_bytes()))
+}
+
+/// An access to the API as written to the audit log.
+#[derive(Debug, Serialize)]
+pub struct AuditRecord<'a> {
+    pub time: String,
+    /// The name of the client, `None` if it sent no valid token.
+    pub client: Option<&'a str>,
+    pub method: &'a str,
+    /// The UUID of the entry that was read or changed, several are separated by commas.
+    pub entry: Option<String>,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+/// An append-only log of every access to the API, one JSON record per line.
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    /// Create a new `AuditLog` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the log. It is created on the first record.
+    ///
+    /// # Returns
+    ///
+    /// A new `AuditLog` instance.
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Append a record to the log.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The access to record.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the log cannot be written.
+    pub fn record(&self, record: &AuditRecord) -> Result<(), Box<dyn std::error::Error>> {
+        let mut options = std::fs::OpenOptions::new();
+        options.append(true).create(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        options.open(&self.path)?.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// A JSON-RPC 2.0 request.
+#[derive(Debug, Deserialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    #[serde(default)]
+    pub id: Value,
+}
+
+/// A JSON-RPC 2.0 error.
+#[derive(Debug)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    /// Create a new `RpcError` instance.
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Turn an error of a method into a JSON-RPC error, keeping invalid input and missing
+    /// entries apart from internal errors.
+    pub fn from_error(error: &(dyn std::error::Error + 'static)) -> Self {
+        let code = match error.downcast_ref::<CliError>() {
+            Some(CliError::InvalidInput(_)) => INVALID_PARAMS,
+            Some(CliError::NotFound(_)) => NOT_FOUND,
+            _ if matches!(
+                error.downcast_ref::<DatabaseError>(),
+                Some(DatabaseError::NotFound(_))
+            ) =>
+            {
+                NOT_FOUND
+            }
+            _ => INTERNAL_ERROR,
+        };
+        Self::new(code, error.to_string())
+    }
+}
+
+/// Build a JSON-RPC 2.0 response.
+///
+/// # Arguments
+///
+/// * `id` - The ID of the request.
+/// * `result` - The result of the method or its error.
+///
+/// # Returns
+///
+/// The response object.
+pub fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(error) => json!({
+            "jsonrpc": "2.0",
+            "error": { "code": error.code, "message": error.message },
+            "id": id,
+        }),
+    }
+}
+
+/// A client of the API of a running `serve`.
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct ApiClient {
+    socket: PathBuf,
+    token: SecretString,
+}
+
+#[cfg(unix)]
+impl ApiClient {
+    /// Create a new `ApiClient` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The socket `serve` listens on.
+    /// * `token` - The token of the client.
+    ///
+    /// # Returns
+    ///
+    /// A new `ApiClient` instance.
+    pub fn new(socket: PathBuf, token: SecretString) -> Self {
+        Self { socket, token }
+    }
+
+    /// Call a method of the API.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The name of the method, e.g. `get`.
+    /// * `params` - The params of the method.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the result of the method or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if `serve` cannot be reached, or the method fails. Invalid
+    /// params and missing entries are returned as the matching `CliError`.
+    pub fn call(&self, method: &str, params: Value) -> Result<Value, Box<dyn std::error::Error>> {
+        use std::io::Read;
+        use std::os::unix::net::UnixStream;
+        use std::time::Duration;
+
+        let mut stream = UnixStream::connect(&self.socket).map_err(|e| {
+            CliError::Locked(format!(
+                "Cannot reach `rusty-toolkit serve` on {}: {}",
+                self.socket.display(),
+                e
+            ))
+        })?;
+        stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+
+        let body = json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 1 });
+        let body = body.to_string();
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.token.expose_secret(),
+            body.len(),
+            body
+        )?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let (_, body) = response
+            .split_once("\r\n\r\n")
+            .ok_or("Invalid response from the API")?;
+        let mut response: Value = serde_json::from_str(body)?;
+
+        match response.get_mut("error") {
+            Some(error) => {
+                let message = error["message"]
+                    .as_str()
+                    .unwrap_or("Unknown error")
+                    .to_string();
+                Err(match error["code"].as_i64() {
+                    Some(INVALID_PARAMS) => CliError::InvalidInput(message).into(),
+                    Some(NOT_FOUND) => CliError::NotFound(message).into(),
+                    _ => message.into(),
+                })
+            }
+            None => Ok(response["result"].take()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("api-tokens.json");
+        let mut tokens = ApiTokens::load(&path).unwrap();
+        assert!(tokens.list().is_empty());
+
+        let ci = tokens.create("ci", true).unwrap();
+        let deploy = tokens.create("deploy", false).unwrap();
+        assert!(ci.expose_secret().starts_with(TOKEN_PREFIX));
+        assert!(tokens.create("ci", false).is_err());
+        assert!(tokens.create(" ", false).is_err());
+
+        let stored = std::fs::read_to_string(&path).unwrap();
+        assert!(!stored.contains(ci.expose_secret()));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let mut tokens = ApiTokens::load(&path).unwrap();
+        let client = tokens.verify(ci.expose_secret()).unwrap();
+        assert_eq!(client.name, "ci");
+        assert!(client.read_only);
+        assert!(!tokens.verify(deploy.expose_secret()).unwrap().read_only);
+        assert!(tokens.verify("rtk_guess").is_none());
+
+        // A store that was made readable by others is restricted again on the next save.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        }
+        tokens.revoke("ci").unwrap();
+        assert!(tokens.revoke("ci").is_err());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let tokens = ApiTokens::load(&path).unwrap();
+        assert!(tokens.verify(ci.expose_secret()).is_none());
+        assert_eq!(tokens.list().len(), 1);
+    }
+
+    #[test]
+    fn test_rpc_error() {
+        let errors: [(Box<dyn std::error::Error>, i64); 4] = [
+            (
+                CliError::InvalidInput("entry".into()).into(),
+                INVALID_PARAMS,
+            ),
+            (CliError::NotFound("Mail".into()).into(), NOT_FOUND),
+            (DatabaseError::NotFound(7).into(), NOT_FOUND),
+            ("Something failed".into(), INTERNAL_ERROR),
+        ];
+        for (error, code) in errors {
+            assert_eq!(RpcError::from_error(error.as_ref()).code, code);
+        }
+
+        assert_eq!(
+            response(json!(1), Err(RpcError::new(FORBIDDEN, "read-only"))),
+            json!({
+                "jsonrpc": "2.0",
+                "error": { "code": FORBIDDEN, "message": "read-only" },
+                "id": 1,
+            })
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use log::info;
+
+use crate::prelude::{CliError, Config, Database, Encryption, SecretString};
+
+const BACKUP_PREFIX: &str = "backup-";
+const DATABASE_FILE: &str = "vault.db";
+const MASTER_FILE: &str = "master.key";
+
+#[derive(Debug)]
+pub struct BackupManager {
+    pub directory: PathBuf,
+    pub retention: usize,
+    pub db_path: PathBuf,
+    pub master_file: PathBuf,
+}
+
+impl BackupManager {
+    /// Create a new `BackupManager` instance from the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration to read the backup settings from.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `BackupManager` instance or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the backup directory cannot be created.
+    pub fn new(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            directory: config.get_backup_dir()?,
+            retention: config.backup.retention,
+            db_path: config.get_db_path()?,
+            master_file: Config::get_config_dir()?.join(MASTER_FILE),
+        })
+    }
+
+    /// Create a backup of the vault.
+    ///
+    /// The database is copied with the SQLite online backup API, so the snapshot is
+    /// consistent even while the vault is in use. Old backups are rotated afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `database` - The unlocked database to back up.
+    /// * `label` - A short label describing why the backup was taken.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the path of the new backup or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the backup cannot be written.
+    pub fn create(
+        &self,
+        database: &Database,
+        label: &str,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let backup_dir = self.create_backup_dir(label)?;
+
+        database.backup_to(&backup_dir.join(DATABASE_FILE))?;
+        fs::copy(&self.master_file, backup_dir.join(MASTER_FILE))?;
+
+        info!("Created backup: {}", backup_dir.display());
+        self.rotate(None)?;
+
+        Ok(backup_dir)
+    }
+
+    /// List all backups, oldest first.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `Vec` of backup paths or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the backup directory cannot be read.
+    pub fn list(&self) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        let mut backups = Vec::new();
+
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            let is_backup = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(BACKUP_PREFIX));
+
+            if is_backup && path.join(DATABASE_FILE).exists() {
+                backups.push(path);
+            }
+        }
+
+        backups.sort();
+        Ok(backups)
+    }
+
+    /// Resolve a backup by its name or path.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the backup in the backup directory or a path to a backup.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the path of the backup or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if no backup with that name exists.
+    pub fn resolve(&self, name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let path = PathBuf::from(name);
+        let backup_dir = if path.is_absolute() || path.exists() {
+            path
+        } else {
+            self.directory.join(name)
+        };
+
+        if !backup_dir.join(DATABASE_FILE).exists() || !backup_dir.join(MASTER_FILE).exists() {
+            return Err(CliError::NotFound(format!("Backup not found: {}", name)).into());
+        }
+
+        Ok(backup_dir)
+    }
+
+    /// Restore the vault from a backup.
+    ///
+    /// The backup is unlocked and verified in a staging copy before it replaces the live
+    /// vault. The live vault is snapshotted first, even if it can no longer be unlocked.
+    /// Old backups are only rotated after the swap, and never the restored one.
+    ///
+    /// # Arguments
+    ///
+    /// * `backup_dir` - The path of the backup to restore.
+    /// * `master_password` - The master password of the backup.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the backup cannot be unlocked, fails verification or
+    /// cannot be swapped in. The live vault is left untouched in the first two cases.
+    pub fn restore(
+        &self,
+        backup_dir: &Path,
+        master_password: &SecretString,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let salt = Encryption::unlock_master_file(&backup_dir.join(MASTER_FILE), master_password)
+            .map_err(|e| CliError::Locked(e.to_string()))?;
+
+        let staging = sibling(&self.db_path, ".restore");
+        let master_staging = sibling(&self.master_file, ".restore");
+        remove_database_files(&staging)?;
+        fs::copy(backup_dir.join(DATABASE_FILE), &staging)?;
+        fs::copy(backup_dir.join(MASTER_FILE), &master_staging)?;
+
+        let report = Database::new(staging.clone(), master_password, &salt)
+            .and_then(|database| Ok(database.verify()?));
+        let failure = match report {
+            Ok(report) if report.is_ok() => None,
+            Ok(_) => Some("Backup failed verification".into()),
+            Err(e) => Some(e),
+        };
+        if let Some(e) = failure {
+            remove_database_files(&staging)?;
+            fs::remove_file(&master_staging)?;
+            return Err(e);
+        }
+
+        self.snapshot_files("pre-restore")?;
+
+        remove_database_files(&self.db_path)?;
+        fs::rename(&staging, &self.db_path)?;
+        fs::rename(&master_staging, &self.master_file)?;
+
+        info!("Restored backup: {}", backup_dir.display());
+        self.rotate(Some(backup_dir))?;
+        Ok(())
+    }
+
+    /// Copy the raw vault files into a new backup without unlocking them. Old backups are
+    /// not rotated.
+    fn snapshot_files(&self, label: &str) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+        if !self.db_path.exists() {
+            return Ok(None);
+        }
+
+        let backup_dir = self.create_backup_dir(label)?;
+        for suffix in ["", "-wal", "-shm"] {
+            let source = sibling(&self.db_path, suffix);
+            if source.exists() {
+                fs::copy(
+                    &source,
+                    backup_dir.join(format!("{}{}", DATABASE_FILE, suffix)),
+                )?;
+            }
+        }
+        if self.master_file.exists() {
+            fs::copy(&self.master_file, backup_dir.join(MASTER_FILE))?;
+        }
+
+        info!("Created backup: {}", backup_dir.display());
+
+        Ok(Some(backup_dir))
+    }
+
+    /// Create an empty, timestamped backup directory.
+    fn create_backup_dir(&self, label: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let name = format!(
+            "{}{}-{}",
+            BACKUP_PREFIX,
+            Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+            label
+        );
+        let backup_dir = self.directory.join(name);
+        fs::create_dir_all(&self.directory)?;
+        fs::create_dir(&backup_dir)?;
+
+        Ok(backup_dir)
+    }
+
+    /// Remove the oldest backups beyond the retention count, except the one to keep.
+    fn rotate(&self, keep: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+        let backups = self.list()?;
+        let excess = backups.len().saturating_sub(self.retention.max(1));
+
+        let kept = |backup: &PathBuf| {
+            keep.is_some_and(|keep| fs::canonicalize(keep).ok() == fs::canonicalize(backup).ok())
+        };
+        for backup in backups.iter().filter(|backup| !kept(backup)).take(excess) {
+            info!("Removing old backup: {}", backup.display());
+            fs::remove_dir_all(backup)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Append a suffix to the file name of a path.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Remove a database file together with its WAL and shared-memory files.
+fn remove_database_files(path: &Path) -> std::io::Result<()> {
+    for suffix in ["", "-wal", "-shm"] {
+        let file = sibling(path, suffix);
+        if file.exists() {
+            fs::remove_file(file)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::PasswordEntry;
+
+    fn create_test_manager(dir: &Path) -> BackupManager {
+        BackupManager {
+            directory: dir.join("backups"),
+            retention: 2,
+            db_path: dir.join("pass.db"),
+            master_file: dir.join(MASTER_FILE),
+        }
+    }
+
+    fn create_test_vault(manager: &BackupManager, master_password: &SecretString) -> Database {
+        let salt = Encryption::create_master_file(&manager.master_file, master_password).unwrap();
+        Database::new(manager.db_path.clone(), master_password, &salt).unwrap()
+    }
+
+    fn create_test_entry(service: &str) -> PasswordEntry {
+        PasswordEntry::new(
+            service.to_string(),
+            "user".to_string(),
+            "secret".into(),
+            "".to_string(),
+            "".to_string(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_create_and_rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(dir.path());
+        let database = create_test_vault(&manager, &"master".into());
+
+        let first = manager.create(&database, "manual").unwrap();
+        manager.create(&database, "manual").unwrap();
+        manager.create(&database, "import").unwrap();
+
+        let backups = manager.list().unwrap();
+        assert_eq!(backups.len(), 2);
+        assert!(!backups.contains(&first));
+        assert!(backups[1].to_string_lossy().ends_with("-import"));
+        assert!(backups[1].join(MASTER_FILE).exists());
+    }
+
+    #[test]
+    fn test_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(dir.path());
+        let master_password = SecretString::from("master");
+        let database = create_test_vault(&manager, &master_password);
+        database.create(&create_test_entry("before")).unwrap();
+        let backup = manager.create(&database, "manual").unwrap();
+        database.create(&create_test_entry("after")).unwrap();
+        drop(database);
+
+        assert!(manager.restore(&backup, &"wrong".into()).is_err());
+
+        manager.restore(&backup, &master_password).unwrap();
+
+        let salt = Encryption::unlock_master_file(&manager.master_file, &master_password).unwrap();
+        let database = Database::new(manager.db_path.clone(), &master_password, &salt).unwrap();
+        let entries = database.read().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].service, "before");
+
+        let backups = manager.list().unwrap();
+        assert!(backups[1].to_string_lossy().ends_with("-pre-restore"));
+    }
+
+    #[test]
+    fn test_restore_oldest_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(dir.path());
+        let master_password = SecretString::from("master");
+        let database = create_test_vault(&manager, &master_password);
+        database.create(&create_test_entry("oldest")).unwrap();
+        let oldest = manager.create(&database, "manual").unwrap();
+        database.create(&create_test_entry("newer")).unwrap();
+        manager.create(&database, "manual").unwrap();
+        drop(database);
+        assert_eq!(manager.list().unwrap().len(), manager.retention);
+
+        manager.restore(&oldest, &master_password).unwrap();
+
+        let salt = Encryption::unlock_master_file(&manager.master_file, &master_password).unwrap();
+        let database = Database::new(manager.db_path.clone(), &master_password, &salt).unwrap();
+        assert_eq!(database.read().unwrap().len(), 1);
+
+        let backups = manager.list().unwrap();
+        assert_eq!(backups.len(), manager.retention);
+        assert_eq!(backups[0], oldest);
+        assert!(backups[1].to_string_lossy().ends_with("-pre-restore"));
+        assert!(!sibling(&manager.db_path, ".restore").exists());
+        assert!(!sibling(&manager.master_file, ".restore").exists());
+    }
+
+    #[test]
+    fn test_resolve_unknown_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(dir.path());
+
+        assert!(manager.resolve("backup-unknown").is_err());
+    }
+}
@@ -0,0 +1,344 @@
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::digest;
+
+use crate::prelude::SecretString;
+
+/// A way to put text on the system clipboard.
+pub trait Clipboard {
+    /// The name of the backend, as used in the configuration.
+    fn name(&self) -> &'static str;
+
+    /// Put a value on the clipboard.
+    fn copy(&self, value: &SecretString) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Read the clipboard. Returns `None` if the backend cannot read it.
+    fn paste(&self) -> Result<Option<SecretString>, Box<dyn std::error::Error>>;
+
+    /// Whether the backend can read the clipboard. Backends that cannot are cleared even
+    /// if something else was copied in the meantime.
+    fn can_paste(&self) -> bool {
+        true
+    }
+
+    /// Empty the clipboard.
+    fn clear(&self) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// A clipboard reached through command line tools.
+#[derive(Debug)]
+struct CommandClipboard {
+    name: &'static str,
+    copy: &'static [&'static str],
+    paste: &'static [&'static str],
+    /// The command that empties the clipboard, or `None` to copy an empty value instead.
+    clear: Option<&'static [&'static str]>,
+}
+
+const WL_COPY: CommandClipboard = CommandClipboard {
+    name: "wl-copy",
+    copy: &["wl-copy"],
+    paste: &["wl-paste", "--no-newline"],
+    clear: Some(&["wl-copy", "--clear"]),
+};
+
+const XCLIP: CommandClipboard = CommandClipboard {
+    name: "xclip",
+    copy: &["xclip", "-selection", "clipboard"],
+    paste: &["xclip", "-selection", "clipboard", "-o"],
+    clear: None,
+};
+
+const XSEL: CommandClipboard = CommandClipboard {
+    name: "xsel",
+    copy: &["xsel", "--clipboard", "--input"],
+    paste: &["xsel", "--clipboard", "--output"],
+    clear: Some(&["xsel", "--clipboard", "--delete"]),
+};
+
+/// The clipboard of the terminal, set with the OSC 52 escape sequence.
+///
+/// This works over SSH, but the clipboard cannot be read back, so it is cleared without
+/// checking its content.
+#[derive(Debug)]
+struct Osc52Clipboard;
+
+impl Clipboard for CommandClipboard {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn copy(&self, value: &SecretString) -> Result<(), Box<dyn std::error::Error>> {
+        let mut child = Command::new(self.copy[0])
+            .args(&self.copy[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to run {}: {}", self.copy[0], e))?;
+
+        child
+            .stdin
+            .take()
+            .ok_or("Failed to open the clipboard")?
+            .write_all(value.expose_secret().as_bytes())?;
+        if !child.wait()?.success() {
+            return Err(format!("{} failed to copy to the clipboard", self.copy[0]).into());
+        }
+
+        Ok(())
+    }
+
+    fn paste(&self) -> Result<Option<SecretString>, Box<dyn std::error::Error>> {
+        let output = Command::new(self.paste[0])
+            .args(&self.paste[1..])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()?;
+
+        // The tools fail if the clipboard is empty.
+        if !output.status.success() {
+            return Ok(Some(SecretString::default()));
+        }
+        Ok(Some(SecretString::new(String::from_utf8(output.stdout)?)))
+    }
+
+    fn clear(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(clear) = self.clear else {
+            return self.copy(&SecretString::default());
+        };
+
+        let status = Command::new(clear[0])
+            .args(&clear[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        if !status.success() {
+            return Err(format!("{} failed to clear the clipboard", clear[0]).into());
+        }
+
+        Ok(())
+    }
+}
+
+impl Clipboard for Osc52Clipboard {
+    fn name(&self) -> &'static str {
+        "osc52"
+    }
+
+    fn copy(&self, value: &SecretString) -> Result<(), Box<dyn std::error::Error>> {
+        write_terminal(&osc52_sequence(value))
+    }
+
+    fn paste(&self) -> Result<Option<SecretString>, Box<dyn std::error::Error>> {
+        Ok(None)
+    }
+
+    fn can_paste(&self) -> bool {
+        false
+    }
+
+    fn clear(&self) -> Result<(), Box<dyn std::error::Error>> {
+        write_terminal(&osc52_sequence(&SecretString::default()))
+    }
+}
+
+/// An in-memory clipboard for tests.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MockClipboard {
+    pub content: std::cell::RefCell<SecretString>,
+}
+
+#[cfg(test)]
+impl Clipboard for MockClipboard {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn copy(&self, value: &SecretString) -> Result<(), Box<dyn std::error::Error>> {
+        *self.content.borrow_mut() = value.clone();
+        Ok(())
+    }
+
+    fn paste(&self) -> Result<Option<SecretString>, Box<dyn std::error::Error>> {
+        Ok(Some(self.content.borrow().clone()))
+    }
+
+    fn clear(&self) -> Result<(), Box<dyn std::error::Error>> {
+        *self.content.borrow_mut() = SecretString::default();
+        Ok(())
+    }
+}
+
+/// Get a clipboard backend by name.
+///
+/// # Arguments
+///
+/// * `name` - `wl-copy`, `xclip`, `xsel`, `osc52` or `auto` to detect the backend.
+///
+/// # Returns
+///
+/// A `Result` containing the backend or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the name is unknown.
+pub fn backend(name: &str) -> Result<Box<dyn Clipboard>, String> {
+    match name {
+        "auto" => Ok(detect()),
+        "wl-copy" => Ok(Box::new(WL_COPY)),
+        "xclip" => Ok(Box::new(XCLIP)),
+        "xsel" => Ok(Box::new(XSEL)),
+        "osc52" => Ok(Box::new(Osc52Clipboard)),
+        _ => Err(format!("Unknown clipboard backend: {}", name)),
+    }
+}
+
+/// Pick the clipboard of the current session.
+///
+/// Wayland and X11 sessions use their clipboard tools if they are installed. Everything
+/// else, e.g. an SSH session, uses the terminal clipboard.
+fn detect() -> Box<dyn Clipboard> {
+    let has = |variable: &str| env::var_os(variable).is_some_and(|value| !value.is_empty());
+    let over_ssh = has("SSH_TTY") || has("SSH_CONNECTION");
+
+    if !over_ssh && has("WAYLAND_DISPLAY") && on_path("wl-copy") {
+        Box::new(WL_COPY)
+    } else if !over_ssh && has("DISPLAY") && on_path("xclip") {
+        Box::new(XCLIP)
+    } else if !over_ssh && has("DISPLAY") && on_path("xsel") {
+        Box::new(XSEL)
+    } else {
+        Box::new(Osc52Clipboard)
+    }
+}
+
+/// Check if a program can be found on the `PATH`.
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|path| env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// Build the OSC 52 sequence that sets the clipboard of the terminal.
+fn osc52_sequence(value: &SecretString) -> SecretString {
+    SecretString::new(format!(
+        "\x1b]52;c;{}\x07",
+        STANDARD.encode(value.expose_secret())
+    ))
+}
+
+/// Write an escape sequence to the controlling terminal, or to stdout without one.
+fn write_terminal(sequence: &SecretString) -> Result<(), Box<dyn std::error::Error>> {
+    match OpenOptions::new().write(true).open("/dev/tty") {
+        Ok(mut tty) => tty.write_all(sequence.expose_secret().as_bytes())?,
+        Err(_) => {
+            let mut stdout = std::io::stdout();
+            stdout.write_all(sequence.expose_secret().as_bytes())?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Fingerprint a copied value, so the clipboard can be checked without keeping the value.
+///
+/// # Arguments
+///
+/// * `value` - The copied value.
+///
+/// # Returns
+///
+/// The hex encoded SHA-256 digest of the value.
+pub fn fingerprint(value: &SecretString) -> String {
+    digest::digest(&digest::SHA256, value.expose_secret().as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Clear the clipboard unless something else was copied in the meantime.
+///
+/// A clipboard the backend cannot read is cleared unconditionally, see
+/// `Clipboard::can_paste`.
+///
+/// # Arguments
+///
+/// * `clipboard` - The clipboard backend.
+/// * `expected` - The fingerprint of the copied value.
+///
+/// # Returns
+///
+/// A `Result` containing a `bool` indicating if the clipboard was cleared or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the clipboard cannot be read or cleared.
+pub fn clear_if_unchanged(
+    clipboard: &dyn Clipboard,
+    expected: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    if let Some(current) = clipboard.paste()? {
+        if fingerprint(&current) != expected {
+            return Ok(false);
+        }
+    }
+
+    clipboard.clear()?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clear_if_unchanged() {
+        let clipboard = MockClipboard::default();
+        let secret = SecretString::from("secret");
+
+        clipboard.copy(&secret).unwrap();
+        assert!(clear_if_unchanged(&clipboard, &fingerprint(&secret)).unwrap());
+        assert!(clipboard.content.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_keep_changed_clipboard() {
+        let clipboard = MockClipboard::default();
+        let secret = SecretString::from("secret");
+
+        clipboard.copy(&secret).unwrap();
+        clipboard.copy(&"something else".into()).unwrap();
+        assert!(!clear_if_unchanged(&clipboard, &fingerprint(&secret)).unwrap());
+        assert_eq!(clipboard.content.borrow().expose_secret(), "something else");
+    }
+
+    #[test]
+    fn test_osc52_clears_unconditionally() {
+        let clipboard = Osc52Clipboard;
+        assert!(!clipboard.can_paste());
+        assert!(MockClipboard::default().can_paste());
+    }
+
+    #[test]
+    fn test_backend() {
+        assert_eq!(backend("xsel").unwrap().name(), "xsel");
+        assert_eq!(backend("osc52").unwrap().name(), "osc52");
+        assert!(backend("pbcopy").is_err());
+    }
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(
+            osc52_sequence(&"secret".into()).expose_secret(),
+            "\x1b]52;c;c2VjcmV0\x07"
+        );
+    }
+}
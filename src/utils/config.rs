@@ -8,6 +8,12 @@ use std::path::PathBuf;
 pub struct Config {
     pub logging: LogConfig,
     pub database: DatabaseConfig,
+    #[serde(default)]
+    pub backup: BackupConfig,
+    #[serde(default)]
+    pub clipboard: ClipboardConfig,
+    #[serde(default)]
+    pub git_credential: GitCredentialConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,6 +26,57 @@ pub struct DatabaseConfig {
     pub db_name: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupConfig {
+    /// The directory to store backups in. Defaults to `backups` in the config directory.
+    pub directory: Option<PathBuf>,
+    /// The number of backups to keep.
+    pub retention: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        BackupConfig {
+            directory: None,
+            retention: 10,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClipboardConfig {
+    /// The clipboard backend: `auto`, `wl-copy`, `xclip`, `xsel` or `osc52`.
+    pub backend: String,
+    /// The number of seconds after which a copied secret is cleared, 0 to keep it.
+    pub clear_after: u64,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        ClipboardConfig {
+            backend: "auto".to_string(),
+            clear_after: 45,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitCredentialConfig {
+    /// Whether logins that git asks to store or erase change the vault.
+    pub store: bool,
+    /// The folder logins from git are stored in. Only entries in it are updated or erased.
+    pub folder: String,
+}
+
+impl Default for GitCredentialConfig {
+    fn default() -> Self {
+        GitCredentialConfig {
+            store: false,
+            folder: "Git".to_string(),
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
@@ -29,6 +86,9 @@ impl Default for Config {
             database: DatabaseConfig {
                 db_name: "pass.db".to_string(),
             },
+            backup: BackupConfig::default(),
+            clipboard: ClipboardConfig::default(),
+            git_credential: GitCredentialConfig::default(),
         }
     }
 }
@@ -157,6 +217,28 @@ impl Config {
 
         Ok(db_path)
     }
+
+    /// Get the backup directory.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the backup directory path or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the backup directory cannot be found or created.
+    pub fn get_backup_dir(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let backup_dir = match &self.backup.directory {
+            Some(directory) => directory.clone(),
+            None => Self::get_config_dir()?.join("backups"),
+        };
+
+        if !backup_dir.exists() {
+            std::fs::create_dir_all(&backup_dir)?;
+        }
+
+        Ok(backup_dir)
+    }
 }
 
 #[cfg(test)]
@@ -169,6 +251,30 @@ mod tests {
 
         assert_eq!(config.logging.level, "info");
         assert_eq!(config.database.db_name, "pass.db");
+        assert_eq!(config.backup.directory, None);
+        assert_eq!(config.backup.retention, 10);
+        assert_eq!(config.clipboard.backend, "auto");
+        assert_eq!(config.clipboard.clear_after, 45);
+        assert!(!config.git_credential.store);
+        assert_eq!(config.git_credential.folder, "Git");
+    }
+
+    #[test]
+    fn test_config_without_backup_section() {
+        let config: Config = toml::from_str(
+            "
+                [logging]
+                level = \"info\"
+
+                [database]
+                db_name = \"pass.db\"
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(config.backup.retention, 10);
+        assert_eq!(config.clipboard.clear_after, 45);
+        assert!(!config.git_credential.store);
     }
 
     #[test]
@@ -1,23 +1,93 @@
-use std::path::PathBuf;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
 use chrono::Utc;
-use rusqlite::{params, Connection};
+use log::warn;
+use ring::rand::{SecureRandom, SystemRandom};
+use rusqlite::backup::Backup;
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
 
-use crate::prelude::Encryption;
+use crate::prelude::{DatabaseError, Encryption, SecretString};
+
+/// The schema version stored in `PRAGMA user_version` once all migrations ran.
+const SCHEMA_VERSION: i32 = 6;
+
+/// The columns of the `passwords` table in the order `row_to_entry` expects them.
+const ENTRY_COLUMNS: &str =
+    "id, service, username, password, url, notes, created_at, updated_at, folder, totp, custom_fields, source, uuid, field_times, tags";
+
+/// The fields of an entry that carry their own modification timestamp.
+pub const ENTRY_FIELDS: [&str; 9] = [
+    "service",
+    "username",
+    "password",
+    "url",
+    "notes",
+    "folder",
+    "totp",
+    "custom_fields",
+    "tags",
+];
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PasswordEntry {
     pub id: Option<i32>,
     pub service: String,
     pub username: String,
-    pub password: String,
+    pub password: SecretString,
     pub url: String,
     pub notes: String,
     pub created_at: String,
     pub updated_at: String,
+    #[serde(default)]
+    pub folder: String,
+    #[serde(default)]
+    pub totp: SecretString,
+    #[serde(default)]
+    pub custom_fields: Vec<CustomField>,
+    /// Where the entry came from, e.g. `import:bitwarden:2026-10-01`. Empty for entries
+    /// created in this vault.
+    #[serde(default)]
+    pub source: String,
+    /// Identifies the entry across copies of the vault. Assigned when the entry is stored.
+    #[serde(default)]
+    pub uuid: String,
+    /// When each field was last modified, keyed by the names in `ENTRY_FIELDS`. Fields
+    /// without a timestamp were last modified at `updated_at`.
+    #[serde(default)]
+    pub field_times: BTreeMap<String, String>,
+    /// Labels for finding related entries across folders, e.g. `work` or `2fa`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CustomField {
+    pub name: String,
+    pub value: SecretString,
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+/// The login for a remote the vault is synced with.
+#[derive(Debug, Clone)]
+pub struct RemoteCredentials {
+    pub url: String,
+    pub username: String,
+    pub password: SecretString,
+}
+
+#[derive(Debug)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub integrity_errors: Vec<String>,
+    pub corrupt_entries: Vec<DatabaseError>,
 }
 
 #[derive(Debug)]
@@ -25,38 +95,63 @@ pub struct Database {
     pub connection: Connection,
     pub path: PathBuf,
     pub encryption: Encryption,
+    key: SecretString,
 }
 
 impl Database {
-    /// Create a new `Database` instance.
+    /// Create a new `Database` instance and apply pending migrations.
     ///
     /// # Arguments
     ///
     /// * `path` - The path to the database file.
+    /// * `master_password` - The master password of the vault.
+    /// * `salt` - The salt of the vault.
     ///
     /// # Returns
     ///
     /// A new `Database` instance.
     pub fn new(
         path: PathBuf,
-        master_password: &str,
+        master_password: &SecretString,
+        salt: &[u8; 16],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let db = Self::open(path, master_password, salt)?;
+        db.migrate()?;
+
+        Ok(db)
+    }
+
+    /// Open a `Database` instance without applying pending migrations.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the database file.
+    /// * `master_password` - The master password of the vault.
+    /// * `salt` - The salt of the vault.
+    ///
+    /// # Returns
+    ///
+    /// A new `Database` instance.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the database cannot be opened or unlocked.
+    pub fn open(
+        path: PathBuf,
+        master_password: &SecretString,
         salt: &[u8; 16],
     ) -> Result<Self, Box<dyn std::error::Error>> {
         let conn = Connection::open(&path)?;
 
         let encryption = Encryption::new(master_password, salt);
         let key = encryption.get_key(master_password)?;
-        conn.execute_batch(&format!(
+        Self::apply_key(&conn, &key)?;
+        conn.execute_batch(
             "
-                PRAGMA key = '{}';
-                PRAGMA cipher_page_size = 4096;
-                PRAGMA kdf_iter = 64000;
-                PRAGMA cipher_memory_security = ON;
                 PRAGMA foreign_keys = ON;
                 PRAGMA journal_mode = WAL;
             ",
-            key
-        ))?;
+        )?;
 
         conn.execute(
             "CREATE TABLE IF NOT EXISTS passwords (
@@ -76,11 +171,288 @@ impl Database {
             connection: conn,
             path,
             encryption,
+            key,
         })
     }
 
+    /// Configure SQLCipher on a connection.
+    fn apply_key(conn: &Connection, key: &SecretString) -> Result<(), rusqlite::Error> {
+        let pragmas = Zeroizing::new(format!(
+            "
+                PRAGMA key = '{}';
+                PRAGMA cipher_page_size = 4096;
+                PRAGMA kdf_iter = 64000;
+                PRAGMA cipher_memory_security = ON;
+            ",
+            key.expose_secret()
+        ));
+        conn.execute_batch(&pragmas)
+    }
+
+    /// Copy a consistent snapshot of the database to a new file.
+    ///
+    /// The snapshot is taken with the SQLite online backup API and encrypted with the
+    /// same key as the source database.
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - The path of the snapshot file to create.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the snapshot cannot be written.
+    pub fn backup_to(&self, destination: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut target = Connection::open(destination)?;
+        Self::apply_key(&target, &self.key)?;
+
+        let backup = Backup::new(&self.connection, &mut target)?;
+        backup.run_to_completion(64, Duration::ZERO, None)?;
+
+        Ok(())
+    }
+
+    /// Check if the database has migrations that have not been applied yet.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `bool` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the schema version cannot be read.
+    pub fn has_pending_migrations(&self) -> Result<bool, DatabaseError> {
+        Ok(self.schema_version()? < SCHEMA_VERSION)
+    }
+
+    /// Read the schema version of the database.
+    fn schema_version(&self) -> Result<i32, DatabaseError> {
+        Ok(self
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?)
+    }
+
+    /// Bring the database schema and its encrypted fields up to date.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if a migration fails. Failed migrations are rolled back.
+    pub fn migrate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let version = self.schema_version()?;
+
+        for target in (version + 1)..=SCHEMA_VERSION {
+            let tx = self.connection.unchecked_transaction()?;
+            match target {
+                1 => {
+                    self.rewrap_legacy_passwords()?;
+                }
+                2 => {
+                    tx.execute_batch(
+                        "
+                            ALTER TABLE passwords ADD COLUMN folder TEXT NOT NULL DEFAULT '';
+                            ALTER TABLE passwords ADD COLUMN totp TEXT NOT NULL DEFAULT '';
+                            ALTER TABLE passwords ADD COLUMN custom_fields TEXT NOT NULL DEFAULT '';
+                        ",
+                    )?;
+                }
+                3 => {
+                    tx.execute(
+                        "ALTER TABLE passwords ADD COLUMN source TEXT NOT NULL DEFAULT ''",
+                        [],
+                    )?;
+                }
+                4 => {
+                    tx.execute_batch(
+                        "
+                            ALTER TABLE passwords ADD COLUMN uuid TEXT NOT NULL DEFAULT '';
+                            ALTER TABLE passwords ADD COLUMN field_times TEXT NOT NULL DEFAULT '';
+                            CREATE TABLE deleted_entries (
+                                uuid TEXT PRIMARY KEY,
+                                deleted_at TEXT NOT NULL
+                            );
+                            CREATE TABLE vault_meta (
+                                key TEXT PRIMARY KEY,
+                                value TEXT NOT NULL
+                            );
+                        ",
+                    )?;
+                    self.assign_uuids()?;
+                    tx.execute("CREATE UNIQUE INDEX passwords_uuid ON passwords (uuid)", [])?;
+                    tx.execute(
+                        "INSERT INTO vault_meta (key, value) VALUES ('vault_id', ?1)",
+                        params![generate_uuid()?],
+                    )?;
+                }
+                5 => {
+                    tx.execute(
+                        "CREATE TABLE remotes (
+                            url TEXT PRIMARY KEY,
+                            username TEXT NOT NULL,
+                            password TEXT NOT NULL
+                        )",
+                        [],
+                    )?;
+                }
+                6 => {
+                    tx.execute(
+                        "ALTER TABLE passwords ADD COLUMN tags TEXT NOT NULL DEFAULT ''",
+                        [],
+                    )?;
+                }
+                _ => unreachable!("no migration defined for schema version {}", target),
+            }
+            tx.pragma_update(None, "user_version", target)?;
+            tx.commit()?;
+        }
+
+        Ok(())
+    }
+
+    /// Re-encrypt legacy passwords into the versioned envelope bound to their entry.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the number of rewrapped entries or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if a password cannot be written back. Entries that
+    /// cannot be decrypted are left untouched for `verify` to report.
+    fn rewrap_legacy_passwords(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT id, password FROM passwords")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut rewrapped = 0;
+        for (id, encoded_password) in rows {
+            if self.read_secret(id, "password", &encoded_password).is_ok() {
+                continue;
+            }
+
+            let password = match STANDARD
+                .decode(encoded_password)
+                .map(|encrypted_password| self.encryption.decrypt_legacy(&encrypted_password))
+            {
+                Ok(Ok(password)) => password,
+                _ => {
+                    warn!("Skipping corrupt entry {} during migration", id);
+                    continue;
+                }
+            };
+            self.write_secret(id, "password", &password)?;
+            rewrapped += 1;
+        }
+
+        Ok(rewrapped)
+    }
+
+    /// Give every entry without a UUID a new one.
+    fn assign_uuids(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT id FROM passwords WHERE uuid = ''")?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, i32>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for id in ids {
+            self.connection.execute(
+                "UPDATE passwords SET uuid = ?1 WHERE id = ?2",
+                params![generate_uuid()?, id],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Encrypt a secret field bound to its entry and store it.
+    fn write_secret(
+        &self,
+        id: i32,
+        column: &'static str,
+        value: &SecretString,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let encrypted_value = self
+            .encryption
+            .encrypt_with_aad(value.expose_secret(), &field_aad(id, column))
+            .map_err(|_| format!("Failed to encrypt {}", column))?;
+        let encoded_value = STANDARD.encode(encrypted_value);
+
+        self.connection.execute(
+            &format!("UPDATE passwords SET {} = ?1 WHERE id = ?2", column),
+            params![encoded_value, id],
+        )?;
+        Ok(())
+    }
+
+    /// Encrypt all secret fields of an entry and store them.
+    fn write_secrets(
+        &self,
+        id: i32,
+        entry: &PasswordEntry,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let custom_fields = SecretString::new(serde_json::to_string(&entry.custom_fields)?);
+
+        self.write_secret(id, "password", &entry.password)?;
+        self.write_secret(id, "totp", &entry.totp)?;
+        self.write_secret(id, "custom_fields", &custom_fields)?;
+        Ok(())
+    }
+
+    /// Decrypt a stored secret field bound to its entry.
+    fn read_secret(
+        &self,
+        id: i32,
+        column: &'static str,
+        encoded_value: &str,
+    ) -> Result<SecretString, DatabaseError> {
+        let encrypted_value =
+            STANDARD
+                .decode(encoded_value)
+                .map_err(|e| DatabaseError::CorruptEntry {
+                    id,
+                    reason: format!("invalid {} encoding: {}", column, e),
+                })?;
+
+        self.encryption
+            .decrypt_with_aad(&encrypted_value, &field_aad(id, column))
+            .map_err(|_| DatabaseError::CorruptEntry {
+                id,
+                reason: format!("failed to decrypt {}", column),
+            })
+    }
+
+    /// Decrypt a secret field that is empty on rows written before it existed.
+    fn read_optional_secret(
+        &self,
+        id: i32,
+        column: &'static str,
+        encoded_value: &str,
+    ) -> Result<SecretString, DatabaseError> {
+        if encoded_value.is_empty() {
+            Ok(SecretString::default())
+        } else {
+            self.read_secret(id, column, encoded_value)
+        }
+    }
+
     /// Create a new PasswordEntry in the database.
     ///
+    /// The timestamps of the entry are stored as given, so imported entries keep their history.
+    /// The entry keeps its UUID unless it has none or another entry already uses it, and a
+    /// deletion recorded for that UUID is forgotten.
+    ///
     /// # Arguments
     ///
     /// * `entry` - The PasswordEntry to create.
@@ -93,21 +465,40 @@ impl Database {
     ///
     /// An error will be returned if the PasswordEntry cannot be created.
     pub fn create(&self, entry: &PasswordEntry) -> Result<(), Box<dyn std::error::Error>> {
-        let encrypted_password = self.encryption.encrypt(&entry.password).unwrap();
-        let encoded_password = STANDARD.encode(encrypted_password);
+        let tx = self.connection.unchecked_transaction()?;
 
-        self.connection.execute(
-            "INSERT INTO passwords (service, username, password, url, notes, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        let uuid = if entry.uuid.is_empty() || self.find_by_uuid(&entry.uuid)?.is_some() {
+            generate_uuid()?
+        } else {
+            entry.uuid.clone()
+        };
+        let field_times: BTreeMap<&str, &str> = ENTRY_FIELDS
+            .iter()
+            .map(|field| (*field, entry.field_time(field)))
+            .collect();
+
+        tx.execute(
+            "INSERT INTO passwords (service, username, password, url, notes, created_at, updated_at, folder, source, uuid, field_times, tags)
+                VALUES (?1, ?2, '', ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
             params![
                 entry.service,
                 entry.username,
-                encoded_password,
                 entry.url,
                 entry.notes,
-                Utc::now().to_rfc3339(),
-                Utc::now().to_rfc3339(),
+                entry.created_at,
+                entry.updated_at,
+                entry.folder,
+                entry.source,
+                uuid,
+                serde_json::to_string(&field_times)?,
+                encode_tags(&entry.tags)?,
             ],
         )?;
+        let id = i32::try_from(tx.last_insert_rowid())?;
+        self.write_secrets(id, entry)?;
+        tx.execute("DELETE FROM deleted_entries WHERE uuid = ?1", params![uuid])?;
+
+        tx.commit()?;
         Ok(())
     }
 
@@ -119,36 +510,30 @@ impl Database {
     ///
     /// # Errors
     ///
-    /// An error will be returned if the PasswordEntries cannot be read.
-    pub fn read(&self) -> Result<Vec<PasswordEntry>, Box<dyn std::error::Error>> {
-        let mut stmt = self.connection.prepare(
-            "SELECT id, service, username, password, url, notes, created_at, updated_at
-            FROM passwords",
-        )?;
+    /// An error will be returned if the PasswordEntries cannot be read or if any
+    /// entry is corrupt.
+    pub fn read(&self) -> Result<Vec<PasswordEntry>, DatabaseError> {
+        self.read_entries()?.into_iter().collect()
+    }
 
-        let entries = stmt.query_map([], |row| {
-            let encoded_password: String = row.get(3)?;
-            let d_password = STANDARD.decode(encoded_password).unwrap();
-            let password = self.encryption.decrypt(&d_password).unwrap();
-
-            Ok(PasswordEntry {
-                id: row.get(0)?,
-                service: row.get(1)?,
-                username: row.get(2)?,
-                password,
-                url: row.get(4)?,
-                notes: row.get(5)?,
-                created_at: row.get(6)?,
-                updated_at: row.get(7)?,
-            })
-        })?;
+    /// Read all PasswordEntries from the database, decrypting each one independently.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `Vec` with one `Result` per stored entry or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the PasswordEntries cannot be queried. Entries that
+    /// cannot be decrypted are returned as `DatabaseError::CorruptEntry`.
+    pub fn read_entries(&self) -> Result<Vec<Result<PasswordEntry, DatabaseError>>, DatabaseError> {
+        let mut stmt = self
+            .connection
+            .prepare(&format!("SELECT {} FROM passwords", ENTRY_COLUMNS))?;
 
-        let mut result = Vec::new();
-        for entry in entries {
-            result.push(entry?);
-        }
+        let entries = stmt.query_map([], |row| self.row_to_entry(row))?;
 
-        Ok(result)
+        Ok(entries.collect::<Result<Vec<_>, _>>()?)
     }
 
     /// Read a single PasswordEntry from the database.
@@ -163,41 +548,180 @@ impl Database {
     ///
     /// # Errors
     ///
-    /// An error will be returned if the PasswordEntry cannot be read.
-    pub fn read_by_id(&self, id: i32) -> Result<PasswordEntry, Box<dyn std::error::Error>> {
-        let mut stmt = self.connection.prepare(
-            "SELECT id, service, username, password, url, notes, created_at, updated_at
-            FROM passwords
-            WHERE id = ?1",
-        )?;
+    /// An error will be returned if the PasswordEntry does not exist, is corrupt or
+    /// cannot be read.
+    pub fn read_by_id(&self, id: i32) -> Result<PasswordEntry, DatabaseError> {
+        let mut stmt = self.connection.prepare(&format!(
+            "SELECT {} FROM passwords WHERE id = ?1",
+            ENTRY_COLUMNS
+        ))?;
 
-        let entry = stmt.query_map(params![id], |row| {
-            let encoded_password: String = row.get(3)?;
-            let d_password = STANDARD.decode(encoded_password).unwrap();
-            let password = self.encryption.decrypt(&d_password).unwrap();
-
-            Ok(PasswordEntry {
-                id: row.get(0)?,
-                service: row.get(1)?,
-                username: row.get(2)?,
-                password,
-                url: row.get(4)?,
-                notes: row.get(5)?,
-                created_at: row.get(6)?,
-                updated_at: row.get(7)?,
-            })
-        })?;
+        let mut entries = stmt.query_map(params![id], |row| self.row_to_entry(row))?;
 
-        let mut result = Vec::new();
-        for entry in entry {
-            result.push(entry?);
+        match entries.next() {
+            Some(entry) => entry?,
+            None => Err(DatabaseError::NotFound(id)),
         }
+    }
+
+    /// Find the id of the entry with the given UUID.
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The UUID of the entry.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the id, `None` if no entry has the UUID, or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the entries cannot be queried.
+    pub fn find_by_uuid(&self, uuid: &str) -> Result<Option<i32>, DatabaseError> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT id FROM passwords WHERE uuid = ?1",
+                params![uuid],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Check the vault for damage.
+    ///
+    /// Runs the SQLCipher and SQLite integrity checks and decrypts every entry.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `VerifyReport` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the checks cannot be run at all.
+    pub fn verify(&self) -> Result<VerifyReport, DatabaseError> {
+        let mut integrity_errors = Vec::new();
+
+        let mut stmt = self.connection.prepare("PRAGMA cipher_integrity_check")?;
+        for message in stmt.query_map([], |row| row.get::<_, String>(0))? {
+            integrity_errors.push(message?);
+        }
+
+        let mut stmt = self.connection.prepare("PRAGMA integrity_check")?;
+        for message in stmt.query_map([], |row| row.get::<_, String>(0))? {
+            let message = message?;
+            if message != "ok" {
+                integrity_errors.push(message);
+            }
+        }
+
+        let mut checked = 0;
+        let mut corrupt_entries = Vec::new();
+        for entry in self.read_entries()? {
+            checked += 1;
+            if let Err(e) = entry {
+                corrupt_entries.push(e);
+            }
+        }
+
+        Ok(VerifyReport {
+            checked,
+            integrity_errors,
+            corrupt_entries,
+        })
+    }
+
+    /// Map a `passwords` row to a PasswordEntry, decrypting its secret fields.
+    ///
+    /// The outer `Result` carries SQL errors, the inner one reports a corrupt entry.
+    fn row_to_entry(
+        &self,
+        row: &rusqlite::Row,
+    ) -> rusqlite::Result<Result<PasswordEntry, DatabaseError>> {
+        let id: i32 = row.get(0)?;
+        let encoded_password: String = row.get(3)?;
+        let encoded_totp: String = row.get(9)?;
+        let encoded_custom_fields: String = row.get(10)?;
+
+        let secrets = self
+            .read_secret(id, "password", &encoded_password)
+            .and_then(|password| {
+                let totp = self.read_optional_secret(id, "totp", &encoded_totp)?;
+                let custom_fields =
+                    self.read_optional_secret(id, "custom_fields", &encoded_custom_fields)?;
+                Ok((password, totp, custom_fields))
+            });
+        let (password, totp, custom_fields) = match secrets {
+            Ok(secrets) => secrets,
+            Err(e) => return Ok(Err(e)),
+        };
+        let encoded_field_times: String = row.get(13)?;
+        let field_times = if encoded_field_times.is_empty() {
+            BTreeMap::new()
+        } else {
+            match serde_json::from_str(&encoded_field_times) {
+                Ok(field_times) => field_times,
+                Err(e) => {
+                    return Ok(Err(DatabaseError::CorruptEntry {
+                        id,
+                        reason: format!("invalid field times: {}", e),
+                    }))
+                }
+            }
+        };
+        let encoded_tags: String = row.get(14)?;
+        let tags = if encoded_tags.is_empty() {
+            Vec::new()
+        } else {
+            match serde_json::from_str(&encoded_tags) {
+                Ok(tags) => tags,
+                Err(e) => {
+                    return Ok(Err(DatabaseError::CorruptEntry {
+                        id,
+                        reason: format!("invalid tags: {}", e),
+                    }))
+                }
+            }
+        };
+        let custom_fields = if custom_fields.is_empty() {
+            Vec::new()
+        } else {
+            match serde_json::from_str(custom_fields.expose_secret()) {
+                Ok(custom_fields) => custom_fields,
+                Err(e) => {
+                    return Ok(Err(DatabaseError::CorruptEntry {
+                        id,
+                        reason: format!("invalid custom fields: {}", e),
+                    }))
+                }
+            }
+        };
 
-        Ok(result[0].clone())
+        Ok(Ok(PasswordEntry {
+            id: Some(id),
+            service: row.get(1)?,
+            username: row.get(2)?,
+            password,
+            url: row.get(4)?,
+            notes: row.get(5)?,
+            created_at: row.get(6)?,
+            updated_at: row.get(7)?,
+            folder: row.get(8)?,
+            totp,
+            custom_fields,
+            source: row.get(11)?,
+            uuid: row.get(12)?,
+            field_times,
+            tags,
+        }))
     }
 
     /// Update a PasswordEntry in the database.
     ///
+    /// The `updated_at` timestamp of the entry is stored as given. Fields that changed are
+    /// stamped with it unless the entry carries a new timestamp for them, the UUID of the
+    /// stored entry is kept.
+    ///
     /// # Arguments
     ///
     /// * `entry` - The PasswordEntry to update.
@@ -210,28 +734,58 @@ impl Database {
     ///
     /// An error will be returned if the PasswordEntry cannot be updated.
     pub fn update(&self, id: i32, entry: PasswordEntry) -> Result<(), Box<dyn std::error::Error>> {
-        let encrypted_password = self.encryption.encrypt(&entry.password).unwrap();
-        let encoded_password = STANDARD.encode(encrypted_password);
+        let stored = self.read_by_id(id)?;
+        let field_times: BTreeMap<&str, &str> = ENTRY_FIELDS
+            .iter()
+            .map(|field| {
+                let given = entry.field_times.get(*field).map(String::as_str);
+                let time = if stored.same_field(&entry, field) {
+                    given.unwrap_or_else(|| stored.field_time(field))
+                } else {
+                    match given {
+                        Some(time)
+                            if Some(time) != stored.field_times.get(*field).map(String::as_str) =>
+                        {
+                            time
+                        }
+                        _ => entry.updated_at.as_str(),
+                    }
+                };
+                (*field, time)
+            })
+            .collect();
 
-        self.connection.execute(
+        let tx = self.connection.unchecked_transaction()?;
+
+        tx.execute(
             "UPDATE passwords
-                SET service = ?1, username = ?2, password = ?3, url = ?4, notes = ?5, updated_at = ?6
-                WHERE id = ?7",
+                SET service = ?1, username = ?2, url = ?3, notes = ?4, updated_at = ?5, folder = ?6,
+                    source = ?7, field_times = ?8, tags = ?9
+                WHERE id = ?10",
             params![
                 entry.service,
                 entry.username,
-                encoded_password,
                 entry.url,
                 entry.notes,
-                Utc::now().to_rfc3339(),
+                entry.updated_at,
+                entry.folder,
+                entry.source,
+                serde_json::to_string(&field_times)?,
+                encode_tags(&entry.tags)?,
                 id,
             ],
         )?;
+        self.write_secrets(id, &entry)?;
+
+        tx.commit()?;
         Ok(())
     }
 
     /// Delete a PasswordEntry from the database.
     ///
+    /// The deletion is recorded by UUID, so a merge removes the entry from other copies of
+    /// the vault as well.
+    ///
     /// # Arguments
     ///
     /// * `id` - The id of the PasswordEntry to delete.
@@ -244,13 +798,233 @@ impl Database {
     ///
     /// An error will be returned if the PasswordEntry cannot be deleted.
     pub fn delete(&self, id: i32) -> Result<(), Box<dyn std::error::Error>> {
-        self.connection
-            .execute("DELETE FROM passwords WHERE id = ?1", params![id])?;
+        let tx = self.connection.unchecked_transaction()?;
+
+        tx.execute(
+            "INSERT OR REPLACE INTO deleted_entries (uuid, deleted_at)
+                SELECT uuid, ?2 FROM passwords WHERE id = ?1 AND uuid != ''",
+            params![id, Utc::now().to_rfc3339()],
+        )?;
+        tx.execute("DELETE FROM passwords WHERE id = ?1", params![id])?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Delete the entry with the given UUID, if any, and record when it was deleted.
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The UUID of the deleted entry.
+    /// * `deleted_at` - When the entry was deleted.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the deletion cannot be stored.
+    pub fn mark_deleted(&self, uuid: &str, deleted_at: &str) -> Result<(), DatabaseError> {
+        let tx = self.connection.unchecked_transaction()?;
+
+        tx.execute("DELETE FROM passwords WHERE uuid = ?1", params![uuid])?;
+        tx.execute(
+            "INSERT OR REPLACE INTO deleted_entries (uuid, deleted_at) VALUES (?1, ?2)",
+            params![uuid, deleted_at],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Read the recorded deletions.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a map from the UUID of each deleted entry to the time it was
+    /// deleted or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the deletions cannot be read.
+    pub fn read_deleted(&self) -> Result<BTreeMap<String, String>, DatabaseError> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT uuid, deleted_at FROM deleted_entries")?;
+        let deleted = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        Ok(deleted.collect::<Result<_, _>>()?)
+    }
+
+    /// Read the stored login for a remote.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the remote.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `RemoteCredentials`, `None` if none are stored, or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the login cannot be read or decrypted.
+    pub fn read_remote(&self, url: &str) -> Result<Option<RemoteCredentials>, DatabaseError> {
+        let row: Option<(String, String)> = self
+            .connection
+            .query_row(
+                "SELECT username, password FROM remotes WHERE url = ?1",
+                params![url],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((username, encoded_password)) = row else {
+            return Ok(None);
+        };
+
+        let password = STANDARD
+            .decode(encoded_password)
+            .ok()
+            .and_then(|encrypted_password| {
+                self.encryption
+                    .decrypt_with_aad(&encrypted_password, &remote_aad(url))
+                    .ok()
+            })
+            .ok_or_else(|| {
+                DatabaseError::IntegrityError(format!("failed to decrypt the login for {}", url))
+            })?;
+
+        Ok(Some(RemoteCredentials {
+            url: url.to_string(),
+            username,
+            password,
+        }))
+    }
+
+    /// Store the login for a remote, replacing any previous one.
+    ///
+    /// # Arguments
+    ///
+    /// * `credentials` - The login to store.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the login cannot be encrypted or stored.
+    pub fn save_remote(
+        &self,
+        credentials: &RemoteCredentials,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let encrypted_password = self
+            .encryption
+            .encrypt_with_aad(
+                credentials.password.expose_secret(),
+                &remote_aad(&credentials.url),
+            )
+            .map_err(|_| "Failed to encrypt the remote password")?;
+
+        self.connection.execute(
+            "INSERT OR REPLACE INTO remotes (url, username, password) VALUES (?1, ?2, ?3)",
+            params![
+                credentials.url,
+                credentials.username,
+                STANDARD.encode(encrypted_password)
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// List the URLs of all remotes with a stored login.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `Vec` of URLs or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the remotes cannot be read.
+    pub fn list_remotes(&self) -> Result<Vec<String>, DatabaseError> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT url FROM remotes ORDER BY url")?;
+        let urls = stmt.query_map([], |row| row.get(0))?;
+
+        Ok(urls.collect::<Result<_, _>>()?)
+    }
+
+    /// Read the identifier of this vault, which copies of the vault share.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the identifier or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the identifier cannot be read.
+    pub fn vault_id(&self) -> Result<String, DatabaseError> {
+        Ok(self.connection.query_row(
+            "SELECT value FROM vault_meta WHERE key = 'vault_id'",
+            [],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Read when this vault was last merged with another vault.
+    ///
+    /// # Arguments
+    ///
+    /// * `peer` - The identifier of the other vault.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the time of the last merge, `None` if the vaults were never
+    /// merged, or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the merge time cannot be read.
+    pub fn merge_base(&self, peer: &str) -> Result<Option<String>, DatabaseError> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT value FROM vault_meta WHERE key = ?1",
+                params![format!("merged:{}", peer)],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Record when this vault was merged with another vault.
+    ///
+    /// # Arguments
+    ///
+    /// * `peer` - The identifier of the other vault.
+    /// * `merged_at` - When the vaults were merged.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the merge time cannot be stored.
+    pub fn set_merge_base(&self, peer: &str, merged_at: &str) -> Result<(), DatabaseError> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO vault_meta (key, value) VALUES (?1, ?2)",
+            params![format!("merged:{}", peer), merged_at],
+        )?;
         Ok(())
     }
 
     /// Search for PasswordEntries in the database.
     ///
+    /// Each match is decrypted on its own. Corrupt entries are skipped with a warning, so
+    /// they do not hide the others.
+    ///
     /// # Arguments
     ///
     /// * `query` - The search query.
@@ -262,36 +1036,126 @@ impl Database {
     /// # Errors
     ///
     /// An error will be returned if the PasswordEntries cannot be searched.
-    pub fn search(&self, query: &str) -> Result<Vec<PasswordEntry>, Box<dyn std::error::Error>> {
-        let mut stmt = self.connection.prepare(
-            "SELECT id, service, username, password, url, notes, created_at, updated_at
-            FROM passwords
-            WHERE service LIKE ?1 OR username LIKE ?1",
-        )?;
+    pub fn search(&self, query: &str) -> Result<Vec<PasswordEntry>, DatabaseError> {
+        let mut stmt = self.connection.prepare(&format!(
+            "SELECT {} FROM passwords WHERE service LIKE ?1 OR username LIKE ?1",
+            ENTRY_COLUMNS
+        ))?;
 
         let search_pattern = format!("%{}%", query);
-        let entries = stmt.query_map(params![search_pattern], |row| {
-            Ok(PasswordEntry {
-                id: row.get(0)?,
-                service: row.get(1)?,
-                username: row.get(2)?,
-                password: row.get(3)?,
-                url: row.get(4)?,
-                notes: row.get(5)?,
-                created_at: row.get(6)?,
-                updated_at: row.get(7)?,
-            })
-        })?;
+        let entries = stmt.query_map(params![search_pattern], |row| self.row_to_entry(row))?;
 
-        Ok(entries.collect::<Result<Vec<_>, _>>()?)
+        let mut found = Vec::new();
+        for entry in entries.collect::<Result<Vec<_>, _>>()? {
+            match entry {
+                Ok(entry) => found.push(entry),
+                Err(e @ DatabaseError::CorruptEntry { .. }) => {
+                    warn!("Skipping corrupt entry in search results: {}", e)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(found)
     }
 }
 
+impl VerifyReport {
+    /// Check if the vault passed every check.
+    ///
+    /// # Returns
+    ///
+    /// A `bool` indicating if no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.integrity_errors.is_empty() && self.corrupt_entries.is_empty()
+    }
+}
+
+/// Generate a random version 4 UUID in its textual form.
+///
+/// # Returns
+///
+/// A `Result` containing the UUID or an error.
+///
+/// # Errors
+///
+/// An error will be returned if no random bytes are available.
+pub fn generate_uuid() -> Result<String, Box<dyn std::error::Error>> {
+    let mut id = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut id)
+        .map_err(|_| "Failed to generate a UUID")?;
+    id[6] = (id[6] & 0x0f) | 0x40;
+    id[8] = (id[8] & 0x3f) | 0x80;
+
+    Ok(format_uuid(&id))
+}
+
+/// Format 16 bytes as a UUID in its textual form.
+///
+/// # Arguments
+///
+/// * `id` - The bytes of the UUID.
+///
+/// # Returns
+///
+/// The UUID in lower case hex with dashes.
+pub fn format_uuid(id: &[u8; 16]) -> String {
+    let hex: String = id.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Parse a UUID in its textual form.
+///
+/// # Arguments
+///
+/// * `uuid` - The UUID, with or without dashes.
+///
+/// # Returns
+///
+/// The 16 bytes of the UUID, or `None` if it is not a valid UUID.
+pub fn parse_uuid(uuid: &str) -> Option<[u8; 16]> {
+    let hex: String = uuid.chars().filter(|c| *c != '-').collect();
+    if hex.len() != 32 || !hex.is_ascii() {
+        return None;
+    }
+    let mut id = [0u8; 16];
+    for (index, byte) in id.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok()?;
+    }
+    Some(id)
+}
+
+/// Store tags as a JSON array, leaving the column empty for entries without tags.
+fn encode_tags(tags: &[String]) -> Result<String, serde_json::Error> {
+    if tags.is_empty() {
+        Ok(String::new())
+    } else {
+        serde_json::to_string(tags)
+    }
+}
+
+/// Build the associated data that binds an encrypted field to its entry.
+fn field_aad(id: i32, field: &str) -> Vec<u8> {
+    format!("passwords:{}:{}", id, field).into_bytes()
+}
+
+/// Build the associated data that binds an encrypted remote password to its URL.
+fn remote_aad(url: &str) -> Vec<u8> {
+    format!("remotes:{}:password", url).into_bytes()
+}
+
 impl PasswordEntry {
     pub fn new(
         service: String,
         username: String,
-        password: String,
+        password: SecretString,
         url: String,
         notes: String,
     ) -> Result<Self, Box<dyn std::error::Error>> {
@@ -304,8 +1168,116 @@ impl PasswordEntry {
             notes,
             created_at: Utc::now().to_rfc3339(),
             updated_at: Utc::now().to_rfc3339(),
+            folder: String::new(),
+            totp: SecretString::default(),
+            custom_fields: Vec::new(),
+            source: String::new(),
+            uuid: generate_uuid()?,
+            field_times: BTreeMap::new(),
+            tags: Vec::new(),
         })
     }
+
+    /// Read when a field was last modified.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - The name of the field, one of `ENTRY_FIELDS`.
+    ///
+    /// # Returns
+    ///
+    /// The RFC 3339 timestamp of the last modification.
+    pub fn field_time(&self, field: &str) -> &str {
+        self.field_times
+            .get(field)
+            .map(String::as_str)
+            .unwrap_or(&self.updated_at)
+    }
+
+    /// Check if a field has the same value in two entries.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The entry to compare with.
+    /// * `field` - The name of the field, one of `ENTRY_FIELDS`.
+    ///
+    /// # Returns
+    ///
+    /// A `bool` indicating if the values are equal.
+    pub fn same_field(&self, other: &PasswordEntry, field: &str) -> bool {
+        match field {
+            "service" => self.service == other.service,
+            "username" => self.username == other.username,
+            "password" => self.password == other.password,
+            "url" => self.url == other.url,
+            "notes" => self.notes == other.notes,
+            "folder" => self.folder == other.folder,
+            "totp" => self.totp == other.totp,
+            "custom_fields" => self.custom_fields == other.custom_fields,
+            "tags" => self.tags == other.tags,
+            _ => unreachable!("unknown entry field {}", field),
+        }
+    }
+
+    /// Order the values of a field in two entries, to pick one of two values that were
+    /// changed at the same time the same way in every vault.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The entry to compare with.
+    /// * `field` - The name of the field, one of `ENTRY_FIELDS`.
+    ///
+    /// # Returns
+    ///
+    /// The `Ordering` of the value of this entry to the value of the other one.
+    pub fn cmp_field<'a>(&'a self, other: &'a PasswordEntry, field: &str) -> Ordering {
+        match field {
+            "service" => self.service.cmp(&other.service),
+            "username" => self.username.cmp(&other.username),
+            "password" => self
+                .password
+                .expose_secret()
+                .cmp(other.password.expose_secret()),
+            "url" => self.url.cmp(&other.url),
+            "notes" => self.notes.cmp(&other.notes),
+            "folder" => self.folder.cmp(&other.folder),
+            "totp" => self.totp.expose_secret().cmp(other.totp.expose_secret()),
+            "custom_fields" => {
+                let key = |field: &'a CustomField| {
+                    (&field.name, field.value.expose_secret(), field.hidden)
+                };
+                self.custom_fields
+                    .iter()
+                    .map(key)
+                    .cmp(other.custom_fields.iter().map(key))
+            }
+            "tags" => self.tags.cmp(&other.tags),
+            _ => unreachable!("unknown entry field {}", field),
+        }
+    }
+
+    /// Copy the value of a field and its modification time from another entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The entry to copy from.
+    /// * `field` - The name of the field, one of `ENTRY_FIELDS`.
+    pub fn copy_field(&mut self, other: &PasswordEntry, field: &str) {
+        match field {
+            "service" => self.service = other.service.clone(),
+            "username" => self.username = other.username.clone(),
+            "password" => self.password = other.password.clone(),
+            "url" => self.url = other.url.clone(),
+            "notes" => self.notes = other.notes.clone(),
+            "folder" => self.folder = other.folder.clone(),
+            "totp" => self.totp = other.totp.clone(),
+            "custom_fields" => self.custom_fields = other.custom_fields.clone(),
+            "tags" => self.tags = other.tags.clone(),
+            _ => unreachable!("unknown entry field {}", field),
+        }
+        self.field_times
+            .insert(field.to_string(), other.field_time(field).to_string());
+    }
 }
 
 #[cfg(test)]
@@ -319,15 +1291,23 @@ mod tests {
         let mut salt = [0u8; 16];
         let rng = SystemRandom::new();
         rng.fill(&mut salt).unwrap();
-        Encryption::new("test_password", &salt)
+        Encryption::new(&"test_password".into(), &salt)
     }
 
     fn create_test_db() -> Database {
+        let db = create_legacy_test_db();
+        db.migrate().unwrap();
+
+        db
+    }
+
+    fn create_legacy_test_db() -> Database {
         let encryption = create_test_encryption();
         let db = Database {
             connection: Connection::open(":memory:").unwrap(),
             path: PathBuf::from(":memory:"),
             encryption,
+            key: SecretString::default(),
         };
 
         db.connection
@@ -352,6 +1332,17 @@ mod tests {
         db
     }
 
+    fn create_test_entry(service: &str, password: &str) -> PasswordEntry {
+        PasswordEntry::new(
+            service.to_string(),
+            "test_user".to_string(),
+            password.into(),
+            "https://example.com".to_string(),
+            "".to_string(),
+        )
+        .unwrap()
+    }
+
     #[test]
     fn test_crud_operations() {
         let db = create_test_db();
@@ -359,11 +1350,22 @@ mod tests {
             id: None,
             service: "test_service".to_string(),
             username: "test_user".to_string(),
-            password: "test_pass".to_string(),
+            password: "test_pass".into(),
             url: "https://example.com".to_string(),
             notes: "test notes".to_string(),
-            created_at: Utc::now().to_rfc3339(),
-            updated_at: Utc::now().to_rfc3339(),
+            created_at: "2020-05-01T10:00:00+00:00".to_string(),
+            updated_at: "2021-06-01T10:00:00+00:00".to_string(),
+            folder: "Work".to_string(),
+            totp: "otpauth://totp/test?secret=JBSWY3DPEHPK3PXP".into(),
+            custom_fields: vec![CustomField {
+                name: "PIN".to_string(),
+                value: "1234".into(),
+                hidden: true,
+            }],
+            source: "import:csv:2026-10-01".to_string(),
+            uuid: "6f1c2a8e-3b4d-4e5f-8a9b-0c1d2e3f4a5b".to_string(),
+            field_times: BTreeMap::new(),
+            tags: vec!["work".to_string(), "2fa".to_string()],
         };
 
         // Test Create
@@ -374,6 +1376,13 @@ mod tests {
         assert_eq!(entries.len(), 1);
         assert_eq!(entries[0].service, entry.service);
         assert_eq!(entries[0].password, entry.password);
+        assert_eq!(entries[0].folder, entry.folder);
+        assert_eq!(entries[0].totp, entry.totp);
+        assert_eq!(entries[0].custom_fields, entry.custom_fields);
+        assert_eq!(entries[0].created_at, entry.created_at);
+        assert_eq!(entries[0].updated_at, entry.updated_at);
+        assert_eq!(entries[0].source, entry.source);
+        assert_eq!(entries[0].tags, entry.tags);
 
         // Test Read by ID
         let id = entries[0].id.unwrap();
@@ -383,11 +1392,13 @@ mod tests {
         // Test Update
         let mut updated_entry = entries[0].clone();
         updated_entry.service = "updated_service".to_string();
+        updated_entry.tags.clear();
         let id = updated_entry.id.unwrap();
         db.update(id, updated_entry).unwrap();
 
         let updated_entries = db.read().unwrap();
         assert_eq!(updated_entries[0].service, "updated_service");
+        assert!(updated_entries[0].tags.is_empty());
 
         // Test Search
         let search_results = db.search("updated").unwrap();
@@ -400,4 +1411,221 @@ mod tests {
         let deleted_entries = db.read().unwrap();
         assert_eq!(deleted_entries.len(), 0);
     }
+
+    #[test]
+    fn test_swapped_ciphertext_is_rejected() {
+        let db = create_test_db();
+        db.create(&create_test_entry("first", "first_pass"))
+            .unwrap();
+        db.create(&create_test_entry("second", "second_pass"))
+            .unwrap();
+
+        let first_password: String = db
+            .connection
+            .query_row("SELECT password FROM passwords WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        db.connection
+            .execute(
+                "UPDATE passwords SET password = ?1 WHERE id = 2",
+                params![first_password],
+            )
+            .unwrap();
+
+        assert_eq!(
+            db.read_secret(1, "password", &first_password)
+                .unwrap()
+                .expose_secret(),
+            "first_pass"
+        );
+        assert!(db.read_secret(2, "password", &first_password).is_err());
+    }
+
+    #[test]
+    fn test_migrate_rewraps_legacy_passwords() {
+        let db = create_legacy_test_db();
+        let legacy_password = STANDARD.encode(db.encryption.encrypt_legacy("legacy_pass"));
+        db.connection
+            .execute(
+                "INSERT INTO passwords (service, username, password, url, notes, created_at, updated_at)
+                VALUES ('legacy', 'user', ?1, '', '', '', '')",
+                params![legacy_password],
+            )
+            .unwrap();
+
+        db.migrate().unwrap();
+
+        let entries = db.read().unwrap();
+        assert_eq!(entries[0].password.expose_secret(), "legacy_pass");
+        assert_eq!(entries[0].uuid.len(), 36);
+        assert_eq!(db.vault_id().unwrap().len(), 36);
+        let version: i32 = db
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_update_stamps_changed_fields() {
+        let db = create_test_db();
+        let mut entry = create_test_entry("service", "secret");
+        entry.updated_at = "2024-01-01T00:00:00+00:00".to_string();
+        db.create(&entry).unwrap();
+        let id = db.find_by_uuid(&entry.uuid).unwrap().unwrap();
+
+        let mut changed = create_test_entry("service", "changed");
+        changed.updated_at = "2025-01-01T00:00:00+00:00".to_string();
+        db.update(id, changed).unwrap();
+
+        let stored = db.read_by_id(id).unwrap();
+        assert_eq!(stored.uuid, entry.uuid);
+        assert_eq!(stored.field_time("password"), "2025-01-01T00:00:00+00:00");
+        assert_eq!(stored.field_time("service"), "2024-01-01T00:00:00+00:00");
+
+        // A reused UUID is replaced on create.
+        db.create(&entry).unwrap();
+        assert_ne!(db.read().unwrap()[1].uuid, entry.uuid);
+    }
+
+    #[test]
+    fn test_delete_records_uuid() {
+        let db = create_test_db();
+        let entry = create_test_entry("service", "secret");
+        db.create(&entry).unwrap();
+
+        db.delete(db.find_by_uuid(&entry.uuid).unwrap().unwrap())
+            .unwrap();
+        assert!(db.read_deleted().unwrap().contains_key(&entry.uuid));
+
+        // Storing the entry again forgets the deletion.
+        db.create(&entry).unwrap();
+        assert!(db.read_deleted().unwrap().is_empty());
+
+        db.mark_deleted(&entry.uuid, "2025-01-01T00:00:00+00:00")
+            .unwrap();
+        assert!(db.read().unwrap().is_empty());
+        assert_eq!(
+            db.read_deleted().unwrap()[&entry.uuid],
+            "2025-01-01T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_remote_credentials() {
+        let db = create_test_db();
+        assert!(db
+            .read_remote("https://dav.example.com/vault/")
+            .unwrap()
+            .is_none());
+
+        let credentials = RemoteCredentials {
+            url: "https://dav.example.com/vault/".to_string(),
+            username: "team".to_string(),
+            password: "dav-secret".into(),
+        };
+        db.save_remote(&credentials).unwrap();
+
+        let stored = db.read_remote(&credentials.url).unwrap().unwrap();
+        assert_eq!(stored.username, "team");
+        assert_eq!(stored.password.expose_secret(), "dav-secret");
+        assert_eq!(db.list_remotes().unwrap(), vec![credentials.url]);
+
+        let encoded: String = db
+            .connection
+            .query_row("SELECT password FROM remotes", [], |row| row.get(0))
+            .unwrap();
+        assert!(!encoded.contains("dav-secret"));
+    }
+
+    #[test]
+    fn test_generate_uuid() {
+        let uuid = generate_uuid().unwrap();
+
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+        assert_ne!(uuid, generate_uuid().unwrap());
+        assert_eq!(parse_uuid(&uuid).map(|id| format_uuid(&id)), Some(uuid));
+        assert_eq!(parse_uuid("not-a-uuid"), None);
+        assert_eq!(parse_uuid("6f1c2a8e-3b4d-4e5f-8a9b-0c1d2e3f4a5g"), None);
+    }
+
+    #[test]
+    fn test_read_by_unknown_id() {
+        let db = create_test_db();
+
+        assert!(matches!(
+            db.read_by_id(42),
+            Err(DatabaseError::NotFound(42))
+        ));
+    }
+
+    #[test]
+    fn test_corrupt_entry_does_not_panic() {
+        let db = create_test_db();
+        db.create(&create_test_entry("intact", "intact_pass"))
+            .unwrap();
+        db.create(&create_test_entry("corrupt", "corrupt_pass"))
+            .unwrap();
+        db.connection
+            .execute(
+                "UPDATE passwords SET password = 'not base64!' WHERE id = 2",
+                [],
+            )
+            .unwrap();
+
+        let entries = db.read_entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_ok());
+        assert!(matches!(
+            entries[1],
+            Err(DatabaseError::CorruptEntry { id: 2, .. })
+        ));
+
+        assert!(db.read().is_err());
+        let found = db.search("").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].service, "intact");
+        assert!(db.read_by_id(1).is_ok());
+        assert!(matches!(
+            db.read_by_id(2),
+            Err(DatabaseError::CorruptEntry { id: 2, .. })
+        ));
+
+        let report = db.verify().unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.corrupt_entries.len(), 1);
+    }
+
+    #[test]
+    fn test_verify_intact_vault() {
+        let db = create_test_db();
+        db.create(&create_test_entry("intact", "intact_pass"))
+            .unwrap();
+
+        let report = db.verify().unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.checked, 1);
+    }
+
+    #[test]
+    fn test_backup_to() {
+        let dir = tempfile::tempdir().unwrap();
+        let master_password = SecretString::from("master");
+        let salt = [1u8; 16];
+        let db = Database::new(dir.path().join("pass.db"), &master_password, &salt).unwrap();
+        db.create(&create_test_entry("service", "secret")).unwrap();
+
+        let snapshot = dir.path().join("snapshot.db");
+        db.backup_to(&snapshot).unwrap();
+
+        let restored = Database::new(snapshot.clone(), &master_password, &salt).unwrap();
+        let entries = restored.read().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].password.expose_secret(), "secret");
+
+        assert!(Database::new(snapshot, &"wrong".into(), &salt).is_err());
+    }
 }
@@ -0,0 +1,284 @@
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+
+/// The Public Suffix List from <https://publicsuffix.org>, under the MPL 2.0. It includes
+/// the private domains, so e.g. two `github.io` pages are different sites.
+const PUBLIC_SUFFIX_LIST: &str = include_str!("public_suffix_list.dat");
+
+/// The rules of the Public Suffix List, in lower case ASCII.
+struct Rules {
+    /// Rules like `co.uk`.
+    suffixes: HashSet<String>,
+    /// Rules like `*.ck`, stored without the `*.`.
+    wildcards: HashSet<String>,
+    /// Rules like `!www.ck`, stored without the `!`.
+    exceptions: HashSet<String>,
+}
+
+fn rules() -> &'static Rules {
+    static RULES: OnceLock<Rules> = OnceLock::new();
+    RULES.get_or_init(|| parse_rules(PUBLIC_SUFFIX_LIST))
+}
+
+fn parse_rules(list: &str) -> Rules {
+    let mut rules = Rules {
+        suffixes: HashSet::new(),
+        wildcards: HashSet::new(),
+        exceptions: HashSet::new(),
+    };
+    for line in list.lines() {
+        // A rule is the first word of a line, anything after it is ignored.
+        let Some(rule) = line.split_whitespace().next() else {
+            continue;
+        };
+        if rule.starts_with("//") {
+            continue;
+        }
+
+        let (set, rule) = if let Some(rule) = rule.strip_prefix('!') {
+            (&mut rules.exceptions, rule)
+        } else if let Some(rule) = rule.strip_prefix("*.") {
+            (&mut rules.wildcards, rule)
+        } else {
+            (&mut rules.suffixes, rule)
+        };
+        if let Some(rule) = to_ascii(rule) {
+            set.insert(rule);
+        }
+    }
+    rules
+}
+
+/// Convert a host name to lower case ASCII, e.g. `Bücher.example.` to
+/// `xn--bcher-kva.example`.
+fn to_ascii(host: &str) -> Option<String> {
+    let host = host.trim().trim_end_matches('.');
+    let host = if host.is_ascii() {
+        host.to_ascii_lowercase()
+    } else {
+        idna::domain_to_ascii(host).ok()?
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+/// The public suffix of a host in lower case ASCII: the longest rule that matches, or its
+/// last label if none does.
+fn public_suffix(host: &str) -> &str {
+    let rules = rules();
+    let mut candidate = host;
+    loop {
+        let parent = candidate.split_once('.').map(|(_, parent)| parent);
+        // An exception is longer than the wildcard it is an exception to, so it is found
+        // first.
+        if rules.exceptions.contains(candidate) {
+            return parent.unwrap_or(candidate);
+        }
+        if rules.suffixes.contains(candidate)
+            || parent.is_some_and(|parent| rules.wildcards.contains(parent))
+        {
+            return candidate;
+        }
+        match parent {
+            Some(parent) => candidate = parent,
+            None => return candidate,
+        }
+    }
+}
+
+/// The registrable domain of a host: its public suffix and the label before it, e.g.
+/// `example.co.uk` for `login.example.co.uk`.
+///
+/// # Arguments
+///
+/// * `host` - The host name.
+///
+/// # Returns
+///
+/// The registrable domain in lower case ASCII, or `None` for IP addresses and hosts that
+/// are a public suffix themselves, e.g. `github.io` or `localhost`.
+pub fn registrable_domain(host: &str) -> Option<String> {
+    let host = to_ascii(host)?;
+    if host.parse::<IpAddr>().is_ok() {
+        return None;
+    }
+
+    let suffix = public_suffix(&host);
+    let prefix = host.strip_suffix(suffix)?.strip_suffix('.')?;
+    let label = prefix.rsplit('.').next()?;
+    Some(format!("{}.{}", label, suffix))
+}
+
+/// Split a URL into its lower case scheme and its host without user info or port. The
+/// scheme is empty for URLs without one, e.g. `example.com/login`.
+fn split_url(url: &str) -> Option<(String, String)> {
+    let url = url.trim();
+    let (scheme, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (scheme.to_lowercase(), rest),
+        None => (String::new(), url),
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    let host = match host.strip_prefix('[') {
+        Some(address) => address.split(']').next().unwrap_or(address),
+        None => host.split(':').next().unwrap_or(host),
+    };
+
+    if host.parse::<IpAddr>().is_ok() {
+        Some((scheme, host.to_string()))
+    } else {
+        Some((scheme, to_ascii(host)?))
+    }
+}
+
+/// The host of a web page logins can be filled into.
+///
+/// # Arguments
+///
+/// * `origin` - The origin or URL of the page, e.g. `https://example.com`.
+///
+/// # Returns
+///
+/// The host in lower case ASCII, or `None` if the page is not served over HTTP or HTTPS.
+pub fn page_host(origin: &str) -> Option<String> {
+    split_url(origin)
+        .filter(|(scheme, _)| scheme == "http" || scheme == "https")
+        .map(|(_, host)| host)
+}
+
+/// How well the URL of an entry matches a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OriginMatch {
+    /// The URL is on another host of the same registrable domain.
+    Site,
+    /// The URL is on the host of the page.
+    Host,
+}
+
+/// Check if the login of an entry may be filled into a page.
+///
+/// The hosts must be equal or share their registrable domain, so `login.example.com`
+/// matches `www.example.com` but `alice.github.io` does not match `bob.github.io`. IP
+/// addresses only match themselves. A URL without a scheme matches both HTTP and HTTPS
+/// pages, but an `https` login is never offered to a page served over plain HTTP.
+///
+/// # Arguments
+///
+/// * `url` - The URL of the entry.
+/// * `origin` - The origin or URL of the page.
+///
+/// # Returns
+///
+/// How well the entry matches, or `None` if it does not.
+pub fn match_origin(url: &str, origin: &str) -> Option<OriginMatch> {
+    let (page_scheme, page_host) = split_url(origin)?;
+    let (scheme, host) = split_url(url)?;
+    let scheme_allowed = match scheme.as_str() {
+        "" | "http" => page_scheme == "http" || page_scheme == "https",
+        "https" => page_scheme == "https",
+        _ => false,
+    };
+    if !scheme_allowed {
+        return None;
+    }
+
+    if host == page_host {
+        return Some(OriginMatch::Host);
+    }
+    let domain = registrable_domain(&host)?;
+    (registrable_domain(&page_host)? == domain).then_some(OriginMatch::Site)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registrable_domain() {
+        let cases = [
+            ("example.com", Some("example.com")),
+            ("Login.Example.COM.", Some("example.com")),
+            ("a.b.example.co.uk", Some("example.co.uk")),
+            ("alice.github.io", Some("alice.github.io")),
+            ("github.io", None),
+            ("co.uk", None),
+            ("localhost", None),
+            ("printer.local", Some("printer.local")),
+            // `*.ck` with the exception `!www.ck`.
+            ("shop.co.ck", Some("shop.co.ck")),
+            ("co.ck", None),
+            ("www.ck", Some("www.ck")),
+            ("a.www.ck", Some("www.ck")),
+            ("www.bücher.de", Some("xn--bcher-kva.de")),
+            ("www.例え.jp", Some("xn--r8jz45g.jp")),
+            ("www.shop.公司.cn", Some("shop.xn--55qx5d.cn")),
+            ("192.168.1.1", None),
+            ("", None),
+        ];
+        for (host, expected) in cases {
+            assert_eq!(registrable_domain(host).as_deref(), expected, "{}", host);
+        }
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rules =
+            parse_rules("// comment\n\ncom\n*.kawasaki.jp\n!city.kawasaki.jp\n東京.jp  trailing\n");
+        assert!(rules.suffixes.contains("com"));
+        assert!(rules.suffixes.contains("xn--1lqs71d.jp"));
+        assert!(rules.wildcards.contains("kawasaki.jp"));
+        assert!(rules.exceptions.contains("city.kawasaki.jp"));
+        assert_eq!(rules.suffixes.len(), 2);
+    }
+
+    #[test]
+    fn test_page_host() {
+        assert_eq!(
+            page_host("https://user@Mail.Example.com:8443/inbox").as_deref(),
+            Some("mail.example.com")
+        );
+        assert_eq!(page_host("http://[::1]:8080").as_deref(), Some("::1"));
+        assert_eq!(page_host("chrome://settings"), None);
+        assert_eq!(page_host("example.com"), None);
+    }
+
+    #[test]
+    fn test_match_origin() {
+        let origin = "https://www.example.co.uk";
+        assert_eq!(
+            match_origin("https://www.example.co.uk/login", origin),
+            Some(OriginMatch::Host)
+        );
+        assert_eq!(
+            match_origin("accounts.example.co.uk", origin),
+            Some(OriginMatch::Site)
+        );
+        assert_eq!(
+            match_origin("http://example.co.uk:8080", origin),
+            Some(OriginMatch::Site)
+        );
+        assert_eq!(match_origin("https://other.co.uk", origin), None);
+        assert_eq!(match_origin("ftp://example.co.uk", origin), None);
+        assert_eq!(match_origin("", origin), None);
+
+        // An HTTPS login is not offered to a page without TLS.
+        assert_eq!(
+            match_origin("https://example.com", "http://example.com"),
+            None
+        );
+        assert_eq!(
+            match_origin("example.com", "http://example.com"),
+            Some(OriginMatch::Host)
+        );
+
+        assert_eq!(
+            match_origin("https://alice.github.io", "https://bob.github.io"),
+            None
+        );
+        assert_eq!(
+            match_origin("https://10.0.0.1/admin", "https://10.0.0.1:8443"),
+            Some(OriginMatch::Host)
+        );
+        assert_eq!(match_origin("https://10.0.0.1", "https://10.0.0.2"), None);
+    }
+}
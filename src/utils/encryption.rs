@@ -1,10 +1,19 @@
 use std::num::NonZeroU32;
+use std::path::Path;
 
 use ring::{
     aead::{self, Nonce},
     pbkdf2,
     rand::{SecureRandom, SystemRandom},
 };
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::prelude::SecretString;
+
+/// The version byte prefixed to every ciphertext envelope.
+pub const CIPHERTEXT_VERSION: u8 = 1;
+
+const NONCE_LEN: usize = 12;
 
 #[derive(Debug)]
 pub struct Encryption {
@@ -25,25 +34,48 @@ impl Encryption {
     /// # Panics
     ///
     /// Panics if the key cannot be created.
-    pub fn new(master_password: &str, salt: &[u8; 16]) -> Self {
-        let mut key = [0u8; 32];
+    pub fn new(master_password: &SecretString, salt: &[u8; 16]) -> Self {
+        Self::with_iterations(master_password, salt, NonZeroU32::new(100_000).unwrap())
+    }
+
+    /// Create a new `Encryption` instance with a custom PBKDF2 iteration count.
+    ///
+    /// # Arguments
+    ///
+    /// * `password` - The password to derive the key from.
+    /// * `salt` - The salt to derive the key with.
+    /// * `iterations` - The number of PBKDF2 iterations.
+    ///
+    /// # Returns
+    ///
+    /// A new `Encryption` instance.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key cannot be created.
+    pub fn with_iterations(
+        password: &SecretString,
+        salt: &[u8; 16],
+        iterations: NonZeroU32,
+    ) -> Self {
+        let mut key = Zeroizing::new([0u8; 32]);
         pbkdf2::derive(
             pbkdf2::PBKDF2_HMAC_SHA256,
-            NonZeroU32::new(100_000).unwrap(),
+            iterations,
             salt,
-            master_password.as_bytes(),
-            &mut key,
+            password.expose_secret().as_bytes(),
+            key.as_mut(),
         );
 
-        let unbound_key =
-            aead::UnboundKey::new(&aead::CHACHA20_POLY1305, &key).expect("Failed to create key");
+        let unbound_key = aead::UnboundKey::new(&aead::CHACHA20_POLY1305, key.as_ref())
+            .expect("Failed to create key");
 
         Self {
             key: aead::LessSafeKey::new(unbound_key),
         }
     }
 
-    /// Encrypt data.
+    /// Encrypt data without associated data.
     ///
     /// # Arguments
     ///
@@ -57,23 +89,51 @@ impl Encryption {
     ///
     /// An error will be returned if the data cannot be encrypted.
     pub fn encrypt(&self, data: &str) -> Result<Vec<u8>, ring::error::Unspecified> {
+        self.encrypt_with_aad(data, &[])
+    }
+
+    /// Encrypt data into a versioned envelope bound to the given associated data.
+    ///
+    /// The envelope is laid out as `version || nonce || ciphertext || tag`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to encrypt.
+    /// * `aad` - The associated data to authenticate alongside the ciphertext.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the encrypted data as a `Vec<u8>`.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the data cannot be encrypted.
+    pub fn encrypt_with_aad(
+        &self,
+        data: &str,
+        aad: &[u8],
+    ) -> Result<Vec<u8>, ring::error::Unspecified> {
         let rng = SystemRandom::new();
-        let mut nonce_bytes = [0u8; 12];
+        let mut nonce_bytes = [0u8; NONCE_LEN];
         rng.fill(&mut nonce_bytes)?;
         let nonce = aead::Nonce::assume_unique_for_key(nonce_bytes);
 
-        let mut in_out = data.as_bytes().to_vec();
+        let mut in_out = Zeroizing::new(data.as_bytes().to_vec());
         self.key
-            .seal_in_place_append_tag(nonce, aead::Aad::empty(), &mut in_out)?;
+            .seal_in_place_append_tag(nonce, aead::Aad::from(aad), &mut *in_out)?;
 
-        let mut result = Vec::with_capacity(nonce_bytes.len() + in_out.len());
+        let mut result = Vec::with_capacity(1 + nonce_bytes.len() + in_out.len());
+        result.push(CIPHERTEXT_VERSION);
         result.extend_from_slice(&nonce_bytes);
         result.extend_from_slice(&in_out);
 
         Ok(result)
     }
 
-    /// Decrypt data.
+    /// Decrypt data that was encrypted without associated data.
+    ///
+    /// Both the versioned envelope and the legacy `nonce || ciphertext || tag`
+    /// format are accepted.
     ///
     /// # Arguments
     ///
@@ -81,28 +141,187 @@ impl Encryption {
     ///
     /// # Returns
     ///
-    /// A `Result` containing the decrypted data as a `String`.
+    /// A `Result` containing the decrypted data as a `SecretString`.
     ///
     /// # Errors
     ///
     /// An error will be returned if the data cannot be decrypted.
-    pub fn decrypt(&self, encrypted_data: &[u8]) -> Result<String, ring::error::Unspecified> {
-        if encrypted_data.len() < 12 {
+    pub fn decrypt(&self, encrypted_data: &[u8]) -> Result<SecretString, ring::error::Unspecified> {
+        self.decrypt_with_aad(encrypted_data, &[])
+            .or_else(|_| self.decrypt_legacy(encrypted_data))
+    }
+
+    /// Decrypt a versioned envelope bound to the given associated data.
+    ///
+    /// Legacy ciphertexts are rejected; use `decrypt_legacy` to read them.
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted_data` - The encrypted data to decrypt.
+    /// * `aad` - The associated data the ciphertext was bound to.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the decrypted data as a `SecretString`.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the version is unknown, the associated data
+    /// does not match or the data cannot be decrypted.
+    pub fn decrypt_with_aad(
+        &self,
+        encrypted_data: &[u8],
+        aad: &[u8],
+    ) -> Result<SecretString, ring::error::Unspecified> {
+        match encrypted_data.split_first() {
+            Some((&CIPHERTEXT_VERSION, rest)) => self.open(rest, aad),
+            _ => Err(ring::error::Unspecified),
+        }
+    }
+
+    /// Decrypt data in the legacy `nonce || ciphertext || tag` format.
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted_data` - The encrypted data to decrypt.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the decrypted data as a `SecretString`.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the data cannot be decrypted.
+    pub fn decrypt_legacy(
+        &self,
+        encrypted_data: &[u8],
+    ) -> Result<SecretString, ring::error::Unspecified> {
+        self.open(encrypted_data, &[])
+    }
+
+    /// Open a `nonce || ciphertext || tag` buffer.
+    fn open(
+        &self,
+        encrypted_data: &[u8],
+        aad: &[u8],
+    ) -> Result<SecretString, ring::error::Unspecified> {
+        if encrypted_data.len() < NONCE_LEN {
             return Err(ring::error::Unspecified);
         }
 
         let nonce = Nonce::assume_unique_for_key(
-            encrypted_data[..12]
+            encrypted_data[..NONCE_LEN]
                 .try_into()
                 .map_err(|_| ring::error::Unspecified)?,
         );
-        let mut in_out = encrypted_data[12..].to_vec();
+        let mut in_out = encrypted_data[NONCE_LEN..].to_vec();
 
-        let plain_text = self
+        let plain_text_len = match self
             .key
-            .open_in_place(nonce, aead::Aad::empty(), &mut in_out)?;
+            .open_in_place(nonce, aead::Aad::from(aad), &mut in_out)
+        {
+            Ok(plain_text) => plain_text.len(),
+            Err(e) => {
+                in_out.zeroize();
+                return Err(e);
+            }
+        };
+        in_out.truncate(plain_text_len);
 
-        String::from_utf8(plain_text.to_vec()).map_err(|_| ring::error::Unspecified)
+        String::from_utf8(in_out)
+            .map(SecretString::new)
+            .map_err(|e| {
+                e.into_bytes().zeroize();
+                ring::error::Unspecified
+            })
+    }
+
+    /// Encrypt data in the legacy format, for exercising migrations in tests.
+    #[cfg(test)]
+    pub(crate) fn encrypt_legacy(&self, data: &str) -> Vec<u8> {
+        let rng = SystemRandom::new();
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        rng.fill(&mut nonce_bytes).unwrap();
+        let nonce = aead::Nonce::assume_unique_for_key(nonce_bytes);
+
+        let mut in_out = data.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(nonce, aead::Aad::empty(), &mut in_out)
+            .unwrap();
+
+        let mut result = nonce_bytes.to_vec();
+        result.extend_from_slice(&in_out);
+        result
+    }
+
+    /// Create a master key file for a new vault.
+    ///
+    /// The file holds a random salt followed by the master password encrypted with itself,
+    /// which is used to verify the master password on unlock.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to write the master key file to.
+    /// * `master_password` - The master password of the vault.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the salt of the vault or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the file cannot be written.
+    pub fn create_master_file(
+        path: &Path,
+        master_password: &SecretString,
+    ) -> Result<[u8; 16], Box<dyn std::error::Error>> {
+        let rng = SystemRandom::new();
+        let mut salt = [0u8; 16];
+        rng.fill(&mut salt).map_err(|_| "Failed to generate salt")?;
+
+        let encryption = Encryption::new(master_password, &salt);
+        let verification_data = encryption
+            .encrypt(master_password.expose_secret())
+            .map_err(|_| "Failed to encrypt verification data")?;
+
+        let mut file_content = Vec::new();
+        file_content.extend_from_slice(&salt);
+        file_content.extend_from_slice(&verification_data);
+        std::fs::write(path, file_content)?;
+
+        Ok(salt)
+    }
+
+    /// Check a master password against a master key file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the master key file.
+    /// * `master_password` - The master password to check.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the salt of the vault or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the file cannot be read or the master password is invalid.
+    pub fn unlock_master_file(
+        path: &Path,
+        master_password: &SecretString,
+    ) -> Result<[u8; 16], Box<dyn std::error::Error>> {
+        let file_content = std::fs::read(path)?;
+        if file_content.len() < 16 {
+            return Err("Invalid master key file".into());
+        }
+        let salt: [u8; 16] = file_content[..16].try_into()?;
+        let verification_data = &file_content[16..];
+
+        let encryption = Encryption::new(master_password, &salt);
+        match encryption.decrypt(verification_data) {
+            Ok(decrypted) if decrypted == *master_password => Ok(salt),
+            _ => Err("Invalid master password".into()),
+        }
     }
 
     /// Get the encryption key.
@@ -113,20 +332,23 @@ impl Encryption {
     ///
     /// # Returns
     ///
-    /// A `Result` containing the encryption key as a `String`.
+    /// A `Result` containing the encryption key as a hex encoded `SecretString`.
     ///
     /// # Errors
     ///
     /// An error will be returned if the key cannot be generated.
-    pub fn get_key(&self, master_password: &str) -> Result<String, Box<dyn std::error::Error>> {
-        let mut key_bytes = [0u8; 32];
+    pub fn get_key(
+        &self,
+        master_password: &SecretString,
+    ) -> Result<SecretString, Box<dyn std::error::Error>> {
+        let mut key_bytes = Zeroizing::new([0u8; 32]);
 
         pbkdf2::derive(
             pbkdf2::PBKDF2_HMAC_SHA256,
             NonZeroU32::new(100_000).unwrap(),
             b"db_encryption",
-            master_password.as_bytes(),
-            &mut key_bytes,
+            master_password.expose_secret().as_bytes(),
+            key_bytes.as_mut(),
         );
 
         let hex_string = key_bytes
@@ -137,7 +359,7 @@ impl Encryption {
                 acc
             });
 
-        Ok(hex_string)
+        Ok(SecretString::new(hex_string))
     }
 }
 
@@ -158,17 +380,17 @@ mod tests {
         let data = "data";
         let salt = create_test_salt();
 
-        let encryption = Encryption::new(master_password, &salt);
+        let encryption = Encryption::new(&master_password.into(), &salt);
         let encrypted_data = encryption.encrypt(data).unwrap();
         let decrypted_data = encryption.decrypt(&encrypted_data).unwrap();
 
-        assert_eq!(data, decrypted_data);
+        assert_eq!(data, decrypted_data.expose_secret());
     }
 
     #[test]
     fn test_decrypt_invalid_data() {
         let salt = create_test_salt();
-        let encryption = Encryption::new("password", &salt);
+        let encryption = Encryption::new(&"password".into(), &salt);
         let result = encryption.decrypt(&[0u8; 8]);
         assert!(result.is_err());
     }
@@ -177,8 +399,8 @@ mod tests {
     fn test_different_passwords() {
         let data = "data";
         let salt = create_test_salt();
-        let encryption1 = Encryption::new("password1", &salt);
-        let encryption2 = Encryption::new("password2", &salt);
+        let encryption1 = Encryption::new(&"password1".into(), &salt);
+        let encryption2 = Encryption::new(&"password2".into(), &salt);
 
         let encrypted_data = encryption1.encrypt(data).unwrap();
         let result = encryption2.decrypt(&encrypted_data);
@@ -192,11 +414,68 @@ mod tests {
         let salt1 = create_test_salt();
         let salt2 = create_test_salt();
 
-        let encryption1 = Encryption::new(password, &salt1);
-        let encryption2 = Encryption::new(password, &salt2);
+        let encryption1 = Encryption::new(&password.into(), &salt1);
+        let encryption2 = Encryption::new(&password.into(), &salt2);
 
         let encrypted_data = encryption1.encrypt(data).unwrap();
         let result = encryption2.decrypt(&encrypted_data);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_ciphertext_is_versioned() {
+        let salt = create_test_salt();
+        let encryption = Encryption::new(&"password".into(), &salt);
+        let encrypted_data = encryption.encrypt_with_aad("data", b"aad").unwrap();
+
+        assert_eq!(encrypted_data[0], CIPHERTEXT_VERSION);
+    }
+
+    #[test]
+    fn test_mismatched_aad() {
+        let salt = create_test_salt();
+        let encryption = Encryption::new(&"password".into(), &salt);
+
+        let encrypted_data = encryption.encrypt_with_aad("data", b"1:password").unwrap();
+        let decrypted_data = encryption.decrypt_with_aad(&encrypted_data, b"1:password");
+        assert_eq!(decrypted_data.unwrap().expose_secret(), "data");
+
+        let result = encryption.decrypt_with_aad(&encrypted_data, b"2:password");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_decrypt_legacy_format() {
+        let salt = create_test_salt();
+        let encryption = Encryption::new(&"password".into(), &salt);
+        let legacy_data = encryption.encrypt_legacy("data");
+
+        assert_eq!(
+            encryption.decrypt(&legacy_data).unwrap().expose_secret(),
+            "data"
+        );
+        assert_eq!(
+            encryption
+                .decrypt_legacy(&legacy_data)
+                .unwrap()
+                .expose_secret(),
+            "data"
+        );
+        assert!(encryption.decrypt_with_aad(&legacy_data, &[]).is_err());
+    }
+
+    #[test]
+    fn test_master_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("master.key");
+        let master_password = SecretString::from("master");
+
+        let salt = Encryption::create_master_file(&path, &master_password).unwrap();
+
+        assert_eq!(
+            Encryption::unlock_master_file(&path, &master_password).unwrap(),
+            salt
+        );
+        assert!(Encryption::unlock_master_file(&path, &"wrong".into()).is_err());
+    }
 }
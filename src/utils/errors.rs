@@ -38,3 +38,141 @@ impl From<TemplateError> for FileSearchError {
         FileSearchError::SearchError(err.to_string())
     }
 }
+
+#[derive(Debug)]
+pub enum DatabaseError {
+    SqlError(rusqlite::Error),
+    NotFound(i32),
+    CorruptEntry { id: i32, reason: String },
+    IntegrityError(String),
+}
+
+impl std::error::Error for DatabaseError {}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DatabaseError::SqlError(err) => write!(f, "SQL error: {}", err),
+            DatabaseError::NotFound(id) => write!(f, "Entry {} not found", id),
+            DatabaseError::CorruptEntry { id, reason } => {
+                write!(f, "Entry {} is corrupt: {}", id, reason)
+            }
+            DatabaseError::IntegrityError(msg) => write!(f, "Integrity error: {}", msg),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for DatabaseError {
+    fn from(err: rusqlite::Error) -> Self {
+        DatabaseError::SqlError(err)
+    }
+}
+
+/// Exit code for errors without a more specific code.
+pub const EXIT_FAILURE: u8 = 1;
+/// Exit code for missing or invalid input. Matches the code of command line parse errors.
+pub const EXIT_INVALID_INPUT: u8 = 2;
+/// Exit code for a vault that cannot be unlocked.
+pub const EXIT_LOCKED: u8 = 3;
+/// Exit code for an entry or backup that does not exist.
+pub const EXIT_NOT_FOUND: u8 = 4;
+
+/// Errors of the command line that scripts need to tell apart by their exit code.
+#[derive(Debug)]
+pub enum CliError {
+    InvalidInput(String),
+    Locked(String),
+    NotFound(String),
+}
+
+impl CliError {
+    /// Get the exit code of the error.
+    ///
+    /// # Returns
+    ///
+    /// The exit code of the error.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CliError::InvalidInput(_) => EXIT_INVALID_INPUT,
+            CliError::Locked(_) => EXIT_LOCKED,
+            CliError::NotFound(_) => EXIT_NOT_FOUND,
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            CliError::Locked(msg) => write!(f, "Vault locked: {}", msg),
+            CliError::NotFound(msg) => write!(f, "Not found: {}", msg),
+        }
+    }
+}
+
+impl From<InquireError> for CliError {
+    fn from(err: InquireError) -> Self {
+        CliError::InvalidInput(err.to_string())
+    }
+}
+
+impl From<CliError> for FileSearchError {
+    fn from(err: CliError) -> Self {
+        match err {
+            CliError::InvalidInput(msg) => FileSearchError::InputError(msg),
+            err => FileSearchError::SearchError(err.to_string()),
+        }
+    }
+}
+
+/// Get the exit code for an error returned by a command.
+///
+/// # Arguments
+///
+/// * `error` - The error returned by the command.
+///
+/// # Returns
+///
+/// The exit code, `EXIT_FAILURE` for errors without a more specific code.
+pub fn exit_code(error: &(dyn std::error::Error + 'static)) -> u8 {
+    if let Some(error) = error.downcast_ref::<CliError>() {
+        error.exit_code()
+    } else if let Some(DatabaseError::NotFound(_)) = error.downcast_ref::<DatabaseError>() {
+        EXIT_NOT_FOUND
+    } else if let Some(FileSearchError::InputError(_)) = error.downcast_ref::<FileSearchError>() {
+        EXIT_INVALID_INPUT
+    } else if error.downcast_ref::<InquireError>().is_some() {
+        EXIT_INVALID_INPUT
+    } else {
+        EXIT_FAILURE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        let errors: [(Box<dyn std::error::Error>, u8); 6] = [
+            (
+                CliError::InvalidInput("id".into()).into(),
+                EXIT_INVALID_INPUT,
+            ),
+            (
+                CliError::Locked("wrong password".into()).into(),
+                EXIT_LOCKED,
+            ),
+            (CliError::NotFound("Mail".into()).into(), EXIT_NOT_FOUND),
+            (DatabaseError::NotFound(7).into(), EXIT_NOT_FOUND),
+            (InquireError::NotTTY.into(), EXIT_INVALID_INPUT),
+            ("Something failed".into(), EXIT_FAILURE),
+        ];
+
+        for (error, code) in errors {
+            assert_eq!(exit_code(error.as_ref()), code);
+        }
+    }
+}
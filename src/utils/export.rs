@@ -0,0 +1,281 @@
+use std::num::NonZeroU32;
+
+use chrono::Utc;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::prelude::{Encryption, PasswordEntry, SecretString};
+
+/// The magic bytes at the start of every encrypted export file.
+const MAGIC: &[u8; 4] = b"RTKX";
+/// The version of the encrypted export file format.
+const FORMAT_VERSION: u8 = 1;
+/// Identifies PBKDF2-HMAC-SHA256 as the key derivation function in the header.
+const KDF_PBKDF2_SHA256: u8 = 1;
+/// The PBKDF2 iteration count used for new exports.
+const KDF_ITERATIONS: u32 = 600_000;
+/// The largest PBKDF2 iteration count accepted from a file, so a crafted header cannot
+/// keep the key derivation busy for hours.
+const MAX_KDF_ITERATIONS: u32 = 10 * KDF_ITERATIONS;
+/// The length of the header: magic, version, KDF id, iterations and salt.
+const HEADER_LEN: usize = 4 + 1 + 1 + 4 + 16;
+
+/// The decrypted content of an encrypted export file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportPayload {
+    pub version: u8,
+    pub exported_at: String,
+    pub entries: Vec<PasswordEntry>,
+}
+
+impl ExportPayload {
+    /// Create a new `ExportPayload` instance stamped with the current time.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The entries to export.
+    ///
+    /// # Returns
+    ///
+    /// A new `ExportPayload` instance.
+    pub fn new(entries: Vec<PasswordEntry>) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            exported_at: Utc::now().to_rfc3339(),
+            entries,
+        }
+    }
+}
+
+/// Check if data looks like an encrypted export file.
+///
+/// # Arguments
+///
+/// * `data` - The file content to check.
+///
+/// # Returns
+///
+/// A `bool` indicating if the data starts with the encrypted export magic bytes.
+pub fn is_encrypted_export(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Serialize an export payload as plain, unencrypted JSON.
+///
+/// # Arguments
+///
+/// * `payload` - The payload to serialize.
+///
+/// # Returns
+///
+/// A `Result` containing the JSON document or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the payload cannot be serialized.
+pub fn to_json(payload: &ExportPayload) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    Ok(Zeroizing::new(serde_json::to_string_pretty(payload)?))
+}
+
+/// Read a plain JSON export.
+///
+/// # Arguments
+///
+/// * `data` - The content of the JSON export.
+///
+/// # Returns
+///
+/// A `Result` containing the payload or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the data is not a JSON export of this format.
+pub fn from_json(data: &[u8]) -> Result<ExportPayload, Box<dyn std::error::Error>> {
+    let payload: ExportPayload = serde_json::from_slice(data)?;
+    if payload.version != FORMAT_VERSION {
+        return Err(format!("Unsupported export format version: {}", payload.version).into());
+    }
+
+    Ok(payload)
+}
+
+/// Encrypt an export payload with a password.
+///
+/// The file is laid out as `header || envelope`, where the header holds the KDF
+/// parameters and is authenticated as associated data of the envelope.
+///
+/// # Arguments
+///
+/// * `payload` - The payload to encrypt.
+/// * `password` - The password to protect the export with.
+///
+/// # Returns
+///
+/// A `Result` containing the encrypted file content or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the payload cannot be serialized or encrypted.
+pub fn seal(
+    payload: &ExportPayload,
+    password: &SecretString,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; 16];
+    rng.fill(&mut salt).map_err(|_| "Failed to generate salt")?;
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.push(FORMAT_VERSION);
+    header.push(KDF_PBKDF2_SHA256);
+    header.extend_from_slice(&KDF_ITERATIONS.to_be_bytes());
+    header.extend_from_slice(&salt);
+
+    let iterations = NonZeroU32::new(KDF_ITERATIONS).ok_or("Invalid iteration count")?;
+    let encryption = Encryption::with_iterations(password, &salt, iterations);
+    let json = Zeroizing::new(serde_json::to_string(payload)?);
+    let envelope = encryption
+        .encrypt_with_aad(&json, &header)
+        .map_err(|_| "Failed to encrypt export")?;
+
+    let mut result = header;
+    result.extend_from_slice(&envelope);
+    Ok(result)
+}
+
+/// Decrypt an encrypted export file.
+///
+/// # Arguments
+///
+/// * `data` - The encrypted file content.
+/// * `password` - The password the export was protected with.
+///
+/// # Returns
+///
+/// A `Result` containing the decrypted payload or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the file is malformed, asks for more key derivation
+/// iterations than `MAX_KDF_ITERATIONS`, was tampered with or the password is wrong.
+pub fn open(
+    data: &[u8],
+    password: &SecretString,
+) -> Result<ExportPayload, Box<dyn std::error::Error>> {
+    if !is_encrypted_export(data) || data.len() < HEADER_LEN {
+        return Err("Not an encrypted export file".into());
+    }
+
+    let (header, envelope) = data.split_at(HEADER_LEN);
+    if header[4] != FORMAT_VERSION {
+        return Err(format!("Unsupported export format version: {}", header[4]).into());
+    }
+    if header[5] != KDF_PBKDF2_SHA256 {
+        return Err(format!("Unsupported key derivation function: {}", header[5]).into());
+    }
+    let iterations = u32::from_be_bytes(header[6..10].try_into()?);
+    if iterations > MAX_KDF_ITERATIONS {
+        return Err(format!(
+            "The export asks for {} key derivation iterations, at most {} are allowed",
+            iterations, MAX_KDF_ITERATIONS
+        )
+        .into());
+    }
+    let iterations = NonZeroU32::new(iterations).ok_or("Invalid iteration count")?;
+    let salt: [u8; 16] = header[10..].try_into()?;
+
+    let encryption = Encryption::with_iterations(password, &salt, iterations);
+    let json = encryption
+        .decrypt_with_aad(envelope, header)
+        .map_err(|_| "Failed to decrypt export: wrong password or damaged file")?;
+
+    Ok(serde_json::from_str(json.expose_secret())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::CustomField;
+
+    fn create_test_payload() -> ExportPayload {
+        ExportPayload::new(vec![PasswordEntry::new(
+            "service".to_string(),
+            "user".to_string(),
+            "secret".into(),
+            "https://example.com".to_string(),
+            "notes".to_string(),
+        )
+        .unwrap()])
+    }
+
+    #[test]
+    fn test_seal_open() {
+        let password = SecretString::from("export");
+        let data = seal(&create_test_payload(), &password).unwrap();
+
+        assert!(is_encrypted_export(&data));
+        assert!(!String::from_utf8_lossy(&data).contains("secret"));
+
+        let payload = open(&data, &password).unwrap();
+        assert_eq!(payload.version, FORMAT_VERSION);
+        assert_eq!(payload.entries.len(), 1);
+        assert_eq!(payload.entries[0].service, "service");
+        assert_eq!(payload.entries[0].password.expose_secret(), "secret");
+    }
+
+    #[test]
+    fn test_open_wrong_password() {
+        let data = seal(&create_test_payload(), &"export".into()).unwrap();
+
+        assert!(open(&data, &"wrong".into()).is_err());
+    }
+
+    #[test]
+    fn test_open_tampered_header() {
+        let password = SecretString::from("export");
+        let mut data = seal(&create_test_payload(), &password).unwrap();
+        data[HEADER_LEN - 1] ^= 1;
+
+        assert!(open(&data, &password).is_err());
+    }
+
+    #[test]
+    fn test_open_too_many_iterations() {
+        let password = SecretString::from("export");
+        let mut data = seal(&create_test_payload(), &password).unwrap();
+        data[6..10].copy_from_slice(&u32::MAX.to_be_bytes());
+
+        let error = open(&data, &password).unwrap_err();
+        assert!(error.to_string().contains("iterations"));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut payload = create_test_payload();
+        let entry = &mut payload.entries[0];
+        entry.folder = "Work/Mail".to_string();
+        entry.totp = "JBSWY3DPEHPK3PXP".into();
+        entry.source = "import:csv:2026-10-01".to_string();
+        entry.tags = vec!["mail".to_string()];
+        entry.custom_fields = vec![CustomField {
+            name: "PIN".to_string(),
+            value: "1234".into(),
+            hidden: true,
+        }];
+
+        let json = to_json(&payload).unwrap();
+        let restored = from_json(json.as_bytes()).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&restored.entries).unwrap(),
+            serde_json::to_value(&payload.entries).unwrap()
+        );
+        assert!(from_json(b"{\"version\": 99, \"exported_at\": \"\", \"entries\": []}").is_err());
+    }
+
+    #[test]
+    fn test_is_encrypted_export() {
+        assert!(!is_encrypted_export(b"Service,Username,Password"));
+    }
+}
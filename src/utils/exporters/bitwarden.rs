@@ -0,0 +1,205 @@
+use chrono::SecondsFormat;
+use serde_json::{json, Value};
+use zeroize::Zeroizing;
+
+use super::format_timestamp;
+use crate::prelude::PasswordEntry;
+use crate::utils::database::generate_uuid;
+
+const ITEM_LOGIN: u8 = 1;
+const FIELD_TEXT: u8 = 0;
+const FIELD_HIDDEN: u8 = 1;
+/// The item property with the data Bitwarden has no place for. Bitwarden ignores it.
+pub const EXTENSION_KEY: &str = "rustyToolkit";
+
+/// Write entries as an unencrypted Bitwarden JSON export.
+///
+/// Every entry becomes a login item identified by the UUID of the entry. Folders are kept,
+/// custom fields become text or hidden fields and the TOTP secret is stored in the login.
+/// Tags, the source and the field modification times are stored in an additional item
+/// property that only this tool reads.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to export.
+///
+/// # Returns
+///
+/// A `Result` containing the JSON document or an error.
+///
+/// # Errors
+///
+/// An error will be returned if no identifiers can be generated or the export cannot be
+/// serialized.
+pub fn write(entries: &[PasswordEntry]) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    let mut folders: Vec<(String, &str)> = Vec::new();
+    let mut items = Vec::with_capacity(entries.len());
+
+    for entry in entries {
+        let folder_id = if entry.folder.is_empty() {
+            Value::Null
+        } else {
+            let existing = folders.iter().find(|(_, name)| *name == entry.folder);
+            let id = match existing {
+                Some((id, _)) => id.clone(),
+                None => {
+                    let id = generate_uuid()?;
+                    folders.push((id.clone(), &entry.folder));
+                    id
+                }
+            };
+            Value::String(id)
+        };
+
+        let fields: Vec<Value> = entry
+            .custom_fields
+            .iter()
+            .map(|field| {
+                json!({
+                    "name": field.name,
+                    "value": field.value.expose_secret(),
+                    "type": if field.hidden { FIELD_HIDDEN } else { FIELD_TEXT },
+                    "linkedId": null,
+                })
+            })
+            .collect();
+        let uris: Vec<Value> = if entry.url.is_empty() {
+            Vec::new()
+        } else {
+            vec![json!({ "match": null, "uri": entry.url })]
+        };
+        let totp = if entry.totp.is_empty() {
+            Value::Null
+        } else {
+            Value::String(entry.totp.expose_secret().to_string())
+        };
+
+        let id = if entry.uuid.is_empty() {
+            generate_uuid()?
+        } else {
+            entry.uuid.clone()
+        };
+        items.push(json!({
+            "id": id,
+            "organizationId": null,
+            "folderId": folder_id,
+            "type": ITEM_LOGIN,
+            "reprompt": 0,
+            "name": entry.service,
+            "notes": if entry.notes.is_empty() { Value::Null } else { Value::String(entry.notes.clone()) },
+            "favorite": false,
+            "fields": fields,
+            "login": {
+                "uris": uris,
+                "username": entry.username,
+                "password": entry.password.expose_secret(),
+                "totp": totp,
+            },
+            "collectionIds": null,
+            "creationDate": format_timestamp(&entry.created_at, SecondsFormat::Millis),
+            "revisionDate": format_timestamp(&entry.updated_at, SecondsFormat::Millis),
+            EXTENSION_KEY: {
+                "tags": entry.tags,
+                "source": entry.source,
+                "fieldTimes": entry.field_times,
+            },
+        }));
+    }
+
+    let folders: Vec<Value> = folders
+        .into_iter()
+        .map(|(id, name)| json!({ "id": id, "name": name }))
+        .collect();
+    let export = json!({
+        "encrypted": false,
+        "folders": folders,
+        "items": items,
+    });
+
+    Ok(Zeroizing::new(serde_json::to_string_pretty(&export)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::CustomField;
+    use crate::utils::importers::bitwarden;
+
+    fn create_test_entries() -> Vec<PasswordEntry> {
+        let mut github = PasswordEntry::new(
+            "GitHub".to_string(),
+            "octocat".to_string(),
+            "correct-horse".into(),
+            "https://github.com/login".to_string(),
+            "Work account".to_string(),
+        )
+        .unwrap();
+        github.folder = "Development".to_string();
+        github.totp = "otpauth://totp/GitHub:octocat?secret=JBSWY3DPEHPK3PXP".into();
+        github.created_at = "2022-04-10T08:15:30+00:00".to_string();
+        github.updated_at = "2024-11-02T19:45:00.123+00:00".to_string();
+        github.tags = vec!["work".to_string()];
+        github.source = "import:keepass:2023-01-15".to_string();
+        github
+            .field_times
+            .insert("totp".to_string(), "2024-06-01T07:00:00+00:00".to_string());
+        github.custom_fields = vec![
+            CustomField {
+                name: "Recovery Code".to_string(),
+                value: "1234-5678".into(),
+                hidden: true,
+            },
+            CustomField {
+                name: "Team".to_string(),
+                value: "platform".into(),
+                hidden: false,
+            },
+        ];
+
+        let mut wifi = PasswordEntry::new(
+            "Wifi".to_string(),
+            "".to_string(),
+            "hunter2".into(),
+            "".to_string(),
+            "".to_string(),
+        )
+        .unwrap();
+        wifi.folder = "Development".to_string();
+        wifi.created_at = "2020-01-01T00:00:00+00:00".to_string();
+        wifi.updated_at = "2020-01-01T00:00:00+00:00".to_string();
+
+        vec![github, wifi]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let entries = create_test_entries();
+        let json = write(&entries).unwrap();
+
+        let result = bitwarden::parse(&json).unwrap();
+        assert!(result.unmapped.is_empty());
+        assert_eq!(result.entries.len(), entries.len());
+
+        for (actual, expected) in result.entries.iter().zip(&entries) {
+            assert_eq!(
+                serde_json::to_value(actual).unwrap(),
+                serde_json::to_value(expected).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_write_shares_folders() {
+        let entries = create_test_entries();
+        let json = write(&entries).unwrap();
+        let export: Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(export["folders"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            export["items"][0]["folderId"],
+            export["items"][1]["folderId"]
+        );
+        assert_eq!(export["items"][0]["fields"][0]["type"], FIELD_HIDDEN);
+        assert_eq!(export["items"][1]["id"], entries[1].uuid);
+    }
+}
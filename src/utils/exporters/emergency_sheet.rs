@@ -0,0 +1,307 @@
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use qrcode::render::svg;
+use qrcode::QrCode;
+use ring::digest;
+use zeroize::Zeroizing;
+
+use crate::prelude::{PasswordEntry, SecretString};
+
+/// The print styles of the sheet. Every entry stays on one page and the QR codes are large
+/// enough to be scanned from paper.
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #000; background: #fff; }
+header { border-bottom: 2px solid #000; margin-bottom: 1.5em; }
+.warning { border: 2px solid #000; padding: 0.5em; font-weight: bold; }
+.entry { border: 1px solid #000; padding: 1em; margin-bottom: 1em; page-break-inside: avoid; break-inside: avoid; }
+.entry h2 { margin-top: 0; }
+dl { display: grid; grid-template-columns: max-content 1fr; gap: 0.3em 1em; }
+dt { font-weight: bold; }
+dd { margin: 0; white-space: pre-wrap; word-break: break-all; }
+.secret { font-family: monospace; font-size: 1.2em; }
+.codes { display: flex; gap: 2em; }
+figure { margin: 0; text-align: center; }
+figure svg { width: 40mm; height: 40mm; }
+@page { margin: 15mm; }
+@media print { body { margin: 0; } }
+";
+
+/// Compute the fingerprint of a vault.
+///
+/// The fingerprint covers the vault ID and the master key file, so it changes when the
+/// master password changes. It tells which vault and master password a printed sheet
+/// belongs to.
+///
+/// # Arguments
+///
+/// * `vault_id` - The ID of the vault.
+/// * `master_key` - The content of the master key file.
+///
+/// # Returns
+///
+/// The first 128 bits of the SHA-256 digest as groups of four hex digits.
+pub fn fingerprint(vault_id: &str, master_key: &[u8]) -> String {
+    let mut context = digest::Context::new(&digest::SHA256);
+    context.update(vault_id.as_bytes());
+    context.update(master_key);
+
+    context.finish().as_ref()[..16]
+        .chunks(2)
+        .map(|pair| format!("{:02x}{:02x}", pair[0], pair[1]))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Write entries as a self-contained HTML page for printing.
+///
+/// Every entry lists its login in plain text, with QR codes for the password and the TOTP
+/// secret. The page states when it was generated and the fingerprint of the vault.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to print.
+/// * `fingerprint` - The fingerprint of the vault.
+/// * `generated_at` - When the sheet was generated.
+///
+/// # Returns
+///
+/// A `Result` containing the HTML document or an error.
+///
+/// # Errors
+///
+/// An error will be returned if a secret is too long for a QR code.
+pub fn write(
+    entries: &[PasswordEntry],
+    fingerprint: &str,
+    generated_at: DateTime<Local>,
+) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    let generated = format!(
+        "{} ({})",
+        generated_at.format("%Y-%m-%d %H:%M:%S %:z"),
+        generated_at
+            .with_timezone(&Utc)
+            .to_rfc3339_opts(SecondsFormat::Secs, true)
+    );
+
+    let mut html = Zeroizing::new(String::new());
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!(
+        "<title>Emergency sheet {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(&generated_at.format("%Y-%m-%d").to_string()),
+        STYLE
+    ));
+    html.push_str(&format!(
+        concat!(
+            "<header>\n<h1>Emergency sheet</h1>\n",
+            "<p>Generated on <strong>{}</strong>. Entries changed after this time are not ",
+            "on this sheet.</p>\n",
+            "<p>Vault fingerprint: <strong class=\"secret\">{}</strong></p>\n",
+            "<p>{} entries</p>\n",
+            "<p class=\"warning\">This sheet contains passwords in plain text. Keep it ",
+            "locked away and destroy older sheets.</p>\n</header>\n",
+        ),
+        escape(&generated),
+        escape(fingerprint),
+        entries.len()
+    ));
+
+    for entry in entries {
+        write_entry(&mut html, entry)?;
+    }
+
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+/// Write the section of one entry.
+fn write_entry(html: &mut String, entry: &PasswordEntry) -> Result<(), Box<dyn std::error::Error>> {
+    html.push_str(&format!(
+        "<section class=\"entry\">\n<h2>{}</h2>\n<dl>\n",
+        escape(&entry.service)
+    ));
+    let tags = entry.tags.join(", ");
+    for (name, value) in [
+        ("Folder", entry.folder.as_str()),
+        ("Tags", tags.as_str()),
+        ("Username", entry.username.as_str()),
+        ("URL", entry.url.as_str()),
+    ] {
+        if !value.is_empty() {
+            html.push_str(&format!("<dt>{}</dt><dd>{}</dd>\n", name, escape(value)));
+        }
+    }
+    html.push_str(&format!(
+        "<dt>Password</dt><dd class=\"secret\">{}</dd>\n",
+        escape(entry.password.expose_secret())
+    ));
+    if !entry.totp.is_empty() {
+        html.push_str(&format!(
+            "<dt>TOTP</dt><dd class=\"secret\">{}</dd>\n",
+            escape(entry.totp.expose_secret())
+        ));
+    }
+    for field in &entry.custom_fields {
+        html.push_str(&format!(
+            "<dt>{}</dt><dd>{}</dd>\n",
+            escape(&field.name),
+            escape(field.value.expose_secret())
+        ));
+    }
+    if !entry.notes.is_empty() {
+        html.push_str(&format!(
+            "<dt>Notes</dt><dd>{}</dd>\n",
+            escape(&entry.notes)
+        ));
+    }
+    html.push_str(&format!(
+        "<dt>Last changed</dt><dd>{}</dd>\n</dl>\n<div class=\"codes\">\n",
+        escape(&entry.updated_at)
+    ));
+
+    if !entry.password.is_empty() {
+        write_code(html, "Password", &entry.password)?;
+    }
+    if !entry.totp.is_empty() {
+        write_code(html, "TOTP secret", &totp_uri(entry))?;
+    }
+
+    html.push_str("</div>\n</section>\n");
+    Ok(())
+}
+
+/// Write a captioned QR code of a secret as inline SVG.
+fn write_code(
+    html: &mut String,
+    caption: &str,
+    value: &SecretString,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let code = QrCode::new(value.expose_secret().as_bytes())
+        .map_err(|e| format!("Failed to encode the {} as a QR code: {}", caption, e))?;
+    let image = Zeroizing::new(code.render::<svg::Color>().min_dimensions(160, 160).build());
+    // The XML declaration is not allowed inside an HTML document.
+    let image = image
+        .split_once("?>")
+        .map_or(image.as_str(), |(_, svg)| svg);
+
+    html.push_str(&format!(
+        "<figure>{}<figcaption>{}</figcaption></figure>\n",
+        image, caption
+    ));
+    Ok(())
+}
+
+/// The `otpauth://` URI of an entry's TOTP secret, which authenticator apps can scan.
+///
+/// URIs are kept as they are, bare secrets are wrapped with the service and username.
+fn totp_uri(entry: &PasswordEntry) -> SecretString {
+    let secret = entry.totp.expose_secret().trim();
+    if secret.starts_with("otpauth://") {
+        return SecretString::new(secret.to_string());
+    }
+
+    let issuer = percent_encode(&entry.service);
+    SecretString::new(format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}",
+        issuer,
+        percent_encode(&entry.username),
+        secret.replace(' ', ""),
+        issuer
+    ))
+}
+
+/// Percent-encode a label of an `otpauth://` URI.
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Escape text for use in HTML.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn create_test_entry() -> PasswordEntry {
+        let mut entry = PasswordEntry::new(
+            "Bank <Main>".to_string(),
+            "alice@example.com".to_string(),
+            "p&ss\"word".into(),
+            "https://bank.example.com".to_string(),
+            "PIN in the safe".to_string(),
+        )
+        .unwrap();
+        entry.folder = "Finance".to_string();
+        entry.tags = vec!["banking".to_string(), "family".to_string()];
+        entry.totp = "JBSW Y3DP EHPK 3PXP".into();
+        entry
+    }
+
+    #[test]
+    fn test_write_sheet() {
+        let generated_at = Local.with_ymd_and_hms(2026, 10, 18, 9, 30, 0).unwrap();
+        let html = write(&[create_test_entry()], "abcd ef01", generated_at).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("Generated on <strong>2026-10-18 09:30:00"));
+        assert!(html.contains("Vault fingerprint: <strong class=\"secret\">abcd ef01"));
+        assert!(html.contains("<h2>Bank &lt;Main&gt;</h2>"));
+        assert!(html.contains("p&amp;ss&quot;word"));
+        assert!(html.contains("<dt>Folder</dt><dd>Finance</dd>"));
+        assert!(html.contains("<dt>Tags</dt><dd>banking, family</dd>"));
+        assert_eq!(html.matches("<svg").count(), 2);
+        assert!(!html.contains("<?xml"));
+        // Nothing is loaded from elsewhere.
+        assert!(!html.contains("src="));
+        assert!(!html.contains("<link"));
+    }
+
+    #[test]
+    fn test_totp_uri() {
+        let mut entry = create_test_entry();
+
+        assert_eq!(
+            totp_uri(&entry).expose_secret(),
+            "otpauth://totp/Bank%20%3CMain%3E:alice%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Bank%20%3CMain%3E"
+        );
+
+        entry.totp = "otpauth://totp/Bank?secret=JBSWY3DPEHPK3PXP".into();
+        assert_eq!(
+            totp_uri(&entry).expose_secret(),
+            "otpauth://totp/Bank?secret=JBSWY3DPEHPK3PXP"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let fingerprint = fingerprint("vault", b"master key");
+
+        assert_eq!(fingerprint.len(), 39);
+        assert_eq!(fingerprint.split(' ').count(), 8);
+        assert_ne!(
+            fingerprint,
+            super::fingerprint("other vault", b"master key")
+        );
+        assert_ne!(fingerprint, super::fingerprint("vault", b"new master key"));
+    }
+}
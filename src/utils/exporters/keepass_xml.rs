@@ -0,0 +1,357 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::SecondsFormat;
+use zeroize::Zeroizing;
+
+use super::{format_timestamp, random_id};
+use crate::prelude::PasswordEntry;
+use crate::utils::database::parse_uuid;
+
+/// The custom data key holding where an entry came from.
+pub const SOURCE_KEY: &str = "rusty-toolkit:source";
+/// The custom data key holding the JSON encoded modification times of the fields.
+pub const FIELD_TIMES_KEY: &str = "rusty-toolkit:field-times";
+
+/// A group of the exported tree, built from the slash separated folder names.
+#[derive(Default)]
+struct Group<'a> {
+    name: &'a str,
+    entries: Vec<&'a PasswordEntry>,
+    groups: Vec<Group<'a>>,
+}
+
+impl<'a> Group<'a> {
+    /// Add an entry to the subgroup at the given path, creating missing groups.
+    fn insert(&mut self, path: &[&'a str], entry: &'a PasswordEntry) {
+        let Some((name, rest)) = path.split_first() else {
+            self.entries.push(entry);
+            return;
+        };
+
+        let index = match self.groups.iter().position(|group| group.name == *name) {
+            Some(index) => index,
+            None => {
+                self.groups.push(Group {
+                    name,
+                    ..Group::default()
+                });
+                self.groups.len() - 1
+            }
+        };
+        self.groups[index].insert(rest, entry);
+    }
+}
+
+/// Write entries as an unencrypted KeePass 2 XML file.
+///
+/// Folders become nested groups, the TOTP secret is stored in the `otp` field used by
+/// KeePassXC and custom fields become additional string fields. Passwords, TOTP secrets
+/// and hidden custom fields are marked as protected. Entries keep their UUID and tags, the
+/// source and field modification times are stored as custom data of the entry.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to export.
+///
+/// # Returns
+///
+/// A `Result` containing the XML document or an error.
+///
+/// # Errors
+///
+/// An error will be returned if a field contains a control character XML cannot
+/// represent, no identifiers can be generated or the field times cannot be serialized.
+pub fn write(entries: &[PasswordEntry]) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    let mut root = Group {
+        name: "Root",
+        ..Group::default()
+    };
+    for entry in entries {
+        check_entry(entry)?;
+        let path: Vec<&str> = entry
+            .folder
+            .split('/')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .collect();
+        root.insert(&path, entry);
+    }
+
+    let mut xml = Zeroizing::new(String::new());
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\n");
+    xml.push_str("<KeePassFile>\n");
+    xml.push_str("\t<Meta>\n\t\t<Generator>rusty-toolkit</Generator>\n\t</Meta>\n");
+    xml.push_str("\t<Root>\n");
+    write_group(&mut xml, &root, 2)?;
+    xml.push_str("\t</Root>\n</KeePassFile>\n");
+
+    Ok(xml)
+}
+
+/// Write a group with its entries and subgroups.
+fn write_group(
+    xml: &mut String,
+    group: &Group,
+    depth: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let indent = "\t".repeat(depth);
+
+    xml.push_str(&format!("{}<Group>\n", indent));
+    xml.push_str(&format!(
+        "{}\t<UUID>{}</UUID>\n",
+        indent,
+        STANDARD.encode(random_id()?)
+    ));
+    xml.push_str(&format!(
+        "{}\t<Name>{}</Name>\n",
+        indent,
+        escape(group.name)
+    ));
+    for entry in &group.entries {
+        write_entry(xml, entry, depth + 1)?;
+    }
+    for subgroup in &group.groups {
+        write_group(xml, subgroup, depth + 1)?;
+    }
+    xml.push_str(&format!("{}</Group>\n", indent));
+
+    Ok(())
+}
+
+/// Write a single entry.
+fn write_entry(
+    xml: &mut String,
+    entry: &PasswordEntry,
+    depth: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let indent = "\t".repeat(depth);
+
+    let uuid = match parse_uuid(&entry.uuid) {
+        Some(uuid) => uuid,
+        None => random_id()?,
+    };
+    xml.push_str(&format!("{}<Entry>\n", indent));
+    xml.push_str(&format!(
+        "{}\t<UUID>{}</UUID>\n",
+        indent,
+        STANDARD.encode(uuid)
+    ));
+    if !entry.tags.is_empty() {
+        xml.push_str(&format!(
+            "{}\t<Tags>{}</Tags>\n",
+            indent,
+            escape(&entry.tags.join(";"))
+        ));
+    }
+    xml.push_str(&format!(
+        "{0}\t<Times>\n{0}\t\t<CreationTime>{1}</CreationTime>\n{0}\t\t<LastModificationTime>{2}</LastModificationTime>\n{0}\t</Times>\n",
+        indent,
+        format_timestamp(&entry.created_at, SecondsFormat::Secs),
+        format_timestamp(&entry.updated_at, SecondsFormat::Secs),
+    ));
+
+    let mut strings = vec![
+        ("Title", entry.service.as_str(), false),
+        ("UserName", entry.username.as_str(), false),
+        ("Password", entry.password.expose_secret(), true),
+        ("URL", entry.url.as_str(), false),
+        ("Notes", entry.notes.as_str(), false),
+    ];
+    if !entry.totp.is_empty() {
+        strings.push(("otp", entry.totp.expose_secret(), true));
+    }
+    for field in &entry.custom_fields {
+        strings.push((&field.name, field.value.expose_secret(), field.hidden));
+    }
+
+    for (key, value, protected) in strings {
+        let attribute = if protected {
+            " ProtectInMemory=\"True\""
+        } else {
+            ""
+        };
+        xml.push_str(&format!(
+            "{}\t<String>\n{0}\t\t<Key>{}</Key>\n{0}\t\t<Value{}>{}</Value>\n{0}\t</String>\n",
+            indent,
+            escape(key),
+            attribute,
+            escape(value)
+        ));
+    }
+
+    let mut custom_data = Vec::new();
+    if !entry.source.is_empty() {
+        custom_data.push((SOURCE_KEY, entry.source.clone()));
+    }
+    if !entry.field_times.is_empty() {
+        custom_data.push((FIELD_TIMES_KEY, serde_json::to_string(&entry.field_times)?));
+    }
+    if !custom_data.is_empty() {
+        xml.push_str(&format!("{}\t<CustomData>\n", indent));
+        for (key, value) in custom_data {
+            xml.push_str(&format!(
+                "{}\t\t<Item>\n{0}\t\t\t<Key>{}</Key>\n{0}\t\t\t<Value>{}</Value>\n{0}\t\t</Item>\n",
+                indent,
+                escape(key),
+                escape(&value)
+            ));
+        }
+        xml.push_str(&format!("{}\t</CustomData>\n", indent));
+    }
+    xml.push_str(&format!("{}</Entry>\n", indent));
+
+    Ok(())
+}
+
+/// Check that every text of an entry can be written to XML 1.0, which has no way to
+/// represent control characters other than tab, line feed and carriage return.
+fn check_entry(entry: &PasswordEntry) -> Result<(), String> {
+    let mut texts = vec![
+        ("service", entry.service.as_str()),
+        ("username", entry.username.as_str()),
+        ("password", entry.password.expose_secret()),
+        ("URL", entry.url.as_str()),
+        ("notes", entry.notes.as_str()),
+        ("folder", entry.folder.as_str()),
+        ("TOTP", entry.totp.expose_secret()),
+        ("source", entry.source.as_str()),
+    ];
+    texts.extend(entry.tags.iter().map(|tag| ("tag", tag.as_str())));
+    for field in &entry.custom_fields {
+        texts.push(("custom field name", field.name.as_str()));
+        texts.push(("custom field", field.value.expose_secret()));
+    }
+
+    match texts
+        .into_iter()
+        .find(|(_, text)| text.chars().any(is_invalid_char))
+    {
+        Some((field, _)) => Err(format!(
+            "The {} field of \"{}\" has a control character that KeePass XML cannot store",
+            field, entry.service
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Check if a character is not allowed in an XML 1.0 document.
+fn is_invalid_char(c: char) -> bool {
+    matches!(c, '\0'..='\x08' | '\x0b' | '\x0c' | '\x0e'..='\x1f' | '\u{fffe}' | '\u{ffff}')
+}
+
+/// Escape text for use in XML content and attribute values.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\r' => escaped.push_str("&#13;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::CustomField;
+    use crate::utils::importers::kdbx;
+
+    fn create_test_entries() -> Vec<PasswordEntry> {
+        let mut router = PasswordEntry::new(
+            "Router <office>".to_string(),
+            "admin".to_string(),
+            "p&ss\"word'".into(),
+            "http://192.168.1.1/?a=1&b=2".to_string(),
+            "Rack 2\nShelf 3".to_string(),
+        )
+        .unwrap();
+        router.folder = "Infrastructure/Network".to_string();
+        router.totp = "otpauth://totp/Router?secret=JBSWY3DPEHPK3PXP".into();
+        router.created_at = "2019-07-01T09:30:00+00:00".to_string();
+        router.updated_at = "2024-02-29T18:00:05+00:00".to_string();
+        router.tags = vec!["hardware".to_string(), "office".to_string()];
+        router.source = "import:csv:2023-05-04".to_string();
+        router.field_times.insert(
+            "password".to_string(),
+            "2023-05-04T12:00:00+00:00".to_string(),
+        );
+        router.custom_fields = vec![
+            CustomField {
+                name: "PIN".to_string(),
+                value: "4711".into(),
+                hidden: true,
+            },
+            CustomField {
+                name: "Serial".to_string(),
+                value: "RT-1234".into(),
+                hidden: false,
+            },
+        ];
+
+        let mut mail = PasswordEntry::new(
+            "Mail".to_string(),
+            "me@example.com".to_string(),
+            "secret".into(),
+            "".to_string(),
+            "".to_string(),
+        )
+        .unwrap();
+        mail.created_at = "2020-01-01T00:00:00+00:00".to_string();
+        mail.updated_at = "2021-01-01T00:00:00+00:00".to_string();
+
+        vec![router, mail]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let entries = create_test_entries();
+        let xml = write(&entries).unwrap();
+
+        let result = kdbx::parse_xml(&xml).unwrap();
+        assert!(result.unmapped.is_empty());
+        assert_eq!(result.entries.len(), entries.len());
+
+        for expected in &entries {
+            let actual = result
+                .entries
+                .iter()
+                .find(|entry| entry.service == expected.service)
+                .unwrap();
+            assert_eq!(
+                serde_json::to_value(actual).unwrap(),
+                serde_json::to_value(expected).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_write_groups() {
+        let xml = write(&create_test_entries()).unwrap();
+
+        assert!(xml.contains("<Name>Infrastructure</Name>"));
+        assert!(xml.contains("<Name>Network</Name>"));
+        assert!(xml.contains("<Value ProtectInMemory=\"True\">4711</Value>"));
+        assert!(xml.contains("<CreationTime>2019-07-01T09:30:00Z</CreationTime>"));
+        assert!(xml.contains("<Tags>hardware;office</Tags>"));
+    }
+
+    #[test]
+    fn test_write_rejects_control_characters() {
+        let mut entries = create_test_entries();
+        entries[1].password = "sec\u{1b}ret".into();
+
+        let error = write(&entries).unwrap_err().to_string();
+        assert!(error.contains("password field of \"Mail\""), "{}", error);
+        assert!(!error.contains("sec"));
+
+        // Tabs, line feeds and carriage returns are allowed.
+        entries[1].password = "a\tb\r\nc".into();
+        let result = kdbx::parse_xml(&write(&entries).unwrap()).unwrap();
+        assert_eq!(result.entries[0].password.expose_secret(), "a\tb\r\nc");
+    }
+}
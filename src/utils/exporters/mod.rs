@@ -0,0 +1,28 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+
+pub mod bitwarden;
+pub mod emergency_sheet;
+pub mod keepass_xml;
+
+/// Format a stored RFC 3339 timestamp in UTC with a `Z` suffix, as foreign formats expect.
+///
+/// Values that are not valid RFC 3339 are passed through unchanged.
+fn format_timestamp(value: &str, precision: SecondsFormat) -> String {
+    DateTime::parse_from_rfc3339(value)
+        .map(|timestamp| {
+            timestamp
+                .with_timezone(&Utc)
+                .to_rfc3339_opts(precision, true)
+        })
+        .unwrap_or_else(|_| value.to_string())
+}
+
+/// Generate random bytes for the identifiers of exported items.
+fn random_id() -> Result<[u8; 16], Box<dyn std::error::Error>> {
+    let mut id = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut id)
+        .map_err(|_| "Failed to generate an identifier")?;
+    Ok(id)
+}
@@ -0,0 +1,315 @@
+use std::io::{BufRead, Write};
+
+use crate::prelude::{CliError, PasswordEntry, SecretString};
+
+/// A credential as git exchanges it with helpers, see `git help credential`.
+#[derive(Debug, Default)]
+pub struct GitCredential {
+    pub protocol: String,
+    /// The host name, including the port if git was given one.
+    pub host: String,
+    /// The repository path, only sent if `credential.useHttpPath` is set.
+    pub path: String,
+    pub username: String,
+    pub password: SecretString,
+}
+
+impl GitCredential {
+    /// Read a credential as `key=value` lines up to an empty line or the end of input.
+    ///
+    /// Unknown keys are ignored, so newer versions of git can send more attributes.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The input from git.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the credential or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the input cannot be read or a line is not `key=value`.
+    pub fn read(reader: impl BufRead) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut credential = GitCredential::default();
+        for line in reader.lines() {
+            let line = line?;
+            if line.is_empty() {
+                break;
+            }
+
+            let (key, value) = line.split_once('=').ok_or_else(|| {
+                CliError::InvalidInput(format!("Expected key=value from git, got {}", line))
+            })?;
+            match key {
+                "protocol" => credential.protocol = value.to_string(),
+                "host" => credential.host = value.to_string(),
+                "path" => credential.path = value.to_string(),
+                "username" => credential.username = value.to_string(),
+                "password" => credential.password = SecretString::new(value.to_string()),
+                "url" => {
+                    let (protocol, host, path) = split_url(value);
+                    credential.protocol = protocol;
+                    credential.host = host;
+                    credential.path = path;
+                }
+                _ => {}
+            }
+        }
+        Ok(credential)
+    }
+
+    /// Write the login of an entry as the answer to `get`.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The entry with the login.
+    /// * `writer` - The output to git.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the output cannot be written or a value contains a line
+    /// break, which the protocol cannot carry.
+    pub fn write_login(
+        entry: &PasswordEntry,
+        mut writer: impl Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let password = entry.password.expose_secret();
+        if [entry.username.as_str(), password]
+            .iter()
+            .any(|value| value.contains(['\n', '\0']))
+        {
+            return Err(format!(
+                "The login of {} cannot be sent to git, it contains a line break",
+                entry.service
+            )
+            .into());
+        }
+
+        write!(
+            writer,
+            "username={}\npassword={}\n",
+            entry.username, password
+        )?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// The URL of the credential, used as the URL of stored entries.
+    ///
+    /// # Returns
+    ///
+    /// The URL, e.g. `https://github.com/org/repo.git`.
+    pub fn url(&self) -> String {
+        if self.path.is_empty() {
+            format!("{}://{}", self.protocol, self.host)
+        } else {
+            format!(
+                "{}://{}/{}",
+                self.protocol,
+                self.host,
+                self.path.trim_start_matches('/')
+            )
+        }
+    }
+
+    /// Check if an entry is a login for this credential.
+    ///
+    /// The protocol and host of the entry URL must match; a URL without a protocol matches
+    /// any. A path in the entry URL must be a prefix of the requested path, but matches if
+    /// git sent no path. If git sent a username, it must match too.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The entry to check.
+    ///
+    /// # Returns
+    ///
+    /// How specific the match is, higher is better, or `None` if the entry does not match.
+    pub fn matches(&self, entry: &PasswordEntry) -> Option<usize> {
+        let (protocol, host, path) = split_url(&entry.url);
+        if host.is_empty()
+            || (!protocol.is_empty() && !protocol.eq_ignore_ascii_case(&self.protocol))
+            || !same_host(&host, &self.host, &self.protocol)
+            || (!self.username.is_empty() && entry.username != self.username)
+        {
+            return None;
+        }
+
+        let path = trim_path(&path);
+        let requested = trim_path(&self.path);
+        if path.is_empty() {
+            Some(1)
+        } else if requested.is_empty() {
+            Some(0)
+        } else if requested == path || requested.starts_with(&format!("{}/", path)) {
+            Some(1 + path.len())
+        } else {
+            None
+        }
+    }
+
+    /// Find the entry that is the best login for this credential.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The entries to search.
+    ///
+    /// # Returns
+    ///
+    /// The most specific match, the most recently updated one of equally specific ones.
+    pub fn find<'a>(&self, entries: &'a [PasswordEntry]) -> Option<&'a PasswordEntry> {
+        entries
+            .iter()
+            .filter(|entry| !entry.password.is_empty())
+            .filter_map(|entry| self.matches(entry).map(|score| (score, entry)))
+            .max_by(|(a, a_entry), (b, b_entry)| {
+                a.cmp(b).then(a_entry.updated_at.cmp(&b_entry.updated_at))
+            })
+            .map(|(_, entry)| entry)
+    }
+}
+
+/// Split a URL into its protocol, host and path. User info is dropped, and the protocol is
+/// empty for URLs without one, e.g. `github.com/org`.
+fn split_url(url: &str) -> (String, String, String) {
+    let url = url.trim();
+    let (protocol, rest) = match url.split_once("://") {
+        Some((protocol, rest)) => (protocol.to_lowercase(), rest),
+        None => (String::new(), url),
+    };
+    let (authority, path) = match rest.find(['/', '?', '#']) {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, ""),
+    };
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    let path = path.split(['?', '#']).next().unwrap_or("");
+
+    (protocol, host.to_lowercase(), path.to_string())
+}
+
+/// Compare hosts ignoring case and the default port of the protocol.
+fn same_host(a: &str, b: &str, protocol: &str) -> bool {
+    let default_port = match protocol {
+        "https" => ":443",
+        "http" => ":80",
+        _ => "",
+    };
+    let strip = |host: &str| {
+        let host = host.to_lowercase();
+        match host.strip_suffix(default_port) {
+            Some(stripped) if !default_port.is_empty() => stripped.to_string(),
+            _ => host,
+        }
+    };
+    strip(a) == strip(b)
+}
+
+/// Trim slashes and a `.git` suffix, so `/org/repo.git` and `org/repo` compare equal.
+fn trim_path(path: &str) -> &str {
+    let path = path.trim_matches('/');
+    path.strip_suffix(".git").unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_entry(username: &str, url: &str, updated_at: &str) -> PasswordEntry {
+        let mut entry = PasswordEntry::new(
+            url.to_string(),
+            username.to_string(),
+            format!("token-{}", username).into(),
+            url.to_string(),
+            String::new(),
+        )
+        .unwrap();
+        entry.updated_at = updated_at.to_string();
+        entry
+    }
+
+    fn request(input: &str) -> GitCredential {
+        GitCredential::read(input.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_read() {
+        let credential = request(
+            "protocol=https\nhost=git.example.com:8443\nwwwauth[]=Basic\nusername=bob\npassword=a=b\n\nignored=1\n",
+        );
+        assert_eq!(credential.protocol, "https");
+        assert_eq!(credential.host, "git.example.com:8443");
+        assert_eq!(credential.username, "bob");
+        assert_eq!(credential.password.expose_secret(), "a=b");
+        assert_eq!(credential.url(), "https://git.example.com:8443");
+
+        let credential = request("url=https://alice@github.com/org/repo.git\n");
+        assert_eq!(credential.host, "github.com");
+        assert_eq!(credential.path, "/org/repo.git");
+
+        assert!(GitCredential::read("protocol\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let credential = request("protocol=https\nhost=github.com\npath=org/repo.git\n");
+
+        let cases = [
+            ("https://github.com", Some(1)),
+            ("github.com", Some(1)),
+            ("https://GitHub.com:443/", Some(1)),
+            ("https://github.com/org", Some(4)),
+            ("https://github.com/org/repo", Some(9)),
+            ("https://github.com/other", None),
+            ("https://github.com/organisation", None),
+            ("http://github.com", None),
+            ("https://gitlab.com", None),
+            ("", None),
+        ];
+        for (url, score) in cases {
+            assert_eq!(
+                credential.matches(&create_entry("alice", url, "")),
+                score,
+                "{}",
+                url
+            );
+        }
+
+        let without_path = request("protocol=https\nhost=github.com\nusername=bob\n");
+        assert_eq!(
+            without_path.matches(&create_entry("bob", "https://github.com/org", "")),
+            Some(0)
+        );
+        assert_eq!(
+            without_path.matches(&create_entry("alice", "https://github.com", "")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_find() {
+        let entries = vec![
+            create_entry("old", "https://github.com", "2026-01-01T00:00:00+00:00"),
+            create_entry("new", "https://github.com", "2026-02-01T00:00:00+00:00"),
+            create_entry(
+                "repo",
+                "https://github.com/org/repo",
+                "2025-01-01T00:00:00+00:00",
+            ),
+        ];
+
+        let credential = request("protocol=https\nhost=github.com\n");
+        assert_eq!(credential.find(&entries).unwrap().username, "new");
+
+        let credential = request("protocol=https\nhost=github.com\npath=org/repo.git\n");
+        assert_eq!(credential.find(&entries).unwrap().username, "repo");
+
+        let mut output = Vec::new();
+        GitCredential::write_login(&entries[2], &mut output).unwrap();
+        assert_eq!(output, b"username=repo\npassword=token-repo\n");
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Deserialize;
+
+use super::{set_timestamps, ImportResult};
+use crate::prelude::{CustomField, PasswordEntry, SecretString};
+use crate::utils::database::{format_uuid, parse_uuid};
+
+const ITEM_LOGIN: u8 = 1;
+const ITEM_SECURE_NOTE: u8 = 2;
+const ITEM_CARD: u8 = 3;
+const ITEM_IDENTITY: u8 = 4;
+
+const FIELD_TEXT: u8 = 0;
+const FIELD_HIDDEN: u8 = 1;
+const FIELD_BOOLEAN: u8 = 2;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitwardenExport {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    folders: Vec<BitwardenFolder>,
+    #[serde(default)]
+    collections: Vec<BitwardenFolder>,
+    #[serde(default)]
+    items: Vec<BitwardenItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitwardenFolder {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitwardenItem {
+    id: Option<String>,
+    #[serde(rename = "type")]
+    item_type: u8,
+    name: Option<String>,
+    notes: Option<String>,
+    folder_id: Option<String>,
+    collection_ids: Option<Vec<String>>,
+    #[serde(default)]
+    fields: Vec<BitwardenField>,
+    login: Option<BitwardenLogin>,
+    card: Option<BitwardenCard>,
+    creation_date: Option<String>,
+    revision_date: Option<String>,
+    /// Written by our own exporter, see `exporters::bitwarden::EXTENSION_KEY`.
+    rusty_toolkit: Option<ToolkitData>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolkitData {
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    source: String,
+    #[serde(default)]
+    field_times: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitwardenField {
+    name: Option<String>,
+    value: Option<String>,
+    #[serde(rename = "type")]
+    field_type: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitwardenLogin {
+    username: Option<String>,
+    password: Option<String>,
+    totp: Option<String>,
+    #[serde(default)]
+    uris: Vec<BitwardenUri>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitwardenUri {
+    uri: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitwardenCard {
+    cardholder_name: Option<String>,
+    brand: Option<String>,
+    number: Option<String>,
+    exp_month: Option<String>,
+    exp_year: Option<String>,
+    code: Option<String>,
+}
+
+/// Parse an unencrypted Bitwarden JSON export.
+///
+/// Logins, secure notes and cards are mapped onto entries. Folder names are kept,
+/// additional URIs and card details become custom fields.
+///
+/// # Arguments
+///
+/// * `json` - The content of the Bitwarden export.
+///
+/// # Returns
+///
+/// A `Result` containing the mapped entries and the items that could not be mapped.
+///
+/// # Errors
+///
+/// An error will be returned if the export is encrypted or not valid Bitwarden JSON.
+pub fn parse(json: &str) -> Result<ImportResult, Box<dyn std::error::Error>> {
+    let export: BitwardenExport = serde_json::from_str(json)?;
+    if export.encrypted {
+        return Err(
+            "Encrypted Bitwarden exports are not supported, please export as unencrypted JSON"
+                .into(),
+        );
+    }
+
+    let folders: HashMap<String, String> = export
+        .folders
+        .into_iter()
+        .chain(export.collections)
+        .map(|folder| (folder.id, folder.name))
+        .collect();
+
+    let mut result = ImportResult::default();
+    for item in export.items {
+        if let Some(entry) = map_item(item, &folders, &mut result.unmapped) {
+            result.entries.push(entry);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Map a single Bitwarden item, recording why it was skipped if it cannot be mapped.
+fn map_item(
+    item: BitwardenItem,
+    folders: &HashMap<String, String>,
+    unmapped: &mut Vec<String>,
+) -> Option<PasswordEntry> {
+    let name = item
+        .name
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "Unnamed".to_string());
+
+    let mut entry = PasswordEntry::new(
+        name.clone(),
+        String::new(),
+        SecretString::default(),
+        String::new(),
+        item.notes.unwrap_or_default(),
+    )
+    .ok()?;
+
+    let created_at = item.creation_date.as_deref().unwrap_or_default();
+    let updated_at = item.revision_date.as_deref().unwrap_or_default();
+    if let Err(e) = set_timestamps(&mut entry, created_at, updated_at) {
+        unmapped.push(format!("{}: {}", name, e));
+        return None;
+    }
+
+    if let Some(uuid) = item.id.as_deref().and_then(parse_uuid) {
+        entry.uuid = format_uuid(&uuid);
+    }
+    let data = item.rusty_toolkit.unwrap_or_default();
+    entry.tags = data.tags;
+    entry.source = data.source;
+    entry.field_times = data.field_times;
+
+    entry.folder = item
+        .folder_id
+        .as_ref()
+        .or_else(|| item.collection_ids.as_ref().and_then(|ids| ids.first()))
+        .and_then(|id| folders.get(id))
+        .cloned()
+        .unwrap_or_default();
+
+    match item.item_type {
+        ITEM_LOGIN => {
+            let Some(login) = item.login else {
+                unmapped.push(format!("{}: login item without login data", name));
+                return None;
+            };
+            entry.username = login.username.unwrap_or_default();
+            entry.password = login.password.unwrap_or_default().into();
+            entry.totp = login.totp.unwrap_or_default().into();
+
+            let mut uris = login.uris.into_iter().filter_map(|uri| uri.uri);
+            entry.url = uris.next().unwrap_or_default();
+            for (index, uri) in uris.enumerate() {
+                entry.custom_fields.push(CustomField {
+                    name: format!("URL {}", index + 2),
+                    value: uri.into(),
+                    hidden: false,
+                });
+            }
+        }
+        ITEM_SECURE_NOTE => {}
+        ITEM_CARD => {
+            let Some(card) = item.card else {
+                unmapped.push(format!("{}: card item without card data", name));
+                return None;
+            };
+            entry.username = card.cardholder_name.clone().unwrap_or_default();
+
+            let expiry = match (card.exp_month, card.exp_year) {
+                (Some(month), Some(year)) => Some(format!("{}/{}", month, year)),
+                (month, year) => month.or(year),
+            };
+            for (field_name, value, hidden) in [
+                ("Cardholder", card.cardholder_name, false),
+                ("Brand", card.brand, false),
+                ("Number", card.number, true),
+                ("Expiry", expiry, false),
+                ("Security Code", card.code, true),
+            ] {
+                if let Some(value) = value.filter(|value| !value.is_empty()) {
+                    entry.custom_fields.push(CustomField {
+                        name: field_name.to_string(),
+                        value: value.into(),
+                        hidden,
+                    });
+                }
+            }
+        }
+        ITEM_IDENTITY => {
+            unmapped.push(format!("{}: identities are not supported", name));
+            return None;
+        }
+        other => {
+            unmapped.push(format!("{}: unknown item type {}", name, other));
+            return None;
+        }
+    }
+
+    for field in item.fields {
+        let field_name = field.name.unwrap_or_default();
+        match field.field_type {
+            FIELD_TEXT | FIELD_HIDDEN | FIELD_BOOLEAN => entry.custom_fields.push(CustomField {
+                name: field_name,
+                value: field.value.unwrap_or_default().into(),
+                hidden: field.field_type == FIELD_HIDDEN,
+            }),
+            _ => unmapped.push(format!(
+                "{}: linked field \"{}\" was not imported",
+                name, field_name
+            )),
+        }
+    }
+
+    Some(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/bitwarden.json"
+    ));
+    const ORGANIZATION_FIXTURE: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/bitwarden-organization.json"
+    ));
+
+    fn find<'a>(result: &'a ImportResult, service: &str) -> &'a PasswordEntry {
+        result
+            .entries
+            .iter()
+            .find(|entry| entry.service == service)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_login() {
+        let result = parse(FIXTURE).unwrap();
+        let entry = find(&result, "GitHub");
+
+        assert_eq!(entry.username, "octocat");
+        assert_eq!(entry.password.expose_secret(), "correct-horse");
+        assert_eq!(entry.url, "https://github.com/login");
+        assert_eq!(entry.notes, "Work account");
+        assert_eq!(entry.folder, "Development");
+        assert_eq!(
+            entry.totp.expose_secret(),
+            "otpauth://totp/GitHub:octocat?secret=JBSWY3DPEHPK3PXP"
+        );
+
+        let names: Vec<_> = entry
+            .custom_fields
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(names, ["URL 2", "Recovery Code", "Team"]);
+        assert!(entry.custom_fields[1].hidden);
+        assert_eq!(entry.custom_fields[1].value.expose_secret(), "1234-5678");
+
+        assert_eq!(entry.created_at, "2022-04-10T08:15:30+00:00");
+        assert_eq!(entry.updated_at, "2024-11-02T19:45:00.123+00:00");
+    }
+
+    #[test]
+    fn test_parse_secure_note_and_card() {
+        let result = parse(FIXTURE).unwrap();
+
+        let note = find(&result, "Wifi");
+        assert_eq!(note.notes, "SSID: office\nKey: hunter2");
+        assert_eq!(note.folder, "");
+
+        let card = find(&result, "Company Visa");
+        assert_eq!(card.username, "Jane Doe");
+        assert_eq!(card.folder, "Finance");
+        let number = card
+            .custom_fields
+            .iter()
+            .find(|f| f.name == "Number")
+            .unwrap();
+        assert_eq!(number.value.expose_secret(), "4111111111111111");
+        assert!(number.hidden);
+        assert!(card
+            .custom_fields
+            .iter()
+            .any(|f| f.name == "Expiry" && f.value.expose_secret() == "12/2030"));
+    }
+
+    #[test]
+    fn test_report_unmapped_items() {
+        let result = parse(FIXTURE).unwrap();
+
+        assert_eq!(result.entries.len(), 3);
+        assert_eq!(result.unmapped.len(), 2);
+        assert!(result.unmapped[0].contains("linked field \"Username\""));
+        assert!(result.unmapped[1].starts_with("Passport: identities"));
+    }
+
+    #[test]
+    fn test_parse_organization_export() {
+        let result = parse(ORGANIZATION_FIXTURE).unwrap();
+
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.entries[0].folder, "Infrastructure");
+    }
+
+    #[test]
+    fn test_reject_encrypted_export() {
+        assert!(parse(r#"{"encrypted": true, "items": []}"#).is_err());
+    }
+}
@@ -0,0 +1,468 @@
+use std::str::FromStr;
+
+use csv::{ReaderBuilder, StringRecord};
+use log::info;
+
+use super::{set_timestamps, ImportResult};
+use crate::prelude::PasswordEntry;
+
+/// A field of a password entry that a CSV column can be mapped to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Service,
+    Username,
+    Password,
+    Url,
+    Notes,
+    Totp,
+    Folder,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "service" => Ok(Field::Service),
+            "username" => Ok(Field::Username),
+            "password" => Ok(Field::Password),
+            "url" => Ok(Field::Url),
+            "notes" => Ok(Field::Notes),
+            "totp" => Ok(Field::Totp),
+            "folder" => Ok(Field::Folder),
+            "created_at" => Ok(Field::CreatedAt),
+            "updated_at" => Ok(Field::UpdatedAt),
+            other => Err(format!(
+                "Unknown field \"{}\", expected one of: service, username, password, url, notes, totp, folder, created_at, updated_at",
+                other
+            )),
+        }
+    }
+}
+
+/// A CSV layout of a known browser or password manager, recognized by its headers.
+struct Layout {
+    name: &'static str,
+    /// The headers that must all be present for the layout to match.
+    required: &'static [&'static str],
+    /// The headers mapped onto fields, where present.
+    columns: &'static [(Field, &'static str)],
+}
+
+/// The known layouts, most specific first.
+const LAYOUTS: &[Layout] = &[
+    Layout {
+        name: "rusty-toolkit",
+        required: &["service", "username", "password", "url"],
+        columns: &[
+            (Field::Service, "service"),
+            (Field::Username, "username"),
+            (Field::Password, "password"),
+            (Field::Url, "url"),
+            (Field::Notes, "notes"),
+            (Field::CreatedAt, "created_at"),
+            (Field::UpdatedAt, "updated_at"),
+        ],
+    },
+    Layout {
+        name: "Bitwarden",
+        required: &["name", "login_username", "login_password", "login_uri"],
+        columns: &[
+            (Field::Service, "name"),
+            (Field::Username, "login_username"),
+            (Field::Password, "login_password"),
+            (Field::Url, "login_uri"),
+            (Field::Notes, "notes"),
+            (Field::Totp, "login_totp"),
+            (Field::Folder, "folder"),
+        ],
+    },
+    Layout {
+        name: "LastPass",
+        required: &["name", "username", "password", "url", "extra", "grouping"],
+        columns: &[
+            (Field::Service, "name"),
+            (Field::Username, "username"),
+            (Field::Password, "password"),
+            (Field::Url, "url"),
+            (Field::Notes, "extra"),
+            (Field::Totp, "totp"),
+            (Field::Folder, "grouping"),
+        ],
+    },
+    Layout {
+        name: "Safari or 1Password",
+        required: &["title", "username", "password", "url"],
+        columns: &[
+            (Field::Service, "title"),
+            (Field::Username, "username"),
+            (Field::Password, "password"),
+            (Field::Url, "url"),
+            (Field::Notes, "notes"),
+            (Field::Totp, "otpauth"),
+        ],
+    },
+    Layout {
+        name: "Chrome or Edge",
+        required: &["name", "username", "password", "url"],
+        columns: &[
+            (Field::Service, "name"),
+            (Field::Username, "username"),
+            (Field::Password, "password"),
+            (Field::Url, "url"),
+            (Field::Notes, "note"),
+        ],
+    },
+    Layout {
+        name: "Firefox",
+        required: &["username", "password", "url", "formactionorigin"],
+        columns: &[
+            (Field::Username, "username"),
+            (Field::Password, "password"),
+            (Field::Url, "url"),
+            (Field::CreatedAt, "timecreated"),
+            (Field::UpdatedAt, "timepasswordchanged"),
+        ],
+    },
+];
+
+/// The column index of every mapped field.
+#[derive(Debug, Default)]
+struct ColumnMap {
+    columns: Vec<(Field, usize)>,
+}
+
+impl ColumnMap {
+    /// Map a field to a column, replacing any previous mapping of the field.
+    fn set(&mut self, field: Field, column: usize) {
+        self.columns.retain(|(mapped, _)| *mapped != field);
+        self.columns.push((field, column));
+    }
+
+    /// Read the value of a field from a record.
+    ///
+    /// Surrounding whitespace is trimmed, except from the password, the TOTP secret and
+    /// the notes, which are kept byte for byte so a secret is never altered.
+    fn get<'a>(&self, record: &'a StringRecord, field: Field) -> &'a str {
+        let value = self
+            .columns
+            .iter()
+            .find(|(mapped, _)| *mapped == field)
+            .and_then(|(_, column)| record.get(*column))
+            .unwrap_or_default();
+        match field {
+            Field::Password | Field::Totp | Field::Notes => value,
+            _ => value.trim(),
+        }
+    }
+}
+
+/// Parse a column mapping override like `service=name,url=origin`.
+///
+/// # Arguments
+///
+/// * `value` - The comma separated `field=column` pairs.
+///
+/// # Returns
+///
+/// A `Result` containing the fields and the column names they are mapped to.
+///
+/// # Errors
+///
+/// An error will be returned if a pair is malformed or names an unknown field.
+pub fn parse_overrides(value: &str) -> Result<Vec<(Field, String)>, Box<dyn std::error::Error>> {
+    value
+        .split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (field, column) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid mapping \"{}\", expected field=column", pair))?;
+            Ok((field.parse::<Field>()?, column.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Parse a CSV export of this tool, a browser or another password manager.
+///
+/// The layout is detected from the headers. Overrides are applied on top of the detected
+/// layout and can map a file with an unknown layout on their own. If no service column is
+/// mapped, the service is derived from the host of the URL.
+///
+/// # Arguments
+///
+/// * `data` - The content of the CSV file.
+/// * `overrides` - Fields mapped to column names, taking precedence over the detected layout.
+///
+/// # Returns
+///
+/// A `Result` containing the mapped entries and the rows that could not be mapped.
+///
+/// # Errors
+///
+/// An error will be returned if the headers cannot be read, an override names an unknown
+/// column or the layout is unknown and no password column is mapped.
+pub fn parse(
+    data: &[u8],
+    overrides: &[(Field, String)],
+) -> Result<ImportResult, Box<dyn std::error::Error>> {
+    let mut reader = ReaderBuilder::new().flexible(true).from_reader(data);
+    let headers: Vec<String> = reader.headers()?.iter().map(normalize_header).collect();
+    let position = |name: &str| headers.iter().position(|header| header == name);
+
+    let mut columns = ColumnMap::default();
+    let layout = LAYOUTS.iter().find(|layout| {
+        layout
+            .required
+            .iter()
+            .all(|header| position(header).is_some())
+    });
+    if let Some(layout) = layout {
+        info!("Detected {} CSV layout", layout.name);
+        for (field, header) in layout.columns {
+            if let Some(column) = position(header) {
+                columns.set(*field, column);
+            }
+        }
+    }
+
+    for (field, name) in overrides {
+        let column = position(&normalize_header(name))
+            .ok_or_else(|| format!("Column \"{}\" not found in the CSV headers", name))?;
+        columns.set(*field, column);
+    }
+
+    if !columns
+        .columns
+        .iter()
+        .any(|(field, _)| *field == Field::Password)
+    {
+        return Err(format!(
+            "Unknown CSV layout with columns: {}. Use --map to map them, e.g. --map password=secret,url=site",
+            headers.join(", ")
+        )
+        .into());
+    }
+
+    let mut result = ImportResult::default();
+    for (index, record) in reader.records().enumerate() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                result.unmapped.push(format!("Row {}: {}", index + 2, e));
+                continue;
+            }
+        };
+        if record.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+
+        let url = columns.get(&record, Field::Url);
+        let service = match columns.get(&record, Field::Service) {
+            "" => host(url).unwrap_or("Unnamed"),
+            service => service,
+        };
+
+        let mut entry = PasswordEntry::new(
+            service.to_string(),
+            columns.get(&record, Field::Username).to_string(),
+            columns.get(&record, Field::Password).into(),
+            url.to_string(),
+            columns.get(&record, Field::Notes).to_string(),
+        )?;
+        entry.totp = columns.get(&record, Field::Totp).into();
+        entry.folder = columns.get(&record, Field::Folder).to_string();
+
+        let created_at = columns.get(&record, Field::CreatedAt);
+        let updated_at = columns.get(&record, Field::UpdatedAt);
+        if let Err(e) = set_timestamps(&mut entry, created_at, updated_at) {
+            result.unmapped.push(format!("Row {}: {}", index + 2, e));
+            continue;
+        }
+
+        result.entries.push(entry);
+    }
+
+    Ok(result)
+}
+
+/// Normalize a header for comparison, so `Created At` matches `created_at`.
+fn normalize_header(header: &str) -> String {
+    header.trim().to_lowercase().replace(' ', "_")
+}
+
+/// Extract the host name of a URL, without a `www.` prefix.
+fn host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host_port = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = if host_port.starts_with('[') {
+        host_port.split_inclusive(']').next()?
+    } else {
+        host_port.split(':').next()?
+    };
+    let host = host.strip_prefix("www.").unwrap_or(host);
+
+    (!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn services(result: &ImportResult) -> Vec<&str> {
+        result
+            .entries
+            .iter()
+            .map(|entry| entry.service.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_chrome() {
+        let csv = "name,url,username,password,note\n\
+                   GitHub,https://github.com/login,octocat,secret,work\n";
+        let result = parse(csv.as_bytes(), &[]).unwrap();
+
+        let entry = &result.entries[0];
+        assert_eq!(entry.service, "GitHub");
+        assert_eq!(entry.url, "https://github.com/login");
+        assert_eq!(entry.username, "octocat");
+        assert_eq!(entry.password.expose_secret(), "secret");
+        assert_eq!(entry.notes, "work");
+    }
+
+    #[test]
+    fn test_parse_keeps_secrets_unchanged() {
+        let csv = "name,url,username,password,note,totp\n \
+                   GitHub , https://github.com ,octocat , pw ,\"  indented\n\",JBSWY3DP \n";
+        let overrides = parse_overrides("totp=totp").unwrap();
+        let result = parse(csv.as_bytes(), &overrides).unwrap();
+
+        let entry = &result.entries[0];
+        assert_eq!(entry.service, "GitHub");
+        assert_eq!(entry.url, "https://github.com");
+        assert_eq!(entry.username, "octocat");
+        assert_eq!(entry.password.expose_secret(), " pw ");
+        assert_eq!(entry.notes, "  indented\n");
+        assert_eq!(entry.totp.expose_secret(), "JBSWY3DP ");
+    }
+
+    #[test]
+    fn test_parse_firefox_derives_service_from_host() {
+        let csv = "\"url\",\"username\",\"password\",\"httpRealm\",\"formActionOrigin\",\"guid\"\n\
+                   \"https://www.example.com:8443/login\",\"jane\",\"pw1\",,\"https://www.example.com\",\"{1}\"\n\
+                   \"https://user@mail.example.org\",\"joe\",\"pw2\",,,\"{2}\"\n";
+        let result = parse(csv.as_bytes(), &[]).unwrap();
+
+        assert_eq!(services(&result), ["example.com", "mail.example.org"]);
+        assert_eq!(result.entries[0].url, "https://www.example.com:8443/login");
+    }
+
+    #[test]
+    fn test_parse_lastpass_and_safari() {
+        let lastpass = "url,username,password,totp,extra,name,grouping,fav\n\
+                        https://a.com,ann,pw,JBSWY3DP,note,A,Work/Mail,0\n";
+        let result = parse(lastpass.as_bytes(), &[]).unwrap();
+        assert_eq!(result.entries[0].service, "A");
+        assert_eq!(result.entries[0].folder, "Work/Mail");
+        assert_eq!(result.entries[0].notes, "note");
+        assert_eq!(result.entries[0].totp.expose_secret(), "JBSWY3DP");
+
+        let safari = "Title,URL,Username,Password,Notes,OTPAuth\n\
+                      B,https://b.com,bob,pw,,otpauth://totp/B?secret=X\n";
+        let result = parse(safari.as_bytes(), &[]).unwrap();
+        assert_eq!(result.entries[0].service, "B");
+        assert_eq!(
+            result.entries[0].totp.expose_secret(),
+            "otpauth://totp/B?secret=X"
+        );
+    }
+
+    #[test]
+    fn test_parse_own_export_and_template() {
+        let export = "service,username,password,url,notes,created_at,updated_at\n\
+                      Mail,me,pw,https://mail.com,,,\n";
+        assert_eq!(services(&parse(export.as_bytes(), &[]).unwrap()), ["Mail"]);
+
+        let template = "Service,Username,Password,URL,Notes,Created At,Updated At\n\
+                        Google,me,pw,https://google.com,Notes,,\n";
+        assert_eq!(
+            services(&parse(template.as_bytes(), &[]).unwrap()),
+            ["Google"]
+        );
+    }
+
+    #[test]
+    fn test_parse_timestamps() {
+        let csv = "Service,Username,Password,URL,Notes,Created At,Updated At\n\
+                   A,me,pw,https://a.com,,2020-01-02 03:04:05,2021-01-02T03:04:05Z\n\
+                   B,me,pw,https://b.com,,someday,\n";
+        let result = parse(csv.as_bytes(), &[]).unwrap();
+
+        assert_eq!(services(&result), ["A"]);
+        assert_eq!(result.entries[0].created_at, "2020-01-02T03:04:05+00:00");
+        assert_eq!(result.entries[0].updated_at, "2021-01-02T03:04:05+00:00");
+        assert!(result.unmapped[0].starts_with("Row 3: Unsupported date format"));
+
+        let firefox = "url,username,password,formActionOrigin,timeCreated,timePasswordChanged\n\
+                       https://c.com,me,pw,,1709649000000,1709649000000\n";
+        let result = parse(firefox.as_bytes(), &[]).unwrap();
+        assert_eq!(result.entries[0].created_at, "2024-03-05T14:30:00+00:00");
+    }
+
+    #[test]
+    fn test_overrides() {
+        let csv = "site,origin,login,secret\nHome,https://home.lan,admin,pw\n";
+        assert!(parse(csv.as_bytes(), &[]).is_err());
+
+        let overrides =
+            parse_overrides("service=site, url=origin,username=login,password=secret").unwrap();
+        let result = parse(csv.as_bytes(), &overrides).unwrap();
+        let entry = &result.entries[0];
+        assert_eq!(entry.service, "Home");
+        assert_eq!(entry.url, "https://home.lan");
+        assert_eq!(entry.username, "admin");
+        assert_eq!(entry.password.expose_secret(), "pw");
+
+        let overrides = parse_overrides("password=missing").unwrap();
+        assert!(parse(csv.as_bytes(), &overrides).is_err());
+        assert!(parse_overrides("colour=site").is_err());
+        assert!(parse_overrides("service").is_err());
+    }
+
+    #[test]
+    fn test_override_detected_layout() {
+        let csv = "name,url,username,password\nGitHub,https://github.com,octocat,pw\n";
+        let overrides = parse_overrides("service=url").unwrap();
+        let result = parse(csv.as_bytes(), &overrides).unwrap();
+
+        assert_eq!(result.entries[0].service, "https://github.com");
+    }
+
+    #[test]
+    fn test_skip_empty_rows_and_report_invalid_rows() {
+        let csv = b"name,url,username,password\n,,,\nA,https://a.com,u,p\nB,\xff,u,p\n";
+        let result = parse(csv, &[]).unwrap();
+
+        assert_eq!(services(&result), ["A"]);
+        assert_eq!(result.unmapped.len(), 1);
+        assert!(result.unmapped[0].starts_with("Row 4:"));
+    }
+
+    #[test]
+    fn test_host() {
+        assert_eq!(
+            host("https://www.example.com/path?q=1"),
+            Some("example.com")
+        );
+        assert_eq!(host("example.com/login"), Some("example.com"));
+        assert_eq!(host("ftp://user:pw@files.lan:21"), Some("files.lan"));
+        assert_eq!(host("http://[::1]:8080/"), Some("[::1]"));
+        assert_eq!(host(""), None);
+    }
+}
@@ -0,0 +1,920 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecryptMut, BlockEncrypt, KeyInit, KeyIvInit, StreamCipher};
+use aes::Aes256;
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20::ChaCha20;
+use chrono::DateTime;
+use flate2::read::GzDecoder;
+use ring::{digest, hmac};
+use roxmltree::{Document, Node, NodeId};
+use zeroize::Zeroizing;
+
+use super::{set_timestamps, ImportResult};
+use crate::prelude::{CustomField, PasswordEntry, SecretString};
+use crate::utils::database::format_uuid;
+use crate::utils::exporters::keepass_xml::{FIELD_TIMES_KEY, SOURCE_KEY};
+
+const SIGNATURE_1: u32 = 0x9AA2_D903;
+const SIGNATURE_2: u32 = 0xB54B_FB67;
+const SUPPORTED_MAJOR_VERSION: u16 = 4;
+
+const HEADER_END: u8 = 0;
+const HEADER_CIPHER_ID: u8 = 2;
+const HEADER_COMPRESSION: u8 = 3;
+const HEADER_MASTER_SEED: u8 = 4;
+const HEADER_ENCRYPTION_IV: u8 = 7;
+const HEADER_KDF_PARAMETERS: u8 = 11;
+
+const INNER_END: u8 = 0;
+const INNER_STREAM_ID: u8 = 1;
+const INNER_STREAM_KEY: u8 = 2;
+const INNER_BINARY: u8 = 3;
+const INNER_STREAM_CHACHA20: u32 = 3;
+
+const CIPHER_AES256: [u8; 16] = uuid(0x31C1F2E6_BF71_4350_BE58_05216AFC5AFF);
+const CIPHER_CHACHA20: [u8; 16] = uuid(0xD6038A2B_8B6F_4CB5_A524_339A31DBB59A);
+const KDF_AES: [u8; 16] = uuid(0xC9D9F39A_628A_4460_BF74_0D08C18A4FEA);
+const KDF_ARGON2D: [u8; 16] = uuid(0xEF636DDF_8C29_444B_91F7_A9A403E30A0C);
+const KDF_ARGON2ID: [u8; 16] = uuid(0x9E298B19_56DB_4773_B23D_FC3EC6F0A1E6);
+
+/// The largest attachment that is imported as a text custom field.
+const MAX_TEXT_ATTACHMENT: usize = 64 * 1024;
+
+/// The outer header fields needed to decrypt the database.
+struct OuterHeader {
+    cipher_id: Vec<u8>,
+    compressed: bool,
+    master_seed: Vec<u8>,
+    encryption_iv: Vec<u8>,
+    kdf_parameters: HashMap<String, Vec<u8>>,
+}
+
+/// The inner header: the protected value stream and the attachment pool.
+struct InnerHeader {
+    stream: ChaCha20,
+    binaries: Vec<Vec<u8>>,
+}
+
+/// Check if data looks like a KeePass database.
+///
+/// # Arguments
+///
+/// * `data` - The file content to check.
+///
+/// # Returns
+///
+/// A `bool` indicating if the data starts with the KeePass signature.
+pub fn is_kdbx(data: &[u8]) -> bool {
+    data.len() >= 8
+        && read_u32(&data[..4]).is_ok_and(|signature| signature == SIGNATURE_1)
+        && read_u32(&data[4..8]).is_ok_and(|signature| signature == SIGNATURE_2)
+}
+
+/// Decrypt and parse a KeePass KDBX 4 database.
+///
+/// Groups become slash separated folder names, standard fields are mapped onto the
+/// entry and other string fields become custom fields. Text attachments and previous
+/// passwords from the entry history are kept as hidden custom fields. Entries in the
+/// recycle bin are skipped.
+///
+/// # Arguments
+///
+/// * `data` - The content of the KDBX file.
+/// * `password` - The password of the database. An empty password is ignored if a key file is given.
+/// * `keyfile` - The content of the key file, if the database uses one.
+///
+/// # Returns
+///
+/// A `Result` containing the mapped entries and the items that could not be mapped.
+///
+/// # Errors
+///
+/// An error will be returned if the file is not a KDBX 4 database, uses an unsupported
+/// cipher or key derivation function, was tampered with or the credentials are wrong.
+pub fn parse(
+    data: &[u8],
+    password: &SecretString,
+    keyfile: Option<&[u8]>,
+) -> Result<ImportResult, Box<dyn std::error::Error>> {
+    let mut cursor = data;
+    let (header, header_bytes) = read_outer_header(&mut cursor)?;
+
+    let header_hash = take(&mut cursor, 32)?;
+    if digest::digest(&digest::SHA256, header_bytes).as_ref() != header_hash {
+        return Err("The KDBX header is corrupted".into());
+    }
+
+    let composite = composite_key(password, keyfile)?;
+    let transformed = transform_key(&composite, &header.kdf_parameters)?;
+
+    let mut seed_and_key = Zeroizing::new(header.master_seed.clone());
+    seed_and_key.extend_from_slice(&transformed[..]);
+    let encryption_key = sha(&digest::SHA256, &seed_and_key);
+    seed_and_key.push(1);
+    let hmac_key = sha(&digest::SHA512, &seed_and_key);
+
+    let header_hmac = take(&mut cursor, 32)?;
+    let key = block_hmac_key(u64::MAX, &hmac_key);
+    if hmac::verify(&key, header_bytes, header_hmac).is_err() {
+        return Err("Failed to unlock the KDBX database: wrong password or key file".into());
+    }
+
+    let ciphertext = read_blocks(cursor, &hmac_key)?;
+    let mut payload = decrypt_payload(&header, &encryption_key, ciphertext)?;
+    if header.compressed {
+        let mut decompressed = Zeroizing::new(Vec::new());
+        GzDecoder::new(&payload[..]).read_to_end(&mut decompressed)?;
+        payload = decompressed;
+    }
+
+    let mut cursor = &payload[..];
+    let mut inner = read_inner_header(&mut cursor)?;
+    let xml = std::str::from_utf8(cursor)?;
+    let document = Document::parse(xml)?;
+
+    let protected = unprotect_values(&document, &mut inner.stream)?;
+
+    map_document(&document, protected, &inner.binaries)
+}
+
+/// Parse an unencrypted KeePass 2 XML export.
+///
+/// The XML is mapped like the content of a KDBX database. Values marked with
+/// `ProtectInMemory` become hidden custom fields and attachments are read from the
+/// binary pool in the metadata.
+///
+/// # Arguments
+///
+/// * `xml` - The content of the XML export.
+///
+/// # Returns
+///
+/// A `Result` containing the mapped entries and the items that could not be mapped.
+///
+/// # Errors
+///
+/// An error will be returned if the file is not valid KeePass XML.
+pub fn parse_xml(xml: &str) -> Result<ImportResult, Box<dyn std::error::Error>> {
+    let document = Document::parse(xml)?;
+
+    let mut binaries = Vec::new();
+    let pool = child(document.root_element(), "Meta").and_then(|meta| child(meta, "Binaries"));
+    for binary in pool
+        .into_iter()
+        .flat_map(|pool| pool.children().filter(|n| n.has_tag_name("Binary")))
+    {
+        let data = STANDARD.decode(binary.text().unwrap_or_default().trim())?;
+        if binary.attribute("Compressed") == Some("True") {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(&data[..]).read_to_end(&mut decompressed)?;
+            binaries.push(decompressed);
+        } else {
+            binaries.push(data);
+        }
+    }
+
+    map_document(&document, HashMap::new(), &binaries)
+}
+
+/// Read the outer header and return it together with its raw bytes.
+fn read_outer_header<'a>(
+    cursor: &mut &'a [u8],
+) -> Result<(OuterHeader, &'a [u8]), Box<dyn std::error::Error>> {
+    let start = *cursor;
+
+    let signature_1 = read_u32(take(cursor, 4)?)?;
+    let signature_2 = read_u32(take(cursor, 4)?)?;
+    if signature_1 != SIGNATURE_1 || signature_2 != SIGNATURE_2 {
+        return Err("Not a KeePass database".into());
+    }
+    let version = take(cursor, 4)?;
+    let major = u16::from_le_bytes([version[2], version[3]]);
+    if major != SUPPORTED_MAJOR_VERSION {
+        return Err(format!(
+            "Unsupported KDBX version {}, please save the database as KDBX 4",
+            major
+        )
+        .into());
+    }
+
+    let mut header = OuterHeader {
+        cipher_id: Vec::new(),
+        compressed: false,
+        master_seed: Vec::new(),
+        encryption_iv: Vec::new(),
+        kdf_parameters: HashMap::new(),
+    };
+    loop {
+        let id = take(cursor, 1)?[0];
+        let size = read_u32(take(cursor, 4)?)? as usize;
+        let value = take(cursor, size)?;
+        match id {
+            HEADER_END => break,
+            HEADER_CIPHER_ID => header.cipher_id = value.to_vec(),
+            HEADER_COMPRESSION => header.compressed = read_u32(value)? == 1,
+            HEADER_MASTER_SEED => header.master_seed = value.to_vec(),
+            HEADER_ENCRYPTION_IV => header.encryption_iv = value.to_vec(),
+            HEADER_KDF_PARAMETERS => header.kdf_parameters = read_variant_dictionary(value)?,
+            _ => {}
+        }
+    }
+
+    if header.master_seed.len() != 32 {
+        return Err("The KDBX header has an invalid master seed".into());
+    }
+
+    let header_len = start.len() - cursor.len();
+    Ok((header, &start[..header_len]))
+}
+
+/// Read a KeePass variant dictionary, keeping the raw value bytes of every item.
+fn read_variant_dictionary(
+    mut data: &[u8],
+) -> Result<HashMap<String, Vec<u8>>, Box<dyn std::error::Error>> {
+    let cursor = &mut data;
+    let version = take(cursor, 2)?;
+    if version[1] != 1 {
+        return Err("Unsupported KDF parameter format".into());
+    }
+
+    let mut items = HashMap::new();
+    loop {
+        let kind = take(cursor, 1)?[0];
+        if kind == 0 {
+            break;
+        }
+        let key_len = read_u32(take(cursor, 4)?)? as usize;
+        let key = String::from_utf8(take(cursor, key_len)?.to_vec())?;
+        let value_len = read_u32(take(cursor, 4)?)? as usize;
+        items.insert(key, take(cursor, value_len)?.to_vec());
+    }
+
+    Ok(items)
+}
+
+/// Combine the password and key file into the composite key.
+fn composite_key(
+    password: &SecretString,
+    keyfile: Option<&[u8]>,
+) -> Result<Zeroizing<Vec<u8>>, Box<dyn std::error::Error>> {
+    let mut parts = Zeroizing::new(Vec::new());
+    if !password.is_empty() || keyfile.is_none() {
+        let hash = digest::digest(&digest::SHA256, password.expose_secret().as_bytes());
+        parts.extend_from_slice(hash.as_ref());
+    }
+    if let Some(keyfile) = keyfile {
+        parts.extend_from_slice(&keyfile_key(keyfile)?);
+    }
+
+    Ok(sha(&digest::SHA256, &parts))
+}
+
+/// Derive the key contributed by a key file.
+///
+/// XML key files (version 1 and 2), raw 32 byte keys and 64 character hex keys are used
+/// as is, any other file is hashed.
+fn keyfile_key(data: &[u8]) -> Result<Zeroizing<Vec<u8>>, Box<dyn std::error::Error>> {
+    if let Some(key) = xml_keyfile_key(data)? {
+        return Ok(key);
+    }
+    if data.len() == 32 {
+        return Ok(Zeroizing::new(data.to_vec()));
+    }
+    if data.len() == 64 {
+        if let Some(key) = std::str::from_utf8(data).ok().and_then(decode_hex) {
+            return Ok(key);
+        }
+    }
+
+    Ok(sha(&digest::SHA256, data))
+}
+
+/// Read the key from an XML key file, if the file is one.
+fn xml_keyfile_key(data: &[u8]) -> Result<Option<Zeroizing<Vec<u8>>>, Box<dyn std::error::Error>> {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return Ok(None);
+    };
+    let Ok(document) = Document::parse(text) else {
+        return Ok(None);
+    };
+    let root = document.root_element();
+    if !root.has_tag_name("KeyFile") {
+        return Ok(None);
+    }
+
+    let version = child(root, "Meta")
+        .and_then(|meta| child(meta, "Version"))
+        .and_then(|version| version.text())
+        .unwrap_or("1.0");
+    let data = child(root, "Key")
+        .and_then(|key| child(key, "Data"))
+        .ok_or("The key file has no key data")?;
+    let text = data.text().unwrap_or_default();
+
+    let key = if version.starts_with("2.") {
+        let hex: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+        let key = decode_hex(&hex).ok_or("The key file contains invalid key data")?;
+        if let Some(expected) = data.attribute("Hash") {
+            let hash = digest::digest(&digest::SHA256, &key);
+            let expected = decode_hex(expected).ok_or("The key file has an invalid hash")?;
+            if !hash.as_ref().starts_with(&expected) {
+                return Err("The key file is corrupted".into());
+            }
+        }
+        key
+    } else {
+        Zeroizing::new(STANDARD.decode(text.trim())?)
+    };
+
+    Ok(Some(key))
+}
+
+/// Run the key derivation function described by the KDF parameters.
+fn transform_key(
+    composite: &[u8],
+    parameters: &HashMap<String, Vec<u8>>,
+) -> Result<Zeroizing<Vec<u8>>, Box<dyn std::error::Error>> {
+    let uuid = parameters
+        .get("$UUID")
+        .ok_or("The KDBX header has no key derivation function")?;
+    let salt = parameters
+        .get("S")
+        .ok_or("The KDBX header has no key derivation salt")?;
+    let mut key = Zeroizing::new(vec![0u8; 32]);
+
+    if uuid[..] == KDF_AES {
+        let rounds = read_u64(parameters.get("R").ok_or("Missing AES-KDF rounds")?)?;
+        let cipher = Aes256::new_from_slice(salt).map_err(|_| "Invalid AES-KDF seed")?;
+        key.copy_from_slice(composite);
+        for _ in 0..rounds {
+            for block in key.chunks_exact_mut(16) {
+                cipher.encrypt_block(GenericArray::from_mut_slice(block));
+            }
+        }
+        let hash = digest::digest(&digest::SHA256, &key);
+        key.copy_from_slice(hash.as_ref());
+    } else if uuid[..] == KDF_ARGON2D || uuid[..] == KDF_ARGON2ID {
+        let algorithm = if uuid[..] == KDF_ARGON2D {
+            Algorithm::Argon2d
+        } else {
+            Algorithm::Argon2id
+        };
+        let version = match read_u32(parameters.get("V").ok_or("Missing Argon2 version")?)? {
+            0x10 => Version::V0x10,
+            0x13 => Version::V0x13,
+            other => return Err(format!("Unsupported Argon2 version: {:#x}", other).into()),
+        };
+        let memory = read_u64(parameters.get("M").ok_or("Missing Argon2 memory")?)? / 1024;
+        let iterations = read_u64(parameters.get("I").ok_or("Missing Argon2 iterations")?)?;
+        let parallelism = read_u32(parameters.get("P").ok_or("Missing Argon2 parallelism")?)?;
+
+        let params = Params::new(
+            u32::try_from(memory)?,
+            u32::try_from(iterations)?,
+            parallelism,
+            Some(32),
+        )
+        .map_err(|e| format!("Invalid Argon2 parameters: {}", e))?;
+        Argon2::new(algorithm, version, params)
+            .hash_password_into(composite, salt, &mut key)
+            .map_err(|e| format!("Failed to derive the KDBX key: {}", e))?;
+    } else {
+        return Err("Unsupported KDBX key derivation function".into());
+    }
+
+    Ok(key)
+}
+
+/// The HMAC key of a single block in the HMAC block stream.
+fn block_hmac_key(index: u64, hmac_key: &[u8]) -> hmac::Key {
+    let mut data = Zeroizing::new(index.to_le_bytes().to_vec());
+    data.extend_from_slice(hmac_key);
+    hmac::Key::new(
+        hmac::HMAC_SHA256,
+        digest::digest(&digest::SHA512, &data).as_ref(),
+    )
+}
+
+/// Read and authenticate the HMAC block stream, returning the encrypted payload.
+fn read_blocks(mut data: &[u8], hmac_key: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let cursor = &mut data;
+    let mut payload = Vec::new();
+
+    for index in 0u64.. {
+        let mac = take(cursor, 32)?;
+        let size_bytes = take(cursor, 4)?;
+        let size = usize::try_from(i32::from_le_bytes(size_bytes.try_into()?))?;
+        let block = take(cursor, size)?;
+
+        let mut message = index.to_le_bytes().to_vec();
+        message.extend_from_slice(size_bytes);
+        message.extend_from_slice(block);
+        if hmac::verify(&block_hmac_key(index, hmac_key), &message, mac).is_err() {
+            return Err(format!("KDBX block {} failed authentication", index).into());
+        }
+
+        if size == 0 {
+            break;
+        }
+        payload.extend_from_slice(block);
+    }
+
+    Ok(payload)
+}
+
+/// Decrypt the payload with the cipher named in the outer header.
+fn decrypt_payload(
+    header: &OuterHeader,
+    key: &[u8],
+    mut data: Vec<u8>,
+) -> Result<Zeroizing<Vec<u8>>, Box<dyn std::error::Error>> {
+    if header.cipher_id[..] == CIPHER_AES256 {
+        let decryptor = cbc::Decryptor::<Aes256>::new_from_slices(key, &header.encryption_iv)
+            .map_err(|_| "Invalid AES-256 encryption IV")?;
+        let len = decryptor
+            .decrypt_padded_mut::<Pkcs7>(&mut data)
+            .map_err(|_| "Failed to decrypt the KDBX payload")?
+            .len();
+        data.truncate(len);
+    } else if header.cipher_id[..] == CIPHER_CHACHA20 {
+        let mut cipher = ChaCha20::new_from_slices(key, &header.encryption_iv)
+            .map_err(|_| "Invalid ChaCha20 encryption IV")?;
+        cipher.apply_keystream(&mut data);
+    } else {
+        return Err("Unsupported KDBX cipher".into());
+    }
+
+    Ok(Zeroizing::new(data))
+}
+
+/// Read the inner header, returning the protected value stream and the attachments.
+fn read_inner_header(cursor: &mut &[u8]) -> Result<InnerHeader, Box<dyn std::error::Error>> {
+    let mut stream_id = None;
+    let mut stream_key = Zeroizing::new(Vec::new());
+    let mut binaries = Vec::new();
+
+    loop {
+        let id = take(cursor, 1)?[0];
+        let size = read_u32(take(cursor, 4)?)? as usize;
+        let value = take(cursor, size)?;
+        match id {
+            INNER_END => break,
+            INNER_STREAM_ID => stream_id = Some(read_u32(value)?),
+            INNER_STREAM_KEY => stream_key = Zeroizing::new(value.to_vec()),
+            INNER_BINARY if !value.is_empty() => binaries.push(value[1..].to_vec()),
+            _ => {}
+        }
+    }
+
+    if stream_id != Some(INNER_STREAM_CHACHA20) {
+        return Err("Unsupported KDBX protected value stream".into());
+    }
+    let hash = digest::digest(&digest::SHA512, &stream_key);
+    let stream = ChaCha20::new_from_slices(&hash.as_ref()[..32], &hash.as_ref()[32..44])
+        .map_err(|_| "Invalid protected value stream key")?;
+
+    Ok(InnerHeader { stream, binaries })
+}
+
+/// Map the decrypted XML document onto entries.
+fn map_document(
+    document: &Document,
+    protected: HashMap<NodeId, SecretString>,
+    binaries: &[Vec<u8>],
+) -> Result<ImportResult, Box<dyn std::error::Error>> {
+    let root = document.root_element();
+    let meta = child(root, "Meta");
+    let recycle_bin = meta
+        .filter(|meta| child_text(*meta, "RecycleBinEnabled") != "False")
+        .map(|meta| child_text(meta, "RecycleBinUUID"))
+        .filter(|uuid| !uuid.is_empty());
+
+    let root_group = child(root, "Root")
+        .and_then(|root| child(root, "Group"))
+        .ok_or("The KeePass database has no root group")?;
+
+    let mut mapper = EntryMapper {
+        protected,
+        binaries,
+        recycle_bin,
+        result: ImportResult::default(),
+    };
+    mapper.walk_group(root_group, &[]);
+
+    Ok(mapper.result)
+}
+
+/// Decrypt every protected value. The stream is consumed in document order.
+fn unprotect_values(
+    document: &Document,
+    stream: &mut ChaCha20,
+) -> Result<HashMap<NodeId, SecretString>, Box<dyn std::error::Error>> {
+    let mut values = HashMap::new();
+
+    for node in document.descendants() {
+        if !node.has_tag_name("Value") || node.attribute("Protected") != Some("True") {
+            continue;
+        }
+        let mut value = Zeroizing::new(STANDARD.decode(node.text().unwrap_or_default())?);
+        stream.apply_keystream(&mut value);
+        let value = String::from_utf8(value.to_vec())
+            .map_err(|_| "A protected KDBX value is not valid UTF-8")?;
+        values.insert(node.id(), SecretString::new(value));
+    }
+
+    Ok(values)
+}
+
+/// Walks the group tree and collects the mapped entries.
+struct EntryMapper<'a> {
+    protected: HashMap<NodeId, SecretString>,
+    binaries: &'a [Vec<u8>],
+    recycle_bin: Option<&'a str>,
+    result: ImportResult,
+}
+
+impl EntryMapper<'_> {
+    /// Map the entries of a group and its subgroups.
+    fn walk_group<'d>(&mut self, group: Node<'d, '_>, path: &[&'d str]) {
+        let in_recycle_bin = self
+            .recycle_bin
+            .is_some_and(|uuid| child_text(group, "UUID") == uuid);
+
+        for node in group.children().filter(Node::is_element) {
+            match node.tag_name().name() {
+                "Entry" if in_recycle_bin => {
+                    let fields = self.strings(node);
+                    self.result
+                        .unmapped
+                        .push(format!("{}: entry is in the recycle bin", title(&fields)));
+                }
+                "Entry" => {
+                    let entry = self.map_entry(node, path);
+                    self.result.entries.extend(entry);
+                }
+                "Group" => {
+                    let name = child_text(node, "Name");
+                    let mut path = path.to_vec();
+                    path.push(name);
+                    self.walk_group(node, &path);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Map a single entry, recording attachments that could not be imported.
+    fn map_entry(&mut self, node: Node, path: &[&str]) -> Option<PasswordEntry> {
+        let mut fields = self.strings(node);
+        let name = title(&fields);
+        let mut take_field = |key: &str| {
+            fields
+                .iter()
+                .position(|(field, _, _)| field == key)
+                .map(|index| fields.remove(index).1)
+                .unwrap_or_default()
+        };
+
+        let mut entry = PasswordEntry::new(
+            name.clone(),
+            take_field("UserName").expose_secret().to_string(),
+            take_field("Password"),
+            take_field("URL").expose_secret().to_string(),
+            take_field("Notes").expose_secret().to_string(),
+        )
+        .ok()?;
+        entry.totp = take_field("otp");
+        take_field("Title");
+        entry.folder = path.join("/");
+        entry.tags = child_text(node, "Tags")
+            .split([';', ','])
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect();
+        if let Some(uuid) = STANDARD
+            .decode(child_text(node, "UUID"))
+            .ok()
+            .and_then(|uuid| <[u8; 16]>::try_from(uuid).ok())
+        {
+            entry.uuid = format_uuid(&uuid);
+        }
+        for item in child(node, "CustomData")
+            .into_iter()
+            .flat_map(|data| data.children().filter(|n| n.has_tag_name("Item")))
+        {
+            let value = child_text(item, "Value");
+            match child_text(item, "Key") {
+                SOURCE_KEY => entry.source = value.to_string(),
+                FIELD_TIMES_KEY => {
+                    entry.field_times = serde_json::from_str(value).unwrap_or_default()
+                }
+                _ => {}
+            }
+        }
+
+        let times = child(node, "Times");
+        let time = |name: &str| times.map(|times| kdbx_time(child_text(times, name)));
+        let created_at = time("CreationTime").unwrap_or_default();
+        let updated_at = time("LastModificationTime").unwrap_or_default();
+        if let Err(e) = set_timestamps(&mut entry, &created_at, &updated_at) {
+            self.result.unmapped.push(format!("{}: {}", name, e));
+            return None;
+        }
+
+        for (field_name, value, hidden) in fields {
+            entry.custom_fields.push(CustomField {
+                name: field_name,
+                value,
+                hidden,
+            });
+        }
+
+        for binary in node.children().filter(|n| n.has_tag_name("Binary")) {
+            let key = child_text(binary, "Key");
+            let content = child(binary, "Value")
+                .and_then(|value| value.attribute("Ref"))
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| self.binaries.get(index));
+            let text = content
+                .filter(|content| content.len() <= MAX_TEXT_ATTACHMENT)
+                .and_then(|content| std::str::from_utf8(content).ok());
+
+            match text {
+                Some(text) => entry.custom_fields.push(CustomField {
+                    name: format!("Attachment: {}", key),
+                    value: text.into(),
+                    hidden: true,
+                }),
+                None => self.result.unmapped.push(format!(
+                    "{}: attachment \"{}\" is not a text file and was not imported",
+                    name, key
+                )),
+            }
+        }
+
+        let mut previous: Vec<SecretString> = Vec::new();
+        for old in child(node, "History")
+            .into_iter()
+            .flat_map(|history| history.children().filter(|n| n.has_tag_name("Entry")))
+            .rev()
+        {
+            let password = self
+                .strings(old)
+                .into_iter()
+                .find(|(key, _, _)| key == "Password")
+                .map(|(_, value, _)| value)
+                .unwrap_or_default();
+            if !password.is_empty() && password != entry.password && !previous.contains(&password) {
+                previous.push(password);
+            }
+        }
+        for (index, password) in previous.into_iter().enumerate() {
+            entry.custom_fields.push(CustomField {
+                name: format!("Previous Password {}", index + 1),
+                value: password,
+                hidden: true,
+            });
+        }
+
+        Some(entry)
+    }
+
+    /// Read the string fields of an entry as `(key, value, protected)`.
+    fn strings(&self, entry: Node) -> Vec<(String, SecretString, bool)> {
+        entry
+            .children()
+            .filter(|n| n.has_tag_name("String"))
+            .map(|string| {
+                let key = child_text(string, "Key").to_string();
+                let value = child(string, "Value");
+                let protected = value.and_then(|value| self.protected.get(&value.id()));
+                let in_memory = value.and_then(|value| value.attribute("ProtectInMemory"));
+                let text = match protected {
+                    Some(secret) => secret.clone(),
+                    None => value
+                        .and_then(|value| value.text())
+                        .unwrap_or_default()
+                        .into(),
+                };
+                (key, text, protected.is_some() || in_memory == Some("True"))
+            })
+            .collect()
+    }
+}
+
+/// The title of an entry, falling back to a placeholder for untitled entries.
+fn title(fields: &[(String, SecretString, bool)]) -> String {
+    fields
+        .iter()
+        .find(|(key, value, _)| key == "Title" && !value.is_empty())
+        .map(|(_, value, _)| value.expose_secret().to_string())
+        .unwrap_or_else(|| "Unnamed".to_string())
+}
+
+/// Convert a KDBX time to RFC 3339.
+///
+/// KDBX 4 stores base64 encoded seconds since 0001-01-01, older writers use ISO 8601 text,
+/// which is passed through.
+fn kdbx_time(value: &str) -> String {
+    const SECONDS_BEFORE_UNIX_EPOCH: i64 = 62_135_596_800;
+
+    let seconds = STANDARD
+        .decode(value)
+        .ok()
+        .and_then(|bytes| <[u8; 8]>::try_from(bytes).ok())
+        .map(i64::from_le_bytes);
+    match seconds.and_then(|s| DateTime::from_timestamp(s - SECONDS_BEFORE_UNIX_EPOCH, 0)) {
+        Some(timestamp) => timestamp.to_rfc3339(),
+        None => value.to_string(),
+    }
+}
+
+/// Find the first child element with the given tag name.
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+/// The text of the first child element with the given tag name.
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> &'a str {
+    child(node, name).and_then(|n| n.text()).unwrap_or_default()
+}
+
+/// Split `len` bytes off the front of the cursor.
+fn take<'a>(cursor: &mut &'a [u8], len: usize) -> Result<&'a [u8], Box<dyn std::error::Error>> {
+    if cursor.len() < len {
+        return Err("The KDBX file is truncated".into());
+    }
+    let (head, tail) = cursor.split_at(len);
+    *cursor = tail;
+    Ok(head)
+}
+
+/// Hash data into a buffer that is cleared on drop.
+fn sha(algorithm: &'static digest::Algorithm, data: &[u8]) -> Zeroizing<Vec<u8>> {
+    Zeroizing::new(digest::digest(algorithm, data).as_ref().to_vec())
+}
+
+fn read_u32(data: &[u8]) -> Result<u32, Box<dyn std::error::Error>> {
+    Ok(u32::from_le_bytes(data.try_into()?))
+}
+
+fn read_u64(data: &[u8]) -> Result<u64, Box<dyn std::error::Error>> {
+    Ok(u64::from_le_bytes(data.try_into()?))
+}
+
+/// Decode a hex string, returning `None` if it is not valid hex.
+fn decode_hex(hex: &str) -> Option<Zeroizing<Vec<u8>>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    Some(Zeroizing::new(bytes))
+}
+
+/// Build a KeePass UUID constant from its canonical hex form.
+const fn uuid(value: u128) -> [u8; 16] {
+    value.to_be_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/keepass.kdbx"
+    ));
+    const KEYFILE_FIXTURE: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/keepass-keyfile.kdbx"
+    ));
+    const KEYFILE: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/keepass.keyx"
+    ));
+
+    fn password() -> SecretString {
+        "fixture-password".into()
+    }
+
+    fn find<'a>(result: &'a ImportResult, service: &str) -> &'a PasswordEntry {
+        result
+            .entries
+            .iter()
+            .find(|entry| entry.service == service)
+            .unwrap()
+    }
+
+    fn field<'a>(entry: &'a PasswordEntry, name: &str) -> &'a CustomField {
+        entry.custom_fields.iter().find(|f| f.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_parse_aes_argon2id() {
+        let result = parse(FIXTURE, &password(), None).unwrap();
+        let entry = find(&result, "Router");
+
+        assert_eq!(entry.username, "admin");
+        assert_eq!(entry.password.expose_secret(), "r0uter!");
+        assert_eq!(entry.url, "http://192.168.1.1");
+        assert_eq!(entry.notes, "Office router\nRack 2");
+        assert_eq!(entry.folder, "");
+        assert_eq!(
+            entry.totp.expose_secret(),
+            "otpauth://totp/Router?secret=JBSWY3DPEHPK3PXP"
+        );
+
+        assert!(!field(entry, "Serial").hidden);
+        assert_eq!(field(entry, "Serial").value.expose_secret(), "RT-1234");
+        assert!(field(entry, "PIN").hidden);
+        assert_eq!(field(entry, "PIN").value.expose_secret(), "4711");
+
+        assert_eq!(entry.created_at, "2019-07-01T09:30:00+00:00");
+        assert_eq!(entry.updated_at, "2024-02-29T18:00:05+00:00");
+    }
+
+    #[test]
+    fn test_parse_groups_attachments_and_history() {
+        let result = parse(FIXTURE, &password(), None).unwrap();
+
+        assert_eq!(result.entries.len(), 2);
+        assert_eq!(find(&result, "db01").folder, "Infrastructure/Servers");
+        assert_eq!(find(&result, "db01").password.expose_secret(), "pg-secret");
+
+        let router = find(&result, "Router");
+        assert_eq!(
+            field(router, "Attachment: config.txt")
+                .value
+                .expose_secret(),
+            "ssid=office\nchannel=6\n"
+        );
+        assert_eq!(
+            field(router, "Previous Password 1").value.expose_secret(),
+            "old-2"
+        );
+        assert_eq!(
+            field(router, "Previous Password 2").value.expose_secret(),
+            "old-1"
+        );
+
+        assert_eq!(result.unmapped.len(), 2);
+        assert!(result.unmapped[0].contains("attachment \"logo.png\""));
+        assert!(result.unmapped[1].starts_with("Deleted: entry is in the recycle bin"));
+    }
+
+    #[test]
+    fn test_parse_chacha20_with_keyfile() {
+        let result = parse(KEYFILE_FIXTURE, &password(), Some(KEYFILE)).unwrap();
+
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.entries[0].service, "VPN");
+        assert_eq!(result.entries[0].folder, "Network");
+        assert_eq!(result.entries[0].password.expose_secret(), "tunnel & key");
+    }
+
+    #[test]
+    fn test_reject_wrong_credentials() {
+        assert!(parse(FIXTURE, &"wrong".into(), None).is_err());
+        assert!(parse(KEYFILE_FIXTURE, &password(), None).is_err());
+    }
+
+    #[test]
+    fn test_reject_tampered_file() {
+        let mut data = FIXTURE.to_vec();
+        let last = data.len() - 40;
+        data[last] ^= 1;
+
+        assert!(parse(&data, &password(), None).is_err());
+        assert!(parse(b"Service,Username,Password", &password(), None).is_err());
+    }
+
+    #[test]
+    fn test_is_kdbx() {
+        assert!(is_kdbx(FIXTURE));
+        assert!(!is_kdbx(b"Service,Username,Password"));
+    }
+
+    #[test]
+    fn test_keyfile_formats() {
+        let raw = [7u8; 32];
+        assert_eq!(&keyfile_key(&raw).unwrap()[..], &raw);
+
+        let hex = "07".repeat(32);
+        assert_eq!(&keyfile_key(hex.as_bytes()).unwrap()[..], &raw);
+
+        let other = b"any file can be a key file";
+        assert_eq!(
+            &keyfile_key(other).unwrap()[..],
+            digest::digest(&digest::SHA256, other).as_ref()
+        );
+    }
+}
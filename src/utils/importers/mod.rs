@@ -0,0 +1,163 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+use crate::prelude::PasswordEntry;
+
+pub mod bitwarden;
+pub mod csv_mapping;
+pub mod kdbx;
+pub mod plan;
+
+/// The entries read from a foreign export and the items that could not be mapped.
+#[derive(Debug, Default)]
+pub struct ImportResult {
+    pub entries: Vec<PasswordEntry>,
+    pub unmapped: Vec<String>,
+}
+
+/// Set the timestamps of an imported entry from the values found in the import file.
+///
+/// Empty values keep the current time. A missing creation time falls back to the update
+/// time, so the creation time is never later than the update time.
+///
+/// # Arguments
+///
+/// * `entry` - The imported entry.
+/// * `created_at` - The creation time from the import file, possibly empty.
+/// * `updated_at` - The update time from the import file, possibly empty.
+///
+/// # Returns
+///
+/// A `Result` containing `()` or an error.
+///
+/// # Errors
+///
+/// An error will be returned if a non-empty value is not a supported date format.
+pub fn set_timestamps(
+    entry: &mut PasswordEntry,
+    created_at: &str,
+    updated_at: &str,
+) -> Result<(), String> {
+    let created_at = parse_timestamp(created_at)?;
+    let updated_at = parse_timestamp(updated_at)?;
+
+    if let Some(updated_at) = &updated_at {
+        entry.updated_at = updated_at.clone();
+    }
+    if let Some(created_at) = created_at.or(updated_at) {
+        entry.created_at = created_at;
+    }
+
+    Ok(())
+}
+
+/// Normalize a timestamp to RFC 3339 in UTC.
+///
+/// Besides RFC 3339, ISO dates with or without a time, `dd.mm.yyyy` and `mm/dd/yyyy`
+/// dates and Unix timestamps in seconds or milliseconds are accepted. Values without a
+/// time zone are read as UTC.
+///
+/// # Arguments
+///
+/// * `value` - The timestamp to parse.
+///
+/// # Returns
+///
+/// A `Result` containing the normalized timestamp, or `None` if the value is empty.
+///
+/// # Errors
+///
+/// An error will be returned if the value is not a supported date format.
+pub fn parse_timestamp(value: &str) -> Result<Option<String>, String> {
+    const DATE_TIME_FORMATS: &[&str] = &[
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+        "%Y/%m/%d %H:%M:%S",
+        "%d.%m.%Y %H:%M:%S",
+        "%d.%m.%Y %H:%M",
+        "%m/%d/%Y %H:%M:%S",
+        "%m/%d/%Y %H:%M",
+    ];
+    const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%Y/%m/%d", "%d.%m.%Y", "%m/%d/%Y"];
+
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    let parsed = if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        Some(timestamp.with_timezone(&Utc))
+    } else if value.bytes().all(|b| b.is_ascii_digit()) {
+        let number: i64 = value
+            .parse()
+            .map_err(|_| format!("Invalid timestamp: {}", value))?;
+        // Thirteen digits are milliseconds until the year 2286.
+        if value.len() >= 12 {
+            DateTime::from_timestamp_millis(number)
+        } else {
+            DateTime::from_timestamp(number, 0)
+        }
+    } else {
+        DATE_TIME_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+            .or_else(|| {
+                DATE_FORMATS
+                    .iter()
+                    .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            })
+            .map(|timestamp| timestamp.and_utc())
+    };
+
+    parsed
+        .map(|timestamp| Some(timestamp.to_rfc3339()))
+        .ok_or_else(|| format!("Unsupported date format: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        let expected = Some("2024-03-05T14:30:00+00:00".to_string());
+
+        assert_eq!(
+            parse_timestamp("2024-03-05T15:30:00+01:00").unwrap(),
+            expected
+        );
+        assert_eq!(parse_timestamp("2024-03-05 14:30:00").unwrap(), expected);
+        assert_eq!(parse_timestamp("05.03.2024 14:30").unwrap(), expected);
+        assert_eq!(parse_timestamp("03/05/2024 14:30:00").unwrap(), expected);
+        assert_eq!(parse_timestamp("1709649000").unwrap(), expected);
+        assert_eq!(parse_timestamp("1709649000000").unwrap(), expected);
+        assert_eq!(
+            parse_timestamp("2024-03-05").unwrap(),
+            Some("2024-03-05T00:00:00+00:00".to_string())
+        );
+        assert_eq!(parse_timestamp(" ").unwrap(), None);
+        assert!(parse_timestamp("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_set_timestamps() {
+        let mut entry = PasswordEntry::new(
+            "service".to_string(),
+            "user".to_string(),
+            "secret".into(),
+            "".to_string(),
+            "".to_string(),
+        )
+        .unwrap();
+
+        set_timestamps(&mut entry, "", "2024-03-05").unwrap();
+        assert_eq!(entry.created_at, "2024-03-05T00:00:00+00:00");
+        assert_eq!(entry.updated_at, "2024-03-05T00:00:00+00:00");
+
+        set_timestamps(&mut entry, "2020-01-01", "").unwrap();
+        assert_eq!(entry.created_at, "2020-01-01T00:00:00+00:00");
+
+        assert!(set_timestamps(&mut entry, "soon", "").is_err());
+    }
+}
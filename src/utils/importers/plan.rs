@@ -0,0 +1,348 @@
+use chrono::{DateTime, FixedOffset};
+
+use crate::prelude::{ConflictStrategy, PasswordEntry};
+
+/// What an import will do with the entries of a file.
+#[derive(Debug, Default)]
+pub struct ImportPlan {
+    /// Entries that will be created.
+    pub added: Vec<PasswordEntry>,
+    /// Existing entries that will be overwritten, with the names of the changed fields.
+    pub updated: Vec<(PasswordEntry, Vec<&'static str>)>,
+    /// Entries that will not be imported, with the reason.
+    pub skipped: Vec<(PasswordEntry, &'static str)>,
+}
+
+impl ImportPlan {
+    /// Decide what to do with each imported entry.
+    ///
+    /// Entries are duplicates if their service, username and URL match, ignoring case.
+    /// Duplicates that are identical to the stored entry are always skipped, the others
+    /// are resolved with the conflict strategy. Duplicates within the imported entries
+    /// are detected as well. IDs of the imported entries belong to the exporting vault and
+    /// are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `existing` - The entries already stored in the vault.
+    /// * `incoming` - The entries read from the import file.
+    /// * `strategy` - How to resolve entries that already exist.
+    ///
+    /// # Returns
+    ///
+    /// A new `ImportPlan` instance.
+    pub fn new(
+        existing: Vec<PasswordEntry>,
+        incoming: Vec<PasswordEntry>,
+        strategy: ConflictStrategy,
+    ) -> Self {
+        let mut plan = Self::default();
+        let mut known = existing;
+
+        for mut entry in incoming {
+            entry.id = None;
+            let Some(index) = known
+                .iter()
+                .position(|known| duplicate_key(known) == duplicate_key(&entry))
+            else {
+                known.push(entry.clone());
+                plan.added.push(entry);
+                continue;
+            };
+
+            let current = &known[index];
+            let changes = changed_fields(current, &entry);
+            if changes.is_empty() {
+                plan.skipped.push((entry, "unchanged"));
+                continue;
+            }
+
+            let overwrite = match strategy {
+                ConflictStrategy::Skip => false,
+                ConflictStrategy::Overwrite => true,
+                ConflictStrategy::Newest => is_newer(&entry, current),
+                ConflictStrategy::KeepBoth => {
+                    known.push(entry.clone());
+                    plan.added.push(entry);
+                    continue;
+                }
+            };
+            if !overwrite {
+                let reason = match strategy {
+                    ConflictStrategy::Newest => "stored entry is newer",
+                    _ => "already exists",
+                };
+                plan.skipped.push((entry, reason));
+                continue;
+            }
+
+            match current.id {
+                Some(id) => {
+                    let mut entry = entry;
+                    entry.id = Some(id);
+                    entry.created_at = current.created_at.clone();
+                    known[index] = entry.clone();
+                    plan.updated.push((entry, changes));
+                }
+                None => {
+                    // The duplicate was added earlier in this import and is not stored yet.
+                    let added = plan
+                        .added
+                        .iter_mut()
+                        .find(|added| duplicate_key(added) == duplicate_key(&entry));
+                    if let Some(added) = added {
+                        *added = entry.clone();
+                    }
+                    known[index] = entry;
+                }
+            }
+        }
+
+        plan
+    }
+
+    /// Check if the plan would not change the vault.
+    ///
+    /// # Returns
+    ///
+    /// A `bool` indicating if no entries would be added or updated.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty()
+    }
+
+    /// Describe every planned change, one line per entry.
+    ///
+    /// Added entries are prefixed with `+`, updated entries with `~` and skipped entries
+    /// with `=`.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` of lines describing the plan.
+    pub fn diff(&self) -> Vec<String> {
+        let added = self
+            .added
+            .iter()
+            .map(|entry| format!("+ {}", describe(entry)));
+        let updated = self
+            .updated
+            .iter()
+            .map(|(entry, changes)| format!("~ {}: {}", describe(entry), changes.join(", ")));
+        let skipped = self
+            .skipped
+            .iter()
+            .map(|(entry, reason)| format!("= {}: {}", describe(entry), reason));
+
+        added.chain(updated).chain(skipped).collect()
+    }
+}
+
+/// The fields that identify duplicate entries.
+fn duplicate_key(entry: &PasswordEntry) -> (String, String, String) {
+    (
+        entry.service.trim().to_lowercase(),
+        entry.username.trim().to_lowercase(),
+        entry.url.trim().trim_end_matches('/').to_lowercase(),
+    )
+}
+
+/// The names of the fields that differ between two duplicate entries.
+fn changed_fields(current: &PasswordEntry, incoming: &PasswordEntry) -> Vec<&'static str> {
+    [
+        ("password", current.password == incoming.password),
+        ("notes", current.notes == incoming.notes),
+        ("folder", current.folder == incoming.folder),
+        ("tags", current.tags == incoming.tags),
+        ("totp", current.totp == incoming.totp),
+        (
+            "custom fields",
+            current.custom_fields == incoming.custom_fields,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, equal)| !equal)
+    .map(|(name, _)| name)
+    .collect()
+}
+
+/// Check if the incoming entry was updated after the stored one.
+fn is_newer(incoming: &PasswordEntry, current: &PasswordEntry) -> bool {
+    let parse = |value: &str| DateTime::<FixedOffset>::parse_from_rfc3339(value).ok();
+    match (parse(&incoming.updated_at), parse(&current.updated_at)) {
+        (Some(incoming), Some(current)) => incoming > current,
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+/// A short, secret free description of an entry.
+fn describe(entry: &PasswordEntry) -> String {
+    let mut description = entry.service.clone();
+    if !entry.username.is_empty() {
+        description.push_str(&format!(" ({})", entry.username));
+    }
+    if !entry.url.is_empty() {
+        description.push_str(&format!(" {}", entry.url));
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_entry(service: &str, password: &str, updated_at: &str) -> PasswordEntry {
+        let mut entry = PasswordEntry::new(
+            service.to_string(),
+            "user".to_string(),
+            password.into(),
+            "https://example.com".to_string(),
+            "".to_string(),
+        )
+        .unwrap();
+        entry.updated_at = updated_at.to_string();
+        entry
+    }
+
+    fn create_stored_entry(service: &str, password: &str, updated_at: &str) -> PasswordEntry {
+        let mut entry = create_test_entry(service, password, updated_at);
+        entry.id = Some(1);
+        entry.created_at = "2020-01-01T00:00:00+00:00".to_string();
+        entry
+    }
+
+    fn plan(strategy: ConflictStrategy) -> ImportPlan {
+        let existing = vec![
+            create_stored_entry("Mail", "old", "2024-01-01T00:00:00+00:00"),
+            create_stored_entry("Bank", "same", "2024-01-01T00:00:00+00:00"),
+        ];
+        let incoming = vec![
+            create_test_entry("mail", "new", "2025-01-01T00:00:00+00:00"),
+            create_test_entry("Bank", "same", "2025-01-01T00:00:00+00:00"),
+            create_test_entry("Shop", "shop", "2025-01-01T00:00:00+00:00"),
+        ];
+        ImportPlan::new(existing, incoming, strategy)
+    }
+
+    #[test]
+    fn test_skip() {
+        let plan = plan(ConflictStrategy::Skip);
+
+        assert_eq!(plan.added.len(), 1);
+        assert_eq!(plan.added[0].service, "Shop");
+        assert!(plan.updated.is_empty());
+        assert_eq!(plan.skipped.len(), 2);
+        assert_eq!(plan.skipped[0].1, "already exists");
+        assert_eq!(plan.skipped[1].1, "unchanged");
+    }
+
+    #[test]
+    fn test_overwrite() {
+        let plan = plan(ConflictStrategy::Overwrite);
+
+        assert_eq!(plan.updated.len(), 1);
+        let (entry, changes) = &plan.updated[0];
+        assert_eq!(entry.id, Some(1));
+        assert_eq!(entry.password.expose_secret(), "new");
+        assert_eq!(entry.created_at, "2020-01-01T00:00:00+00:00");
+        assert_eq!(changes, &["password"]);
+        assert_eq!(plan.skipped.len(), 1);
+    }
+
+    #[test]
+    fn test_keep_both() {
+        let plan = plan(ConflictStrategy::KeepBoth);
+
+        assert_eq!(plan.added.len(), 2);
+        assert!(plan.updated.is_empty());
+        assert_eq!(plan.skipped.len(), 1);
+    }
+
+    #[test]
+    fn test_newest() {
+        let plan = plan(ConflictStrategy::Newest);
+        assert_eq!(plan.updated.len(), 1);
+
+        let existing = vec![create_stored_entry(
+            "Mail",
+            "old",
+            "2026-01-01T00:00:00+00:00",
+        )];
+        let incoming = vec![create_test_entry(
+            "Mail",
+            "new",
+            "2025-01-01T00:00:00+00:00",
+        )];
+        let plan = ImportPlan::new(existing, incoming, ConflictStrategy::Newest);
+        assert!(plan.is_empty());
+        assert_eq!(plan.skipped[0].1, "stored entry is newer");
+    }
+
+    #[test]
+    fn test_duplicates_within_import() {
+        let incoming = vec![
+            create_test_entry("Mail", "first", ""),
+            create_test_entry("Mail", "second", ""),
+        ];
+
+        let plan = ImportPlan::new(Vec::new(), incoming.clone(), ConflictStrategy::Skip);
+        assert_eq!(plan.added.len(), 1);
+        assert_eq!(plan.added[0].password.expose_secret(), "first");
+
+        let plan = ImportPlan::new(Vec::new(), incoming, ConflictStrategy::Overwrite);
+        assert_eq!(plan.added.len(), 1);
+        assert_eq!(plan.added[0].password.expose_secret(), "second");
+    }
+
+    #[test]
+    fn test_ids_of_other_vaults() {
+        let existing = vec![create_stored_entry(
+            "Bank",
+            "bank",
+            "2024-01-01T00:00:00+00:00",
+        )];
+        let mut incoming = vec![
+            create_test_entry("Mail", "first", ""),
+            create_test_entry("Mail", "second", ""),
+        ];
+        // IDs from the exporting vault, one of them taken by an unrelated local entry.
+        incoming[0].id = Some(1);
+        incoming[1].id = Some(2);
+
+        for strategy in [ConflictStrategy::Overwrite, ConflictStrategy::Newest] {
+            let plan = ImportPlan::new(existing.clone(), incoming.clone(), strategy);
+            assert!(plan.updated.is_empty());
+            assert_eq!(plan.added.len(), 1);
+            assert_eq!(plan.added[0].id, None);
+        }
+    }
+
+    #[test]
+    fn test_changed_tags() {
+        let existing = vec![create_stored_entry(
+            "Mail",
+            "same",
+            "2024-01-01T00:00:00+00:00",
+        )];
+        let mut incoming = create_test_entry("Mail", "same", "2025-01-01T00:00:00+00:00");
+        incoming.tags = vec!["work".to_string()];
+
+        let plan = ImportPlan::new(existing, vec![incoming], ConflictStrategy::Overwrite);
+        assert_eq!(plan.updated.len(), 1);
+        assert_eq!(plan.updated[0].1, ["tags"]);
+    }
+
+    #[test]
+    fn test_diff() {
+        let diff = plan(ConflictStrategy::Overwrite).diff();
+
+        assert_eq!(
+            diff,
+            [
+                "+ Shop (user) https://example.com",
+                "~ mail (user) https://example.com: password",
+                "= Bank (user) https://example.com: unchanged",
+            ]
+        );
+        assert!(!diff.iter().any(|line| line.contains("new")));
+    }
+}
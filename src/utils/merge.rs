@@ -0,0 +1,578 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+
+use crate::prelude::{Database, DatabaseError, PasswordEntry};
+use crate::utils::database::ENTRY_FIELDS;
+
+/// The entries and recorded deletions of one vault.
+#[derive(Debug, Default)]
+pub struct VaultState {
+    pub entries: Vec<PasswordEntry>,
+    /// The time each entry was deleted, keyed by its UUID.
+    pub deleted: BTreeMap<String, String>,
+}
+
+/// The vault a value was taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Local,
+    Other,
+}
+
+/// A change made in both vaults since they were last merged.
+#[derive(Debug, Serialize)]
+pub struct Conflict {
+    /// A short, secret free description of the entry.
+    pub entry: String,
+    /// The conflicting field, or `entry` if the entry was deleted in one vault.
+    pub field: &'static str,
+    /// The vault whose change was kept.
+    pub kept: Side,
+}
+
+/// The result of merging two vaults, which both vaults are brought to.
+#[derive(Debug, Default)]
+pub struct MergeResult {
+    pub entries: Vec<PasswordEntry>,
+    pub deleted: BTreeMap<String, String>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// How many entries applying a merge changed in one vault.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MergeChanges {
+    pub added: usize,
+    pub updated: usize,
+    pub deleted: usize,
+}
+
+impl VaultState {
+    /// Read the entries and deletions of a vault.
+    ///
+    /// # Arguments
+    ///
+    /// * `database` - The unlocked vault.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `VaultState` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the vault cannot be read or an entry is corrupt.
+    pub fn read(database: &Database) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            entries: database.read()?,
+            deleted: database.read_deleted()?,
+        })
+    }
+}
+
+impl Conflict {
+    /// Describe the conflict and how it was resolved.
+    ///
+    /// # Returns
+    ///
+    /// A `String` describing the conflict.
+    pub fn describe(&self) -> String {
+        let kept = match self.kept {
+            Side::Local => "this vault",
+            Side::Other => "the other vault",
+        };
+        if self.field == "entry" {
+            format!(
+                "{}: deleted in one vault and changed in the other, kept the entry from {}",
+                self.entry, kept
+            )
+        } else {
+            format!(
+                "{}: {} changed in both vaults, kept the newer value from {}",
+                self.entry, self.field, kept
+            )
+        }
+    }
+}
+
+/// Merge two copies of a vault.
+///
+/// Entries are matched by UUID and merged field by field. A field changed in only one vault
+/// since the last merge takes that change. A field changed in both vaults takes the value
+/// with the newer modification time and is reported as a conflict; if both times are equal
+/// the greater value is kept, so both vaults resolve the tie the same way. Entries deleted
+/// in one vault are deleted in both unless they were changed after the deletion, in which
+/// case the changed entry is kept. Without a previous merge every differing field counts
+/// as a conflict.
+///
+/// # Arguments
+///
+/// * `local` - The state of this vault.
+/// * `other` - The state of the other vault.
+/// * `base` - When the vaults were last merged, if ever.
+///
+/// # Returns
+///
+/// The `MergeResult` both vaults should be brought to.
+pub fn merge(local: &VaultState, other: &VaultState, base: Option<&str>) -> MergeResult {
+    let base = base.and_then(parse_time);
+    let mut result = MergeResult {
+        deleted: local.deleted.clone(),
+        ..MergeResult::default()
+    };
+    for (uuid, deleted_at) in &other.deleted {
+        let newer = result
+            .deleted
+            .get(uuid)
+            .is_none_or(|known| parse_time(deleted_at) > parse_time(known));
+        if newer {
+            result.deleted.insert(uuid.clone(), deleted_at.clone());
+        }
+    }
+
+    let others: HashMap<&str, &PasswordEntry> = other
+        .entries
+        .iter()
+        .map(|entry| (entry.uuid.as_str(), entry))
+        .collect();
+    let mut candidates = Vec::new();
+    for entry in &local.entries {
+        match others.get(entry.uuid.as_str()) {
+            Some(theirs) => candidates.push((
+                merge_entry(entry, theirs, base, &mut result.conflicts),
+                None,
+            )),
+            None => candidates.push((entry.clone(), Some(Side::Local))),
+        }
+    }
+    let locals: HashMap<&str, &PasswordEntry> = local
+        .entries
+        .iter()
+        .map(|entry| (entry.uuid.as_str(), entry))
+        .collect();
+    for entry in &other.entries {
+        if !locals.contains_key(entry.uuid.as_str()) {
+            candidates.push((entry.clone(), Some(Side::Other)));
+        }
+    }
+
+    for (entry, only_in) in candidates {
+        let Some(deleted_at) = result.deleted.get(&entry.uuid) else {
+            result.entries.push(entry);
+            continue;
+        };
+
+        let modified = last_modified(&entry);
+        if modified > parse_time(deleted_at) {
+            result.deleted.remove(&entry.uuid);
+            if base.is_none_or(|base| modified > Some(base)) {
+                result.conflicts.push(Conflict {
+                    entry: describe(&entry),
+                    field: "entry",
+                    kept: only_in.unwrap_or(Side::Local),
+                });
+            }
+            result.entries.push(entry);
+        }
+    }
+
+    result
+}
+
+/// Bring a vault to the result of a merge.
+///
+/// # Arguments
+///
+/// * `database` - The unlocked vault.
+/// * `result` - The result of the merge.
+///
+/// # Returns
+///
+/// A `Result` containing the `MergeChanges` made to the vault or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the vault cannot be read or written.
+pub fn apply(
+    database: &Database,
+    result: &MergeResult,
+) -> Result<MergeChanges, Box<dyn std::error::Error>> {
+    let existing: HashMap<String, PasswordEntry> = database
+        .read()?
+        .into_iter()
+        .map(|entry| (entry.uuid.clone(), entry))
+        .collect();
+    let mut changes = MergeChanges::default();
+
+    for entry in &result.entries {
+        match existing
+            .get(&entry.uuid)
+            .and_then(|current| Some((current, current.id?)))
+        {
+            None => {
+                let mut entry = entry.clone();
+                entry.id = None;
+                database.create(&entry)?;
+                changes.added += 1;
+            }
+            Some((current, id)) if differs(current, entry) => {
+                database.update(id, entry.clone())?;
+                changes.updated += 1;
+            }
+            Some(_) => {}
+        }
+    }
+
+    let known = database.read_deleted()?;
+    for (uuid, deleted_at) in &result.deleted {
+        if existing.contains_key(uuid) {
+            changes.deleted += 1;
+        } else if known.get(uuid) == Some(deleted_at) {
+            continue;
+        }
+        database.mark_deleted(uuid, deleted_at)?;
+    }
+
+    Ok(changes)
+}
+
+/// Merge the fields of an entry that exists in both vaults.
+fn merge_entry(
+    local: &PasswordEntry,
+    other: &PasswordEntry,
+    base: Option<DateTime<FixedOffset>>,
+    conflicts: &mut Vec<Conflict>,
+) -> PasswordEntry {
+    let mut merged = local.clone();
+
+    for field in ENTRY_FIELDS {
+        let local_time = parse_time(local.field_time(field));
+        let other_time = parse_time(other.field_time(field));
+
+        if local.same_field(other, field) {
+            let newest = if other_time > local_time {
+                other
+            } else {
+                local
+            };
+            merged
+                .field_times
+                .insert(field.to_string(), newest.field_time(field).to_string());
+            continue;
+        }
+
+        let other_wins = match other_time.cmp(&local_time) {
+            Ordering::Equal => other.cmp_field(local, field) == Ordering::Greater,
+            ordering => ordering == Ordering::Greater,
+        };
+        let kept = if other_wins {
+            merged.copy_field(other, field);
+            Side::Other
+        } else {
+            merged
+                .field_times
+                .insert(field.to_string(), local.field_time(field).to_string());
+            Side::Local
+        };
+
+        let changed_since = |time| base.is_none_or(|base| time > Some(base));
+        if changed_since(local_time) && changed_since(other_time) {
+            conflicts.push(Conflict {
+                entry: describe(local),
+                field,
+                kept,
+            });
+        }
+    }
+
+    if parse_time(&other.updated_at) > parse_time(&local.updated_at) {
+        merged.updated_at = other.updated_at.clone();
+    }
+    if parse_time(&other.created_at) < parse_time(&local.created_at) {
+        merged.created_at = other.created_at.clone();
+    }
+    if merged.source.is_empty() || (!other.source.is_empty() && other.source < merged.source) {
+        merged.source = other.source.clone();
+    }
+
+    merged
+}
+
+/// Check if a stored entry differs from its merged version.
+fn differs(current: &PasswordEntry, merged: &PasswordEntry) -> bool {
+    current.updated_at != merged.updated_at
+        || current.source != merged.source
+        || ENTRY_FIELDS.iter().any(|field| {
+            !current.same_field(merged, field)
+                || current.field_time(field) != merged.field_time(field)
+        })
+}
+
+/// The last time any field of an entry was modified.
+fn last_modified(entry: &PasswordEntry) -> Option<DateTime<FixedOffset>> {
+    ENTRY_FIELDS
+        .iter()
+        .map(|field| parse_time(entry.field_time(field)))
+        .chain([parse_time(&entry.updated_at)])
+        .max()
+        .flatten()
+}
+
+/// Parse a stored timestamp. Invalid timestamps sort before every valid one.
+fn parse_time(value: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(value).ok()
+}
+
+/// A short, secret free description of an entry.
+fn describe(entry: &PasswordEntry) -> String {
+    if entry.username.is_empty() {
+        entry.service.clone()
+    } else {
+        format!("{} ({})", entry.service, entry.username)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::SecretString;
+
+    const BASE: &str = "2025-01-01T00:00:00+00:00";
+    const BEFORE: &str = "2024-06-01T00:00:00+00:00";
+    const AFTER: &str = "2025-02-01T00:00:00+00:00";
+    const LATER: &str = "2025-03-01T00:00:00+00:00";
+
+    fn create_test_entry(service: &str, password: &str, updated_at: &str) -> PasswordEntry {
+        let mut entry = PasswordEntry::new(
+            service.to_string(),
+            "user".to_string(),
+            password.into(),
+            "https://example.com".to_string(),
+            "".to_string(),
+        )
+        .unwrap();
+        entry.created_at = BEFORE.to_string();
+        entry.updated_at = updated_at.to_string();
+        for field in ENTRY_FIELDS {
+            entry
+                .field_times
+                .insert(field.to_string(), updated_at.to_string());
+        }
+        entry
+    }
+
+    fn edit(entry: &PasswordEntry, field: &str, value: &str, at: &str) -> PasswordEntry {
+        let mut edited = entry.clone();
+        match field {
+            "password" => edited.password = value.into(),
+            "notes" => edited.notes = value.to_string(),
+            _ => unreachable!(),
+        }
+        edited.updated_at = at.to_string();
+        edited.field_times.insert(field.to_string(), at.to_string());
+        edited
+    }
+
+    fn state(entries: Vec<PasswordEntry>) -> VaultState {
+        VaultState {
+            entries,
+            deleted: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_merge_changes_to_different_fields() {
+        let entry = create_test_entry("Mail", "secret", BEFORE);
+        let local = state(vec![edit(&entry, "notes", "local note", AFTER)]);
+        let other = state(vec![edit(&entry, "password", "changed", LATER)]);
+
+        let result = merge(&local, &other, Some(BASE));
+
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.entries.len(), 1);
+        let merged = &result.entries[0];
+        assert_eq!(merged.notes, "local note");
+        assert_eq!(merged.password.expose_secret(), "changed");
+        assert_eq!(merged.field_time("password"), LATER);
+        assert_eq!(merged.field_time("notes"), AFTER);
+        assert_eq!(merged.updated_at, LATER);
+    }
+
+    #[test]
+    fn test_conflict_keeps_newer_value() {
+        let entry = create_test_entry("Mail", "secret", BEFORE);
+        let newer = edit(&entry, "password", "newer", LATER);
+        let older = edit(&entry, "password", "older", AFTER);
+
+        let result = merge(
+            &state(vec![older.clone()]),
+            &state(vec![newer.clone()]),
+            Some(BASE),
+        );
+        assert_eq!(result.entries[0].password.expose_secret(), "newer");
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].field, "password");
+        assert_eq!(result.conflicts[0].kept, Side::Other);
+        assert_eq!(
+            result.conflicts[0].describe(),
+            "Mail (user): password changed in both vaults, kept the newer value from the other vault"
+        );
+
+        // The outcome does not depend on which vault runs the merge.
+        let result = merge(&state(vec![newer]), &state(vec![older]), Some(BASE));
+        assert_eq!(result.entries[0].password.expose_secret(), "newer");
+        assert_eq!(result.conflicts[0].kept, Side::Local);
+    }
+
+    #[test]
+    fn test_tie_is_resolved_the_same_in_both_vaults() {
+        let mut entry = create_test_entry("Mail", "secret", BEFORE);
+        entry.source = "import:csv:2025-01-01".to_string();
+        let mut first = edit(&entry, "password", "alpha", AFTER);
+        first = edit(&first, "notes", "zulu", AFTER);
+        let mut second = edit(&entry, "password", "bravo", AFTER);
+        second = edit(&second, "notes", "yankee", AFTER);
+        second.source = "import:csv:2024-01-01".to_string();
+
+        let forward = merge(
+            &state(vec![first.clone()]),
+            &state(vec![second.clone()]),
+            Some(BASE),
+        );
+        let backward = merge(&state(vec![second]), &state(vec![first]), Some(BASE));
+
+        let merged = &forward.entries[0];
+        assert_eq!(merged.password.expose_secret(), "bravo");
+        assert_eq!(merged.notes, "zulu");
+        assert_eq!(
+            serde_json::to_value(merged).unwrap(),
+            serde_json::to_value(&backward.entries[0]).unwrap()
+        );
+        assert_eq!(forward.conflicts.len(), 2);
+        assert_eq!(forward.conflicts[0].field, "password");
+        assert_eq!(forward.conflicts[0].kept, Side::Other);
+        assert_eq!(backward.conflicts[0].kept, Side::Local);
+    }
+
+    #[test]
+    fn test_change_in_one_vault_is_not_a_conflict() {
+        let entry = create_test_entry("Mail", "secret", BEFORE);
+        let local = state(vec![entry.clone()]);
+        let other = state(vec![edit(&entry, "password", "changed", AFTER)]);
+
+        let result = merge(&local, &other, Some(BASE));
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.entries[0].password.expose_secret(), "changed");
+
+        // Without a previous merge it is unknown which vault changed the field.
+        let result = merge(&local, &other, None);
+        assert_eq!(result.conflicts.len(), 1);
+    }
+
+    #[test]
+    fn test_new_entries_are_added() {
+        let local = state(vec![create_test_entry("Mail", "secret", AFTER)]);
+        let other = state(vec![create_test_entry("Bank", "money", AFTER)]);
+
+        let result = merge(&local, &other, Some(BASE));
+
+        assert!(result.conflicts.is_empty());
+        let services: Vec<&str> = result.entries.iter().map(|e| e.service.as_str()).collect();
+        assert_eq!(services, ["Mail", "Bank"]);
+    }
+
+    #[test]
+    fn test_deletions() {
+        let entry = create_test_entry("Mail", "secret", BEFORE);
+        let mut local = state(Vec::new());
+        local.deleted.insert(entry.uuid.clone(), AFTER.to_string());
+
+        // An entry unchanged since the deletion stays deleted.
+        let result = merge(&local, &state(vec![entry.clone()]), Some(BASE));
+        assert!(result.entries.is_empty());
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.deleted.get(&entry.uuid).unwrap(), AFTER);
+
+        // An entry changed after the deletion is kept.
+        let changed = edit(&entry, "password", "changed", LATER);
+        let result = merge(&local, &state(vec![changed]), Some(BASE));
+        assert_eq!(result.entries.len(), 1);
+        assert!(result.deleted.is_empty());
+        assert_eq!(result.conflicts[0].field, "entry");
+        assert_eq!(result.conflicts[0].kept, Side::Other);
+    }
+
+    #[test]
+    fn test_apply_syncs_both_vaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let master_password = SecretString::from("master");
+        let salt = [1u8; 16];
+        let laptop = Database::new(dir.path().join("laptop.db"), &master_password, &salt).unwrap();
+        let desktop =
+            Database::new(dir.path().join("desktop.db"), &master_password, &salt).unwrap();
+
+        let shared = create_test_entry("Mail", "secret", BEFORE);
+        let removed = create_test_entry("Old", "old", BEFORE);
+        for database in [&laptop, &desktop] {
+            database.create(&shared).unwrap();
+            database.create(&removed).unwrap();
+        }
+        laptop
+            .create(&create_test_entry("Bank", "money", AFTER))
+            .unwrap();
+        let id = desktop.find_by_uuid(&removed.uuid).unwrap().unwrap();
+        desktop.delete(id).unwrap();
+        let id = desktop.find_by_uuid(&shared.uuid).unwrap().unwrap();
+        desktop
+            .update(id, edit(&shared, "password", "changed", AFTER))
+            .unwrap();
+
+        let result = merge(
+            &VaultState::read(&laptop).unwrap(),
+            &VaultState::read(&desktop).unwrap(),
+            Some(BASE),
+        );
+        assert!(result.conflicts.is_empty());
+
+        assert_eq!(
+            apply(&laptop, &result).unwrap(),
+            MergeChanges {
+                added: 0,
+                updated: 1,
+                deleted: 1
+            }
+        );
+        assert_eq!(
+            apply(&desktop, &result).unwrap(),
+            MergeChanges {
+                added: 1,
+                updated: 0,
+                deleted: 0
+            }
+        );
+
+        let mut laptop_entries = laptop.read().unwrap();
+        let mut desktop_entries = desktop.read().unwrap();
+        laptop_entries.sort_by(|a, b| a.uuid.cmp(&b.uuid));
+        desktop_entries.sort_by(|a, b| a.uuid.cmp(&b.uuid));
+        assert_eq!(laptop_entries.len(), 2);
+        for (a, b) in laptop_entries.iter().zip(&desktop_entries) {
+            assert!(!differs(a, b));
+        }
+        assert_eq!(
+            laptop.read_deleted().unwrap(),
+            desktop.read_deleted().unwrap()
+        );
+
+        // Merging again changes nothing.
+        let result = merge(
+            &VaultState::read(&laptop).unwrap(),
+            &VaultState::read(&desktop).unwrap(),
+            None,
+        );
+        assert_eq!(apply(&laptop, &result).unwrap(), MergeChanges::default());
+        assert_eq!(apply(&desktop, &result).unwrap(), MergeChanges::default());
+    }
+}
@@ -1,4 +1,23 @@
+pub mod api;
+pub mod backup;
+pub mod clipboard;
 pub mod config;
 pub mod database;
+pub mod domain;
 pub mod encryption;
 pub mod errors;
+pub mod export;
+pub mod exporters;
+pub mod git_credential;
+pub mod importers;
+pub mod merge;
+pub mod native_messaging;
+pub mod reference;
+pub mod secret;
+#[cfg(unix)]
+pub mod socket;
+#[cfg(unix)]
+pub mod ssh_agent;
+pub mod ssh_key;
+pub mod sync;
+pub mod totp;
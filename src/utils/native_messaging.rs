@@ -0,0 +1,114 @@
+use std::io::{ErrorKind, Read, Write};
+
+use serde_json::Value;
+
+use crate::prelude::CliError;
+
+/// The largest message a browser may send. Logins are small, so this is far below the
+/// limit of the browsers.
+const MAX_REQUEST: usize = 1024 * 1024;
+/// The largest message a native host may send, as Chrome and Firefox specify.
+const MAX_RESPONSE: usize = 1024 * 1024;
+
+/// Read a message of the browser: a 32-bit length in native byte order followed by as
+/// many bytes of JSON.
+///
+/// # Arguments
+///
+/// * `reader` - The standard input of the host.
+///
+/// # Returns
+///
+/// A `Result` containing the message, `None` once the browser closed the input, or an
+/// error.
+///
+/// # Errors
+///
+/// An error will be returned if the input cannot be read, ends within a message, the
+/// message is too large or it is not JSON.
+pub fn read_message(mut reader: impl Read) -> Result<Option<Value>, Box<dyn std::error::Error>> {
+    let mut length = [0; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let length = u32::from_ne_bytes(length) as usize;
+    if length > MAX_REQUEST {
+        return Err(
+            CliError::InvalidInput(format!("Message of {} bytes is too large", length)).into(),
+        );
+    }
+
+    let mut message = vec![0; length];
+    reader.read_exact(&mut message)?;
+    serde_json::from_slice(&message)
+        .map(Some)
+        .map_err(|e| CliError::InvalidInput(format!("Invalid message: {}", e)).into())
+}
+
+/// Write a message to the browser, framed like the messages it sends.
+///
+/// # Arguments
+///
+/// * `writer` - The standard output of the host.
+/// * `message` - The message.
+///
+/// # Returns
+///
+/// A `Result` containing `()` or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the message is larger than browsers accept or the output
+/// cannot be written.
+pub fn write_message(
+    mut writer: impl Write,
+    message: &Value,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let message = serde_json::to_vec(message)?;
+    if message.len() > MAX_RESPONSE {
+        return Err(format!(
+            "Message of {} bytes is too large for the browser",
+            message.len()
+        )
+        .into());
+    }
+
+    writer.write_all(&(message.len() as u32).to_ne_bytes())?;
+    writer.write_all(&message)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_messages() {
+        let mut framed = Vec::new();
+        write_message(&mut framed, &json!({ "action": "get-logins" })).unwrap();
+        write_message(&mut framed, &json!([1, 2])).unwrap();
+        assert_eq!(framed[..4], 23u32.to_ne_bytes());
+
+        let mut reader = framed.as_slice();
+        assert_eq!(
+            read_message(&mut reader).unwrap(),
+            Some(json!({ "action": "get-logins" }))
+        );
+        assert_eq!(read_message(&mut reader).unwrap(), Some(json!([1, 2])));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+
+        // Truncated, oversized and invalid messages fail.
+        assert!(read_message(&framed[..10]).is_err());
+        assert!(read_message(&u32::MAX.to_ne_bytes()[..]).is_err());
+        let mut invalid = 1u32.to_ne_bytes().to_vec();
+        invalid.push(b'{');
+        assert!(read_message(invalid.as_slice()).is_err());
+
+        let large = json!("x".repeat(MAX_RESPONSE));
+        assert!(write_message(&mut Vec::new(), &large).is_err());
+    }
+}
@@ -0,0 +1,460 @@
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+
+use chrono::Utc;
+use zeroize::Zeroizing;
+
+use crate::prelude::{CliError, PasswordEntry, SecretString};
+use crate::utils::totp;
+
+/// The prefix of references on the command line.
+pub const SCHEME: &str = "vault://";
+/// What secrets in the output of a command are replaced with.
+const MASK: &[u8] = b"********";
+
+/// A field of an entry, written as `vault://entry/field` or `entry/field`.
+///
+/// The entry is an ID, a UUID or a service name and may contain slashes; the field is
+/// everything after the last slash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub entry: String,
+    pub field: String,
+}
+
+impl Reference {
+    /// Parse a reference, with or without the `vault://` prefix.
+    ///
+    /// # Arguments
+    ///
+    /// * `reference` - The reference to parse.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the reference or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the entry or the field is missing.
+    pub fn parse(reference: &str) -> Result<Self, CliError> {
+        let path = reference.strip_prefix(SCHEME).unwrap_or(reference);
+        match path.rsplit_once('/') {
+            Some((entry, field)) if !entry.is_empty() && !field.is_empty() => Ok(Reference {
+                entry: entry.to_string(),
+                field: field.to_string(),
+            }),
+            _ => Err(CliError::InvalidInput(format!(
+                "Expected a reference like {}entry/field, got {}",
+                SCHEME, reference
+            ))),
+        }
+    }
+
+    /// Look up the value of the referenced field.
+    ///
+    /// The fields are `password`, `username`, `url`, `notes`, `service`, `folder`, `totp`
+    /// for the current code, or the name of a custom field.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The entries of the vault.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the value or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the entry or field does not exist, or if the entry
+    /// matches several entries.
+    pub fn resolve(&self, entries: &[PasswordEntry]) -> Result<SecretString, CliError> {
+        let entry = find_entry(entries, &self.entry)?;
+        let text = |value: &String| SecretString::new(value.clone());
+
+        let value = match self.field.to_lowercase().as_str() {
+            "password" => entry.password.clone(),
+            "username" => text(&entry.username),
+            "url" => text(&entry.url),
+            "notes" => text(&entry.notes),
+            "service" => text(&entry.service),
+            "folder" => text(&entry.folder),
+            "totp" if entry.totp.is_empty() => {
+                return Err(CliError::NotFound(format!("{} has no TOTP secret", self)))
+            }
+            "totp" => totp::generate(&entry.totp, Utc::now().timestamp().max(0) as u64)
+                .map_err(|e| CliError::InvalidInput(format!("{}: {}", self, e)))?,
+            _ => entry
+                .custom_fields
+                .iter()
+                .find(|field| field.name == self.field)
+                .or_else(|| {
+                    entry
+                        .custom_fields
+                        .iter()
+                        .find(|field| field.name.eq_ignore_ascii_case(&self.field))
+                })
+                .map(|field| field.value.clone())
+                .ok_or_else(|| CliError::NotFound(format!("{} has no such field", self)))?,
+        };
+        Ok(value)
+    }
+
+    /// Check if the field holds a secret that must not show up in output: the password, the
+    /// TOTP code or a custom field.
+    ///
+    /// # Returns
+    ///
+    /// A `bool` indicating if the value of the field is secret.
+    pub fn is_secret(&self) -> bool {
+        !matches!(
+            self.field.to_lowercase().as_str(),
+            "username" | "url" | "notes" | "service" | "folder"
+        )
+    }
+}
+
+/// Find the entry a selector refers to, without asking the user.
+///
+/// The selector is tried as an ID and a UUID, then as a service name ignoring case.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to search.
+/// * `selector` - The ID, UUID or service name.
+///
+/// # Returns
+///
+/// A `Result` containing the entry or an error.
+///
+/// # Errors
+///
+/// An error will be returned if no entry or several entries match.
+pub fn find_entry<'a>(
+    entries: &'a [PasswordEntry],
+    selector: &str,
+) -> Result<&'a PasswordEntry, CliError> {
+    let id = selector.parse::<i32>().ok();
+    let matches = entries
+        .iter()
+        .filter(|entry| id.is_some() && entry.id == id)
+        .chain(entries.iter().filter(|entry| entry.uuid == selector))
+        .collect::<Vec<_>>();
+    let matches = if matches.is_empty() {
+        entries
+            .iter()
+            .filter(|entry| entry.service.eq_ignore_ascii_case(selector))
+            .collect()
+    } else {
+        matches
+    };
+
+    match matches.as_slice() {
+        [entry] => Ok(entry),
+        [] => Err(CliError::NotFound(format!("No entry matches {}", selector))),
+        _ => Err(CliError::InvalidInput(format!(
+            "{} matches {} entries, refer to it by ID or UUID",
+            selector,
+            matches.len()
+        ))),
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}/{}", SCHEME, self.entry, self.field)
+    }
+}
+
+/// Render a template, replacing `{{ vault "entry/field" }}` placeholders with the values
+/// of the referenced fields. Other `{{ ... }}` blocks are kept as they are.
+///
+/// # Arguments
+///
+/// * `template` - The template to render.
+/// * `entries` - The entries of the vault.
+///
+/// # Returns
+///
+/// A `Result` containing the rendered text or an error.
+///
+/// # Errors
+///
+/// An error will be returned if a placeholder is malformed or cannot be resolved. The
+/// error names the line of the placeholder.
+pub fn render(template: &str, entries: &[PasswordEntry]) -> Result<Zeroizing<String>, CliError> {
+    let mut output = Zeroizing::new(String::with_capacity(template.len()));
+    let mut position = 0;
+
+    while let Some(start) = template[position..].find("{{").map(|i| position + i) {
+        output.push_str(&template[position..start]);
+        let line = template[..start].matches('\n').count() + 1;
+        let error = |e: CliError| match e {
+            CliError::InvalidInput(msg) => {
+                CliError::InvalidInput(format!("line {}: {}", line, msg))
+            }
+            CliError::NotFound(msg) => CliError::NotFound(format!("line {}: {}", line, msg)),
+            e => e,
+        };
+
+        let Some(end) = template[start..].find("}}").map(|i| start + i) else {
+            if placeholder(&template[start + 2..]).is_some() {
+                return Err(error(CliError::InvalidInput(
+                    "The placeholder is not closed with }}".to_string(),
+                )));
+            }
+            position = start;
+            break;
+        };
+
+        match placeholder(&template[start + 2..end]) {
+            Some(reference) => {
+                let value = Reference::parse(&reference.map_err(error)?)
+                    .and_then(|reference| reference.resolve(entries))
+                    .map_err(error)?;
+                output.push_str(value.expose_secret());
+            }
+            None => output.push_str(&template[start..end + 2]),
+        }
+        position = end + 2;
+    }
+
+    output.push_str(&template[position..]);
+    Ok(output)
+}
+
+/// Parse the inside of a `{{ ... }}` block.
+///
+/// # Returns
+///
+/// `None` if the block is not a vault placeholder, else the quoted reference or an error if
+/// it is not quoted.
+fn placeholder(block: &str) -> Option<Result<String, CliError>> {
+    let block = block.trim_start();
+    let arguments = block.strip_prefix("vault")?;
+    if !arguments.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let arguments = arguments.trim();
+    Some(
+        arguments
+            .strip_prefix('"')
+            .and_then(|quoted| quoted.strip_suffix('"'))
+            .filter(|reference| !reference.contains('"'))
+            .map(str::to_string)
+            .ok_or_else(|| {
+                CliError::InvalidInput(format!(
+                    "Expected {{{{ vault \"entry/field\" }}}}, got {{{{ {} }}}}",
+                    block.trim_end()
+                ))
+            }),
+    )
+}
+
+/// Replaces secrets in the output of a command.
+pub struct Masker {
+    /// The byte strings to replace, longest first so a secret containing another is
+    /// replaced as a whole.
+    secrets: Vec<Zeroizing<Vec<u8>>>,
+}
+
+impl Masker {
+    /// Create a masker for the given secrets. The lines of multi-line secrets are masked
+    /// on their own, since output is masked line by line.
+    ///
+    /// # Arguments
+    ///
+    /// * `secrets` - The secrets to mask.
+    pub fn new(secrets: &[SecretString]) -> Self {
+        let mut masked = secrets
+            .iter()
+            .flat_map(|secret| secret.expose_secret().lines())
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.is_empty())
+            .map(|line| Zeroizing::new(line.as_bytes().to_vec()))
+            .collect::<Vec<_>>();
+        masked.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        masked.dedup();
+
+        Masker { secrets: masked }
+    }
+
+    /// Replace the secrets in a piece of output.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - The output to mask.
+    ///
+    /// # Returns
+    ///
+    /// The output with every secret replaced by `********`.
+    pub fn mask(&self, output: &[u8]) -> Vec<u8> {
+        let mut masked = Vec::with_capacity(output.len());
+        let mut position = 0;
+        while position < output.len() {
+            match self
+                .secrets
+                .iter()
+                .find(|secret| output[position..].starts_with(secret))
+            {
+                Some(secret) => {
+                    masked.extend_from_slice(MASK);
+                    position += secret.len();
+                }
+                None => {
+                    masked.push(output[position]);
+                    position += 1;
+                }
+            }
+        }
+        masked
+    }
+
+    /// Copy output line by line, masking the secrets in it.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The output of the command.
+    /// * `writer` - Where to write the masked output.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the output cannot be read or written.
+    pub fn copy(&self, reader: impl Read, mut writer: impl Write) -> std::io::Result<()> {
+        let mut reader = BufReader::new(reader);
+        let mut line = Zeroizing::new(Vec::new());
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                return Ok(());
+            }
+            writer.write_all(&self.mask(&line))?;
+            writer.flush()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::CustomField;
+
+    fn create_entries() -> Vec<PasswordEntry> {
+        let mut database = PasswordEntry::new(
+            "prod-db".to_string(),
+            "admin".to_string(),
+            "db-secret".into(),
+            "postgres://db.example.com".to_string(),
+            String::new(),
+        )
+        .unwrap();
+        database.id = Some(1);
+        database.custom_fields.push(CustomField {
+            name: "API Key".to_string(),
+            value: "key-123".into(),
+            hidden: true,
+        });
+
+        let mut nested = PasswordEntry::new(
+            "gitlab.com/org".to_string(),
+            "bot".to_string(),
+            "gl-token".into(),
+            String::new(),
+            String::new(),
+        )
+        .unwrap();
+        nested.id = Some(2);
+
+        let mut duplicate = nested.clone();
+        duplicate.id = Some(3);
+        duplicate.service = "Twin".to_string();
+        let mut twin = duplicate.clone();
+        twin.id = Some(4);
+
+        vec![database, nested, duplicate, twin]
+    }
+
+    fn resolve(reference: &str) -> Result<String, CliError> {
+        Reference::parse(reference)?
+            .resolve(&create_entries())
+            .map(|value| value.expose_secret().to_string())
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("vault://prod-db/password").unwrap(), "db-secret");
+        assert_eq!(resolve("PROD-DB/username").unwrap(), "admin");
+        assert_eq!(resolve("1/api key").unwrap(), "key-123");
+        assert_eq!(
+            resolve("vault://gitlab.com/org/password").unwrap(),
+            "gl-token"
+        );
+
+        assert!(matches!(resolve("prod-db"), Err(CliError::InvalidInput(_))));
+        assert!(matches!(
+            resolve("missing/password"),
+            Err(CliError::NotFound(_))
+        ));
+        assert!(matches!(resolve("prod-db/pin"), Err(CliError::NotFound(_))));
+        assert!(matches!(
+            resolve("prod-db/totp"),
+            Err(CliError::NotFound(_))
+        ));
+        assert!(matches!(
+            resolve("twin/password"),
+            Err(CliError::InvalidInput(_))
+        ));
+        assert_eq!(resolve("4/password").unwrap(), "gl-token");
+    }
+
+    #[test]
+    fn test_is_secret() {
+        assert!(Reference::parse("db/password").unwrap().is_secret());
+        assert!(Reference::parse("db/API Key").unwrap().is_secret());
+        assert!(!Reference::parse("db/Username").unwrap().is_secret());
+    }
+
+    #[test]
+    fn test_render() {
+        let entries = create_entries();
+        let template = "user: {{ vault \"prod-db/username\" }}\npass: {{vault \"vault://prod-db/password\"}}\nname: {{ .Values.name }}\n";
+
+        let rendered = render(template, &entries).unwrap();
+        assert_eq!(
+            rendered.as_str(),
+            "user: admin\npass: db-secret\nname: {{ .Values.name }}\n"
+        );
+        assert_eq!(render("a {{ b", &entries).unwrap().as_str(), "a {{ b");
+
+        let error = render("\n\n{{ vault prod-db/password }}", &entries).unwrap_err();
+        assert!(error.to_string().contains("line 3"), "{}", error);
+        let error = render("{{ vault \"prod-db/pin\" }}", &entries).unwrap_err();
+        assert!(matches!(error, CliError::NotFound(_)));
+        assert!(render("{{ vault \"prod-db/password\"", &entries).is_err());
+    }
+
+    #[test]
+    fn test_masker() {
+        let masker = Masker::new(&[
+            "secret".into(),
+            "secret-long".into(),
+            "".into(),
+            "line one\nline two".into(),
+        ]);
+
+        assert_eq!(
+            masker.mask(b"a secret-long and a secret\n"),
+            b"a ******** and a ********\n"
+        );
+
+        let mut output = Vec::new();
+        masker
+            .copy("key:\nline one\nline two\nend".as_bytes(), &mut output)
+            .unwrap();
+        assert_eq!(output, b"key:\n********\n********\nend");
+    }
+}
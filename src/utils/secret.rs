@@ -0,0 +1,82 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+/// A string holding sensitive data such as a password or a key.
+///
+/// The contents are wiped from memory when the value is dropped and are never
+/// printed by `Debug`.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Create a new `SecretString` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `secret` - The secret to wrap.
+    ///
+    /// # Returns
+    ///
+    /// A new `SecretString` instance.
+    pub fn new(secret: String) -> Self {
+        Self(secret)
+    }
+
+    /// Expose the secret for use.
+    ///
+    /// # Returns
+    ///
+    /// The secret as a `&str`.
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+
+    /// Check if the secret is empty.
+    ///
+    /// # Returns
+    ///
+    /// A `bool` indicating if the secret is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretString(***)")
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self::new(secret.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_hides_secret() {
+        let secret = SecretString::from("hunter2");
+
+        assert_eq!(format!("{:?}", secret), "SecretString(***)");
+        assert_eq!(secret.expose_secret(), "hunter2");
+    }
+}
@@ -0,0 +1,120 @@
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+use crate::prelude::Config;
+
+/// The default path of a socket: in the runtime directory, or in the configuration
+/// directory if there is none.
+///
+/// # Arguments
+///
+/// * `name` - The file name of the socket, e.g. `ssh-agent.sock`.
+///
+/// # Returns
+///
+/// A `Result` containing the path or an error.
+///
+/// # Errors
+///
+/// An error will be returned if the configuration directory cannot be found.
+pub fn default_path(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(match dirs::runtime_dir() {
+        Some(dir) => dir.join("rusty-toolkit").join(name),
+        None => Config::get_config_dir()?.join(name),
+    })
+}
+
+/// Create a socket that only the user can connect to.
+///
+/// A missing directory is created readable by the user only. A socket left behind by a
+/// server that is no longer running is replaced, other files are never removed. The
+/// socket is bound in a private directory and only moved to the path once it is
+/// restricted to the user.
+///
+/// # Arguments
+///
+/// * `path` - The path of the socket.
+///
+/// # Returns
+///
+/// A `Result` containing the listening socket or an error.
+///
+/// # Errors
+///
+/// An error will be returned if another server listens on the path, the path is not a
+/// socket or the socket cannot be created.
+pub fn bind(path: &Path) -> Result<UnixListener, Box<dyn std::error::Error>> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.exists()) {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+    }
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(format!("{} exists and is not a socket", path.display()).into());
+        }
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!("Another server is listening on {}", path.display()).into());
+        }
+        std::fs::remove_file(path)?;
+    }
+
+    let name = path.file_name().ok_or("The socket path has no file name")?;
+    let mut staging_name = std::ffi::OsString::from(".");
+    staging_name.push(name);
+    staging_name.push(format!(".{}", std::process::id()));
+    let staging = path.with_file_name(staging_name);
+    std::fs::DirBuilder::new().mode(0o700).create(&staging)?;
+
+    let staged = staging.join(name);
+    let result = UnixListener::bind(&staged)
+        .map_err(|e| format!("Cannot listen on {}: {}", path.display(), e).into())
+        .and_then(|listener| {
+            std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+            std::fs::rename(&staged, path)?;
+            Ok(listener)
+        });
+    let _ = std::fs::remove_file(&staged);
+    std::fs::remove_dir(&staging)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent/socket");
+        let listener = bind(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let mode = std::fs::metadata(path.parent().unwrap())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        assert!(bind(&path).is_err());
+        drop(listener);
+        // The stale socket of a stopped server is replaced.
+        drop(bind(&path).unwrap());
+        assert_eq!(
+            std::fs::read_dir(dir.path().join("agent")).unwrap().count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_bind_keeps_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.db");
+        std::fs::write(&path, "vault").unwrap();
+
+        assert!(bind(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "vault");
+    }
+}
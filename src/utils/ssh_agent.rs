@@ -0,0 +1,258 @@
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+
+use crate::utils::ssh_key::{put_string, Reader, SshKey};
+
+/// Message numbers of the SSH agent protocol, see draft-miller-ssh-agent.
+pub const SSH_AGENT_FAILURE: u8 = 5;
+pub const SSH_AGENT_SUCCESS: u8 = 6;
+pub const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+pub const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+pub const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+pub const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+pub const SSH_AGENTC_REMOVE_ALL_IDENTITIES: u8 = 19;
+
+/// The largest message a client may send, as in OpenSSH.
+const MAX_MESSAGE: usize = 256 * 1024;
+/// How often the listener checks whether the lifetime of the keys ran out.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Asks the user whether a key may sign, e.g. with `SSH_ASKPASS`.
+pub type Confirm = Box<dyn Fn(&SshKey) -> bool + Send + Sync>;
+
+/// An SSH agent that lists and signs with keys it holds in memory.
+pub struct SshAgent {
+    keys: Mutex<Vec<SshKey>>,
+    confirm: Option<Confirm>,
+}
+
+impl SshAgent {
+    /// Create a new `SshAgent` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - The keys to serve.
+    /// * `confirm` - Asked before every signature if set. A signature is refused unless it
+    ///   returns `true`.
+    ///
+    /// # Returns
+    ///
+    /// A new `SshAgent` instance.
+    pub fn new(keys: Vec<SshKey>, confirm: Option<Confirm>) -> Self {
+        Self {
+            keys: Mutex::new(keys),
+            confirm,
+        }
+    }
+
+    /// Answer a single request of a client.
+    ///
+    /// Requests the agent does not support, e.g. adding keys, are answered with
+    /// `SSH_AGENT_FAILURE`, so keys only come from the vault.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request without its length prefix.
+    ///
+    /// # Returns
+    ///
+    /// The response without its length prefix.
+    pub fn handle(&self, request: &[u8]) -> Vec<u8> {
+        let mut reader = Reader::new(request);
+        let response = match reader.u8() {
+            Ok(SSH_AGENTC_REQUEST_IDENTITIES) => Ok(self.identities()),
+            Ok(SSH_AGENTC_SIGN_REQUEST) => self.sign(&mut reader),
+            Ok(SSH_AGENTC_REMOVE_ALL_IDENTITIES) => {
+                self.remove_all();
+                Ok(vec![SSH_AGENT_SUCCESS])
+            }
+            Ok(number) => Err(format!("Unsupported request {}", number).into()),
+            Err(e) => Err(e),
+        };
+
+        response.unwrap_or_else(|e| {
+            info!("Refused an SSH agent request: {}", e);
+            vec![SSH_AGENT_FAILURE]
+        })
+    }
+
+    /// Forget all keys. The agent keeps answering, with an empty list of identities.
+    pub fn remove_all(&self) {
+        self.keys.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        info!("Removed all SSH keys from the agent");
+    }
+
+    fn identities(&self) -> Vec<u8> {
+        let keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        let mut response = vec![SSH_AGENT_IDENTITIES_ANSWER];
+        response.extend_from_slice(&(keys.len() as u32).to_be_bytes());
+        for key in keys.iter() {
+            put_string(&mut response, key.public_blob());
+            put_string(&mut response, key.comment.as_bytes());
+        }
+        response
+    }
+
+    fn sign(&self, reader: &mut Reader) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let blob = reader.string()?;
+        let data = reader.string()?;
+        let flags = reader.u32()?;
+
+        // Holding the lock while asking also keeps several confirmations from overlapping.
+        let keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        let key = keys
+            .iter()
+            .find(|key| key.public_blob() == blob)
+            .ok_or("The agent does not hold the requested key")?;
+        if let Some(confirm) = &self.confirm {
+            if !confirm(key) {
+                return Err(format!("Signing with {} was denied", key.fingerprint()).into());
+            }
+        }
+
+        let signature = key.sign(data, flags)?;
+        info!("Signed with the SSH key {}", key.fingerprint());
+        let mut response = vec![SSH_AGENT_SIGN_RESPONSE];
+        put_string(&mut response, &signature);
+        Ok(response)
+    }
+
+    /// Answer clients on a socket, each on its own thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `listener` - The socket, see `socket::bind`.
+    /// * `lifetime` - How long to serve the keys. They are removed and the function returns
+    ///   once it runs out. `None` serves until the process ends.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `()` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the socket fails.
+    pub fn serve(
+        self: Arc<Self>,
+        listener: UnixListener,
+        lifetime: Option<Duration>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let deadline = lifetime.map(|lifetime| Instant::now() + lifetime);
+        listener.set_nonblocking(deadline.is_some())?;
+
+        loop {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                self.remove_all();
+                return Ok(());
+            }
+
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false)?;
+                    let agent = Arc::clone(&self);
+                    thread::spawn(move || {
+                        if let Err(e) = agent.serve_client(stream) {
+                            warn!("SSH agent client failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Answer the requests of one client until it disconnects.
+    fn serve_client(&self, mut stream: UnixStream) -> Result<(), Box<dyn std::error::Error>> {
+        loop {
+            let mut length = [0; 4];
+            match stream.read_exact(&mut length) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+            let length = u32::from_be_bytes(length) as usize;
+            if length > MAX_MESSAGE {
+                return Err(format!("Request of {} bytes is too large", length).into());
+            }
+
+            let mut request = vec![0; length];
+            stream.read_exact(&mut request)?;
+            let response = self.handle(&request);
+            stream.write_all(&(response.len() as u32).to_be_bytes())?;
+            stream.write_all(&response)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_agent(confirm: Option<Confirm>) -> SshAgent {
+        let key = SshKey::parse(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/ssh/id_ed25519"
+        )))
+        .unwrap();
+        SshAgent::new(vec![key], confirm)
+    }
+
+    fn sign_request(agent: &SshAgent) -> Vec<u8> {
+        let blob = agent.keys.lock().unwrap()[0].public_blob().to_vec();
+        let mut request = vec![SSH_AGENTC_SIGN_REQUEST];
+        put_string(&mut request, &blob);
+        put_string(&mut request, b"session");
+        request.extend_from_slice(&0u32.to_be_bytes());
+        request
+    }
+
+    #[test]
+    fn test_handle() {
+        let agent = create_agent(None);
+
+        let response = agent.handle(&[SSH_AGENTC_REQUEST_IDENTITIES]);
+        let mut reader = Reader::new(&response);
+        assert_eq!(reader.u8().unwrap(), SSH_AGENT_IDENTITIES_ANSWER);
+        assert_eq!(reader.u32().unwrap(), 1);
+        reader.string().unwrap();
+        assert_eq!(reader.string().unwrap(), b"ed25519@fixture");
+
+        let response = agent.handle(&sign_request(&agent));
+        assert_eq!(response[0], SSH_AGENT_SIGN_RESPONSE);
+
+        // Adding keys, unknown and truncated requests fail.
+        assert_eq!(agent.handle(&[17, 0, 0]), [SSH_AGENT_FAILURE]);
+        assert_eq!(agent.handle(&[]), [SSH_AGENT_FAILURE]);
+        assert_eq!(
+            agent.handle(&[SSH_AGENTC_SIGN_REQUEST, 0, 0, 0, 9]),
+            [SSH_AGENT_FAILURE]
+        );
+
+        let request = sign_request(&agent);
+        agent.handle(&[SSH_AGENTC_REMOVE_ALL_IDENTITIES]);
+        assert_eq!(agent.handle(&request), [SSH_AGENT_FAILURE]);
+        assert_eq!(
+            agent.handle(&[SSH_AGENTC_REQUEST_IDENTITIES]),
+            [SSH_AGENT_IDENTITIES_ANSWER, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_confirm() {
+        let agent = create_agent(Some(Box::new(|_| false)));
+        assert_eq!(agent.handle(&sign_request(&agent)), [SSH_AGENT_FAILURE]);
+
+        let agent = create_agent(Some(Box::new(|key| key.comment == "ed25519@fixture")));
+        assert_eq!(
+            agent.handle(&sign_request(&agent))[0],
+            SSH_AGENT_SIGN_RESPONSE
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use log::info;
+use ureq::{Agent, AgentBuilder, Request, Response};
+
+use crate::prelude::{Database, Encryption, RemoteCredentials, SecretString};
+use crate::utils::merge::{self, Conflict, MergeChanges, VaultState};
+
+/// The name of the vault database in the remote folder.
+const DATABASE_FILE: &str = "vault.db";
+/// The name of the master key file in the remote folder.
+const MASTER_FILE: &str = "master.key";
+/// How often a sync is retried when another client uploads at the same time.
+const MAX_ATTEMPTS: usize = 3;
+
+/// A folder on a WebDAV server holding a copy of the vault and its master key file.
+#[derive(Debug)]
+pub struct WebDavRemote {
+    agent: Agent,
+    url: String,
+    username: String,
+    password: SecretString,
+}
+
+/// A downloaded file and the ETag of the version that was downloaded.
+#[derive(Debug)]
+struct RemoteFile {
+    data: Vec<u8>,
+    etag: String,
+}
+
+/// The condition a remote file must meet to be replaced.
+#[derive(Debug, Clone, Copy)]
+enum Precondition<'a> {
+    /// The file must not exist yet.
+    Absent,
+    /// The file must still have the given ETag.
+    Unchanged(&'a str),
+}
+
+/// What a sync changed.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// The changes made to the local vault.
+    pub pulled: MergeChanges,
+    /// The changes made to the remote vault.
+    pub pushed: MergeChanges,
+    /// Whether the remote vault was created by this sync.
+    pub created: bool,
+    /// Whether a new version of the remote vault was uploaded.
+    pub uploaded: bool,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl WebDavRemote {
+    /// Create a new `WebDavRemote` instance.
+    ///
+    /// `webdav://` URLs are reached over HTTPS, `webdav+http://` URLs over plain HTTP.
+    ///
+    /// # Arguments
+    ///
+    /// * `credentials` - The URL of the remote folder and the login for it. An empty
+    ///   username disables authentication.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `WebDavRemote` instance or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the URL is not a WebDAV URL.
+    pub fn new(credentials: &RemoteCredentials) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            agent: AgentBuilder::new().timeout(Duration::from_secs(60)).build(),
+            url: http_url(&credentials.url)?,
+            username: credentials.username.clone(),
+            password: credentials.password.clone(),
+        })
+    }
+
+    /// Sync a vault with the remote copy.
+    ///
+    /// If the remote folder holds no vault yet, a snapshot of the local vault and its master
+    /// key file are uploaded. Otherwise the remote vault is downloaded, unlocked with the
+    /// local master password and merged with the local vault. The merged vault is uploaded
+    /// only if the remote vault has not changed since it was downloaded, which the server
+    /// checks with its ETag. If it has, the sync starts over with the new remote vault and
+    /// the local vault is left unchanged. The local vault only takes the merge once the
+    /// upload succeeded or nothing had to be uploaded.
+    ///
+    /// A remote vault written by an older version is only upgraded if `migrate_remote` is
+    /// set, since clients still running that version could not open it anymore.
+    ///
+    /// # Arguments
+    ///
+    /// * `database` - The unlocked local vault.
+    /// * `master_file` - The master key file of the local vault.
+    /// * `master_password` - The master password of both vaults.
+    /// * `migrate_remote` - Whether to upgrade a remote vault written by an older version.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `SyncReport` or an error.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the remote cannot be reached, the remote vault cannot be
+    /// unlocked, needs an upgrade that was not allowed or kept changing during every
+    /// attempt.
+    pub fn sync(
+        &self,
+        database: &Database,
+        master_file: &Path,
+        master_password: &SecretString,
+        migrate_remote: bool,
+    ) -> Result<SyncReport, Box<dyn std::error::Error>> {
+        for _ in 0..MAX_ATTEMPTS {
+            let report = match self.get(DATABASE_FILE)? {
+                None => self.push_new(database, master_file)?,
+                Some(remote) => self.merge(database, master_password, &remote, migrate_remote)?,
+            };
+            if let Some(report) = report {
+                return Ok(report);
+            }
+            info!("The remote vault changed during the sync, starting over");
+        }
+
+        Err("The remote vault kept changing during the sync, please try again".into())
+    }
+
+    /// Upload the local vault to an empty remote folder.
+    ///
+    /// The master key file is uploaded first, so a client that finds a remote vault also
+    /// finds the key to unlock it. Returns `None` if another client created the remote vault
+    /// first.
+    fn push_new(
+        &self,
+        database: &Database,
+        master_file: &Path,
+    ) -> Result<Option<SyncReport>, Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let snapshot = dir.path().join(DATABASE_FILE);
+        database.backup_to(&snapshot)?;
+
+        self.create_folder()?;
+        if !self.put(MASTER_FILE, fs::read(master_file)?, Precondition::Absent)? {
+            return Ok(None);
+        }
+        if !self.put(DATABASE_FILE, fs::read(&snapshot)?, Precondition::Absent)? {
+            return Err(
+                "Another client uploaded a vault next to this master key file, please check the remote folder"
+                    .into(),
+            );
+        }
+
+        Ok(Some(SyncReport {
+            created: true,
+            uploaded: true,
+            ..SyncReport::default()
+        }))
+    }
+
+    /// Merge the downloaded remote vault with the local vault and upload the result.
+    ///
+    /// Returns `None` if the remote vault changed before the result could be uploaded, in
+    /// which case the local vault was not changed.
+    fn merge(
+        &self,
+        database: &Database,
+        master_password: &SecretString,
+        vault: &RemoteFile,
+        migrate_remote: bool,
+    ) -> Result<Option<SyncReport>, Box<dyn std::error::Error>> {
+        let master_key = self
+            .get(MASTER_FILE)?
+            .ok_or("The remote vault has no master key file")?;
+
+        let dir = tempfile::tempdir()?;
+        let remote_path = dir.path().join(DATABASE_FILE);
+        let remote_master_file = dir.path().join(MASTER_FILE);
+        fs::write(&remote_path, &vault.data)?;
+        fs::write(&remote_master_file, &master_key.data)?;
+
+        let salt = Encryption::unlock_master_file(&remote_master_file, master_password)
+            .map_err(|_| "The remote vault cannot be unlocked with the local master password")?;
+        let remote = Database::open(remote_path, master_password, &salt)?;
+        let migrated = remote.has_pending_migrations()?;
+        if migrated && !migrate_remote {
+            return Err(
+                "The remote vault was written by an older version. Sync with --migrate-remote to upgrade it once every client is updated"
+                    .into(),
+            );
+        }
+        remote.migrate()?;
+
+        let local_id = database.vault_id()?;
+        let remote_id = remote.vault_id()?;
+        let base = database.merge_base(&remote_id)?;
+        let result = merge::merge(
+            &VaultState::read(database)?,
+            &VaultState::read(&remote)?,
+            base.as_deref(),
+        );
+
+        let pushed = merge::apply(&remote, &result)?;
+        let merged_at = Utc::now().to_rfc3339();
+
+        let uploaded = migrated || pushed != MergeChanges::default();
+        if uploaded {
+            remote.set_merge_base(&local_id, &merged_at)?;
+            let snapshot = dir.path().join("upload.db");
+            remote.backup_to(&snapshot)?;
+            if !self.put(
+                DATABASE_FILE,
+                fs::read(&snapshot)?,
+                Precondition::Unchanged(&vault.etag),
+            )? {
+                return Ok(None);
+            }
+        }
+        let pulled = merge::apply(database, &result)?;
+        database.set_merge_base(&remote_id, &merged_at)?;
+
+        Ok(Some(SyncReport {
+            pulled,
+            pushed,
+            created: false,
+            uploaded,
+            conflicts: result.conflicts,
+        }))
+    }
+
+    /// Download a file from the remote folder.
+    ///
+    /// Returns `None` if the file does not exist.
+    fn get(&self, name: &str) -> Result<Option<RemoteFile>, Box<dyn std::error::Error>> {
+        let response = match self.send(self.agent.get(&self.file_url(name)), None)? {
+            Ok(response) => response,
+            Err(404) => return Ok(None),
+            Err(status) => return Err(self.error(status)),
+        };
+
+        let etag = response
+            .header("ETag")
+            .ok_or("The WebDAV server did not send an ETag")?
+            .to_string();
+        let mut data = Vec::new();
+        response.into_reader().read_to_end(&mut data)?;
+
+        Ok(Some(RemoteFile { data, etag }))
+    }
+
+    /// Upload a file to the remote folder if it meets the precondition.
+    ///
+    /// Returns `false` if the server rejected the upload because of the precondition.
+    fn put(
+        &self,
+        name: &str,
+        data: Vec<u8>,
+        precondition: Precondition,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let request = match precondition {
+            Precondition::Absent => self
+                .agent
+                .put(&self.file_url(name))
+                .set("If-None-Match", "*"),
+            Precondition::Unchanged(etag) => {
+                self.agent.put(&self.file_url(name)).set("If-Match", etag)
+            }
+        };
+
+        match self.send(request, Some(&data))? {
+            Ok(_) => Ok(true),
+            Err(412) => Ok(false),
+            Err(status) => Err(self.error(status)),
+        }
+    }
+
+    /// Create the remote folder. A folder that already exists is not an error.
+    fn create_folder(&self) -> Result<(), Box<dyn std::error::Error>> {
+        match self.send(self.agent.request("MKCOL", &self.url), None)? {
+            Ok(_) | Err(405) => Ok(()),
+            Err(status) => Err(self.error(status)),
+        }
+    }
+
+    /// Send a request with the login of the remote.
+    ///
+    /// The outer `Result` carries connection errors, the inner one the status of a
+    /// request the server refused.
+    fn send(
+        &self,
+        request: Request,
+        body: Option<&[u8]>,
+    ) -> Result<Result<Response, u16>, Box<dyn std::error::Error>> {
+        let request = if self.username.is_empty() {
+            request
+        } else {
+            let login = SecretString::new(format!(
+                "{}:{}",
+                self.username,
+                self.password.expose_secret()
+            ));
+            let header =
+                SecretString::new(format!("Basic {}", STANDARD.encode(login.expose_secret())));
+            request.set("Authorization", header.expose_secret())
+        };
+
+        let response = match body {
+            Some(body) => request.send_bytes(body),
+            None => request.call(),
+        };
+        match response {
+            Ok(response) => Ok(Ok(response)),
+            Err(ureq::Error::Status(status, _)) => Ok(Err(status)),
+            Err(ureq::Error::Transport(transport)) => {
+                Err(format!("Failed to reach the WebDAV server: {}", transport).into())
+            }
+        }
+    }
+
+    /// Turn the status of a refused request into a readable error.
+    fn error(&self, status: u16) -> Box<dyn std::error::Error> {
+        match status {
+            401 | 403 => format!("The WebDAV server rejected the login for {}", self.url).into(),
+            status => format!("The WebDAV server answered with status {}", status).into(),
+        }
+    }
+
+    /// The URL of a file in the remote folder.
+    fn file_url(&self, name: &str) -> String {
+        format!("{}{}", self.url, name)
+    }
+}
+
+/// Translate a WebDAV URL into the HTTP URL of the remote folder.
+fn http_url(remote: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let url = if let Some(rest) = remote.strip_prefix("webdav://") {
+        format!("https://{}", rest)
+    } else if let Some(rest) = remote.strip_prefix("webdav+http://") {
+        format!("http://{}", rest)
+    } else {
+        return Err(format!(
+            "Unsupported remote: {}. Use webdav://host/path or webdav+http://host/path",
+            remote
+        )
+        .into());
+    };
+
+    if url.ends_with('/') {
+        Ok(url)
+    } else {
+        Ok(format!("{}/", url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_url() {
+        assert_eq!(
+            http_url("webdav://dav.example.com/team/vault").unwrap(),
+            "https://dav.example.com/team/vault/"
+        );
+        assert_eq!(
+            http_url("webdav+http://localhost:8080/").unwrap(),
+            "http://localhost:8080/"
+        );
+        assert!(http_url("https://dav.example.com").is_err());
+    }
+}
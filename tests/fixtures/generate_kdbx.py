@@ -0,0 +1,309 @@
+#!/usr/bin/env python3
+"""Generate the KeePass KDBX 4 fixtures used by the kdbx importer tests.
+
+The files are written independently of the importer so the tests check the
+implementation against the format rather than against itself. All random
+values are derived from fixed seeds to keep the output reproducible.
+
+Requires the `cryptography` package:
+
+    python3 tests/fixtures/generate_kdbx.py
+"""
+
+import base64
+import gzip
+import hashlib
+import hmac
+import struct
+from datetime import datetime, timezone
+from pathlib import Path
+
+from cryptography.hazmat.primitives.ciphers import Cipher, algorithms, modes
+from cryptography.hazmat.primitives.kdf.argon2 import Argon2id
+from cryptography.hazmat.primitives import padding
+
+FIXTURES = Path(__file__).parent
+PASSWORD = b"fixture-password"
+
+CIPHER_AES256 = bytes.fromhex("31c1f2e6bf714350be5805216afc5aff")
+CIPHER_CHACHA20 = bytes.fromhex("d6038a2b8b6f4cb5a524339a31dbb59a")
+KDF_ARGON2ID = bytes.fromhex("9e298b1956db4773b23dfc3ec6f0a1e6")
+KDF_AES = bytes.fromhex("c9d9f39a628a4460bf740d08c18a4fea")
+
+
+def fixed(label, length):
+    return hashlib.sha512(label.encode()).digest()[:length]
+
+
+def variant_dictionary(items):
+    out = struct.pack("<H", 0x0100)
+    for key, (kind, value) in items.items():
+        key = key.encode()
+        out += bytes([kind]) + struct.pack("<i", len(key)) + key
+        out += struct.pack("<i", len(value)) + value
+    return out + b"\x00"
+
+
+def field(field_id, data):
+    return bytes([field_id]) + struct.pack("<I", len(data)) + data
+
+
+def block_key(index, hmac_key):
+    return hashlib.sha512(struct.pack("<Q", index) + hmac_key).digest()
+
+
+def composite_key(keyfile):
+    parts = hashlib.sha256(PASSWORD).digest()
+    if keyfile is not None:
+        parts += keyfile
+    return hashlib.sha256(parts).digest()
+
+
+def transform_argon2id(key, salt):
+    kdf = Argon2id(salt=salt, length=32, iterations=2, lanes=1, memory_cost=1024)
+    return kdf.derive(key)
+
+
+def transform_aes(key, seed, rounds):
+    encryptor = Cipher(algorithms.AES(seed), modes.ECB()).encryptor()
+    for _ in range(rounds):
+        key = encryptor.update(key)
+    return hashlib.sha256(key).digest()
+
+
+class ProtectedStream:
+    """The inner ChaCha20 stream that hides protected values in the XML."""
+
+    def __init__(self, key):
+        digest = hashlib.sha512(key).digest()
+        nonce = b"\x00" * 4 + digest[32:44]
+        self.cipher = Cipher(algorithms.ChaCha20(digest[:32], nonce), None).encryptor()
+
+    def protect(self, value):
+        return base64.b64encode(self.cipher.update(value.encode())).decode()
+
+
+def xml_escape(value):
+    return (
+        value.replace("&", "&amp;")
+        .replace("<", "&lt;")
+        .replace(">", "&gt;")
+        .replace('"', "&quot;")
+    )
+
+
+def kdbx_time(value):
+    """KDBX 4 stores times as base64 encoded seconds since 0001-01-01."""
+    epoch = datetime(1, 1, 1, tzinfo=timezone.utc)
+    seconds = int((value - epoch).total_seconds())
+    return base64.b64encode(struct.pack("<q", seconds)).decode()
+
+
+def entry_xml(stream, fields, binaries=(), history=(), times=None):
+    """Render an entry. Values are protected in document order, history last."""
+    out = "<Entry><UUID>%s</UUID>" % base64.b64encode(fixed(fields["Title"][0], 16)).decode()
+    if times:
+        out += "<Times><CreationTime>%s</CreationTime>" % kdbx_time(times[0])
+        out += "<LastModificationTime>%s</LastModificationTime></Times>" % kdbx_time(times[1])
+    for key, (value, protected) in fields.items():
+        if protected:
+            out += '<String><Key>%s</Key><Value Protected="True">%s</Value></String>' % (
+                key,
+                stream.protect(value),
+            )
+        else:
+            out += "<String><Key>%s</Key><Value>%s</Value></String>" % (key, xml_escape(value))
+    for name, ref in binaries:
+        out += '<Binary><Key>%s</Key><Value Ref="%d" /></Binary>' % (name, ref)
+    if history:
+        out += "<History>"
+        for old in history:
+            out += entry_xml(stream, old)
+        out += "</History>"
+    return out + "</Entry>"
+
+
+def group_xml(name, content, uuid_label=None):
+    uuid = base64.b64encode(fixed(uuid_label or name, 16)).decode()
+    return "<Group><UUID>%s</UUID><Name>%s</Name>%s</Group>" % (uuid, name, content)
+
+
+def write_kdbx(path, cipher, kdf, compress, build_xml, binaries=(), keyfile=None):
+    seed = fixed(path.name + "seed", 32)
+    stream_key = fixed(path.name + "stream", 64)
+
+    if kdf == "argon2id":
+        kdf_salt = fixed(path.name + "kdf", 32)
+        kdf_params = variant_dictionary(
+            {
+                "$UUID": (0x42, KDF_ARGON2ID),
+                "S": (0x42, kdf_salt),
+                "P": (0x04, struct.pack("<I", 1)),
+                "M": (0x05, struct.pack("<Q", 1024 * 1024)),
+                "I": (0x05, struct.pack("<Q", 2)),
+                "V": (0x04, struct.pack("<I", 0x13)),
+            }
+        )
+        transformed = transform_argon2id(composite_key(keyfile), kdf_salt)
+    else:
+        kdf_seed = fixed(path.name + "kdf", 32)
+        rounds = 1000
+        kdf_params = variant_dictionary(
+            {
+                "$UUID": (0x42, KDF_AES),
+                "S": (0x42, kdf_seed),
+                "R": (0x05, struct.pack("<Q", rounds)),
+            }
+        )
+        transformed = transform_aes(composite_key(keyfile), kdf_seed, rounds)
+
+    if cipher == "aes":
+        cipher_id, iv = CIPHER_AES256, fixed(path.name + "iv", 16)
+    else:
+        cipher_id, iv = CIPHER_CHACHA20, fixed(path.name + "iv", 12)
+
+    header = struct.pack("<IIHH", 0x9AA2D903, 0xB54BFB67, 0, 4)
+    header += field(2, cipher_id)
+    header += field(3, struct.pack("<I", 1 if compress else 0))
+    header += field(4, seed)
+    header += field(7, iv)
+    header += field(11, kdf_params)
+    header += field(0, b"\r\n\r\n")
+
+    encryption_key = hashlib.sha256(seed + transformed).digest()
+    hmac_key = hashlib.sha512(seed + transformed + b"\x01").digest()
+
+    inner = field(1, struct.pack("<I", 3)) + field(2, stream_key)
+    for binary in binaries:
+        inner += field(3, b"\x01" + binary)
+    inner += field(0, b"")
+    inner += build_xml(ProtectedStream(stream_key)).encode()
+
+    if compress:
+        inner = gzip.compress(inner, mtime=0)
+
+    if cipher == "aes":
+        padder = padding.PKCS7(128).padder()
+        padded = padder.update(inner) + padder.finalize()
+        encryptor = Cipher(algorithms.AES(encryption_key), modes.CBC(iv)).encryptor()
+        payload = encryptor.update(padded) + encryptor.finalize()
+    else:
+        nonce = b"\x00" * 4 + iv
+        encryptor = Cipher(algorithms.ChaCha20(encryption_key, nonce), None).encryptor()
+        payload = encryptor.update(inner)
+
+    out = header
+    out += hashlib.sha256(header).digest()
+    out += hmac.new(block_key(0xFFFFFFFFFFFFFFFF, hmac_key), header, hashlib.sha256).digest()
+
+    index = 0
+    for start in range(0, len(payload), 1024):
+        data = payload[start : start + 1024]
+        size = struct.pack("<i", len(data))
+        mac = hmac.new(block_key(index, hmac_key), struct.pack("<Q", index) + size + data, hashlib.sha256)
+        out += mac.digest() + size + data
+        index += 1
+    size = struct.pack("<i", 0)
+    mac = hmac.new(block_key(index, hmac_key), struct.pack("<Q", index) + size, hashlib.sha256)
+    out += mac.digest() + size
+
+    path.write_bytes(out)
+
+
+def main_fixture(stream):
+    recycle_bin = base64.b64encode(fixed("Recycle Bin", 16)).decode()
+    router = entry_xml(
+        stream,
+        {
+            "Title": ("Router", False),
+            "UserName": ("admin", False),
+            "Password": ("r0uter!", True),
+            "URL": ("http://192.168.1.1", False),
+            "Notes": ("Office router\nRack 2", False),
+            "Serial": ("RT-1234", False),
+            "PIN": ("4711", True),
+            "otp": ("otpauth://totp/Router?secret=JBSWY3DPEHPK3PXP", True),
+        },
+        binaries=[("config.txt", 0), ("logo.png", 1)],
+        history=[
+            {"Title": ("Router", False), "Password": ("old-1", True)},
+            {"Title": ("Router", False), "Password": ("old-2", True)},
+        ],
+        times=(
+            datetime(2019, 7, 1, 9, 30, tzinfo=timezone.utc),
+            datetime(2024, 2, 29, 18, 0, 5, tzinfo=timezone.utc),
+        ),
+    )
+    database = entry_xml(
+        stream,
+        {
+            "Title": ("db01", False),
+            "UserName": ("postgres", False),
+            "Password": ("pg-secret", True),
+            "URL": ("ssh://db01", False),
+            "Notes": ("", False),
+        },
+    )
+    deleted = entry_xml(stream, {"Title": ("Deleted", False), "Password": ("gone", True)})
+
+    root = group_xml(
+        "Database",
+        router
+        + group_xml("Infrastructure", group_xml("Servers", database))
+        + group_xml("Recycle Bin", deleted),
+    )
+    return (
+        '<?xml version="1.0" encoding="utf-8" standalone="yes"?>'
+        "<KeePassFile><Meta><Generator>fixture</Generator>"
+        "<RecycleBinEnabled>True</RecycleBinEnabled>"
+        "<RecycleBinUUID>%s</RecycleBinUUID></Meta>"
+        "<Root>%s<DeletedObjects /></Root></KeePassFile>" % (recycle_bin, root)
+    )
+
+
+def keyfile_fixture(stream):
+    vpn = entry_xml(
+        stream,
+        {
+            "Title": ("VPN", False),
+            "UserName": ("alice", False),
+            "Password": ("tunnel & key", True),
+        },
+    )
+    return (
+        '<?xml version="1.0" encoding="utf-8" standalone="yes"?>'
+        "<KeePassFile><Meta><Generator>fixture</Generator></Meta>"
+        "<Root>%s</Root></KeePassFile>" % group_xml("Keys", group_xml("Network", vpn))
+    )
+
+
+def main():
+    write_kdbx(
+        FIXTURES / "keepass.kdbx",
+        cipher="aes",
+        kdf="argon2id",
+        compress=True,
+        build_xml=main_fixture,
+        binaries=[b"ssid=office\nchannel=6\n", b"\x89PNG\r\n\x1a\n\x00\x00"],
+    )
+
+    key = fixed("keyfile", 32)
+    digest = hashlib.sha256(key).digest()[:4].hex().upper()
+    data = " ".join(key.hex().upper()[i : i + 8] for i in range(0, 64, 8))
+    (FIXTURES / "keepass.keyx").write_text(
+        '<?xml version="1.0" encoding="utf-8"?>\n'
+        "<KeyFile>\n\t<Meta>\n\t\t<Version>2.0</Version>\n\t</Meta>\n"
+        '\t<Key>\n\t\t<Data Hash="%s">%s</Data>\n\t</Key>\n</KeyFile>\n' % (digest, data)
+    )
+    write_kdbx(
+        FIXTURES / "keepass-keyfile.kdbx",
+        cipher="chacha20",
+        kdf="aes",
+        compress=False,
+        build_xml=keyfile_fixture,
+        keyfile=key,
+    )
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,100 @@
+//! Talks to `git-credential` the way git does and checks the logins it answers with.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+const MASTER_PASSWORD: &str = "correct horse battery staple";
+
+/// Run the binary with its configuration in the given directory.
+fn run(config_dir: &tempfile::TempDir, args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rusty-toolkit"))
+        .args(["--no-input", "--password-env", "TEST_MASTER_PASSWORD"])
+        .args(args)
+        .env("XDG_CONFIG_HOME", config_dir.path())
+        .env("HOME", config_dir.path())
+        .env("TEST_MASTER_PASSWORD", MASTER_PASSWORD)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    output
+}
+
+/// Ask for the login of a host like `git credential fill` does.
+fn get(config_dir: &tempfile::TempDir, host: &str) -> String {
+    let request = format!("protocol=https\nhost={}\n\n", host);
+    let output = run(config_dir, &["git-credential", "get"], &request);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_git_credential() {
+    let config_dir = tempfile::tempdir().unwrap();
+    run(
+        &config_dir,
+        &[
+            "password",
+            "manage",
+            "add",
+            "-s",
+            "GitHub",
+            "-u",
+            "alice",
+            "-p",
+            "ghp-token",
+            "--url",
+            "https://github.com",
+            "-n",
+            "",
+        ],
+        "",
+    );
+
+    assert_eq!(
+        get(&config_dir, "github.com"),
+        "username=alice\npassword=ghp-token\n"
+    );
+    assert_eq!(get(&config_dir, "gitlab.com"), "");
+
+    // Storing is off by default.
+    let login = "protocol=https\nhost=gitlab.com\nusername=bob\npassword=first\n\n";
+    run(&config_dir, &["git-credential", "store"], login);
+    assert_eq!(get(&config_dir, "gitlab.com"), "");
+
+    let config_path = config_dir
+        .path()
+        .join("karnes-development/rusty-toolkit/config.toml");
+    let config = std::fs::read_to_string(&config_path).unwrap();
+    std::fs::write(&config_path, config.replace("store = false", "store = true")).unwrap();
+
+    run(&config_dir, &["git-credential", "store"], login);
+    assert_eq!(
+        get(&config_dir, "gitlab.com"),
+        "username=bob\npassword=first\n"
+    );
+
+    let login = login.replace("first", "second");
+    run(&config_dir, &["git-credential", "store"], &login);
+    assert_eq!(
+        get(&config_dir, "gitlab.com"),
+        "username=bob\npassword=second\n"
+    );
+
+    run(&config_dir, &["git-credential", "erase"], &login);
+    assert_eq!(get(&config_dir, "gitlab.com"), "");
+    assert_eq!(
+        get(&config_dir, "github.com"),
+        "username=alice\npassword=ghp-token\n"
+    );
+
+    run(&config_dir, &["git-credential", "capability"], "");
+}
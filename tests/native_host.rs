@@ -0,0 +1,217 @@
+//! Starts the installed native-messaging host like a browser does and exchanges framed
+//! messages with it, with `serve` running.
+#![cfg(unix)]
+
+use std::io::{Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+const MASTER_PASSWORD: &str = "correct horse battery staple";
+const EXTENSION_ID: &str = "abcdefghijklmnopabcdefghijklmnop";
+
+fn command(config_dir: &tempfile::TempDir, program: &str, args: &[&str]) -> Command {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .env("XDG_CONFIG_HOME", config_dir.path())
+        .env("XDG_RUNTIME_DIR", config_dir.path().join("run"))
+        .env("HOME", config_dir.path())
+        .env("TEST_MASTER_PASSWORD", MASTER_PASSWORD)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    command
+}
+
+fn toolkit(config_dir: &tempfile::TempDir, args: &[&str]) -> Command {
+    let mut command = command(config_dir, env!("CARGO_BIN_EXE_rusty-toolkit"), &[]);
+    command
+        .args(["--no-input", "--password-env", "TEST_MASTER_PASSWORD"])
+        .args(args);
+    command
+}
+
+fn run(config_dir: &tempfile::TempDir, args: &[&str]) -> Output {
+    let output = toolkit(config_dir, args).output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    output
+}
+
+/// Kills a process when the test ends, even if it fails.
+struct Process(Child);
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn send(stdin: &mut ChildStdin, stdout: &mut ChildStdout, message: Value) -> Value {
+    let message = message.to_string();
+    stdin
+        .write_all(&(message.len() as u32).to_ne_bytes())
+        .unwrap();
+    stdin.write_all(message.as_bytes()).unwrap();
+
+    let mut length = [0; 4];
+    stdout.read_exact(&mut length).unwrap();
+    let mut response = vec![0; u32::from_ne_bytes(length) as usize];
+    stdout.read_exact(&mut response).unwrap();
+    serde_json::from_slice(&response).unwrap()
+}
+
+#[test]
+fn test_native_host() {
+    let config_dir = tempfile::tempdir().unwrap();
+    run(
+        &config_dir,
+        &[
+            "password",
+            "manage",
+            "add",
+            "-s",
+            "Example",
+            "-u",
+            "alice",
+            "-p",
+            "alice-secret",
+            "--url",
+            "https://accounts.example.co.uk",
+            "-n",
+            "",
+        ],
+    );
+    run(
+        &config_dir,
+        &["native-host", "install", "chrome", "-e", EXTENSION_ID],
+    );
+    let manifest_path = config_dir
+        .path()
+        .join("google-chrome/NativeMessagingHosts/com.karnes_development.rusty_toolkit.json");
+    let manifest: Value =
+        serde_json::from_str(&std::fs::read_to_string(manifest_path).unwrap()).unwrap();
+    assert_eq!(
+        manifest["allowed_origins"],
+        json!([format!("chrome-extension://{}/", EXTENSION_ID)])
+    );
+    let launcher = manifest["path"].as_str().unwrap().to_string();
+
+    // Reinstalling restricts files that were made readable by others.
+    let token_file = Path::new(&launcher).with_file_name("native-host-chrome.token");
+    for path in [&token_file, Path::new(&launcher)] {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o644)).unwrap();
+    }
+    run(
+        &config_dir,
+        &["native-host", "install", "chrome", "-e", EXTENSION_ID],
+    );
+    let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(&token_file), 0o600);
+    assert_eq!(mode(Path::new(&launcher)), 0o700);
+
+    // Printing the manifest does not install it.
+    let output = run(
+        &config_dir,
+        &[
+            "native-host",
+            "install",
+            "firefox",
+            "-e",
+            "logins@example.com",
+            "--print",
+        ],
+    );
+    let manifest: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        manifest["allowed_extensions"],
+        json!(["logins@example.com"])
+    );
+    assert!(!config_dir.path().join(".mozilla").exists());
+    assert!(!Path::new(manifest["path"].as_str().unwrap()).exists());
+
+    let socket = config_dir.path().join("run/rusty-toolkit/api.sock");
+    let _server = Process(toolkit(&config_dir, &["serve"]).spawn().unwrap());
+    let started = Instant::now();
+    while UnixStream::connect(&socket).is_err() {
+        assert!(started.elapsed() < Duration::from_secs(10), "no socket");
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    let mut host = command(
+        &config_dir,
+        &launcher,
+        &[&format!("chrome-extension://{}/", EXTENSION_ID)],
+    );
+    let mut host = Process(host.stdin(Stdio::piped()).spawn().unwrap());
+    let mut stdin = host.0.stdin.take().unwrap();
+    let mut stdout = host.0.stdout.take().unwrap();
+
+    let get_logins = |origin: &str| json!({ "id": 1, "action": "get-logins", "origin": origin });
+    let response = send(
+        &mut stdin,
+        &mut stdout,
+        get_logins("https://www.example.co.uk"),
+    );
+    assert_eq!(response["ok"], true, "{}", response);
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["logins"][0]["username"], "alice");
+    assert_eq!(response["logins"][0]["password"], "alice-secret");
+
+    // Neither another site under the same public suffix nor plain HTTP get the login.
+    for origin in ["https://other.co.uk", "http://accounts.example.co.uk"] {
+        let response = send(&mut stdin, &mut stdout, get_logins(origin));
+        assert_eq!(response["logins"], json!([]), "{}", origin);
+    }
+
+    let save = |username: &str, password: &str| {
+        json!({
+            "action": "save-login",
+            "origin": "https://shop.example.co.uk",
+            "username": username,
+            "password": password,
+        })
+    };
+    let response = send(&mut stdin, &mut stdout, save("alice", "alice-new"));
+    assert_eq!(response["saved"], "updated", "{}", response);
+    let response = send(&mut stdin, &mut stdout, save("bob", "bob-secret"));
+    assert_eq!(response["saved"], "added", "{}", response);
+    let response = send(&mut stdin, &mut stdout, save("bob", "bob-secret"));
+    assert_eq!(response["saved"], "unchanged", "{}", response);
+
+    // The host is the best match for its own logins.
+    let response = send(
+        &mut stdin,
+        &mut stdout,
+        get_logins("https://shop.example.co.uk"),
+    );
+    let logins = response["logins"].as_array().unwrap();
+    assert_eq!(logins.len(), 2);
+    assert_eq!(logins[0]["username"], "bob");
+    assert_eq!(logins[0]["service"], "shop.example.co.uk");
+    assert_eq!(logins[1]["password"], "alice-new");
+
+    let response = send(&mut stdin, &mut stdout, json!({ "action": "unknown" }));
+    assert_eq!(response["ok"], false);
+
+    // The host ends when the browser closes its input.
+    drop(stdin);
+    let status = host.0.wait().unwrap();
+    assert!(status.success());
+
+    let output = run(&config_dir, &["--output", "json", "api-token", "list"]);
+    let clients: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let names = clients
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|client| client["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["browser-chrome"]);
+}
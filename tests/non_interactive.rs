@@ -0,0 +1,283 @@
+//! Runs the binary with `--no-input` the way a cron job would and checks its exit codes.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+const MASTER_PASSWORD: &str = "correct horse battery staple";
+
+/// Run the binary with its configuration in the given directory.
+fn run(config_dir: &tempfile::TempDir, args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rusty-toolkit"))
+        .args(args)
+        .env("XDG_CONFIG_HOME", config_dir.path())
+        .env("HOME", config_dir.path())
+        .env("TEST_MASTER_PASSWORD", MASTER_PASSWORD)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn add_entry(config_dir: &tempfile::TempDir) {
+    let output = run(
+        config_dir,
+        &[
+            "--no-input",
+            "--password-env",
+            "TEST_MASTER_PASSWORD",
+            "password",
+            "manage",
+            "add",
+            "-s",
+            "Mail",
+            "-u",
+            "alice",
+            "-p",
+            "mail-secret",
+            "--url",
+            "https://mail.example.com",
+        ],
+        "",
+    );
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn test_exit_codes() {
+    let config_dir = tempfile::tempdir().unwrap();
+    add_entry(&config_dir);
+
+    let stdin_password = format!("{}\n", MASTER_PASSWORD);
+    let output = run(
+        &config_dir,
+        &[
+            "password",
+            "manage",
+            "show",
+            "-i",
+            "1",
+            "--password-stdin",
+            "--no-input",
+        ],
+        &stdin_password,
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("mail-secret"));
+
+    let output = run(
+        &config_dir,
+        &[
+            "password",
+            "manage",
+            "show",
+            "-i",
+            "1",
+            "--reveal",
+            "--output",
+            "json",
+            "--password-stdin",
+            "--no-input",
+        ],
+        &stdin_password,
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let entry: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(entry["service"], "Mail");
+    assert_eq!(entry["password"], "mail-secret");
+
+    // Without a password source there is nobody to ask.
+    let output = run(
+        &config_dir,
+        &["--no-input", "password", "manage", "list"],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(3));
+
+    let output = run(
+        &config_dir,
+        &[
+            "--no-input",
+            "--password-stdin",
+            "password",
+            "manage",
+            "list",
+        ],
+        "wrong password\n",
+    );
+    assert_eq!(output.status.code(), Some(3));
+
+    let output = run(
+        &config_dir,
+        &[
+            "--no-input",
+            "--password-fd",
+            "0",
+            "password",
+            "manage",
+            "show",
+            "-i",
+            "42",
+        ],
+        &stdin_password,
+    );
+    assert_eq!(output.status.code(), Some(4));
+
+    let output = run(
+        &config_dir,
+        &[
+            "--no-input",
+            "--password-fd",
+            "0",
+            "password",
+            "manage",
+            "show",
+        ],
+        &stdin_password,
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--no-input"));
+}
+
+#[test]
+fn test_json_results() {
+    let config_dir = tempfile::tempdir().unwrap();
+    add_entry(&config_dir);
+
+    let unlock = [
+        "--no-input",
+        "--password-env",
+        "TEST_MASTER_PASSWORD",
+        "--output",
+        "json",
+    ];
+    let output = run(
+        &config_dir,
+        &[
+            &unlock[..],
+            &[
+                "password",
+                "manage",
+                "add",
+                "-s",
+                "Bank",
+                "-u",
+                "bob",
+                "-p",
+                "bank-secret",
+                "--url",
+                "https://bank.example.com",
+            ],
+        ]
+        .concat(),
+        "",
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let added: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(added["action"], "added");
+    assert_eq!(added["id"], 2);
+    assert_eq!(added["service"], "Bank");
+    let uuid = added["uuid"].as_str().unwrap();
+    assert!(!uuid.is_empty());
+
+    let output = run(
+        &config_dir,
+        &[&unlock[..], &["password", "manage", "remove", "-i", "2"]].concat(),
+        "",
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let removed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(removed["action"], "removed");
+    assert_eq!(removed["uuid"], uuid);
+}
+
+#[test]
+fn test_run_and_inject() {
+    let config_dir = tempfile::tempdir().unwrap();
+    add_entry(&config_dir);
+
+    let output = run(
+        &config_dir,
+        &[
+            "--no-input",
+            "--password-env",
+            "TEST_MASTER_PASSWORD",
+            "run",
+            "--env",
+            "MAIL_PASS=vault://Mail/password",
+            "-e",
+            "MAIL_USER=vault://mail/username",
+            "--",
+            "sh",
+            "-c",
+            "echo \"$MAIL_USER:$MAIL_PASS\"; echo \"${#MAIL_PASS}\" >&2; exit 7",
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(7), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "alice:********\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "11\n");
+
+    let output = run(
+        &config_dir,
+        &[
+            "--no-input",
+            "--password-env",
+            "TEST_MASTER_PASSWORD",
+            "run",
+            "--env",
+            "MAIL_PASS=vault://Mail/pin",
+            "--",
+            "true",
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(4));
+
+    let template = config_dir.path().join("config.tpl");
+    let rendered = config_dir.path().join("config.yml");
+    std::fs::write(
+        &template,
+        "mail:\n  user: {{ vault \"Mail/username\" }}\n  password: {{ vault \"vault://Mail/password\" }}\n",
+    )
+    .unwrap();
+    // An existing file is made private as well.
+    std::fs::write(&rendered, "").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&rendered, std::fs::Permissions::from_mode(0o644)).unwrap();
+    }
+    let output = run(
+        &config_dir,
+        &[
+            "--no-input",
+            "--password-env",
+            "TEST_MASTER_PASSWORD",
+            "inject",
+            "-i",
+            template.to_str().unwrap(),
+            "-o",
+            rendered.to_str().unwrap(),
+        ],
+        "",
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        std::fs::read_to_string(&rendered).unwrap(),
+        "mail:\n  user: alice\n  password: mail-secret\n"
+    );
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&rendered).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,212 @@
+//! Calls the JSON-RPC API of `serve` over its socket, the way other tools would.
+#![cfg(unix)]
+
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::process::{Child, Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+const MASTER_PASSWORD: &str = "correct horse battery staple";
+
+fn command(config_dir: &tempfile::TempDir, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rusty-toolkit"));
+    command
+        .args(["--no-input", "--password-env", "TEST_MASTER_PASSWORD"])
+        .args(args)
+        .env("XDG_CONFIG_HOME", config_dir.path())
+        .env("HOME", config_dir.path())
+        .env("TEST_MASTER_PASSWORD", MASTER_PASSWORD)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    command
+}
+
+fn run(config_dir: &tempfile::TempDir, args: &[&str]) -> Output {
+    let output = command(config_dir, args).output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    output
+}
+
+/// Create a token with `api-token create` and return it.
+fn create_token(config_dir: &tempfile::TempDir, args: &[&str]) -> String {
+    let mut args = args.to_vec();
+    args.splice(0..0, ["--output", "json", "api-token", "create"]);
+    let output = run(config_dir, &args);
+    let created: Value = serde_json::from_slice(&output.stdout).unwrap();
+    created["token"].as_str().unwrap().to_string()
+}
+
+/// Kills the server when the test ends, even if it fails.
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// POST a body to the API and return the HTTP status and the JSON response.
+fn post(socket: &Path, token: Option<&str>, body: &str) -> (u16, Value) {
+    let mut stream = UnixStream::connect(socket).unwrap();
+    let authorization = token
+        .map(|token| format!("Authorization: Bearer {}\r\n", token))
+        .unwrap_or_default();
+    write!(
+        stream,
+        "POST / HTTP/1.1\r\nHost: localhost\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        authorization,
+        body.len(),
+        body
+    )
+    .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+fn call(socket: &Path, token: &str, method: &str, params: Value) -> Value {
+    let body = json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 1 });
+    let (status, response) = post(socket, Some(token), &body.to_string());
+    assert_eq!(status, 200, "{}", response);
+    response
+}
+
+#[test]
+fn test_serve() {
+    let config_dir = tempfile::tempdir().unwrap();
+    run(
+        &config_dir,
+        &[
+            "password",
+            "manage",
+            "add",
+            "-s",
+            "Mail",
+            "-u",
+            "alice",
+            "-p",
+            "mail-secret",
+            "--url",
+            "https://mail.example.com",
+            "-n",
+            "",
+        ],
+    );
+    let deploy = create_token(&config_dir, &["deploy"]);
+    let reader = create_token(&config_dir, &["reader", "--read-only"]);
+
+    let socket = config_dir.path().join("run/api.sock");
+    let _server = Server(
+        command(&config_dir, &["serve", "-a", socket.to_str().unwrap()])
+            .spawn()
+            .unwrap(),
+    );
+    let started = Instant::now();
+    while UnixStream::connect(&socket).is_err() {
+        assert!(started.elapsed() < Duration::from_secs(10), "no socket");
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    let response = call(&socket, &reader, "search", json!({ "query": "mai" }));
+    let results = response["result"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["username"], "alice");
+    assert!(results[0].get("password").is_none());
+
+    let response = call(&socket, &reader, "get", json!({ "entry": "mail" }));
+    assert_eq!(response["result"]["password"], "mail-secret");
+    let uuid = response["result"]["uuid"].as_str().unwrap().to_string();
+
+    // Read-only tokens cannot change entries.
+    let add = json!({ "service": "Bank", "username": "bob", "password": "bank-secret" });
+    let response = call(&socket, &reader, "add", add.clone());
+    assert_eq!(response["error"]["code"], -32003);
+
+    let response = call(&socket, &deploy, "add", add);
+    assert_eq!(response["result"]["service"], "Bank");
+    let response = call(
+        &socket,
+        &deploy,
+        "update",
+        json!({ "entry": uuid, "password": "new-secret", "folder": "Work" }),
+    );
+    assert_eq!(response["result"]["folder"], "Work");
+    let response = call(&socket, &deploy, "get", json!({ "entry": "Mail" }));
+    assert_eq!(response["result"]["password"], "new-secret");
+    assert_eq!(response["result"]["username"], "alice");
+
+    let response = call(&socket, &deploy, "get", json!({ "entry": "Nothing" }));
+    assert_eq!(response["error"]["code"], -32004);
+    let response = call(&socket, &deploy, "get", json!({ "id": 1 }));
+    assert_eq!(response["error"]["code"], -32602);
+    let response = call(&socket, &deploy, "delete", json!({ "entry": "Mail" }));
+    assert_eq!(response["error"]["code"], -32601);
+
+    let body = json!({ "jsonrpc": "2.0", "method": "get", "params": { "entry": "Mail" }, "id": 2 });
+    let (status, response) = post(&socket, None, &body.to_string());
+    assert_eq!(status, 401);
+    assert_eq!(response["error"]["code"], -32001);
+    assert!(response.get("result").is_none());
+    let (status, _) = post(&socket, Some("rtk_guess"), &body.to_string());
+    assert_eq!(status, 401);
+    let (status, response) = post(&socket, Some(&deploy), "{");
+    assert_eq!(status, 400);
+    assert_eq!(response["error"]["code"], -32700);
+
+    // Revoked tokens are rejected by the running server.
+    run(&config_dir, &["api-token", "revoke", "reader"]);
+    let (status, _) = post(&socket, Some(&reader), &body.to_string());
+    assert_eq!(status, 401);
+    let output = run(&config_dir, &["--output", "json", "api-token", "list"]);
+    let clients: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(clients.as_array().unwrap().len(), 1);
+    assert_eq!(clients[0]["name"], "deploy");
+
+    let audit = std::fs::read_to_string(
+        config_dir
+            .path()
+            .join("karnes-development/rusty-toolkit/api-audit.log"),
+    )
+    .unwrap();
+    let records: Vec<Value> = audit
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 13);
+    assert_eq!(records[0]["client"], "reader");
+    assert_eq!(records[0]["method"], "search");
+    assert_eq!(records[1]["entry"], uuid.as_str());
+    assert_eq!(records[2]["ok"], false);
+    assert!(records.iter().any(|record| record["client"].is_null()));
+    assert!(!audit.contains("secret"));
+
+    // Without an audit log changes are still reported, but secrets are withheld.
+    let audit_path = config_dir
+        .path()
+        .join("karnes-development/rusty-toolkit/api-audit.log");
+    std::fs::remove_file(&audit_path).unwrap();
+    std::fs::create_dir(&audit_path).unwrap();
+    let response = call(
+        &socket,
+        &deploy,
+        "update",
+        json!({ "entry": uuid, "folder": "Home" }),
+    );
+    assert_eq!(response["result"]["folder"], "Home");
+    assert_eq!(
+        response["result"]["audit_error"],
+        "Cannot write the audit log"
+    );
+    let response = call(&socket, &deploy, "get", json!({ "entry": "Mail" }));
+    assert_eq!(response["error"]["code"], -32603);
+    assert!(response.get("result").is_none());
+}
@@ -0,0 +1,133 @@
+//! Talks the SSH agent protocol to `ssh-agent` over its socket, the way `ssh` does.
+#![cfg(unix)]
+
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ED25519};
+
+const MASTER_PASSWORD: &str = "correct horse battery staple";
+const KEY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/ssh/id_ed25519");
+
+fn command(config_dir: &tempfile::TempDir, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rusty-toolkit"));
+    command
+        .args(["--no-input", "--password-env", "TEST_MASTER_PASSWORD"])
+        .args(args)
+        .env("XDG_CONFIG_HOME", config_dir.path())
+        .env("HOME", config_dir.path())
+        .env("TEST_MASTER_PASSWORD", MASTER_PASSWORD)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    command
+}
+
+fn run(config_dir: &tempfile::TempDir, args: &[&str]) -> Output {
+    let output = command(config_dir, args).output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    output
+}
+
+/// Send a request and read the response, both without their length prefix.
+fn request(stream: &mut UnixStream, request: &[u8]) -> Vec<u8> {
+    stream
+        .write_all(&(request.len() as u32).to_be_bytes())
+        .unwrap();
+    stream.write_all(request).unwrap();
+
+    let mut length = [0; 4];
+    stream.read_exact(&mut length).unwrap();
+    let mut response = vec![0; u32::from_be_bytes(length) as usize];
+    stream.read_exact(&mut response).unwrap();
+    response
+}
+
+fn put_string(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+fn take_string<'a>(data: &mut &'a [u8]) -> &'a [u8] {
+    let length = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+    let (string, rest) = data[4..].split_at(length);
+    *data = rest;
+    string
+}
+
+#[test]
+fn test_ssh_agent() {
+    let config_dir = tempfile::tempdir().unwrap();
+    run(
+        &config_dir,
+        &[
+            "password",
+            "manage",
+            "add-ssh-key",
+            "-k",
+            KEY,
+            "-s",
+            "Laptop",
+        ],
+    );
+    let output = run(&config_dir, &["password", "manage", "list"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Laptop"));
+
+    let socket = config_dir.path().join("agent/agent.sock");
+    let agent = command(
+        &config_dir,
+        &["ssh-agent", "-a", socket.to_str().unwrap(), "-t", "2"],
+    )
+    .spawn()
+    .unwrap();
+    let started = Instant::now();
+    while !socket.exists() {
+        assert!(started.elapsed() < Duration::from_secs(10), "no socket");
+        thread::sleep(Duration::from_millis(20));
+    }
+    let mut stream = UnixStream::connect(&socket).unwrap();
+
+    let response = request(&mut stream, &[11]);
+    assert_eq!(response[..5], [12, 0, 0, 0, 1]);
+    let mut rest = &response[5..];
+    let blob = take_string(&mut rest).to_vec();
+    assert_eq!(take_string(&mut rest), b"ed25519@fixture");
+
+    let public = std::fs::read_to_string(format!("{}.pub", KEY)).unwrap();
+    let encoded = public.split_whitespace().nth(1).unwrap();
+    assert_eq!(STANDARD.decode(encoded).unwrap(), blob);
+
+    let mut sign = vec![13];
+    put_string(&mut sign, &blob);
+    put_string(&mut sign, b"session data");
+    sign.extend_from_slice(&0u32.to_be_bytes());
+    let response = request(&mut stream, &sign);
+    assert_eq!(response[0], 14);
+    let mut rest = &response[1..];
+    let mut signature = take_string(&mut rest);
+    assert_eq!(take_string(&mut signature), b"ssh-ed25519");
+    UnparsedPublicKey::new(&ED25519, &blob[blob.len() - 32..])
+        .verify(b"session data", take_string(&mut signature))
+        .unwrap();
+
+    // Keys cannot be added to the agent.
+    assert_eq!(request(&mut stream, &[17]), [5]);
+
+    // The agent stops once the lifetime runs out and removes its socket.
+    let output = agent.wait_with_output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        format!(
+            "SSH_AUTH_SOCK={}; export SSH_AUTH_SOCK;\n",
+            socket.display()
+        )
+    );
+    assert!(!socket.exists());
+}
@@ -0,0 +1,346 @@
+//! Syncs vaults through a minimal WebDAV server that supports GET, PUT and MKCOL with ETag
+//! preconditions and Basic authentication.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rusty_toolkit::prelude::*;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const USERNAME: &str = "team";
+const PASSWORD: &str = "dav-secret";
+
+/// The files stored by the server with a version counter used as the ETag.
+type Files = Arc<Mutex<HashMap<String, (Vec<u8>, u32)>>>;
+
+struct DavServer {
+    url: String,
+    files: Files,
+    /// Simulate another client uploading between the next download and upload.
+    interfere: Arc<AtomicBool>,
+    rejected: Arc<AtomicUsize>,
+}
+
+impl DavServer {
+    fn start() -> Self {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let files = Files::default();
+        let interfere = Arc::new(AtomicBool::new(false));
+        let rejected = Arc::new(AtomicUsize::new(0));
+
+        let state = (files.clone(), interfere.clone(), rejected.clone());
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                handle(request, &state.0, &state.1, &state.2);
+            }
+        });
+
+        Self {
+            url: format!("webdav+http://127.0.0.1:{}/team/vault", port),
+            files,
+            interfere,
+            rejected,
+        }
+    }
+
+    fn remote(&self, password: &str) -> WebDavRemote {
+        WebDavRemote::new(&RemoteCredentials {
+            url: self.url.clone(),
+            username: USERNAME.to_string(),
+            password: password.into(),
+        })
+        .unwrap()
+    }
+}
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+fn etag(version: u32) -> Header {
+    Header::from_bytes("ETag", format!("\"{}\"", version)).unwrap()
+}
+
+fn handle(mut request: Request, files: &Files, interfere: &AtomicBool, rejected: &AtomicUsize) {
+    let login = format!(
+        "Basic {}",
+        STANDARD.encode(format!("{}:{}", USERNAME, PASSWORD))
+    );
+    if header(&request, "Authorization") != Some(login.as_str()) {
+        request
+            .respond(Response::empty(401).with_header(
+                Header::from_bytes("WWW-Authenticate", "Basic realm=\"dav\"").unwrap(),
+            ))
+            .unwrap();
+        return;
+    }
+
+    let path = request.url().to_string();
+    let mut files = files.lock().unwrap();
+    let response = match request.method() {
+        Method::Get => match files.get(&path) {
+            Some((data, version)) => Response::from_data(data.clone()).with_header(etag(*version)),
+            None => Response::from_data(Vec::new()).with_status_code(404),
+        },
+        Method::Put => {
+            let mut data = Vec::new();
+            request.as_reader().read_to_end(&mut data).unwrap();
+
+            if path.ends_with("vault.db") && interfere.swap(false, Ordering::SeqCst) {
+                if let Some((_, version)) = files.get_mut(&path) {
+                    *version += 1;
+                }
+            }
+
+            let current = files.get(&path).map(|(_, version)| *version);
+            let allowed = match (
+                header(&request, "If-Match"),
+                header(&request, "If-None-Match"),
+            ) {
+                (Some(expected), _) => {
+                    current.is_some_and(|version| format!("\"{}\"", version) == expected)
+                }
+                (None, Some("*")) => current.is_none(),
+                _ => true,
+            };
+            if allowed {
+                let version = current.unwrap_or(0) + 1;
+                files.insert(path, (data, version));
+                Response::from_data(Vec::new())
+                    .with_status_code(201)
+                    .with_header(etag(version))
+            } else {
+                rejected.fetch_add(1, Ordering::SeqCst);
+                Response::from_data(Vec::new()).with_status_code(412)
+            }
+        }
+        Method::NonStandard(method) if method.as_str() == "MKCOL" => {
+            Response::from_data(Vec::new()).with_status_code(201)
+        }
+        _ => Response::from_data(Vec::new()).with_status_code(405),
+    };
+    request.respond(response).unwrap();
+}
+
+struct Vault {
+    database: Database,
+    master_file: PathBuf,
+    _dir: tempfile::TempDir,
+}
+
+fn create_vault(master_password: &str) -> Vault {
+    let dir = tempfile::tempdir().unwrap();
+    let master_file = dir.path().join("master.key");
+    let master_password = SecretString::from(master_password);
+    let salt = Encryption::create_master_file(&master_file, &master_password).unwrap();
+    let database = Database::new(dir.path().join("pass.db"), &master_password, &salt).unwrap();
+
+    Vault {
+        database,
+        master_file,
+        _dir: dir,
+    }
+}
+
+fn sync(server: &DavServer, vault: &Vault) -> SyncReport {
+    server
+        .remote(PASSWORD)
+        .sync(&vault.database, &vault.master_file, &"master".into(), false)
+        .unwrap()
+}
+
+fn add_entry(vault: &Vault, service: &str, password: &str) {
+    let entry = PasswordEntry::new(
+        service.to_string(),
+        "user".to_string(),
+        password.into(),
+        String::new(),
+        String::new(),
+    )
+    .unwrap();
+    vault.database.create(&entry).unwrap();
+}
+
+fn services(vault: &Vault) -> Vec<String> {
+    let mut services: Vec<String> = vault
+        .database
+        .read()
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.service)
+        .collect();
+    services.sort();
+    services
+}
+
+#[test]
+fn test_sync_between_vaults() {
+    let server = DavServer::start();
+    let laptop = create_vault("master");
+    let desktop = create_vault("master");
+    add_entry(&laptop, "Mail", "mail-secret");
+    add_entry(&desktop, "Bank", "bank-secret");
+
+    let report = sync(&server, &laptop);
+    assert!(report.created);
+    assert!(server
+        .files
+        .lock()
+        .unwrap()
+        .contains_key("/team/vault/master.key"));
+
+    let report = sync(&server, &desktop);
+    assert!(!report.created);
+    assert!(report.uploaded);
+    assert_eq!(report.pulled.added, 1);
+    assert_eq!(report.pushed.added, 1);
+    assert_eq!(services(&desktop), ["Bank", "Mail"]);
+
+    let report = sync(&server, &laptop);
+    assert_eq!(report.pulled.added, 1);
+    assert!(!report.uploaded);
+    assert_eq!(services(&laptop), ["Bank", "Mail"]);
+
+    // A change on one machine reaches the other one.
+    let mut mail = laptop
+        .database
+        .read()
+        .unwrap()
+        .into_iter()
+        .find(|entry| entry.service == "Mail")
+        .unwrap();
+    mail.password = "changed".into();
+    mail.updated_at = chrono::Utc::now().to_rfc3339();
+    laptop.database.update(mail.id.unwrap(), mail).unwrap();
+    assert_eq!(sync(&server, &laptop).pushed.updated, 1);
+
+    let report = sync(&server, &desktop);
+    assert_eq!(report.pulled.updated, 1);
+    assert!(report.conflicts.is_empty());
+    let mail = desktop
+        .database
+        .read()
+        .unwrap()
+        .into_iter()
+        .find(|entry| entry.service == "Mail")
+        .unwrap();
+    assert_eq!(mail.password.expose_secret(), "changed");
+}
+
+#[test]
+fn test_concurrent_upload_starts_over() {
+    let server = DavServer::start();
+    let laptop = create_vault("master");
+    let desktop = create_vault("master");
+    add_entry(&laptop, "Mail", "mail-secret");
+    sync(&server, &laptop);
+    add_entry(&desktop, "Bank", "bank-secret");
+
+    server.interfere.store(true, Ordering::SeqCst);
+    let report = sync(&server, &desktop);
+
+    assert_eq!(server.rejected.load(Ordering::SeqCst), 1);
+    assert!(report.uploaded);
+    assert_eq!(report.pushed.added, 1);
+    // The rejected attempt did not touch the local vault, the retry pulled the entry.
+    assert_eq!(report.pulled.added, 1);
+    assert_eq!(services(&desktop), ["Bank", "Mail"]);
+    assert_eq!(sync(&server, &laptop).pulled.added, 1);
+}
+
+#[test]
+fn test_remote_upgrade_needs_flag() {
+    let server = DavServer::start();
+    let laptop = create_vault("master");
+    let desktop = create_vault("master");
+    add_entry(&laptop, "Mail", "mail-secret");
+    sync(&server, &laptop);
+
+    // Turn the remote vault into one written before entries had tags.
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("vault.db");
+    let master_file = dir.path().join("master.key");
+    {
+        let files = server.files.lock().unwrap();
+        std::fs::write(&path, &files["/team/vault/vault.db"].0).unwrap();
+        std::fs::write(&master_file, &files["/team/vault/master.key"].0).unwrap();
+    }
+    let salt = Encryption::unlock_master_file(&master_file, &"master".into()).unwrap();
+    let old = Database::open(path.clone(), &"master".into(), &salt).unwrap();
+    old.connection
+        .execute_batch("ALTER TABLE passwords DROP COLUMN tags; PRAGMA user_version = 5;")
+        .unwrap();
+    drop(old);
+    server
+        .files
+        .lock()
+        .unwrap()
+        .get_mut("/team/vault/vault.db")
+        .unwrap()
+        .0 = std::fs::read(&path).unwrap();
+
+    let remote = server.remote(PASSWORD);
+    let error = remote
+        .sync(
+            &desktop.database,
+            &desktop.master_file,
+            &"master".into(),
+            false,
+        )
+        .unwrap_err();
+    assert!(error.to_string().contains("--migrate-remote"), "{}", error);
+    assert!(services(&desktop).is_empty());
+
+    let report = remote
+        .sync(
+            &desktop.database,
+            &desktop.master_file,
+            &"master".into(),
+            true,
+        )
+        .unwrap();
+    assert!(report.uploaded);
+    assert_eq!(services(&desktop), ["Mail"]);
+    assert_eq!(sync(&server, &laptop).pulled.added, 0);
+}
+
+#[test]
+fn test_rejected_login() {
+    let server = DavServer::start();
+    let laptop = create_vault("master");
+
+    let error = server
+        .remote("wrong")
+        .sync(
+            &laptop.database,
+            &laptop.master_file,
+            &"master".into(),
+            false,
+        )
+        .unwrap_err();
+    assert!(error.to_string().contains("rejected the login"));
+}
+
+#[test]
+fn test_different_master_password() {
+    let server = DavServer::start();
+    let laptop = create_vault("master");
+    let other = create_vault("other");
+    sync(&server, &laptop);
+
+    let error = server
+        .remote(PASSWORD)
+        .sync(&other.database, &other.master_file, &"other".into(), false)
+        .unwrap_err();
+    assert!(error.to_string().contains("cannot be unlocked"));
+}